@@ -0,0 +1,61 @@
+//! Build order of large layered manifests
+//!
+//! Run with `cargo bench --bench build_order`.
+
+use bllvm::versions::{BuildOrderOptions, VersionsManifest};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::fmt::Write;
+use std::hint::black_box;
+
+/// `layers` layers of 100 repos, each requiring three repos of the layer below
+fn layered_manifest(layers: usize) -> VersionsManifest {
+    const WIDTH: usize = 100;
+    let mut content = String::from("[versions]\n");
+    for layer in 0..layers {
+        for i in 0..WIDTH {
+            let requires: Vec<String> = if layer == 0 {
+                Vec::new()
+            } else {
+                (0..3)
+                    .map(|k| format!("\"l{:03}-{:03}>=0.1.0\"", layer - 1, (i + k * 37) % WIDTH))
+                    .collect()
+            };
+            writeln!(
+                content,
+                "l{:03}-{:03} = {{ version = \"0.1.0\", git_tag = \"v0.1.0\", requires = [{}] }}",
+                layer,
+                i,
+                requires.join(", ")
+            )
+            .unwrap();
+        }
+    }
+    VersionsManifest::from_str(&content).expect("layered manifest parses")
+}
+
+fn build_order(c: &mut Criterion) {
+    let mut group = c.benchmark_group("build_order");
+    for layers in [10, 30, 50] {
+        let manifest = layered_manifest(layers);
+        let repos = manifest.versions.len();
+        group.bench_with_input(BenchmarkId::new("graph", repos), &manifest, |b, m| {
+            b.iter(|| black_box(m.build_order().unwrap()))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("with_options", repos),
+            &manifest,
+            |b, m| {
+                b.iter(|| {
+                    black_box(
+                        m.build_order_with_options(&BuildOrderOptions::default())
+                            .unwrap(),
+                    )
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, build_order);
+criterion_main!(benches);
@@ -0,0 +1,68 @@
+//! Building a [`DependencyGraph`](bllvm::graph::DependencyGraph) once and
+//! querying it, against asking the manifest each time
+//!
+//! Run with `cargo bench --bench graph`.
+
+use bllvm::versions::VersionsManifest;
+use std::fmt::Write;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const REPOS: usize = 1_000;
+const QUERIES: usize = 100;
+
+/// `REPOS` repos, each requiring up to three repos declared before it
+fn synthetic_manifest() -> VersionsManifest {
+    let mut content = String::from("[versions]\n");
+    for i in 0..REPOS {
+        let requires: Vec<String> = [1, 7, 31]
+            .iter()
+            .filter(|&&step| i >= step)
+            .map(|step| format!("\"repo-{:04}>=0.1.0\"", i - step))
+            .collect();
+        writeln!(
+            content,
+            "repo-{:04} = {{ version = \"0.1.0\", git_tag = \"v0.1.0\", requires = [{}] }}",
+            i,
+            requires.join(", ")
+        )
+        .unwrap();
+    }
+    VersionsManifest::from_str(&content).expect("synthetic manifest parses")
+}
+
+fn time(label: &str, run: impl FnOnce()) -> Duration {
+    let start = Instant::now();
+    run();
+    let elapsed = start.elapsed();
+    println!("{:<40} {:>10.2?}", label, elapsed);
+    elapsed
+}
+
+fn main() {
+    let manifest = synthetic_manifest();
+    let repos: Vec<String> = (0..QUERIES)
+        .map(|i| format!("repo-{:04}", i * REPOS / QUERIES))
+        .collect();
+    println!("{} repos, {} queries of each kind\n", REPOS, QUERIES);
+
+    let manifest_time = time("manifest: build_order + dependents_of", || {
+        for repo in &repos {
+            black_box(manifest.build_order().unwrap());
+            black_box(manifest.dependents_of(repo, false).unwrap());
+        }
+    });
+
+    let graph_time = time("graph: graph() + topo_order + dependents", || {
+        let graph = manifest.graph().unwrap();
+        for repo in &repos {
+            black_box(graph.topo_order());
+            black_box(graph.dependents(repo).unwrap());
+        }
+    });
+
+    println!(
+        "\ngraph is {:.1}x faster",
+        manifest_time.as_secs_f64() / graph_time.as_secs_f64()
+    );
+}
@@ -0,0 +1,170 @@
+//! [`BuildExecutor::run_parallel`] on tokio (`async-executor` feature)
+//!
+//! ```no_run
+//! # async fn build(manifest: &bllvm::versions::VersionsManifest) -> anyhow::Result<()> {
+//! use bllvm::executor::BuildExecutor;
+//! use tokio_stream::StreamExt;
+//!
+//! let executor = BuildExecutor::new(manifest);
+//! let (run, mut events) = executor.run_parallel_events(4);
+//! let progress = async {
+//!     while let Some(event) = events.next().await {
+//!         println!("{}", event);
+//!     }
+//! };
+//! let (report, ()) = tokio::join!(run, progress);
+//! print!("{}", report?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::executor::{
+    flush_output, shell, BuildExecutor, BuildFailure, BuildReport, BuildStep, Scheduled, Scheduler,
+};
+use serde::Serialize;
+use std::fmt;
+use std::future::Future;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::JoinSet;
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+/// Progress of an async build, see [`BuildExecutor::run_parallel_events`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BuildEvent {
+    /// The repo's build command was started
+    Started { repo: String },
+    /// The repo was built
+    Finished { repo: String },
+    /// The repo was not built: it is up to date, or a dependency failed or
+    /// the run stopped
+    Skipped { repo: String, up_to_date: bool },
+    /// The repo's build command (or one of its hooks) failed
+    Failed { repo: String, reason: String },
+}
+
+impl fmt::Display for BuildEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildEvent::Started { repo } => write!(f, "{}: started", repo),
+            BuildEvent::Finished { repo } => write!(f, "{}: finished", repo),
+            BuildEvent::Skipped {
+                repo,
+                up_to_date: true,
+            } => write!(f, "{}: up to date", repo),
+            BuildEvent::Skipped { repo, .. } => write!(f, "{}: skipped", repo),
+            BuildEvent::Failed { repo, reason } => write!(f, "{}: failed ({})", repo, reason),
+        }
+    }
+}
+
+/// Where [`BuildEvent`]s go; a dropped stream just stops receiving them
+struct Events(Option<UnboundedSender<BuildEvent>>);
+
+impl Events {
+    fn send(&self, event: BuildEvent) {
+        if let Some(sender) = &self.0 {
+            let _ = sender.send(event);
+        }
+    }
+}
+
+impl<'a> BuildExecutor<'a> {
+    /// [`run_parallel`](Self::run_parallel) with `tokio::process` instead of
+    /// a thread per build
+    ///
+    /// Scheduling, failure handling and output buffering are the same.
+    /// Dropping the future kills the builds still running. Hooks run on the
+    /// awaiting task.
+    pub async fn run_parallel_async(&self, jobs: usize) -> anyhow::Result<BuildReport> {
+        self.run_async(jobs, Events(None)).await
+    }
+
+    /// [`run_parallel_async`](Self::run_parallel_async), reporting progress
+    /// as a stream of [`BuildEvent`]s
+    ///
+    /// The stream ends once the returned future has completed; both have to
+    /// be polled, e.g. with `tokio::join!`.
+    pub fn run_parallel_events(
+        &self,
+        jobs: usize,
+    ) -> (
+        impl Future<Output = anyhow::Result<BuildReport>> + '_,
+        UnboundedReceiverStream<BuildEvent>,
+    ) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        (
+            self.run_async(jobs, Events(Some(sender))),
+            UnboundedReceiverStream::new(receiver),
+        )
+    }
+
+    async fn run_async(&self, jobs: usize, events: Events) -> anyhow::Result<BuildReport> {
+        let mut scheduler = Scheduler::new(self, jobs)?;
+        for repo in scheduler.up_to_date() {
+            events.send(BuildEvent::Skipped {
+                repo: repo.clone(),
+                up_to_date: true,
+            });
+        }
+        let mut running = JoinSet::new();
+
+        loop {
+            while running.len() < jobs {
+                match scheduler.next() {
+                    Some(Scheduled::Start(step)) => {
+                        events.send(BuildEvent::Started {
+                            repo: step.repo.clone(),
+                        });
+                        running.spawn(async move {
+                            let started = Instant::now();
+                            let output = run_captured(&step).await;
+                            (step.repo, output, started.elapsed())
+                        });
+                    }
+                    Some(Scheduled::Skipped(repo)) => events.send(BuildEvent::Skipped {
+                        repo,
+                        up_to_date: false,
+                    }),
+                    Some(Scheduled::Failed(BuildFailure { repo, reason })) => {
+                        events.send(BuildEvent::Failed { repo, reason })
+                    }
+                    None => break,
+                }
+            }
+
+            let Some(joined) = running.join_next().await else {
+                break;
+            };
+            let (repo, (result, stdout, stderr), duration) =
+                joined.map_err(|e| anyhow::anyhow!("Build task failed: {}", e))?;
+            flush_output(&repo, &stdout, &stderr);
+            let event = match scheduler.finish(&repo, result, duration) {
+                Ok(()) => BuildEvent::Finished { repo },
+                Err(reason) => BuildEvent::Failed { repo, reason },
+            };
+            events.send(event);
+        }
+
+        Ok(scheduler.into_report())
+    }
+}
+
+/// Run a build step, capturing its stdout and stderr
+async fn run_captured(step: &BuildStep) -> (Result<(), String>, Vec<u8>, Vec<u8>) {
+    let mut command = tokio::process::Command::from(shell(&step.command, &step.dir));
+    match command.kill_on_drop(true).output().await {
+        Ok(output) if output.status.success() => (Ok(()), output.stdout, output.stderr),
+        Ok(output) => (
+            Err(format!("exited with {}", output.status)),
+            output.stdout,
+            output.stderr,
+        ),
+        Err(e) => (
+            Err(format!("failed to run in {}: {}", step.dir.display(), e)),
+            Vec::new(),
+            Vec::new(),
+        ),
+    }
+}
@@ -4,14 +4,26 @@
 //! This binary starts a full Bitcoin node using the bllvm-node library.
 
 use anyhow::{Context, Result};
+use bllvm::bump::BumpPolicy;
+use bllvm::dot::DotOptions;
+use bllvm::executor::BuildExecutor;
+use bllvm::explain::OrderExplanation;
+use bllvm::lint::Severity;
+use bllvm::progress::{LogReporter, ProgressReporter};
+use bllvm::state::{BuildState, StateLock};
+use bllvm::versions::{ValidationError, ValidationResult, VersionsManifest};
 use bllvm_node::config::NodeConfig;
 use bllvm_node::node::Node as ReferenceNode;
 use bllvm_node::ProtocolVersion;
 use clap::{Parser, Subcommand, ValueEnum};
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::env;
+#[cfg(feature = "progress")]
+use std::io::IsTerminal;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tokio::signal;
 use tracing::{error, info, warn};
 
@@ -46,6 +58,10 @@ struct Cli {
     #[arg(short, long)]
     verbose: bool,
 
+    /// versions.toml commands: fail if any requirement does not match its dependency's declared version
+    #[arg(long, global = true)]
+    strict: bool,
+
     /// Feature flags (runtime-configurable features)
     #[command(flatten)]
     features: FeatureFlags,
@@ -113,6 +129,234 @@ enum Command {
         #[arg(long)]
         rpc_addr: Option<SocketAddr>,
     },
+    /// Show the repository dependency graph from versions.toml
+    Graph {
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Output format
+        #[arg(long, value_enum, default_value = "dot")]
+        format: GraphFormat,
+        /// Group repositories by build stage
+        #[arg(long)]
+        cluster_stages: bool,
+        /// Highlight repositories that are part of a dependency cycle
+        #[arg(long)]
+        highlight_cycles: bool,
+    },
+    /// Show the build plan computed from versions.toml
+    Plan {
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Print the plan (or error) as JSON
+        #[arg(long)]
+        json: bool,
+        /// Describe per batch what a build would do, without building
+        #[arg(long, conflicts_with = "json")]
+        explain: bool,
+    },
+    /// Print the build batches as a GitHub Actions matrix (single-line JSON)
+    Matrix {
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+    },
+    /// Print the build order computed from versions.toml, one repository per line
+    Order {
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Group repositories by parallel build stage
+        #[arg(long)]
+        stages: bool,
+        /// Print the order (or stages) as JSON
+        #[arg(long)]
+        json: bool,
+        /// Only include this repository (or group) and its dependencies; repeatable
+        #[arg(long = "target", value_name = "REPO")]
+        targets: Vec<String>,
+    },
+    /// Explain why one repository is built before another
+    Why {
+        /// First repository
+        a: String,
+        /// Second repository
+        b: String,
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+    },
+    /// Show dependency graph statistics and the critical path
+    Stats {
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// JSON object of build durations in seconds per repo, e.g. from previous runs
+        #[arg(long)]
+        durations: Option<PathBuf>,
+        /// Print the statistics as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Run opinionated checks on versions.toml; fails on deny-level findings
+    Lint {
+        /// Path to versions.toml (its [lint] table is the starting configuration)
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Skip a rule (repeatable)
+        #[arg(long, value_name = "RULE")]
+        allow: Vec<String>,
+        /// Report a rule as a warning (repeatable)
+        #[arg(long, value_name = "RULE")]
+        warn: Vec<String>,
+        /// Report a rule as an error (repeatable)
+        #[arg(long, value_name = "RULE")]
+        deny: Vec<String>,
+        /// Longest dependency chain allowed by deep-chain
+        #[arg(long)]
+        max_depth: Option<usize>,
+        /// Treat the manifest as a release (enables prerelease-version)
+        #[arg(long)]
+        release: bool,
+        /// Print the findings as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Generate versions.toml from the Cargo crates below a directory
+    Init {
+        /// Directory to scan for Cargo.toml files
+        #[arg(default_value = ".")]
+        root: PathBuf,
+        /// Where to write the manifest (defaults to <root>/versions.toml)
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Overwrite an existing manifest
+        #[arg(long)]
+        force: bool,
+    },
+    /// Show what changed between two versions.toml files
+    Diff {
+        /// Old versions.toml (e.g. from the base branch)
+        old: PathBuf,
+        /// New versions.toml
+        new: PathBuf,
+        /// Print the diff as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Bump a repository's version and its dependents, rewriting versions.toml
+    Bump {
+        /// Repository to bump
+        repo: String,
+        /// New version
+        version: String,
+        /// Path to versions.toml (rewritten without its comments)
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Only report what the bump would break, leaving versions.toml alone
+        #[arg(long)]
+        dry_run: bool,
+        /// How to bump the repositories depending on it
+        #[arg(long, value_enum, default_value = "patch")]
+        policy: BumpPolicyArg,
+    },
+    /// Run each repository's build command from versions.toml in build order
+    Build {
+        /// Path to versions.toml (repo paths are relative to its directory)
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Print the commands in order without running them
+        #[arg(long)]
+        dry_run: bool,
+        /// Keep building repositories whose dependencies all succeeded after a failure
+        #[arg(long)]
+        keep_going: bool,
+        /// Run up to this many builds at once
+        #[arg(long, short = 'j')]
+        jobs: Option<usize>,
+        /// Rebuild repos that .bllvm-state.toml has as up to date
+        #[arg(long)]
+        force: bool,
+        /// Write how long each repo took to build as JSON (for `bllvm stats --durations`)
+        #[arg(long, value_name = "PATH")]
+        durations_out: Option<PathBuf>,
+    },
+    /// Delete the build state so the next build rebuilds everything
+    CleanState {
+        /// Path to versions.toml (the state file sits next to it)
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+    },
+    /// Check that every git_tag in versions.toml exists on its remote
+    #[cfg(feature = "git")]
+    VerifyTags {
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Skip the check (with a warning) when remotes cannot be reached
+        #[arg(long)]
+        offline: bool,
+    },
+    /// Create each repo's git_tag in its checkout, dependencies first
+    #[cfg(feature = "git")]
+    Tag {
+        /// Path to versions.toml (checkouts are found relative to its directory)
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+        /// Tag message; {repo}, {version} and {tag} are filled in
+        #[arg(long, default_value = "Release {repo} {version}")]
+        message: String,
+        /// Push the tags to this remote
+        #[arg(long, value_name = "REMOTE")]
+        push: Option<String>,
+        /// Run the checks and print the git commands without running them
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Re-plan on every save of versions.toml, showing errors and order changes
+    #[cfg(feature = "watch")]
+    Watch {
+        /// Path to versions.toml
+        #[arg(default_value = "versions.toml")]
+        manifest: PathBuf,
+    },
+    /// List git checkouts missing from versions.toml, and repos not checked out
+    #[cfg(feature = "git")]
+    Checkouts {
+        /// Directory holding the repo checkouts
+        #[arg(default_value = ".")]
+        dir: PathBuf,
+        /// Path to versions.toml
+        #[arg(long, default_value = "versions.toml")]
+        manifest: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum GraphFormat {
+    /// Graphviz DOT (pipe into `dot -Tsvg`)
+    Dot,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum BumpPolicyArg {
+    /// Next patch version
+    Patch,
+    /// Next minor version
+    Minor,
+    /// Keep their versions, only repin them
+    None,
+}
+
+impl From<BumpPolicyArg> for BumpPolicy {
+    fn from(policy: BumpPolicyArg) -> Self {
+        match policy {
+            BumpPolicyArg::Patch => BumpPolicy::Patch,
+            BumpPolicyArg::Minor => BumpPolicy::Minor,
+            BumpPolicyArg::None => BumpPolicy::None,
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -276,6 +520,123 @@ async fn main() -> Result<()> {
             let params: Value = serde_json::from_str(params).context("Invalid JSON parameters")?;
             handle_rpc(rpc_addr, method, params, &config).await
         }
+        Some(Command::Graph {
+            ref manifest,
+            format,
+            cluster_stages,
+            highlight_cycles,
+        }) => {
+            let options = DotOptions {
+                cluster_by_stage: cluster_stages,
+                highlight_cycle_nodes: highlight_cycles,
+                ..Default::default()
+            };
+            handle_graph(manifest, format, &options, cli.strict)
+        }
+        Some(Command::Plan {
+            ref manifest,
+            json,
+            explain,
+        }) => handle_plan(manifest, json, explain, cli.strict),
+        Some(Command::Matrix { ref manifest }) => {
+            println!(
+                "{}",
+                load_manifest(manifest, cli.strict)?.to_github_matrix()?
+            );
+            Ok(())
+        }
+        Some(Command::Order {
+            ref manifest,
+            stages,
+            json,
+            ref targets,
+        }) => handle_order(manifest, stages, json, targets, cli.strict),
+        Some(Command::Stats {
+            ref manifest,
+            ref durations,
+            json,
+        }) => handle_stats(manifest, durations.as_deref(), json, cli.strict),
+        Some(Command::Lint {
+            ref manifest,
+            ref allow,
+            ref warn,
+            ref deny,
+            max_depth,
+            release,
+            json,
+        }) => {
+            let flags = [
+                (allow, None),
+                (warn, Some(Severity::Warn)),
+                (deny, Some(Severity::Deny)),
+            ];
+            handle_lint(manifest, &flags, max_depth, release, json, cli.strict)
+        }
+        Some(Command::Init {
+            ref root,
+            ref output,
+            force,
+        }) => handle_init(root, output.as_deref(), force),
+        Some(Command::Diff {
+            ref old,
+            ref new,
+            json,
+        }) => handle_diff(old, new, json, cli.strict),
+        Some(Command::Bump {
+            ref repo,
+            ref version,
+            ref manifest,
+            dry_run,
+            policy,
+        }) => handle_bump(manifest, repo, version, dry_run, policy.into(), cli.strict),
+        Some(Command::Why {
+            ref a,
+            ref b,
+            ref manifest,
+        }) => handle_why(manifest, a, b, cli.strict),
+        Some(Command::Build {
+            ref manifest,
+            dry_run,
+            keep_going,
+            jobs,
+            force,
+            ref durations_out,
+        }) => handle_build(
+            manifest,
+            dry_run,
+            keep_going,
+            jobs,
+            force,
+            durations_out.as_deref(),
+            cli.strict,
+        ),
+        Some(Command::CleanState { ref manifest }) => handle_clean_state(manifest),
+        #[cfg(feature = "git")]
+        Some(Command::VerifyTags {
+            ref manifest,
+            offline,
+        }) => handle_verify_tags(manifest, offline, cli.strict),
+        #[cfg(feature = "git")]
+        Some(Command::Tag {
+            ref manifest,
+            ref message,
+            ref push,
+            dry_run,
+        }) => {
+            let options = bllvm::tagger::TagOptions {
+                message: message.clone(),
+                push: push.clone(),
+                dry_run,
+            };
+            handle_tag(manifest, &options, cli.strict)
+        }
+        #[cfg(feature = "git")]
+        Some(Command::Checkouts {
+            ref dir,
+            ref manifest,
+        }) => handle_checkouts(dir, manifest, cli.strict),
+        #[cfg(feature = "watch")]
+        Some(Command::Watch { ref manifest }) => handle_watch(manifest).await,
         None | Some(Command::Start) => {
             // Start node (default behavior)
             let (config, data_dir, listen_addr, rpc_addr, network) = build_final_config(&cli);
@@ -1207,3 +1568,597 @@ async fn handle_rpc(
     println!("{}", serde_json::to_string_pretty(&result)?);
     Ok(())
 }
+
+/// Load versions.toml and apply `BLLVM_OVERRIDE_<REPO>` environment overrides
+///
+/// Yanked or deprecated dependencies are reported on stderr. With `strict`
+/// (`--strict`), requirements that do not match their dependency's declared
+/// version are an error, see [`check_pins`].
+fn load_manifest(path: &Path, strict: bool) -> Result<VersionsManifest> {
+    let mut manifest = VersionsManifest::from_file(path)?;
+    if manifest.has_latest() {
+        manifest = resolve_latest(&manifest)?;
+    }
+    for repo in manifest.apply_env_overrides() {
+        eprintln!(
+            "Overriding '{}' with git ref '{}' from the environment",
+            repo, manifest.versions[&repo].git_tag
+        );
+    }
+    for warning in manifest.warnings() {
+        eprintln!("Warning: {}", warning);
+    }
+    if strict {
+        check_pins(&manifest)?;
+    }
+    Ok(manifest)
+}
+
+/// Pin `track = "latest"` repos to the newest tag on their remotes
+#[cfg(feature = "git")]
+fn resolve_latest(manifest: &VersionsManifest) -> Result<VersionsManifest> {
+    let resolved = manifest.resolve_latest(bllvm::git::RemoteVersionResolver::new(manifest))?;
+    for (repo, info) in &manifest.versions {
+        if info.is_latest() {
+            eprintln!(
+                "Resolved '{}' latest to {} ({})",
+                repo,
+                resolved.versions[repo].version,
+                resolved.versions[repo].full_tag()
+            );
+        }
+    }
+    Ok(resolved)
+}
+
+#[cfg(not(feature = "git"))]
+fn resolve_latest(manifest: &VersionsManifest) -> Result<VersionsManifest> {
+    eprintln!("Warning: track = \"latest\" needs bllvm built with the git feature; using the pinned versions");
+    Ok(manifest.clone())
+}
+
+fn handle_graph(
+    manifest: &Path,
+    format: GraphFormat,
+    options: &DotOptions,
+    strict: bool,
+) -> Result<()> {
+    let manifest = load_manifest(manifest, strict)?;
+
+    match format {
+        GraphFormat::Dot => print!("{}", manifest.to_dot_with(options)),
+    }
+
+    Ok(())
+}
+
+fn handle_init(root: &Path, output: Option<&Path>, force: bool) -> Result<()> {
+    let output = output
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| root.join("versions.toml"));
+    if output.exists() && !force {
+        anyhow::bail!(
+            "{} already exists (use --force to overwrite)",
+            output.display()
+        );
+    }
+
+    let manifest = VersionsManifest::discover(root)?;
+    if manifest.versions.is_empty() {
+        anyhow::bail!("No Cargo packages found under {}", root.display());
+    }
+    std::fs::write(&output, manifest.to_toml_string()?)
+        .with_context(|| format!("Failed to write {}", output.display()))?;
+    println!(
+        "Wrote {} repositories to {}",
+        manifest.versions.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+fn handle_diff(old: &Path, new: &Path, json_output: bool, strict: bool) -> Result<()> {
+    let old = load_manifest(old, strict)?;
+    let new = load_manifest(new, strict)?;
+    let diff = old.diff(&new);
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+    } else {
+        print!("{}", diff);
+    }
+
+    Ok(())
+}
+
+fn handle_bump(
+    manifest_path: &Path,
+    repo: &str,
+    version: &str,
+    dry_run: bool,
+    policy: BumpPolicy,
+    strict: bool,
+) -> Result<()> {
+    if dry_run {
+        let impact = load_manifest(manifest_path, strict)?.simulate_bump(repo, version)?;
+        print!("{}", impact);
+        if !impact.is_ok() {
+            anyhow::bail!(
+                "Bumping '{}' to {} breaks {} requirement(s)",
+                repo,
+                version,
+                impact.broken.len()
+            );
+        }
+        return Ok(());
+    }
+
+    // Not load_manifest: environment overrides must not be written back
+    let manifest = VersionsManifest::from_file(manifest_path)?;
+    if strict {
+        check_pins(&manifest)?;
+    }
+    let (bumped, report) = manifest.bump(repo, version, policy)?;
+    bumped.to_file(manifest_path)?;
+    print!("{}", report);
+    Ok(())
+}
+
+fn handle_why(manifest_path: &Path, a: &str, b: &str, strict: bool) -> Result<()> {
+    let manifest = load_manifest(manifest_path, strict)?;
+    let explanation = manifest.explain_order(a, b)?;
+    println!("{}", explanation);
+    if let OrderExplanation::Required { before, after, .. } = &explanation {
+        let paths = manifest.explain_dependency(after, before)?;
+        if paths.len() > 1 {
+            println!("All paths:");
+            for path in paths {
+                println!("  {}", path.join(" -> "));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn handle_order(
+    manifest: &Path,
+    stages: bool,
+    json_output: bool,
+    targets: &[String],
+    strict: bool,
+) -> Result<()> {
+    let manifest = load_manifest(manifest, strict)?;
+    if let ValidationResult::Invalid { errors, .. } = manifest.validate() {
+        for error in &errors {
+            eprintln!("Error: {}", error);
+        }
+        anyhow::bail!("versions.toml has {} validation error(s)", errors.len());
+    }
+
+    let targets: Vec<&str> = targets.iter().map(String::as_str).collect();
+    if stages {
+        let stages = if targets.is_empty() {
+            manifest.build_stages()?
+        } else {
+            manifest.build_stages_for(&targets)?
+        };
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&stages)?);
+        } else {
+            for (index, stage) in stages.iter().enumerate() {
+                println!("Stage {}: {}", index, stage.join(", "));
+            }
+        }
+    } else {
+        let order = if targets.is_empty() {
+            manifest.build_order()?
+        } else {
+            manifest.build_order_for(&targets)?
+        };
+        if json_output {
+            println!("{}", serde_json::to_string_pretty(&order)?);
+        } else {
+            for repo in order {
+                println!("{}", repo);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_lint(
+    manifest: &Path,
+    flags: &[(&Vec<String>, Option<Severity>)],
+    max_depth: Option<usize>,
+    release: bool,
+    json_output: bool,
+    strict: bool,
+) -> Result<()> {
+    let manifest = load_manifest(manifest, strict)?;
+    let mut config = manifest.lint.clone().unwrap_or_default();
+    for (rules, level) in flags {
+        for rule in *rules {
+            config.set(rule, *level);
+        }
+    }
+    if max_depth.is_some() {
+        config.max_depth = max_depth;
+    }
+    config.release |= release;
+
+    let findings = manifest.lint(&config)?;
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&findings)?);
+    } else if findings.is_empty() {
+        println!("No lint findings");
+    } else {
+        for finding in &findings {
+            println!("{}", finding);
+        }
+    }
+
+    let denied = findings
+        .iter()
+        .filter(|finding| finding.severity == Severity::Deny)
+        .count();
+    if denied > 0 {
+        anyhow::bail!("{} lint error(s)", denied);
+    }
+    Ok(())
+}
+
+fn handle_stats(
+    manifest: &Path,
+    durations: Option<&Path>,
+    json_output: bool,
+    strict: bool,
+) -> Result<()> {
+    let manifest = load_manifest(manifest, strict)?;
+    let stats = match durations {
+        Some(path) => {
+            let content = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let seconds: HashMap<String, f64> = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            let durations = seconds
+                .into_iter()
+                .map(|(repo, secs)| {
+                    let duration = Duration::try_from_secs_f64(secs).map_err(|_| {
+                        anyhow::anyhow!(
+                            "Invalid duration {} for '{}' in {}",
+                            secs,
+                            repo,
+                            path.display()
+                        )
+                    })?;
+                    Ok((repo, duration))
+                })
+                .collect::<Result<HashMap<_, _>>>()?;
+            manifest.graph_stats_with_durations(&durations)?
+        }
+        None => manifest.graph_stats()?,
+    };
+
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("Repositories: {}", stats.nodes);
+    println!("Dependencies: {}", stats.edges);
+    println!(
+        "Longest chain ({}): {}",
+        stats.longest_chain.len(),
+        stats.longest_chain.join(" -> ")
+    );
+    println!();
+    let width = stats
+        .repos
+        .keys()
+        .map(String::len)
+        .max()
+        .unwrap_or(0)
+        .max(10);
+    println!("{:<width$}  depth  fan-in  fan-out", "Repository");
+    for (repo, repo_stats) in &stats.repos {
+        println!(
+            "{:<width$}  {:>5}  {:>6}  {:>7}",
+            repo, repo_stats.depth, repo_stats.fan_in, repo_stats.fan_out
+        );
+    }
+    if let Some(timing) = &stats.timing {
+        println!();
+        println!(
+            "Critical path ({:.1}s): {}",
+            timing.min_wall_clock.as_secs_f64(),
+            timing.critical_path.join(" -> ")
+        );
+        println!(
+            "Minimum wall-clock time: {:.1}s (serial: {:.1}s)",
+            timing.min_wall_clock.as_secs_f64(),
+            timing.serial.as_secs_f64()
+        );
+        if !timing.unmeasured.is_empty() {
+            println!("No duration for: {}", timing.unmeasured.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_plan(manifest: &Path, json_output: bool, explain: bool, strict: bool) -> Result<()> {
+    let plan =
+        load_manifest(manifest, strict).and_then(|m| Ok((m.build_plan()?, m.critical_path()?)));
+
+    match plan {
+        Ok((plan, _)) if json_output => println!("{}", plan.to_json()?),
+        Ok((plan, _)) if explain => print!("{}", plan.explain()),
+        Ok((plan, critical_path)) => {
+            for (index, stage) in plan.stages.iter().enumerate() {
+                println!("Stage {}: {}", index, stage.join(", "));
+            }
+            println!("Critical path: {}", critical_path.join(" -> "));
+        }
+        Err(e) if json_output => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&manifest_error_json(&e))?
+            );
+            std::process::exit(1);
+        }
+        Err(e) => return Err(e),
+    }
+
+    Ok(())
+}
+
+/// `--strict`: every requirement has to match its dependency's declared version
+fn check_pins(manifest: &VersionsManifest) -> Result<()> {
+    let drift = manifest.check_pin_consistency();
+    if drift.is_empty() {
+        return Ok(());
+    }
+    for pin in &drift {
+        eprintln!("Error: {}", pin);
+    }
+    anyhow::bail!(
+        "versions.toml has {} requirement(s) that do not match the declared version",
+        drift.len()
+    )
+}
+
+fn handle_build(
+    manifest_path: &Path,
+    dry_run: bool,
+    keep_going: bool,
+    jobs: Option<usize>,
+    force: bool,
+    durations_out: Option<&Path>,
+    strict: bool,
+) -> Result<()> {
+    let manifest = load_manifest(manifest_path, strict)?;
+    let state_path = BuildState::path_for(manifest_path);
+    let root = manifest_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let mut executor = BuildExecutor::new(&manifest)
+        .root(root)
+        .keep_going(keep_going)
+        .state_file(&state_path)
+        .force(force);
+    if let Some(hooks) = &manifest.hooks {
+        executor = executor.hooks(hooks);
+    }
+
+    if dry_run {
+        let state = if force {
+            BuildState::default()
+        } else {
+            BuildState::load(&state_path)?
+        };
+        let to_build: HashSet<String> = manifest
+            .build_order_with_state(&state)?
+            .into_iter()
+            .collect();
+        for step in executor.steps()? {
+            if to_build.contains(&step.repo) {
+                println!("{}", step);
+            }
+        }
+        return Ok(());
+    }
+
+    let reporter = progress_reporter();
+    executor = executor.progress(reporter.as_ref());
+    let report = match jobs {
+        Some(jobs) => executor.run_parallel(jobs)?,
+        None => executor.run()?,
+    };
+    for warning in &report.warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    print!("{}", report);
+    if let Some(path) = durations_out {
+        std::fs::write(path, report.durations_json()?)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    if !report.is_success() {
+        anyhow::bail!("Build failed");
+    }
+
+    Ok(())
+}
+
+/// Progress bars when stderr is a terminal, plain log lines otherwise
+fn progress_reporter() -> Box<dyn ProgressReporter> {
+    #[cfg(feature = "progress")]
+    if std::io::stderr().is_terminal() {
+        return Box::new(bllvm::progress::BarReporter::new());
+    }
+    Box::new(LogReporter)
+}
+
+fn handle_clean_state(manifest_path: &Path) -> Result<()> {
+    let state_path = BuildState::path_for(manifest_path);
+    let mut removed = false;
+    for path in [StateLock::path_for(&state_path), state_path] {
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                println!("Removed {}", path.display());
+                removed = true;
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => anyhow::bail!("Failed to remove {}: {}", path.display(), e),
+        }
+    }
+    if !removed {
+        println!("No build state to remove");
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "git")]
+fn handle_verify_tags(manifest_path: &Path, offline: bool, strict: bool) -> Result<()> {
+    let manifest = load_manifest(manifest_path, strict)?;
+    if offline {
+        eprintln!("Warning: --offline given, git tags were not checked against their remotes");
+        return Ok(());
+    }
+
+    let remotes = bllvm::git::RemoteMap::from_manifest(&manifest)?;
+    let report = manifest.verify_tags(&remotes, bllvm::git::RetryPolicy::default())?;
+    print!("{}", report);
+    let missing = report.missing();
+    if !missing.is_empty() {
+        let repos: Vec<&str> = missing.iter().map(|check| check.repo.as_str()).collect();
+        anyhow::bail!("Missing git tags for {}", repos.join(", "));
+    }
+    let unreachable = report.unreachable();
+    if !unreachable.is_empty() {
+        let repos: Vec<&str> = unreachable
+            .iter()
+            .map(|check| check.repo.as_str())
+            .collect();
+        anyhow::bail!("Could not reach the remotes of {}", repos.join(", "));
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "git")]
+fn handle_tag(
+    manifest_path: &Path,
+    options: &bllvm::tagger::TagOptions,
+    strict: bool,
+) -> Result<()> {
+    use bllvm::tagger::{repo_paths, GitCli, ReleaseTagger};
+
+    let manifest = load_manifest(manifest_path, strict)?;
+    let root = manifest_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let tagged =
+        ReleaseTagger::new(GitCli).tag_all(&manifest, &repo_paths(&manifest, root), options)?;
+    for repo in &tagged {
+        print!("{}", repo);
+    }
+    if !options.dry_run {
+        let created = tagged.iter().filter(|repo| !repo.existed).count();
+        println!(
+            "Created {} tag(s), {} already present",
+            created,
+            tagged.len() - created
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "git")]
+fn handle_checkouts(dir: &Path, manifest_path: &Path, strict: bool) -> Result<()> {
+    let manifest = load_manifest(manifest_path, strict)?;
+    let workspace = bllvm::workspace::Workspace::new(&manifest);
+    let orphans = workspace.orphans(dir)?;
+    let missing = workspace.missing(dir);
+
+    if orphans.is_empty() && missing.is_empty() {
+        println!("Checkouts in {} match versions.toml", dir.display());
+        return Ok(());
+    }
+    if !orphans.is_empty() {
+        println!("Not in versions.toml:");
+        for repo in &orphans {
+            println!("  {}", repo);
+        }
+    }
+    if !missing.is_empty() {
+        println!("Not checked out:");
+        for repo in &missing {
+            println!("  {}", repo);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "watch")]
+async fn handle_watch(manifest_path: &Path) -> Result<()> {
+    use bllvm::watch::ManifestWatcher;
+
+    let watcher = ManifestWatcher::new(manifest_path);
+    let stop = watcher.stop_handle();
+    let path = manifest_path.display().to_string();
+    let mut run = tokio::task::spawn_blocking(move || {
+        watcher.run(|update| {
+            print_watch_update(&path, update);
+            std::ops::ControlFlow::Continue(())
+        })
+    });
+
+    tokio::select! {
+        result = &mut run => return result?,
+        _ = signal::ctrl_c() => {}
+    }
+    stop.stop();
+    run.await?
+}
+
+#[cfg(feature = "watch")]
+fn print_watch_update(path: &str, update: bllvm::watch::WatchUpdate) {
+    use bllvm::watch::WatchUpdate;
+
+    match update {
+        WatchUpdate::Plan { order, changes } => {
+            println!("Build order: {}", order.join(" -> "));
+            match changes {
+                Some(changes) if changes.is_empty() => println!("(unchanged)"),
+                Some(changes) => print!("{}", changes),
+                None => {}
+            }
+        }
+        WatchUpdate::Invalid { errors } => {
+            eprintln!("{} is invalid, keeping the last plan:", path);
+            for error in errors {
+                eprintln!("  {}", error);
+            }
+        }
+    }
+}
+
+/// Structured JSON for manifest errors: `{"error": {"kind": ...}, "message": ...}`
+fn manifest_error_json(e: &anyhow::Error) -> Value {
+    let error = if let Some(err) = e.downcast_ref::<bllvm::Error>() {
+        json!(err)
+    } else if let Some(err) = e.downcast_ref::<ValidationError>() {
+        json!(err)
+    } else {
+        json!({ "kind": "other" })
+    };
+
+    json!({ "error": error, "message": e.to_string() })
+}
@@ -0,0 +1,82 @@
+//! Building manifests in code instead of writing TOML
+//!
+//! ```
+//! use bllvm::builder::VersionsManifestBuilder;
+//!
+//! let manifest = VersionsManifestBuilder::new()
+//!     .add_repo("bllvm-consensus", "0.1.0", "v0.1.0")
+//!     .add_repo("bllvm-protocol", "0.1.0", "v0.1.0")
+//!     .add_requirement("bllvm-protocol", "bllvm-consensus", "^0.1")
+//!     .build()
+//!     .unwrap();
+//! assert_eq!(manifest.build_order().unwrap(), vec!["bllvm-consensus", "bllvm-protocol"]);
+//! ```
+
+use crate::versions::{Dependency, RepoVersion, VersionsManifest};
+use crate::Error;
+
+/// Assembles a [`VersionsManifest`] repo by repo
+///
+/// Mistakes are collected and reported by [`build`](Self::build), so calls
+/// can be chained.
+#[derive(Debug, Clone, Default)]
+pub struct VersionsManifestBuilder {
+    manifest: VersionsManifest,
+    errors: Vec<String>,
+}
+
+impl VersionsManifestBuilder {
+    /// Start from an empty manifest at the latest schema
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `name` at `version`, tagged `git_tag`
+    ///
+    /// Adding a repo twice is an error.
+    pub fn add_repo(mut self, name: &str, version: &str, git_tag: &str) -> Self {
+        if self.manifest.versions.contains_key(name) {
+            self.errors
+                .push(format!("Repository '{}' is added twice", name));
+            return self;
+        }
+        self.manifest.versions.insert(
+            name.to_string(),
+            RepoVersion {
+                version: version.to_string(),
+                git_tag: git_tag.to_string(),
+                ..Default::default()
+            },
+        );
+        self
+    }
+
+    /// Make `repo` require `dep` at `version_req`
+    ///
+    /// `version_req` is anything a `requires` entry accepts after the name,
+    /// e.g. `0.1.0` (exact), `^0.1` or `>=0.1.0, <0.2.0`. `repo` has to be
+    /// added first.
+    pub fn add_requirement(mut self, repo: &str, dep: &str, version_req: &str) -> Self {
+        match self.manifest.versions.get_mut(repo) {
+            Some(info) => info
+                .requires
+                .push(Dependency::from(format!("{}={}", dep, version_req))),
+            None => self.errors.push(format!(
+                "Requirement on '{}' added to '{}', which was not added",
+                dep, repo
+            )),
+        }
+        self
+    }
+
+    /// The manifest, validated like
+    /// [`from_file_strict`](VersionsManifest::from_file_strict)
+    pub fn build(self) -> Result<VersionsManifest, Error> {
+        if !self.errors.is_empty() {
+            return Err(Error::InvalidBuilder {
+                errors: self.errors,
+            });
+        }
+        self.manifest.strict()
+    }
+}
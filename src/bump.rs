@@ -0,0 +1,391 @@
+//! Bumping a repo's version, and what that does to the rest of the manifest
+
+use crate::diff::FieldChange;
+use crate::versions::{
+    parse_requirement, split_requirement, ValidationError, ValidationResult, VersionsManifest,
+};
+use crate::Error;
+use semver::{Op, Version};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A `requires` entry that a bump leaves unsatisfied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrokenRequirement {
+    /// Repo whose `requires` entry breaks
+    pub dependent: String,
+
+    /// The entry as written, e.g. `bllvm-consensus=0.1.0`
+    pub requirement: String,
+
+    /// Whether the entry pins one exact version rather than a range
+    pub exact: bool,
+}
+
+/// Outcome of [`VersionsManifest::simulate_bump`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BumpImpact {
+    /// Repository that would be bumped
+    pub repo: String,
+
+    /// Version it is declared with now
+    pub old_version: String,
+
+    /// Version it would be bumped to
+    pub new_version: String,
+
+    /// Requirements on the repo the new version no longer satisfies, by
+    /// dependent
+    pub broken: Vec<BrokenRequirement>,
+
+    /// The repo and everything depending on it, in build order
+    pub rebuild: Vec<String>,
+}
+
+impl BumpImpact {
+    /// Whether every dependent still accepts the new version
+    pub fn is_ok(&self) -> bool {
+        self.broken.is_empty()
+    }
+}
+
+impl fmt::Display for BumpImpact {
+    /// e.g.
+    ///
+    /// ```text
+    /// bllvm-consensus 0.1.0 -> 0.2.0
+    /// Breaks:
+    /// - bllvm-protocol requires 'bllvm-consensus=0.1.0' (exact pin)
+    /// Rebuild: bllvm-consensus, bllvm-protocol
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} {} -> {}",
+            self.repo, self.old_version, self.new_version
+        )?;
+        if self.broken.is_empty() {
+            writeln!(f, "Breaks nothing")?;
+        } else {
+            writeln!(f, "Breaks:")?;
+            for broken in &self.broken {
+                let kind = if broken.exact { "exact pin" } else { "range" };
+                writeln!(
+                    f,
+                    "- {} requires '{}' ({})",
+                    broken.dependent, broken.requirement, kind
+                )?;
+            }
+        }
+        writeln!(f, "Rebuild: {}", self.rebuild.join(", "))
+    }
+}
+
+impl VersionsManifest {
+    /// Check what setting `repo` to `new_version` would break, without
+    /// changing this manifest
+    ///
+    /// The bump is applied to a copy, which is validated again; each
+    /// `requires` or `optional_requires` entry on `repo` that matched before
+    /// and no longer does is reported. The rebuild set is
+    /// [`rebuild_plan`](Self::rebuild_plan) for `repo`. Unknown repos, repos
+    /// declared with several versions and versions that are not semver are
+    /// errors.
+    pub fn simulate_bump(&self, repo: &str, new_version: &str) -> Result<BumpImpact, Error> {
+        self.check_bump(repo, new_version)?;
+        let info = &self.versions[repo];
+
+        let before = mismatches_on(&self.validate(), repo);
+        let mut bumped = self.clone();
+        if let Some(info) = bumped.versions.get_mut(repo) {
+            info.version = new_version.to_string();
+        }
+        let mut broken: Vec<BrokenRequirement> = mismatches_on(&bumped.validate(), repo)
+            .into_iter()
+            .filter(|mismatch| !before.contains(mismatch))
+            .map(|(dependent, requirement)| BrokenRequirement {
+                exact: is_exact(&requirement),
+                dependent,
+                requirement,
+            })
+            .collect();
+        broken.sort_by(|a, b| (&a.dependent, &a.requirement).cmp(&(&b.dependent, &b.requirement)));
+
+        Ok(BumpImpact {
+            repo: repo.to_string(),
+            old_version: info.version.clone(),
+            new_version: new_version.to_string(),
+            broken,
+            rebuild: self.rebuild_plan(&[repo])?,
+        })
+    }
+
+    /// Set `repo` to `new_version` and carry the bump through its dependents
+    ///
+    /// `repo` gets `new_version` and a matching `git_tag` (the old version in
+    /// the tag replaced, `v<version>` if it did not contain it). Every repo
+    /// that transitively depends on it is bumped by `policy` the same way,
+    /// and their `requires` and `optional_requires` entries on bumped repos
+    /// are rewritten: exact pins, and ranges the new version falls outside,
+    /// become `=<new version>` (keeping features and `?`). With
+    /// [`BumpPolicy::None`] only the direct dependents' entries are
+    /// rewritten. Each repo is bumped once, however many paths lead to it.
+    /// Bumped repos lose `git_commit` and `sha256`, which belonged to the old
+    /// version.
+    ///
+    /// The manifest has to validate first. Returns the bumped manifest and
+    /// the changes made, in build order.
+    pub fn bump(
+        &self,
+        repo: &str,
+        new_version: &str,
+        policy: BumpPolicy,
+    ) -> Result<(VersionsManifest, BumpReport), Error> {
+        if let ValidationResult::Invalid { errors, .. } = self.validate() {
+            return Err(Error::BumpRefused { errors });
+        }
+        let new_version = self.check_bump(repo, new_version)?;
+
+        let mut bumped = self.clone();
+        let mut new_versions: BTreeMap<String, Version> = BTreeMap::new();
+        let mut changes = Vec::new();
+        for name in self.build_order()? {
+            let info = &self.versions[&name];
+            let depends_on_bumped = info
+                .all_requires()
+                .filter_map(|spec| parse_requirement(spec).ok())
+                .any(|requirement| new_versions.contains_key(&requirement.name));
+            if name != repo && !depends_on_bumped {
+                continue;
+            }
+
+            let version = if name == repo {
+                Some(new_version.clone())
+            } else {
+                Version::parse(&info.version)
+                    .ok()
+                    .and_then(|current| policy.next(&current))
+            };
+            let entry = bumped
+                .versions
+                .get_mut(&name)
+                .expect("name comes from the manifest");
+            let mut change = BumpChange {
+                repo: name.clone(),
+                version: None,
+                git_tag: None,
+                requires: Vec::new(),
+            };
+
+            let specs = entry
+                .requires
+                .iter_mut()
+                .map(|dep| &mut dep.spec)
+                .chain(entry.optional_requires.iter_mut());
+            for spec in specs {
+                let Some(rewritten) = repin(spec, &new_versions) else {
+                    continue;
+                };
+                change.requires.push(FieldChange {
+                    old: std::mem::replace(spec, rewritten.clone()),
+                    new: rewritten,
+                });
+            }
+
+            if let Some(version) = version {
+                let git_tag = bumped_tag(&entry.git_tag, &entry.version, &version.to_string());
+                change.version = Some(FieldChange {
+                    old: std::mem::replace(&mut entry.version, version.to_string()),
+                    new: version.to_string(),
+                });
+                change.git_tag = Some(FieldChange {
+                    old: std::mem::replace(&mut entry.git_tag, git_tag.clone()),
+                    new: git_tag,
+                });
+                entry.git_commit = None;
+                entry.sha256 = None;
+                new_versions.insert(name.clone(), version);
+            }
+
+            if change.version.is_some() || !change.requires.is_empty() {
+                changes.push(change);
+            }
+        }
+
+        if let ValidationResult::Invalid { errors, .. } = bumped.validate() {
+            return Err(Error::BumpInvalid {
+                repo: repo.to_string(),
+                version: new_version.to_string(),
+                errors,
+            });
+        }
+        Ok((bumped, BumpReport { changes }))
+    }
+
+    /// Check that `repo` can be set to `new_version`, returning it parsed
+    fn check_bump(&self, repo: &str, new_version: &str) -> Result<Version, Error> {
+        if !self.versions.contains_key(repo) {
+            return Err(self.unknown_repo(repo));
+        }
+        if self.multi_versions.contains_key(repo) {
+            return Err(Error::MultiVersionBump {
+                repo: repo.to_string(),
+            });
+        }
+        Version::parse(new_version).map_err(|_| Error::InvalidVersion {
+            repo: repo.to_string(),
+            version: new_version.to_string(),
+        })
+    }
+}
+
+/// How [`VersionsManifest::bump`] bumps the repos depending on the bumped one
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BumpPolicy {
+    /// Next patch version, `0.1.0 -> 0.1.1`
+    #[default]
+    Patch,
+
+    /// Next minor version, `0.1.3 -> 0.2.0`
+    Minor,
+
+    /// Keep their versions; only rewrite their `requires` on the bumped repo
+    None,
+}
+
+impl BumpPolicy {
+    /// Version a dependent at `current` is bumped to, `None` to leave it
+    fn next(self, current: &Version) -> Option<Version> {
+        match self {
+            BumpPolicy::Patch => Some(Version::new(
+                current.major,
+                current.minor,
+                current.patch + 1,
+            )),
+            BumpPolicy::Minor => Some(Version::new(current.major, current.minor + 1, 0)),
+            BumpPolicy::None => None,
+        }
+    }
+}
+
+/// Everything [`VersionsManifest::bump`] changed in one repo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BumpChange {
+    /// Repository name
+    pub repo: String,
+
+    /// Version change, `None` if only `requires` were rewritten
+    pub version: Option<FieldChange>,
+
+    /// Git tag change, alongside the version change
+    pub git_tag: Option<FieldChange>,
+
+    /// Rewritten `requires` and `optional_requires` entries
+    pub requires: Vec<FieldChange>,
+}
+
+/// Changes made by [`VersionsManifest::bump`], in build order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BumpReport {
+    pub changes: Vec<BumpChange>,
+}
+
+impl fmt::Display for BumpReport {
+    /// Markdown list for a PR description, e.g.
+    ///
+    /// ```text
+    /// - bllvm-consensus 0.1.0 -> 0.2.0 (git_tag v0.1.0 -> v0.2.0)
+    /// - bllvm-protocol 0.1.0 -> 0.1.1 (git_tag v0.1.0 -> v0.1.1); requires bllvm-consensus=0.1.0 -> bllvm-consensus=0.2.0
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for change in &self.changes {
+            write!(f, "- {}", change.repo)?;
+            if let Some(version) = &change.version {
+                write!(f, " {} -> {}", version.old, version.new)?;
+            }
+            if let Some(git_tag) = &change.git_tag {
+                write!(f, " (git_tag {} -> {})", git_tag.old, git_tag.new)?;
+            }
+            if !change.requires.is_empty() {
+                let requires: Vec<String> = change
+                    .requires
+                    .iter()
+                    .map(|spec| format!("{} -> {}", spec.old, spec.new))
+                    .collect();
+                let separator = if change.version.is_some() { ";" } else { ":" };
+                write!(f, "{} requires {}", separator, requires.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// `spec` pinned to its dependency's new version, if it needs rewriting
+///
+/// Exact pins are always moved; a range is kept while it accepts the new
+/// version.
+fn repin(spec: &str, new_versions: &BTreeMap<String, Version>) -> Option<String> {
+    let requirement = parse_requirement(spec).ok()?;
+    let version = new_versions.get(&requirement.name)?;
+    if !is_exact(spec) && requirement.req.matches(version) {
+        return None;
+    }
+
+    let (_, features, optional) = split_requirement(spec);
+    let pinned = match &requirement.alias {
+        Some(alias) => format!("{}={}@{}", alias, requirement.name, version),
+        None => format!("{}={}", requirement.name, version),
+    };
+    Some(format!(
+        "{}{}{}",
+        pinned,
+        features
+            .map(|list| format!("[{}]", list))
+            .unwrap_or_default(),
+        if optional { "?" } else { "" }
+    ))
+}
+
+/// `git_tag` for `new` given the tag of `old`: the version in it replaced,
+/// or `v<new>` if it does not contain it
+fn bumped_tag(git_tag: &str, old: &str, new: &str) -> String {
+    if git_tag.contains(old) {
+        git_tag.replacen(old, new, 1)
+    } else {
+        format!("v{}", new)
+    }
+}
+
+/// `(dependent, requirement)` of each version mismatch on `dependency`
+fn mismatches_on(result: &ValidationResult, dependency: &str) -> Vec<(String, String)> {
+    let ValidationResult::Invalid { errors, .. } = result else {
+        return Vec::new();
+    };
+    errors
+        .iter()
+        .filter_map(|error| match error {
+            ValidationError::VersionMismatch {
+                repo,
+                requirement,
+                dependency: dep,
+                ..
+            } if dep == dependency => Some((repo.clone(), requirement.clone())),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether `spec` accepts a single version, like `repo=0.1.0`
+fn is_exact(spec: &str) -> bool {
+    parse_requirement(spec).is_ok_and(|requirement| {
+        matches!(
+            requirement.req.comparators[..],
+            [ref comparator] if comparator.op == Op::Exact
+                && comparator.minor.is_some()
+                && comparator.patch.is_some()
+        )
+    })
+}
@@ -0,0 +1,124 @@
+//! Release channels: several versions of one repo in `[versions]`
+//!
+//! ```toml
+//! [versions]
+//! bllvm-node = { stable = { version = "0.1.0", git_tag = "v0.1.0" }, nightly = { version = "0.2.0-dev", git_tag = "nightly" } }
+//! ```
+//!
+//! One channel is selected when the manifest is loaded, so everything else
+//! sees a single [`RepoVersion`] per repo in
+//! [`versions`](VersionsManifest::versions).
+
+use crate::versions::{RepoVersion, VersionsManifest};
+use crate::Error;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use toml::Value;
+
+/// Channel selected by [`VersionsManifest::from_file`] and `from_str`
+pub const DEFAULT_CHANNEL: &str = "stable";
+
+impl VersionsManifest {
+    /// Load versions.toml from file, selecting `channel` for repos that have channels
+    ///
+    /// Every repo with channels needs an entry for `channel`. Channels are
+    /// only read from TOML; `.json` and `.yaml` files load as with
+    /// [`from_file`](Self::from_file).
+    pub fn from_file_channel<P: AsRef<Path>>(path: P, channel: &str) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("json" | "yaml" | "yml")
+        ) {
+            return Self::from_file(path);
+        }
+
+        let source = path.display().to_string();
+        let content = std::fs::read_to_string(path).map_err(|e| Error::Io {
+            path: source.clone(),
+            message: e.to_string(),
+        })?;
+        Self::parse_channel(&content, &source, channel)
+    }
+
+    /// Manifest with `channel` selected for every repo that has channels
+    ///
+    /// Repos without channels are the same on every channel. A repo with
+    /// channels but none named `channel` is an error.
+    pub fn resolve_channel(&self, channel: &str) -> Result<VersionsManifest, Error> {
+        let mut resolved = self.clone();
+        resolved
+            .select_channel(channel)
+            .map_err(|(repo, reason)| Error::UnknownChannel { repo, reason })?;
+        Ok(resolved)
+    }
+
+    /// Copy each repo's `channel` entry into `versions`
+    ///
+    /// On failure returns the first repo (by name) without that channel and
+    /// a description of what it has instead.
+    pub(crate) fn select_channel(&mut self, channel: &str) -> Result<(), (String, String)> {
+        let mut repos: Vec<&String> = self.channels.keys().collect();
+        repos.sort();
+        for repo in repos {
+            let by_channel = &self.channels[repo];
+            let Some(info) = by_channel.get(channel) else {
+                let available: Vec<&str> = by_channel.keys().map(String::as_str).collect();
+                return Err((
+                    repo.clone(),
+                    format!(
+                        "no '{}' channel (channels: {})",
+                        channel,
+                        available.join(", ")
+                    ),
+                ));
+            };
+            self.versions.insert(repo.clone(), info.clone());
+        }
+        Ok(())
+    }
+}
+
+/// Take the repos listing channels out of `[versions]`, by repo then channel
+pub(crate) fn take_channels(
+    versions: &mut toml::Table,
+    source: &str,
+) -> Result<HashMap<String, BTreeMap<String, RepoVersion>>, Error> {
+    let repos: Vec<String> = versions
+        .iter()
+        .filter(|(_, entry)| is_channel_entry(entry))
+        .map(|(repo, _)| repo.clone())
+        .collect();
+
+    let mut channels = HashMap::new();
+    for repo in repos {
+        let Some(Value::Table(entry)) = versions.remove(&repo) else {
+            continue;
+        };
+        let mut by_channel = BTreeMap::new();
+        for (name, value) in entry {
+            let info: RepoVersion = value.try_into().map_err(|e: toml::de::Error| {
+                Error::parse(
+                    source,
+                    Some(&repo),
+                    format!("channel '{}': {}", name, e.message().trim()),
+                )
+            })?;
+            by_channel.insert(name, info);
+        }
+        channels.insert(repo, by_channel);
+    }
+    Ok(channels)
+}
+
+/// Whether a `[versions]` entry is a table of channels rather than one version
+fn is_channel_entry(entry: &Value) -> bool {
+    match entry {
+        Value::Table(table) => {
+            !table.is_empty()
+                && !table.contains_key("version")
+                && table.values().all(Value::is_table)
+        }
+        _ => false,
+    }
+}
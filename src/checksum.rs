@@ -0,0 +1,194 @@
+//! Verifying the `sha256` digests recorded for source artifacts
+
+use crate::hash::to_hex;
+use crate::versions::{RepoVersion, VersionsManifest};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Provides the artifact (e.g. source tarball) a repo's checksum covers
+pub trait ArtifactSource {
+    /// Return the bytes of the artifact for `repo` at the pinned version
+    fn fetch(&self, repo: &str, info: &RepoVersion) -> anyhow::Result<Vec<u8>>;
+}
+
+impl<T: ArtifactSource + ?Sized> ArtifactSource for &T {
+    fn fetch(&self, repo: &str, info: &RepoVersion) -> anyhow::Result<Vec<u8>> {
+        (**self).fetch(repo, info)
+    }
+}
+
+/// Reads artifacts from files under a directory
+#[derive(Debug, Clone)]
+pub struct FileArtifactSource {
+    root: PathBuf,
+    pattern: String,
+}
+
+impl FileArtifactSource {
+    /// Read `<root>/<repo>-<version>.tar.gz`
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+            pattern: "{repo}-{version}.tar.gz".to_string(),
+        }
+    }
+
+    /// Use another file name under the root
+    ///
+    /// `{repo}`, `{version}` and `{git_tag}` are replaced with the repo's values.
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = pattern.into();
+        self
+    }
+}
+
+impl ArtifactSource for FileArtifactSource {
+    fn fetch(&self, repo: &str, info: &RepoVersion) -> anyhow::Result<Vec<u8>> {
+        let file = self
+            .pattern
+            .replace("{repo}", repo)
+            .replace("{version}", &info.version)
+            .replace("{git_tag}", &info.git_tag);
+        let path = self.root.join(file);
+        std::fs::read(&path).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to read artifact of '{}' ({}): {}",
+                repo,
+                path.display(),
+                e
+            )
+        })
+    }
+}
+
+/// In-memory artifact source for tests and offline use
+#[derive(Debug, Clone, Default)]
+pub struct MockArtifactSource {
+    artifacts: HashMap<String, Vec<u8>>,
+}
+
+impl MockArtifactSource {
+    /// Create a source with no artifacts
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `repo`'s artifact `content`
+    pub fn with_artifact(mut self, repo: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.artifacts.insert(repo.to_string(), content.into());
+        self
+    }
+}
+
+impl ArtifactSource for MockArtifactSource {
+    fn fetch(&self, repo: &str, _info: &RepoVersion) -> anyhow::Result<Vec<u8>> {
+        self.artifacts
+            .get(repo)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No artifact for '{}'", repo))
+    }
+}
+
+/// An artifact whose digest differs from the recorded `sha256`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChecksumMismatch {
+    /// Repository name
+    pub repo: String,
+
+    /// Digest recorded in the manifest
+    pub expected: String,
+
+    /// Digest of the artifact
+    pub actual: String,
+}
+
+impl fmt::Display for ChecksumMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Checksum mismatch for '{}': expected sha256 {}, got {}",
+            self.repo, self.expected, self.actual
+        )
+    }
+}
+
+impl std::error::Error for ChecksumMismatch {}
+
+/// Outcome of [`VersionsManifest::verify_checksums`]
+///
+/// All lists are sorted by repo name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ChecksumReport {
+    /// Repos whose artifact matches the recorded digest
+    pub verified: Vec<String>,
+
+    /// Repos without a `sha256` entry
+    pub unverified: Vec<String>,
+
+    /// Repos whose artifact does not match
+    pub mismatched: Vec<ChecksumMismatch>,
+}
+
+impl ChecksumReport {
+    /// Whether no artifact mismatched (unverified repos are allowed)
+    pub fn is_ok(&self) -> bool {
+        self.mismatched.is_empty()
+    }
+
+    /// Whether every repo was verified
+    pub fn is_complete(&self) -> bool {
+        self.is_ok() && self.unverified.is_empty()
+    }
+}
+
+impl fmt::Display for ChecksumReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for repo in &self.verified {
+            writeln!(f, "verified    {}", repo)?;
+        }
+        for repo in &self.unverified {
+            writeln!(f, "unverified  {}", repo)?;
+        }
+        for mismatch in &self.mismatched {
+            writeln!(f, "MISMATCH    {}", mismatch)?;
+        }
+        Ok(())
+    }
+}
+
+impl VersionsManifest {
+    /// Compare every repo's `sha256` with the digest of its artifact
+    ///
+    /// Repos without a `sha256` are listed as unverified and their artifact
+    /// is not fetched. Digests are compared case-insensitively. Failing to
+    /// fetch an artifact is an error.
+    pub fn verify_checksums(&self, source: impl ArtifactSource) -> anyhow::Result<ChecksumReport> {
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+
+        let mut report = ChecksumReport::default();
+        for repo in repos {
+            let info = &self.versions[repo];
+            let Some(expected) = info.sha256.as_deref().filter(|s| !s.trim().is_empty()) else {
+                report.unverified.push(repo.clone());
+                continue;
+            };
+
+            let actual = to_hex(&Sha256::digest(source.fetch(repo, info)?));
+            if actual.eq_ignore_ascii_case(expected.trim()) {
+                report.verified.push(repo.clone());
+            } else {
+                report.mismatched.push(ChecksumMismatch {
+                    repo: repo.clone(),
+                    expected: expected.trim().to_string(),
+                    actual,
+                });
+            }
+        }
+
+        Ok(report)
+    }
+}
@@ -0,0 +1,179 @@
+//! Checking versions.toml against the repos' own Cargo.toml files
+
+use crate::discover::{dependency_specs, find_cargo_manifests, package_version, read_toml};
+use crate::versions::{dependency_name, VersionsManifest};
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// A disagreement between versions.toml and a repo's Cargo.toml, see
+/// [`VersionsManifest::check_against_workspaces`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Inconsistency {
+    /// No crate named after the repo was found in its checkout
+    CrateNotFound { repo: String, path: PathBuf },
+
+    /// The crate's version is not the one versions.toml declares
+    VersionMismatch {
+        repo: String,
+        manifest_version: String,
+        crate_version: String,
+    },
+
+    /// A Cargo dependency requirement excludes the version versions.toml
+    /// builds the dependency at
+    DependencyConflict {
+        repo: String,
+        dependency: String,
+        cargo_requirement: String,
+        manifest_version: String,
+    },
+}
+
+impl Inconsistency {
+    /// Repository the problem was found in
+    pub fn repo(&self) -> &str {
+        match self {
+            Inconsistency::CrateNotFound { repo, .. }
+            | Inconsistency::VersionMismatch { repo, .. }
+            | Inconsistency::DependencyConflict { repo, .. } => repo,
+        }
+    }
+}
+
+impl fmt::Display for Inconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Inconsistency::CrateNotFound { repo, path } => write!(
+                f,
+                "{}: no crate named '{}' found in {}",
+                repo,
+                repo,
+                path.display()
+            ),
+            Inconsistency::VersionMismatch {
+                repo,
+                manifest_version,
+                crate_version,
+            } => write!(
+                f,
+                "{}: versions.toml declares {} but Cargo.toml has {}",
+                repo, manifest_version, crate_version
+            ),
+            Inconsistency::DependencyConflict {
+                repo,
+                dependency,
+                cargo_requirement,
+                manifest_version,
+            } => write!(
+                f,
+                "{}: Cargo.toml requires {} {} but versions.toml pins {}",
+                repo, dependency, cargo_requirement, manifest_version
+            ),
+        }
+    }
+}
+
+impl VersionsManifest {
+    /// Compare each repo in `paths` (repo -> checkout directory) with its Cargo.toml
+    ///
+    /// The crate is the package named after the repo, either in
+    /// `<path>/Cargo.toml` or anywhere below it for a workspace checkout.
+    /// Reports crate versions that differ from the declared `version`, and
+    /// version requirements on dependencies listed in `requires` that the
+    /// dependency's declared version does not satisfy. Dependencies without
+    /// a version requirement (plain path or git dependencies) are not
+    /// checked. Results are sorted by repo; unreadable Cargo.toml files and
+    /// repos not in the manifest are errors.
+    pub fn check_against_workspaces(
+        &self,
+        paths: &HashMap<String, PathBuf>,
+    ) -> anyhow::Result<Vec<Inconsistency>> {
+        let mut repos: Vec<&String> = paths.keys().collect();
+        repos.sort();
+
+        let mut found = Vec::new();
+        for repo in repos {
+            let Some(info) = self.versions.get(repo) else {
+                anyhow::bail!("Repository '{}' is not in versions.toml", repo);
+            };
+            let path = &paths[repo];
+            let Some((manifest_path, cargo)) = find_crate(path, repo)? else {
+                found.push(Inconsistency::CrateNotFound {
+                    repo: repo.clone(),
+                    path: path.clone(),
+                });
+                continue;
+            };
+
+            let crate_version = package_version(&cargo["package"], &manifest_path, path)?;
+            if crate_version != info.version {
+                found.push(Inconsistency::VersionMismatch {
+                    repo: repo.clone(),
+                    manifest_version: info.version.clone(),
+                    crate_version,
+                });
+            }
+
+            for (dependency, spec) in dependency_specs(&cargo) {
+                let required = info
+                    .all_requires()
+                    .any(|spec| dependency_name(spec) == dependency);
+                let Some(dep_info) = self.versions.get(&dependency).filter(|_| required) else {
+                    continue;
+                };
+                let Some(cargo_requirement) = cargo_requirement(spec) else {
+                    continue;
+                };
+                let (Ok(req), Ok(version)) = (
+                    VersionReq::parse(cargo_requirement),
+                    Version::parse(&dep_info.version),
+                ) else {
+                    continue;
+                };
+                if !req.matches(&version) {
+                    found.push(Inconsistency::DependencyConflict {
+                        repo: repo.clone(),
+                        dependency,
+                        cargo_requirement: cargo_requirement.to_string(),
+                        manifest_version: dep_info.version.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+/// Cargo.toml of the package `name` at or below `dir`
+fn find_crate(dir: &Path, name: &str) -> anyhow::Result<Option<(PathBuf, Value)>> {
+    let mut manifests = Vec::new();
+    find_cargo_manifests(dir, &mut manifests)?;
+    // Shallowest first, so the checkout's own Cargo.toml wins
+    manifests.sort_by_key(|path| (path.components().count(), path.clone()));
+
+    for manifest_path in manifests {
+        let cargo = read_toml(&manifest_path)?;
+        let package_name = cargo
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(Value::as_str);
+        if package_name == Some(name) {
+            return Ok(Some((manifest_path, cargo)));
+        }
+    }
+    Ok(None)
+}
+
+/// Version requirement of a Cargo dependency spec (`"0.1"` or `{ version = "0.1" }`)
+fn cargo_requirement(spec: &Value) -> Option<&str> {
+    match spec {
+        Value::String(requirement) => Some(requirement),
+        _ => spec.get("version").and_then(Value::as_str),
+    }
+}
@@ -0,0 +1,186 @@
+//! Comparing two manifests for release notes and rebuild decisions
+
+use crate::versions::{Dependency, VersionsManifest};
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A repo that only exists on one side of a [`ManifestDiff`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DiffRepo {
+    /// Repository name
+    pub name: String,
+
+    /// Version on the side the repo exists on
+    pub version: String,
+}
+
+/// An `old -> new` change of a single field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldChange {
+    /// Value in the old manifest
+    pub old: String,
+
+    /// Value in the new manifest
+    pub new: String,
+}
+
+/// A repo present in both manifests whose entry changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepoChange {
+    /// Repository name
+    pub name: String,
+
+    /// Version change, if any
+    pub version: Option<FieldChange>,
+
+    /// Git tag change, if any
+    pub git_tag: Option<FieldChange>,
+
+    /// `requires` entries only in the new manifest, sorted
+    pub requires_added: Vec<String>,
+
+    /// `requires` entries only in the old manifest, sorted
+    pub requires_removed: Vec<String>,
+}
+
+/// Differences between two manifests, see [`VersionsManifest::diff`]
+///
+/// All lists are sorted by repo name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ManifestDiff {
+    /// Repos only in the new manifest
+    pub added: Vec<DiffRepo>,
+
+    /// Repos only in the old manifest
+    pub removed: Vec<DiffRepo>,
+
+    /// Repos whose version, git tag or requires changed
+    pub modified: Vec<RepoChange>,
+}
+
+impl ManifestDiff {
+    /// True if the manifests are equivalent
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+}
+
+impl VersionsManifest {
+    /// Compare this (old) manifest with `other` (new)
+    ///
+    /// A repo counts as modified if its version, git tag or set of `requires`
+    /// entries changed; reordering `requires` is not a change.
+    pub fn diff(&self, other: &VersionsManifest) -> ManifestDiff {
+        let names: BTreeSet<&String> = self.versions.keys().chain(other.versions.keys()).collect();
+        let mut diff = ManifestDiff::default();
+
+        for name in names {
+            match (self.versions.get(name), other.versions.get(name)) {
+                (Some(old), None) => diff.removed.push(DiffRepo {
+                    name: name.clone(),
+                    version: old.version.clone(),
+                }),
+                (None, Some(new)) => diff.added.push(DiffRepo {
+                    name: name.clone(),
+                    version: new.version.clone(),
+                }),
+                (Some(old), Some(new)) => {
+                    let field = |old: &String, new: &String| {
+                        (old != new).then(|| FieldChange {
+                            old: old.clone(),
+                            new: new.clone(),
+                        })
+                    };
+                    let old_requires: BTreeSet<&Dependency> = old.requires.iter().collect();
+                    let new_requires: BTreeSet<&Dependency> = new.requires.iter().collect();
+
+                    let change = RepoChange {
+                        name: name.clone(),
+                        version: field(&old.version, &new.version),
+                        git_tag: field(&old.full_tag(), &new.full_tag()),
+                        requires_added: new_requires
+                            .difference(&old_requires)
+                            .map(|dep| dep.to_string())
+                            .collect(),
+                        requires_removed: old_requires
+                            .difference(&new_requires)
+                            .map(|dep| dep.to_string())
+                            .collect(),
+                    };
+
+                    if change.version.is_some()
+                        || change.git_tag.is_some()
+                        || !change.requires_added.is_empty()
+                        || !change.requires_removed.is_empty()
+                    {
+                        diff.modified.push(change);
+                    }
+                }
+                (None, None) => unreachable!("name comes from one of the manifests"),
+            }
+        }
+
+        diff
+    }
+}
+
+impl fmt::Display for ManifestDiff {
+    /// Changelog-style report, e.g.
+    ///
+    /// ```text
+    /// Added:
+    /// - bllvm-sdk 0.1.0
+    /// Modified:
+    /// - bllvm-node: version 0.1.0 -> 0.2.0, requires +bllvm-sdk=0.1.0
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No changes");
+        }
+
+        if !self.added.is_empty() {
+            writeln!(f, "Added:")?;
+            for repo in &self.added {
+                writeln!(f, "- {} {}", repo.name, repo.version)?;
+            }
+        }
+
+        if !self.removed.is_empty() {
+            writeln!(f, "Removed:")?;
+            for repo in &self.removed {
+                writeln!(f, "- {} {}", repo.name, repo.version)?;
+            }
+        }
+
+        if !self.modified.is_empty() {
+            writeln!(f, "Modified:")?;
+            for change in &self.modified {
+                let mut parts = Vec::new();
+                if let Some(version) = &change.version {
+                    parts.push(format!("version {} -> {}", version.old, version.new));
+                }
+                if let Some(git_tag) = &change.git_tag {
+                    parts.push(format!("git_tag {} -> {}", git_tag.old, git_tag.new));
+                }
+                let requires: Vec<String> = change
+                    .requires_added
+                    .iter()
+                    .map(|dep| format!("+{}", dep))
+                    .chain(
+                        change
+                            .requires_removed
+                            .iter()
+                            .map(|dep| format!("-{}", dep)),
+                    )
+                    .collect();
+                if !requires.is_empty() {
+                    parts.push(format!("requires {}", requires.join(" ")));
+                }
+                writeln!(f, "- {}: {}", change.name, parts.join(", "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
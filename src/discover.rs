@@ -0,0 +1,212 @@
+//! Bootstrapping a manifest from Cargo crates on disk
+
+use crate::versions::{Dependency, RepoVersion, VersionsManifest};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use toml::Value;
+
+/// Dependency tables whose path/git entries become `requires`
+///
+/// Dev-dependencies are left out since they may point back at dependents.
+const DEPENDENCY_TABLES: [&str; 2] = ["dependencies", "build-dependencies"];
+
+/// Directories never searched for crates
+const SKIPPED_DIRS: [&str; 2] = ["target", "node_modules"];
+
+/// A crate found by [`VersionsManifest::discover`]
+struct DiscoveredCrate {
+    version: String,
+    dir: PathBuf,
+    /// Names of path and git dependencies
+    dependencies: Vec<String>,
+}
+
+impl VersionsManifest {
+    /// Build a manifest from the Cargo crates under `root`
+    ///
+    /// Every `Cargo.toml` with a `[package]` below `root` becomes a repo named
+    /// after the crate, with `git_tag = "v<version>"` and `path` set when the
+    /// crate's directory is not `<root>/<name>`. Path and git dependencies on
+    /// other discovered crates become `requires` entries pinned to their
+    /// version; other dependencies are ignored. A `version.workspace = true`
+    /// is taken from the nearest enclosing `[workspace.package]`. Two
+    /// directories declaring the same crate name are an error. `target` and
+    /// hidden directories are not searched.
+    pub fn discover(root: &Path) -> anyhow::Result<Self> {
+        let mut manifests = Vec::new();
+        find_cargo_manifests(root, &mut manifests)?;
+        manifests.sort();
+
+        let mut crates: BTreeMap<String, DiscoveredCrate> = BTreeMap::new();
+        for manifest_path in manifests {
+            let cargo = read_toml(&manifest_path)?;
+            let Some(package) = cargo.get("package") else {
+                continue;
+            };
+            let dir = manifest_path.parent().unwrap_or(root).to_path_buf();
+            let name = package
+                .get("name")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow::anyhow!("{} has no package name", manifest_path.display()))?
+                .to_string();
+            let version = package_version(package, &manifest_path, root)?;
+
+            if let Some(existing) = crates.get(&name) {
+                anyhow::bail!(
+                    "Crate '{}' is declared in both {} and {}",
+                    name,
+                    existing.dir.display(),
+                    dir.display()
+                );
+            }
+            crates.insert(
+                name,
+                DiscoveredCrate {
+                    version,
+                    dependencies: local_dependencies(&cargo),
+                    dir,
+                },
+            );
+        }
+
+        let versions: HashMap<String, RepoVersion> = crates
+            .iter()
+            .map(|(name, discovered)| {
+                let mut requires: Vec<String> = discovered
+                    .dependencies
+                    .iter()
+                    .filter(|dep| *dep != name)
+                    .filter_map(|dep| {
+                        crates
+                            .get(dep)
+                            .map(|dep_crate| format!("{}={}", dep, dep_crate.version))
+                    })
+                    .collect();
+                requires.sort();
+                requires.dedup();
+
+                let relative = discovered.dir.strip_prefix(root).unwrap_or(&discovered.dir);
+                let path = relative.to_string_lossy().replace('\\', "/");
+                let info = RepoVersion {
+                    version: discovered.version.clone(),
+                    git_tag: format!("v{}", discovered.version),
+                    requires: requires.into_iter().map(Dependency::from).collect(),
+                    path: (path != *name).then_some(path),
+                    ..Default::default()
+                };
+                (name.clone(), info)
+            })
+            .collect();
+
+        Ok(VersionsManifest {
+            versions,
+            ..Default::default()
+        })
+    }
+}
+
+/// Collect every `Cargo.toml` below `dir`
+pub(crate) fn find_cargo_manifests(dir: &Path, found: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let entry =
+            entry.map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?;
+        let path = entry.path();
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if path.is_dir() {
+            if !file_name.starts_with('.') && !SKIPPED_DIRS.contains(&file_name.as_ref()) {
+                find_cargo_manifests(&path, found)?;
+            }
+        } else if file_name == "Cargo.toml" {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_toml(path: &Path) -> anyhow::Result<Value> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+    toml::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// `version` of `[package]`, following `version.workspace = true`
+pub(crate) fn package_version(
+    package: &Value,
+    manifest_path: &Path,
+    root: &Path,
+) -> anyhow::Result<String> {
+    match package.get("version") {
+        Some(Value::String(version)) => Ok(version.clone()),
+        Some(Value::Table(table)) if table.get("workspace") == Some(&Value::Boolean(true)) => {
+            for dir in manifest_path.ancestors().skip(1) {
+                let workspace_manifest = dir.join("Cargo.toml");
+                if workspace_manifest.is_file() {
+                    let cargo = read_toml(&workspace_manifest)?;
+                    if let Some(version) = cargo
+                        .get("workspace")
+                        .and_then(|w| w.get("package"))
+                        .and_then(|p| p.get("version"))
+                        .and_then(Value::as_str)
+                    {
+                        return Ok(version.to_string());
+                    }
+                }
+                if dir == root {
+                    break;
+                }
+            }
+            anyhow::bail!(
+                "{} inherits its version but no enclosing [workspace.package] sets one",
+                manifest_path.display()
+            )
+        }
+        // Cargo defaults a missing version to 0.0.0
+        None => Ok("0.0.0".to_string()),
+        Some(_) => anyhow::bail!("{} has an invalid package version", manifest_path.display()),
+    }
+}
+
+/// Crate names of the path and git dependencies in a Cargo manifest
+fn local_dependencies(cargo: &Value) -> Vec<String> {
+    dependency_specs(cargo)
+        .into_iter()
+        .filter(|(_, spec)| spec.get("path").is_some() || spec.get("git").is_some())
+        .map(|(name, _)| name)
+        .collect()
+}
+
+/// Every dependency in a Cargo manifest with its spec
+///
+/// Covers the plain and `[target.*]` dependency tables and honours
+/// `package = "..."` renames, so the name is always the crate name.
+pub(crate) fn dependency_specs(cargo: &Value) -> Vec<(String, &Value)> {
+    let mut tables: Vec<&Value> = DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|table| cargo.get(*table))
+        .collect();
+    if let Some(Value::Table(targets)) = cargo.get("target") {
+        for target in targets.values() {
+            tables.extend(
+                DEPENDENCY_TABLES
+                    .iter()
+                    .filter_map(|table| target.get(*table)),
+            );
+        }
+    }
+
+    let mut dependencies = Vec::new();
+    for table in tables {
+        let Value::Table(table) = table else {
+            continue;
+        };
+        for (key, spec) in table {
+            let name = spec.get("package").and_then(Value::as_str).unwrap_or(key);
+            dependencies.push((name.to_string(), spec));
+        }
+    }
+    dependencies
+}
@@ -0,0 +1,136 @@
+//! Graphviz DOT export of the repository dependency graph
+
+use crate::versions::{dependency_name, is_optional, VersionsManifest};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Options for [`VersionsManifest::to_dot_with`]
+#[derive(Debug, Clone, Default)]
+pub struct DotOptions {
+    /// Graph layout direction (`TB`, `LR`, ...), Graphviz default if unset
+    pub rankdir: Option<String>,
+
+    /// Group repos into one cluster per build stage
+    ///
+    /// Ignored if the graph has a cycle, since there are no stages then.
+    pub cluster_by_stage: bool,
+
+    /// Also draw the repos that are part of a cycle in red
+    pub highlight_cycle_nodes: bool,
+}
+
+impl VersionsManifest {
+    /// Render the dependency graph as a Graphviz digraph
+    ///
+    /// Each repo is a node labelled `name@version` and each `requires` entry
+    /// is an edge from the dependent to the dependency. Edges that are part of
+    /// a cycle are drawn in red, and dependencies that are not defined in the
+    /// manifest are drawn dashed, so a broken manifest can still be rendered.
+    /// Optional dependencies that are not in the manifest are left out.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_with(&DotOptions::default())
+    }
+
+    /// Render the dependency graph as a Graphviz digraph with options
+    pub fn to_dot_with(&self, options: &DotOptions) -> String {
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+
+        let mut component_of: HashMap<String, usize> = HashMap::new();
+        for (id, component) in self.cyclic_components().into_iter().enumerate() {
+            for repo in component {
+                component_of.insert(repo, id);
+            }
+        }
+
+        let mut out = String::new();
+        out.push_str("digraph dependencies {\n");
+        if let Some(rankdir) = &options.rankdir {
+            let _ = writeln!(out, "    rankdir={};", quote(rankdir));
+        }
+        out.push_str("    node [shape=box];\n");
+
+        let stages = if options.cluster_by_stage {
+            self.build_stages().ok()
+        } else {
+            None
+        };
+        match stages {
+            // Stages only exist for acyclic graphs, so there is nothing to highlight here
+            Some(stages) => {
+                for (index, stage) in stages.iter().enumerate() {
+                    let _ = writeln!(out, "    subgraph cluster_stage_{} {{", index);
+                    let _ = writeln!(out, "        label={};", quote(&format!("Stage {}", index)));
+                    for repo in stage {
+                        let _ = writeln!(out, "    {}", self.dot_node(repo, false));
+                    }
+                    out.push_str("    }\n");
+                }
+            }
+            None => {
+                for repo in &repos {
+                    let cyclic =
+                        options.highlight_cycle_nodes && component_of.contains_key(repo.as_str());
+                    let _ = writeln!(out, "{}", self.dot_node(repo, cyclic));
+                }
+            }
+        }
+
+        let mut undefined = BTreeSet::new();
+        for repo in &repos {
+            let deps: BTreeSet<&str> = self.versions[*repo]
+                .requires
+                .iter()
+                .filter(|dep| !is_optional(dep) || self.versions.contains_key(dependency_name(dep)))
+                .map(|dep| dependency_name(dep))
+                .collect();
+            for dep in deps {
+                if !self.versions.contains_key(dep) {
+                    undefined.insert(dep);
+                }
+                let cyclic = matches!(
+                    (component_of.get(repo.as_str()), component_of.get(dep)),
+                    (Some(a), Some(b)) if a == b
+                );
+                let _ = writeln!(
+                    out,
+                    "    {} -> {}{};",
+                    quote(repo),
+                    quote(dep),
+                    if cyclic { " [color=red]" } else { "" }
+                );
+            }
+        }
+
+        for dep in undefined {
+            let _ = writeln!(
+                out,
+                "    {} [label={}, style=dashed];",
+                quote(dep),
+                quote(&format!("{} (undefined)", dep))
+            );
+        }
+
+        out.push_str("}\n");
+        out
+    }
+
+    fn dot_node(&self, repo: &str, cyclic: bool) -> String {
+        let label = format!("{}@{}", repo, self.versions[repo].version);
+        format!(
+            "    {} [label={}{}];",
+            quote(repo),
+            quote(&label),
+            if cyclic {
+                ", color=red, fontcolor=red"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+/// Quote a string as a DOT identifier
+fn quote(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
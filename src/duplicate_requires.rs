@@ -0,0 +1,60 @@
+//! A dependency listed more than once in one entry's requires
+//!
+//! Listing the same requirement twice does no harm, so it is only a warning.
+//! Two different requirements on one repo are an error because only one
+//! version of it gets built; use an alias (`alias=repo@version`, see
+//! [`crate::multi_version`]) when both versions are really needed. Entries
+//! that only apply to different targets never clash.
+
+use crate::versions::{parse_requirement, RepoVersion, Requirement};
+
+/// Two entries in `requires` and `optional_requires` that name the same
+/// dependency
+pub(crate) struct DuplicateRequirement<'a> {
+    /// Required repo, or the alias for `alias=repo@version` entries
+    pub(crate) dependency: String,
+    pub(crate) first: &'a str,
+    pub(crate) second: &'a str,
+    /// Whether both entries ask for the same versions and features
+    pub(crate) identical: bool,
+}
+
+impl RepoVersion {
+    /// Every pair of entries naming the same dependency, in the order listed
+    ///
+    /// Entries that do not parse are reported by
+    /// [`validate`](crate::versions::VersionsManifest::validate) and are
+    /// skipped here.
+    pub(crate) fn duplicate_requirements(&self) -> Vec<DuplicateRequirement<'_>> {
+        let parsed: Vec<(&str, Option<&str>, Requirement)> = self
+            .requires
+            .iter()
+            .map(|dep| (dep.spec.as_str(), dep.target.as_deref()))
+            .chain(
+                self.optional_requires
+                    .iter()
+                    .map(|spec| (spec.as_str(), None)),
+            )
+            .filter_map(|(spec, target)| Some((spec, target, parse_requirement(spec).ok()?)))
+            .collect();
+
+        let mut duplicates = Vec::new();
+        for (i, (first, first_target, a)) in parsed.iter().enumerate() {
+            for (second, second_target, b) in &parsed[i + 1..] {
+                let key = a.alias.as_ref().unwrap_or(&a.name);
+                let same_target = first_target.is_none()
+                    || second_target.is_none()
+                    || first_target == second_target;
+                if same_target && key == b.alias.as_ref().unwrap_or(&b.name) {
+                    duplicates.push(DuplicateRequirement {
+                        dependency: key.clone(),
+                        first,
+                        second,
+                        identical: a == b,
+                    });
+                }
+            }
+        }
+        duplicates
+    }
+}
@@ -0,0 +1,426 @@
+//! Error types for manifest operations
+
+use crate::versions::ValidationError;
+use serde::Serialize;
+
+/// Errors returned by the manifest APIs
+///
+/// Match on the variant instead of the message to tell e.g. a cycle from a
+/// parse error. Converts into `anyhow::Error` with `?`, and can be recovered
+/// from one with `downcast_ref::<bllvm::Error>()`.
+///
+/// APIs that run git, builds or hooks, or talk to the network (the
+/// executors, [`crate::git`], [`crate::hooks`], ...) keep returning
+/// `anyhow::Error`, since their errors are context chains around process and
+/// I/O failures rather than problems with the manifest.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, thiserror::Error)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Error {
+    /// versions.toml could not be read
+    #[error("Failed to read versions.toml ({path}): {message}")]
+    Io { path: String, message: String },
+
+    /// A manifest or lockfile could not be written
+    #[error("Failed to write {path}: {message}")]
+    Write { path: String, message: String },
+
+    /// A manifest, plan or lockfile could not be serialized
+    ///
+    /// `what` names it, e.g. `versions.toml`.
+    #[error("Failed to serialize {what}: {message}")]
+    Serialize { what: String, message: String },
+
+    /// versions.toml is not valid TOML or does not match the manifest format
+    ///
+    /// `file` is the file path, or `<inline>` for [`from_str`](crate::versions::VersionsManifest::from_str).
+    /// `line` and `column` (1-based) point at the offending value when known,
+    /// and `repo` names the `[versions]` entry it belongs to.
+    #[error(
+        "Failed to parse versions.toml ({file}){}: {message}",
+        parse_location(.line, .column, .repo)
+    )]
+    Parse {
+        #[serde(rename = "source")]
+        file: String,
+        line: Option<usize>,
+        column: Option<usize>,
+        repo: Option<String>,
+        message: String,
+    },
+
+    /// versions.toml declares a newer `schema` than this bllvm supports
+    #[error("versions.toml ({file}) uses schema {schema} but this bllvm only supports schema {latest} or older; this bllvm is too old, please upgrade it")]
+    UnsupportedSchema {
+        #[serde(rename = "source")]
+        file: String,
+        schema: u64,
+        latest: u32,
+    },
+
+    /// versions.toml sets `bllvm_min_version` above the running bllvm
+    #[error("versions.toml ({file}) requires bllvm >= {required}, you have {current}; please upgrade bllvm")]
+    BllvmTooOld {
+        #[serde(rename = "source")]
+        file: String,
+        required: String,
+        current: String,
+    },
+
+    /// The same repo key appears more than once in `[versions]`
+    #[error(
+        "Duplicate repository '{repo}' in versions.toml ({file}){}",
+        duplicate_detail(.line, .versions)
+    )]
+    DuplicateRepo {
+        #[serde(rename = "source")]
+        file: String,
+        repo: String,
+        line: Option<usize>,
+        versions: Vec<String>,
+    },
+
+    /// versions.toml has [`validate`](crate::versions::VersionsManifest::validate)
+    /// errors and was loaded strictly
+    #[error(
+        "Invalid versions.toml ({} problem(s)):\n{}",
+        .errors.len(),
+        bullet_list(.errors)
+    )]
+    InvalidManifest { errors: Vec<ValidationError> },
+
+    /// Mistakes collected by a
+    /// [`VersionsManifestBuilder`](crate::builder::VersionsManifestBuilder)
+    #[error("Invalid manifest builder: {}", .errors.join("; "))]
+    InvalidBuilder { errors: Vec<String> },
+
+    /// A repo name that is not in `[versions]`, with the closest defined
+    /// name if there is one
+    #[error(
+        "Unknown repository '{repo}'{} (valid repositories: {})",
+        did_you_mean(.suggestion),
+        .valid.join(", ")
+    )]
+    UnknownRepo {
+        repo: String,
+        suggestion: Option<String>,
+        valid: Vec<String>,
+    },
+
+    /// A group name that is not in `[groups]`
+    #[error("Unknown group '{group}' (valid groups: {})", or_none(.valid))]
+    UnknownGroup { group: String, valid: Vec<String> },
+
+    /// A group lists a repo that is not in `[versions]`
+    #[error("Group '{group}' lists repository '{repo}' which is not defined")]
+    UnknownGroupMember { group: String, repo: String },
+
+    /// A profile name that is not in `[profiles]`
+    #[error("Unknown profile '{profile}' (available profiles: {})", or_none(.available))]
+    UnknownProfile {
+        profile: String,
+        available: Vec<String>,
+    },
+
+    /// A profile overrides a repo that is not in `[versions]` without `add = true`
+    #[error("Profile '{profile}' overrides '{repo}' which is not in [versions] (set add = true to add it)")]
+    ProfileUnknownRepo { profile: String, repo: String },
+
+    /// A profile adds a repo without both `version` and `git_tag`
+    #[error("Profile '{profile}' adds '{repo}' without both version and git_tag")]
+    ProfileIncompleteRepo { profile: String, repo: String },
+
+    /// A repo with channels has none of the selected name
+    ///
+    /// `reason` says what it has instead.
+    #[error("Repository '{repo}' has {reason}")]
+    UnknownChannel { repo: String, reason: String },
+
+    /// A version that is not semver
+    #[error("Invalid version '{version}' for '{repo}' (must be semver, e.g. X.Y.Z)")]
+    InvalidVersion { repo: String, version: String },
+
+    /// A repo has neither its own `git_url` nor a `[workspace]` `git_host`
+    #[error("Repository '{repo}' has no git_url and [workspace] sets no git_host")]
+    NoGitUrl { repo: String },
+
+    /// `requires` entry is not of the form `name<constraint>[features]?`
+    ///
+    /// Returned by [`parse_requirement`](crate::versions::parse_requirement),
+    /// which does not know the repo; `reason` alone is the message.
+    #[error("{reason}")]
+    MalformedRequirement { requirement: String, reason: String },
+
+    /// `requires` entry could not be parsed
+    #[error("Repository '{repo}' has invalid requirement '{requirement}': {reason}")]
+    InvalidRequirement {
+        repo: String,
+        requirement: String,
+        reason: String,
+    },
+
+    /// `requires` entry names a repo that is not in `[versions]`, with the
+    /// closest defined name if there is one
+    #[error(
+        "Repository '{repo}' requires '{missing}' which is not defined{}",
+        suggestion.as_ref().map(|s| format!("; did you mean '{}'?", s)).unwrap_or_default()
+    )]
+    UnknownDependency {
+        repo: String,
+        missing: String,
+        suggestion: Option<String>,
+    },
+
+    /// Dependency's declared version does not satisfy a `requires` entry
+    #[error(
+        "Repository '{repo}' requires '{required}' but '{dependency}' is at version '{found}'"
+    )]
+    VersionMismatch {
+        repo: String,
+        required: String,
+        dependency: String,
+        found: String,
+    },
+
+    /// No version of a repo declared with several satisfies every dependent
+    ///
+    /// See [`resolve`](crate::versions::VersionsManifest::resolve).
+    /// `required_by` lists each dependent with its requirement.
+    #[error(
+        "No version of '{repo}' satisfies every dependent: {} (declared: {})",
+        .required_by.join(", "),
+        .declared.join(", ")
+    )]
+    NoMatchingVersion {
+        repo: String,
+        required_by: Vec<String>,
+        declared: Vec<String>,
+    },
+
+    /// Dependencies form a cycle
+    ///
+    /// The path starts and ends with the same repo, e.g. `[a, b, a]`.
+    #[error("Circular dependency detected: {}", .cycle.join(" -> "))]
+    CircularDependency { cycle: Vec<String> },
+
+    /// A yanked version is required while resolving strictly
+    ///
+    /// See [`BuildOrderOptions::strict`](crate::versions::BuildOrderOptions::strict).
+    #[error(
+        "Repository '{repo}' {version} is yanked but required by {}",
+        .required_by.join(", ")
+    )]
+    YankedDependency {
+        repo: String,
+        version: String,
+        required_by: Vec<String>,
+    },
+
+    /// [`remaining_order`](crate::versions::VersionsManifest::remaining_order)
+    /// was told `repo` is done but `dependency`, which it requires, is not
+    #[error("Cannot skip '{repo}' as done: it requires '{dependency}', which is not done")]
+    PendingDependency { repo: String, dependency: String },
+
+    /// [`schedule`](crate::versions::VersionsManifest::schedule) was given no workers
+    #[error("Number of workers must be at least 1")]
+    NoWorkers,
+
+    /// [`build_order_for_target`](crate::versions::VersionsManifest::build_order_for_target)
+    /// needs a target triple rustc knows to evaluate `cfg(...)` requirements
+    #[error("Unknown target triple '{target}' (cfg(...) requirements need one rustc knows, e.g. x86_64-unknown-linux-gnu)")]
+    UnknownTarget { target: String },
+
+    /// [`explain_order`](crate::versions::VersionsManifest::explain_order)
+    /// was asked about a repo and itself
+    #[error("Cannot order '{repo}' against itself")]
+    SelfOrder { repo: String },
+
+    /// A `[lint]` rule name that is not in [`RULES`](crate::lint::RULES)
+    #[error("Unknown lint rule '{rule}' (known: {})", .known.join(", "))]
+    UnknownLintRule { rule: String, known: Vec<String> },
+
+    /// [`bump`](crate::versions::VersionsManifest::bump) was asked to bump a
+    /// repo declared with several versions
+    #[error("Repository '{repo}' is declared with several versions; bump one entry by hand")]
+    MultiVersionBump { repo: String },
+
+    /// [`bump`](crate::versions::VersionsManifest::bump) needs a manifest
+    /// that validates
+    #[error(
+        "Refusing to bump: versions.toml has {} validation error(s):\n{}",
+        .errors.len(),
+        bullet_list(.errors)
+    )]
+    BumpRefused { errors: Vec<ValidationError> },
+
+    /// The manifest no longer validates after a
+    /// [`bump`](crate::versions::VersionsManifest::bump)
+    #[error(
+        "Bumping '{repo}' to {version} leaves {} validation error(s):\n{}",
+        .errors.len(),
+        bullet_list(.errors)
+    )]
+    BumpInvalid {
+        repo: String,
+        version: String,
+        errors: Vec<ValidationError>,
+    },
+
+    /// Two manifests of a [`ManifestSet`](crate::manifest_set::ManifestSet)
+    /// define the same thing differently
+    ///
+    /// `item` says what, e.g. `Repository 'bllvm-node'`; `first` and
+    /// `second` name the manifests.
+    #[error("{item} is defined differently in {first} and {second}")]
+    ManifestConflict {
+        item: String,
+        first: String,
+        second: String,
+    },
+
+    /// A lockfile could not be read or parsed
+    #[error("Failed to read lockfile ({path}): {message}")]
+    Lockfile { path: String, message: String },
+
+    /// The manifest changed since the lockfile was written
+    ///
+    /// `drift` has one line per added, removed or changed repo.
+    #[error("Lockfile is out of date:\n  {}", .drift.join("\n  "))]
+    LockfileOutOfDate { drift: Vec<String> },
+
+    /// A [`TagResolver`](crate::lockfile::TagResolver) failed
+    ///
+    /// `message` is the resolver's error with its context.
+    #[error("{message}")]
+    TagResolution {
+        repo: String,
+        git_tag: String,
+        message: String,
+    },
+
+    /// Any other problem [`validate`](crate::versions::VersionsManifest::validate) reports
+    #[error("{error}")]
+    Invalid { error: ValidationError },
+}
+
+/// ` at line L, column C for repository 'R'`, as far as known
+fn parse_location(line: &Option<usize>, column: &Option<usize>, repo: &Option<String>) -> String {
+    let mut location = String::new();
+    if let Some(line) = line {
+        location.push_str(&format!(" at line {}", line));
+        if let Some(column) = column {
+            location.push_str(&format!(", column {}", column));
+        }
+    }
+    if let Some(repo) = repo {
+        location.push_str(&format!(" for repository '{}'", repo));
+    }
+    location
+}
+
+/// Where a duplicate repo is and which versions it was given
+fn duplicate_detail(line: &Option<usize>, versions: &[String]) -> String {
+    let at = line
+        .map(|line| format!(" at line {}", line))
+        .unwrap_or_default();
+    if versions.len() > 1 {
+        format!("{}: defined with versions {}", at, versions.join(" and "))
+    } else {
+        format!("{}: defined more than once", at)
+    }
+}
+
+fn did_you_mean(suggestion: &Option<String>) -> String {
+    suggestion
+        .as_ref()
+        .map(|s| format!(", did you mean '{}'?", s))
+        .unwrap_or_default()
+}
+
+fn or_none(names: &[String]) -> String {
+    if names.is_empty() {
+        "none".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+fn bullet_list(errors: &[ValidationError]) -> String {
+    let lines: Vec<String> = errors.iter().map(|e| format!("  - {}", e)).collect();
+    lines.join("\n")
+}
+
+/// Another name for [`Error`], for code that imports several crates' `Error`s
+pub type ManifestError = Error;
+
+impl Error {
+    /// [`Error::Parse`] without a position
+    pub(crate) fn parse(file: &str, repo: Option<&str>, message: String) -> Self {
+        Error::Parse {
+            file: file.to_string(),
+            line: None,
+            column: None,
+            repo: repo.map(str::to_string),
+            message,
+        }
+    }
+
+    /// [`Error::Serialize`] of `what`
+    pub(crate) fn serialize(what: &str, error: impl std::fmt::Display) -> Self {
+        Error::Serialize {
+            what: what.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Name `name` as the file an error came from
+    ///
+    /// Used when content was parsed without knowing its file name.
+    pub(crate) fn with_source(mut self, name: &str) -> Self {
+        match &mut self {
+            Error::Parse { file, .. }
+            | Error::UnsupportedSchema { file, .. }
+            | Error::BllvmTooOld { file, .. }
+            | Error::DuplicateRepo { file, .. } => *file = name.to_string(),
+            _ => {}
+        }
+        self
+    }
+}
+
+impl From<ValidationError> for Error {
+    fn from(error: ValidationError) -> Self {
+        match error {
+            ValidationError::InvalidRequirement {
+                repo,
+                requirement,
+                reason,
+            } => Error::InvalidRequirement {
+                repo,
+                requirement,
+                reason,
+            },
+            ValidationError::UnknownDependency {
+                repo,
+                dependency,
+                suggestion,
+            } => Error::UnknownDependency {
+                repo,
+                missing: dependency,
+                suggestion,
+            },
+            ValidationError::VersionMismatch {
+                repo,
+                requirement,
+                dependency,
+                found,
+            } => Error::VersionMismatch {
+                repo,
+                required: requirement,
+                dependency,
+                found,
+            },
+            ValidationError::CircularDependency { cycle } => Error::CircularDependency { cycle },
+            error => Error::Invalid { error },
+        }
+    }
+}
@@ -0,0 +1,795 @@
+//! Running per-repo build commands in dependency order
+
+use crate::hooks::{BuildHooks, BuildResult, HookWarning, RepoContext};
+use crate::progress::{Progress, ProgressReporter};
+use crate::state::{BuildState, StateLock};
+use crate::versions::{dependency_name, VersionsManifest};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// One build command, see [`BuildExecutor::steps`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildStep {
+    /// Repository name
+    pub repo: String,
+
+    /// Directory the command runs in
+    pub dir: PathBuf,
+
+    /// Shell command to run
+    pub command: String,
+}
+
+impl fmt::Display for BuildStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(cd {} && {})", self.dir.display(), self.command)
+    }
+}
+
+/// A build command that did not succeed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BuildFailure {
+    /// Repository name
+    pub repo: String,
+
+    /// Why the build failed (exit status or spawn error)
+    pub reason: String,
+}
+
+/// Outcome of [`BuildExecutor::run`]
+///
+/// All lists are in build order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct BuildReport {
+    /// Repos whose build command succeeded
+    pub succeeded: Vec<String>,
+
+    /// Repos whose build command failed
+    pub failed: Vec<BuildFailure>,
+
+    /// Repos that were not built because a dependency failed or the run stopped
+    pub skipped: Vec<String>,
+
+    /// Repos not rebuilt because the state file has them as up to date
+    pub up_to_date: Vec<String>,
+
+    /// How long each build command that ran took, serialized as seconds
+    #[serde(serialize_with = "seconds")]
+    pub durations: BTreeMap<String, Duration>,
+
+    /// Problems that did not stop the run, such as a failing hook with
+    /// `on_failure = "warn"` or a state file that could not be written
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+fn seconds<S: serde::Serializer>(
+    durations: &BTreeMap<String, Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(
+        durations
+            .iter()
+            .map(|(repo, duration)| (repo, duration.as_secs_f64())),
+    )
+}
+
+impl BuildReport {
+    /// Whether every build command succeeded
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.skipped.is_empty()
+    }
+
+    /// [`durations`](Self::durations) as a JSON object of seconds per repo,
+    /// the format `bllvm stats --durations` reads
+    pub fn durations_json(&self) -> anyhow::Result<String> {
+        let mut json = serde_json::Map::new();
+        for (repo, duration) in &self.durations {
+            json.insert(repo.clone(), duration.as_secs_f64().into());
+        }
+        serde_json::to_string_pretty(&json)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize durations: {}", e))
+    }
+}
+
+impl fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.up_to_date.is_empty() {
+            writeln!(f, "Up to date: {}", list(&self.up_to_date))?;
+        }
+        writeln!(f, "Succeeded: {}", list(&self.succeeded))?;
+        for failure in &self.failed {
+            writeln!(f, "Failed: {} ({})", failure.repo, failure.reason)?;
+        }
+        if !self.skipped.is_empty() {
+            writeln!(f, "Skipped: {}", list(&self.skipped))?;
+        }
+        if !self.durations.is_empty() {
+            let durations: Vec<String> = self
+                .durations
+                .iter()
+                .map(|(repo, duration)| format!("{} {:.1}s", repo, duration.as_secs_f64()))
+                .collect();
+            writeln!(f, "Durations: {}", durations.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// A hook's outcome with any [`HookWarning`] moved to `warnings`
+fn warned(result: anyhow::Result<()>, warnings: &mut Vec<String>) -> anyhow::Result<()> {
+    match result {
+        Err(e) if e.is::<HookWarning>() => {
+            warnings.push(e.to_string());
+            Ok(())
+        }
+        result => result,
+    }
+}
+
+fn list(repos: &[String]) -> String {
+    if repos.is_empty() {
+        "none".to_string()
+    } else {
+        repos.join(", ")
+    }
+}
+
+/// Runs each repo's `build` command in its `path`, in build order
+///
+/// Repos without a `build` command have nothing to run and are treated as
+/// already built.
+#[derive(Clone)]
+pub struct BuildExecutor<'a> {
+    manifest: &'a VersionsManifest,
+    root: PathBuf,
+    keep_going: bool,
+    hooks: Option<&'a dyn BuildHooks>,
+    progress: Option<&'a dyn ProgressReporter>,
+    state_file: Option<PathBuf>,
+    force: bool,
+}
+
+impl fmt::Debug for BuildExecutor<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BuildExecutor")
+            .field("root", &self.root)
+            .field("keep_going", &self.keep_going)
+            .field("hooks", &self.hooks.is_some())
+            .field("progress", &self.progress.is_some())
+            .field("state_file", &self.state_file)
+            .field("force", &self.force)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> BuildExecutor<'a> {
+    /// Create an executor resolving repo paths against the current directory
+    pub fn new(manifest: &'a VersionsManifest) -> Self {
+        Self {
+            manifest,
+            root: PathBuf::from("."),
+            keep_going: false,
+            hooks: None,
+            progress: None,
+            state_file: None,
+            force: false,
+        }
+    }
+
+    /// Resolve repo `path`s (and repos without one) against `root`
+    pub fn root<P: AsRef<Path>>(mut self, root: P) -> Self {
+        self.root = root.as_ref().to_path_buf();
+        self
+    }
+
+    /// Keep building repos whose dependencies all succeeded after a failure
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Call `hooks` with the plan and around every build command
+    ///
+    /// Hooks run on the calling thread, also with
+    /// [`run_parallel`](Self::run_parallel). See [`BuildHooks`] for how hook
+    /// errors affect the build.
+    pub fn hooks(mut self, hooks: &'a dyn BuildHooks) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Report the progress of the run to `reporter`
+    ///
+    /// See [`crate::progress`] for the built-in reporters.
+    pub fn progress(mut self, reporter: &'a dyn ProgressReporter) -> Self {
+        self.progress = Some(reporter);
+        self
+    }
+
+    /// Skip repos the [`BuildState`] at `path` has as up to date
+    ///
+    /// Every successful build is recorded there as soon as it finishes, so an
+    /// interrupted run picks up where it stopped. The state file is locked
+    /// for the whole run; a second run on the same file fails to start.
+    pub fn state_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.state_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Rebuild repos even if the state file has them as up to date
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Build commands in the order they would run (what `--dry-run` prints)
+    ///
+    /// A repo without `path` runs in `<root>/<repo name>`, a repo in
+    /// `[replace]` in its replacement path relative to `root`; `external`
+    /// repos are skipped.
+    pub fn steps(&self) -> anyhow::Result<Vec<BuildStep>> {
+        Ok(self
+            .manifest
+            .buildable_order()?
+            .into_iter()
+            .filter_map(|repo| {
+                let info = &self.manifest.versions[&repo];
+                let command = info.build.clone()?;
+                let dir = match self.manifest.replace.get(&repo) {
+                    Some(replacement) => self.root.join(&replacement.path),
+                    None => self.root.join(info.path.as_deref().unwrap_or(&repo)),
+                };
+                Some(BuildStep { repo, dir, command })
+            })
+            .collect())
+    }
+
+    /// Run every build command
+    ///
+    /// Stops at the first failure unless [`keep_going`](Self::keep_going) is
+    /// set, in which case only repos depending (transitively) on a failed repo
+    /// are skipped. Command output goes to this process's stdout/stderr.
+    pub fn run(&self) -> anyhow::Result<BuildReport> {
+        let mut report = BuildReport::default();
+        let mut broken: HashSet<String> = HashSet::new();
+        let mut stopped = false;
+        let (mut state, up_to_date) = self.open_state()?;
+        let steps = self.steps()?;
+        let mut progress = Progress {
+            done: steps
+                .iter()
+                .filter(|step| up_to_date.contains(&step.repo))
+                .count(),
+            total: steps.len(),
+        };
+        let stages = self.start_hooks(progress, &mut report.warnings)?;
+        let waits_for = self.step_dependencies(&steps);
+
+        for step in steps {
+            if up_to_date.contains(&step.repo) {
+                report.up_to_date.push(step.repo);
+                continue;
+            }
+            let blocked = waits_for[&step.repo].iter().any(|dep| broken.contains(dep));
+            if stopped || blocked {
+                broken.insert(step.repo.clone());
+                progress.done += 1;
+                self.skipped(&step.repo, progress);
+                report.skipped.push(step.repo);
+                continue;
+            }
+
+            let context = self.context(&step, &stages);
+            let mut duration = None;
+            let result = self
+                .before_repo(&context, &mut report.warnings)
+                .and_then(|()| {
+                    self.started(&step.repo, progress);
+                    let started = Instant::now();
+                    let result = run_command(&step);
+                    duration = Some(started.elapsed());
+                    result
+                });
+            let result = self.after_repo(&context, result, &mut report.warnings);
+            progress.done += 1;
+            self.finished(&mut report, &step.repo, &result, duration, progress);
+            match result {
+                Ok(()) => {
+                    self.record(&mut state, &step.repo, &mut report);
+                    report.succeeded.push(step.repo);
+                }
+                Err(reason) => {
+                    broken.insert(step.repo.clone());
+                    report.failed.push(BuildFailure {
+                        repo: step.repo,
+                        reason,
+                    });
+                    stopped = !self.keep_going;
+                }
+            }
+        }
+
+        self.run_finished(&report);
+        Ok(report)
+    }
+
+    /// Run build commands concurrently, at most `jobs` at a time
+    ///
+    /// Each repo starts as soon as every repo it (transitively) depends on has
+    /// been built, rather than waiting for a whole stage. Output of each
+    /// command is buffered and written in one piece when it finishes. After a
+    /// failure no new builds are started (or, with
+    /// [`keep_going`](Self::keep_going), only dependents of the failed repo
+    /// are skipped), but builds already running are allowed to finish.
+    pub fn run_parallel(&self, jobs: usize) -> anyhow::Result<BuildReport> {
+        let mut scheduler = Scheduler::new(self, jobs)?;
+        let mut running = 0;
+        let (sender, receiver) = mpsc::channel();
+
+        loop {
+            while running < jobs {
+                match scheduler.next() {
+                    Some(Scheduled::Start(step)) => {
+                        let sender = sender.clone();
+                        thread::spawn(move || {
+                            let started = Instant::now();
+                            let output = run_captured(&step);
+                            let _ = sender.send((step.repo, output, started.elapsed()));
+                        });
+                        running += 1;
+                    }
+                    Some(Scheduled::Skipped(_) | Scheduled::Failed(_)) => {}
+                    None => break,
+                }
+            }
+
+            if running == 0 {
+                break;
+            }
+
+            let (repo, (result, stdout, stderr), duration) = receiver
+                .recv()
+                .map_err(|_| anyhow::anyhow!("Build worker exited unexpectedly"))?;
+            running -= 1;
+            flush_output(&repo, &stdout, &stderr);
+            let _ = scheduler.finish(&repo, result, duration);
+        }
+
+        Ok(scheduler.into_report())
+    }
+
+    /// Lock and load the state file, returning it with the repos to skip
+    ///
+    /// Entries that are out of date are dropped from the file right away.
+    fn open_state(&self) -> anyhow::Result<(Option<StateFile>, HashSet<String>)> {
+        let Some(path) = &self.state_file else {
+            return Ok((None, HashSet::new()));
+        };
+        let lock = StateLock::acquire(path)?;
+        let mut state = BuildState::load(path)?;
+        if !state.invalidate(self.manifest)?.is_empty() {
+            state.save(path)?;
+        }
+
+        let up_to_date = if self.force {
+            HashSet::new()
+        } else {
+            state.up_to_date(self.manifest)?
+        };
+        let state = StateFile {
+            path: path.clone(),
+            state,
+            _lock: lock,
+        };
+        Ok((Some(state), up_to_date))
+    }
+
+    /// Record a successful build, adding a warning to `report` if the state
+    /// file cannot be written
+    fn record(&self, state: &mut Option<StateFile>, repo: &str, report: &mut BuildReport) {
+        let Some(file) = state else {
+            return;
+        };
+        file.state.record(self.manifest, repo);
+        if let Err(e) = file.state.save(&file.path) {
+            report.warnings.push(e.to_string());
+        }
+    }
+
+    /// Run the `on_plan` hook and report the plan, returning each repo's
+    /// stage for the repo hooks
+    fn start_hooks(
+        &self,
+        progress: Progress,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<HashMap<String, usize>> {
+        if self.hooks.is_none() && self.progress.is_none() {
+            return Ok(HashMap::new());
+        }
+        let plan = self.manifest.build_plan()?;
+        if let Some(hooks) = self.hooks {
+            warned(hooks.on_plan(&plan), warnings)?;
+        }
+        if let Some(reporter) = self.progress {
+            reporter.plan_computed(&plan, progress);
+        }
+        Ok(plan
+            .repos
+            .into_iter()
+            .map(|repo| (repo.name, repo.stage))
+            .collect())
+    }
+
+    fn started(&self, repo: &str, progress: Progress) {
+        if let Some(reporter) = self.progress {
+            reporter.repo_started(repo, progress);
+        }
+    }
+
+    fn skipped(&self, repo: &str, progress: Progress) {
+        if let Some(reporter) = self.progress {
+            reporter.repo_skipped(repo, progress);
+        }
+    }
+
+    /// Record how long `repo` took (`None` if its command never ran) and
+    /// report it
+    fn finished(
+        &self,
+        report: &mut BuildReport,
+        repo: &str,
+        result: &Result<(), String>,
+        duration: Option<Duration>,
+        progress: Progress,
+    ) {
+        if let Some(duration) = duration {
+            report.durations.insert(repo.to_string(), duration);
+        }
+        if let Some(reporter) = self.progress {
+            let result = match result {
+                Ok(()) => BuildResult::Succeeded,
+                Err(reason) => BuildResult::Failed(reason.clone()),
+            };
+            reporter.repo_finished(repo, &result, duration.unwrap_or_default(), progress);
+        }
+    }
+
+    fn run_finished(&self, report: &BuildReport) {
+        if let Some(reporter) = self.progress {
+            reporter.run_finished(report);
+        }
+    }
+
+    fn context(&self, step: &BuildStep, stages: &HashMap<String, usize>) -> RepoContext {
+        let info = &self.manifest.versions[&step.repo];
+        RepoContext {
+            repo: step.repo.clone(),
+            version: info.version.clone(),
+            git_tag: info.full_tag(),
+            stage: stages.get(&step.repo).copied().unwrap_or_default(),
+            dir: step.dir.clone(),
+        }
+    }
+
+    fn before_repo(&self, context: &RepoContext, warnings: &mut Vec<String>) -> Result<(), String> {
+        match self.hooks {
+            Some(hooks) => warned(hooks.before_repo(context), warnings)
+                .map_err(|e| format!("pre-build hook failed: {}", e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Run the `after_repo` hook, whose failure fails a successful build
+    fn after_repo(
+        &self,
+        context: &RepoContext,
+        result: Result<(), String>,
+        warnings: &mut Vec<String>,
+    ) -> Result<(), String> {
+        let Some(hooks) = self.hooks else {
+            return result;
+        };
+        let build_result = match &result {
+            Ok(()) => BuildResult::Succeeded,
+            Err(reason) => BuildResult::Failed(reason.clone()),
+        };
+        match warned(hooks.after_repo(context, &build_result), warnings) {
+            Err(e) if result.is_ok() => Err(format!("post-build hook failed: {}", e)),
+            _ => result,
+        }
+    }
+
+    /// Build steps each step has to wait for
+    ///
+    /// Follows `requires` through repos without a build command, so a repo
+    /// still waits for steps behind a repo that has nothing to run.
+    fn step_dependencies(&self, steps: &[BuildStep]) -> HashMap<String, HashSet<String>> {
+        let has_step: HashSet<&str> = steps.iter().map(|step| step.repo.as_str()).collect();
+
+        steps
+            .iter()
+            .map(|step| {
+                let mut seen: HashSet<&str> = HashSet::new();
+                let mut waits_for = HashSet::new();
+                let mut queue = VecDeque::from([step.repo.as_str()]);
+                while let Some(repo) = queue.pop_front() {
+                    for dep in &self.manifest.versions[repo].requires {
+                        let dep = dependency_name(dep);
+                        if self.manifest.versions.contains_key(dep) && seen.insert(dep) {
+                            if has_step.contains(dep) {
+                                waits_for.insert(dep.to_string());
+                            }
+                            queue.push_back(dep);
+                        }
+                    }
+                }
+                (step.repo.clone(), waits_for)
+            })
+            .collect()
+    }
+}
+
+/// What [`Scheduler::next`] did with a pending step
+pub(crate) enum Scheduled {
+    /// Its dependencies are built and its `before_repo` hook passed; the
+    /// caller runs it and passes the result to [`Scheduler::finish`]
+    Start(BuildStep),
+    /// A dependency failed or the run stopped
+    Skipped(String),
+    /// Its `before_repo` hook failed
+    Failed(BuildFailure),
+}
+
+/// Scheduling state of [`BuildExecutor::run_parallel`], shared with the
+/// async executor so they only differ in how commands are run and awaited
+pub(crate) struct Scheduler<'s> {
+    executor: &'s BuildExecutor<'s>,
+    state: Option<StateFile>,
+    stages: HashMap<String, usize>,
+    /// Index of each repo in build order, for sorting the report
+    position: HashMap<String, usize>,
+    waits_for: HashMap<String, HashSet<String>>,
+    pending: Vec<BuildStep>,
+    built: HashSet<String>,
+    broken: HashSet<String>,
+    stopped: bool,
+    /// Hook contexts of the steps that were started
+    contexts: HashMap<String, RepoContext>,
+    progress: Progress,
+    report: BuildReport,
+}
+
+impl<'s> Scheduler<'s> {
+    /// Load the state file and plan the steps, running the `on_plan` hook
+    ///
+    /// Up-to-date repos are in [`up_to_date`](Self::up_to_date) right away.
+    /// Fails if `jobs` is 0.
+    pub(crate) fn new(executor: &'s BuildExecutor<'s>, jobs: usize) -> anyhow::Result<Self> {
+        if jobs == 0 {
+            anyhow::bail!("Number of parallel jobs must be at least 1");
+        }
+
+        let (state, up_to_date) = executor.open_state()?;
+        let steps = executor.steps()?;
+        let progress = Progress {
+            done: steps
+                .iter()
+                .filter(|step| up_to_date.contains(&step.repo))
+                .count(),
+            total: steps.len(),
+        };
+        let mut report = BuildReport::default();
+        let stages = executor.start_hooks(progress, &mut report.warnings)?;
+        let position = steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| (step.repo.clone(), index))
+            .collect();
+        let waits_for = executor.step_dependencies(&steps);
+
+        let (done, pending): (Vec<BuildStep>, Vec<BuildStep>) = steps
+            .into_iter()
+            .partition(|step| up_to_date.contains(&step.repo));
+        report.up_to_date = done.into_iter().map(|step| step.repo).collect();
+        let built = report.up_to_date.iter().cloned().collect();
+
+        Ok(Self {
+            executor,
+            state,
+            stages,
+            position,
+            waits_for,
+            pending,
+            built,
+            broken: HashSet::new(),
+            stopped: false,
+            contexts: HashMap::new(),
+            progress,
+            report,
+        })
+    }
+
+    /// Repos skipped because the state file has them up to date
+    pub(crate) fn up_to_date(&self) -> &[String] {
+        &self.report.up_to_date
+    }
+
+    /// Move one pending step on, or `None` if no step can move until a
+    /// running one finishes
+    ///
+    /// Steps that can no longer be built are skipped first, so nothing new
+    /// starts once the run has stopped.
+    pub(crate) fn next(&mut self) -> Option<Scheduled> {
+        let blocked = self.pending.iter().position(|step| {
+            self.stopped
+                || self.waits_for[&step.repo]
+                    .iter()
+                    .any(|dep| self.broken.contains(dep))
+        });
+        if let Some(index) = blocked {
+            let step = self.pending.remove(index);
+            self.broken.insert(step.repo.clone());
+            self.progress.done += 1;
+            self.executor.skipped(&step.repo, self.progress);
+            self.report.skipped.push(step.repo.clone());
+            return Some(Scheduled::Skipped(step.repo));
+        }
+
+        let index = self.pending.iter().position(|step| {
+            self.waits_for[&step.repo]
+                .iter()
+                .all(|dep| self.built.contains(dep))
+        })?;
+        let step = self.pending.remove(index);
+        let context = self.executor.context(&step, &self.stages);
+        if let Err(reason) = self
+            .executor
+            .before_repo(&context, &mut self.report.warnings)
+        {
+            let result = self
+                .executor
+                .after_repo(&context, Err(reason), &mut self.report.warnings);
+            self.progress.done += 1;
+            self.executor
+                .finished(&mut self.report, &step.repo, &result, None, self.progress);
+            let failure = BuildFailure {
+                repo: step.repo,
+                reason: result.unwrap_err(),
+            };
+            self.fail(failure.clone());
+            return Some(Scheduled::Failed(failure));
+        }
+        self.contexts.insert(step.repo.clone(), context);
+        self.executor.started(&step.repo, self.progress);
+        Some(Scheduled::Start(step))
+    }
+
+    /// Record the result of a step [`next`](Self::next) started, returning
+    /// it after the `after_repo` hook
+    pub(crate) fn finish(
+        &mut self,
+        repo: &str,
+        result: Result<(), String>,
+        duration: Duration,
+    ) -> Result<(), String> {
+        let result = match self.contexts.remove(repo) {
+            Some(context) => self
+                .executor
+                .after_repo(&context, result, &mut self.report.warnings),
+            None => result,
+        };
+        self.progress.done += 1;
+        self.executor.finished(
+            &mut self.report,
+            repo,
+            &result,
+            Some(duration),
+            self.progress,
+        );
+        match &result {
+            Ok(()) => {
+                self.executor
+                    .record(&mut self.state, repo, &mut self.report);
+                self.built.insert(repo.to_string());
+                self.report.succeeded.push(repo.to_string());
+            }
+            Err(reason) => self.fail(BuildFailure {
+                repo: repo.to_string(),
+                reason: reason.clone(),
+            }),
+        }
+        result
+    }
+
+    fn fail(&mut self, failure: BuildFailure) {
+        self.broken.insert(failure.repo.clone());
+        self.report.failed.push(failure);
+        self.stopped = !self.executor.keep_going;
+    }
+
+    /// The report of the finished run, in build order
+    pub(crate) fn into_report(mut self) -> BuildReport {
+        let position = &self.position;
+        self.report.succeeded.sort_by_key(|repo| position[repo]);
+        self.report
+            .failed
+            .sort_by_key(|failure| position[&failure.repo]);
+        self.report.skipped.sort_by_key(|repo| position[repo]);
+        self.executor.run_finished(&self.report);
+        self.report
+    }
+}
+
+/// A locked state file kept up to date during a run
+struct StateFile {
+    path: PathBuf,
+    state: BuildState,
+    _lock: StateLock,
+}
+
+/// Command running `script` through the platform shell in `dir`
+pub(crate) fn shell(script: &str, dir: &Path) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(script).current_dir(dir);
+    command
+}
+
+/// Run a build step with output going straight to this process
+fn run_command(step: &BuildStep) -> Result<(), String> {
+    let status = shell(&step.command, &step.dir)
+        .status()
+        .map_err(|e| format!("failed to run in {}: {}", step.dir.display(), e))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("exited with {}", status))
+    }
+}
+
+/// Run a build step, capturing its stdout and stderr
+fn run_captured(step: &BuildStep) -> (Result<(), String>, Vec<u8>, Vec<u8>) {
+    match shell(&step.command, &step.dir).output() {
+        Ok(output) if output.status.success() => (Ok(()), output.stdout, output.stderr),
+        Ok(output) => (
+            Err(format!("exited with {}", output.status)),
+            output.stdout,
+            output.stderr,
+        ),
+        Err(e) => (
+            Err(format!("failed to run in {}: {}", step.dir.display(), e)),
+            Vec::new(),
+            Vec::new(),
+        ),
+    }
+}
+
+/// Write the buffered output of one build without interleaving
+pub(crate) fn flush_output(repo: &str, stdout: &[u8], stderr: &[u8]) {
+    let mut out = std::io::stdout().lock();
+    let _ = writeln!(out, "==> {}", repo);
+    let _ = out.write_all(stdout);
+    let _ = out.flush();
+
+    if !stderr.is_empty() {
+        let mut err = std::io::stderr().lock();
+        let _ = err.write_all(stderr);
+        let _ = err.flush();
+    }
+}
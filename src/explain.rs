@@ -0,0 +1,149 @@
+//! Why the build order puts one repo before another
+//!
+//! ```text
+//! $ bllvm why bllvm-consensus bllvm-node
+//! bllvm-consensus < bllvm-node because bllvm-node requires bllvm-protocol which requires bllvm-consensus
+//! ```
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// Why [`build_order`](VersionsManifest::build_order) builds `before` first,
+/// see [`VersionsManifest::explain_order`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OrderExplanation {
+    /// `after` requires `before`, directly or through other repos
+    Required {
+        before: String,
+        after: String,
+        /// Shortest chain of requires edges, from `after` down to `before`
+        path: Vec<String>,
+    },
+    /// Neither requires the other; any order between them would do
+    Arbitrary { before: String, after: String },
+}
+
+impl std::fmt::Display for OrderExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderExplanation::Required {
+                before,
+                after,
+                path,
+            } => {
+                write!(f, "{} < {} because {}", before, after, after)?;
+                for (i, repo) in path.iter().enumerate().skip(1) {
+                    let verb = if i == 1 { "requires" } else { "which requires" };
+                    write!(f, " {} {}", verb, repo)?;
+                }
+                Ok(())
+            }
+            OrderExplanation::Arbitrary { before, after } => write!(
+                f,
+                "{} < {} is arbitrary tie-breaking: neither requires the other",
+                before, after
+            ),
+        }
+    }
+}
+
+impl VersionsManifest {
+    /// Explain why `a` and `b` come in the order they do in the build order
+    ///
+    /// The explanation is for the actual order, so asking about `b` before
+    /// `a` when `a` is required by `b` gives the same answer. Only the edges
+    /// the build order follows are considered: `requires`, not
+    /// `optional_requires`. Unknown repos are an error suggesting close
+    /// matches, as is a cycle.
+    pub fn explain_order(&self, a: &str, b: &str) -> Result<OrderExplanation, Error> {
+        for repo in [a, b] {
+            if !self.versions.contains_key(repo) {
+                return Err(self.unknown_repo(repo));
+            }
+        }
+        if a == b {
+            return Err(Error::SelfOrder {
+                repo: a.to_string(),
+            });
+        }
+
+        let deps = self.acyclic_deps()?;
+        for (before, after) in [(a, b), (b, a)] {
+            if let Some(path) = shortest_path(&deps, after, before) {
+                return Ok(OrderExplanation::Required {
+                    before: before.to_string(),
+                    after: after.to_string(),
+                    path,
+                });
+            }
+        }
+
+        let order = self.build_order()?;
+        let position = |repo: &str| order.iter().position(|r| r == repo);
+        let (before, after) = if position(a) < position(b) {
+            (a, b)
+        } else {
+            (b, a)
+        };
+        Ok(OrderExplanation::Arbitrary {
+            before: before.to_string(),
+            after: after.to_string(),
+        })
+    }
+
+    /// Every distinct chain of requires edges from `from` down to `to`
+    ///
+    /// Each path starts with `from` and ends with `to`; paths are sorted
+    /// shortest first, then by name. Empty if `from` does not depend on
+    /// `to`. Unknown repos are an error suggesting close matches, as is a
+    /// cycle.
+    pub fn explain_dependency(&self, from: &str, to: &str) -> Result<Vec<Vec<String>>, Error> {
+        for repo in [from, to] {
+            if !self.versions.contains_key(repo) {
+                return Err(self.unknown_repo(repo));
+            }
+        }
+
+        let paths = self.graph()?.paths_between(from, to)?;
+        Ok(paths
+            .into_iter()
+            .map(|path| path.into_iter().map(String::from).collect())
+            .collect())
+    }
+}
+
+/// Dependencies of `repo`, sorted by name
+fn sorted_deps<'a>(deps: &HashMap<&'a str, HashSet<&'a str>>, repo: &str) -> Vec<&'a str> {
+    let mut sorted: Vec<&str> = deps.get(repo).into_iter().flatten().copied().collect();
+    sorted.sort();
+    sorted
+}
+
+/// Shortest path from `from` to `to` along `deps`; ties go to names that
+/// sort first
+fn shortest_path(deps: &HashMap<&str, HashSet<&str>>, from: &str, to: &str) -> Option<Vec<String>> {
+    let mut previous: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(repo) = queue.pop_front() {
+        if repo == to {
+            let mut path = vec![to.to_string()];
+            let mut current = to;
+            while let Some(prev) = previous.get(current) {
+                path.push(prev.to_string());
+                current = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for dep in sorted_deps(deps, repo) {
+            if dep != from && !previous.contains_key(dep) {
+                previous.insert(dep, repo);
+                queue.push_back(dep);
+            }
+        }
+    }
+    None
+}
@@ -0,0 +1,49 @@
+//! Packages that are required but neither built nor ordered (`[external]` table)
+//!
+//! ```toml
+//! [external]
+//! secp256k1 = { version = ">=0.28" }
+//!
+//! [versions]
+//! bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", requires = ["secp256k1>=0.28.2"] }
+//! ```
+//!
+//! Unlike a `[versions]` entry with `external = true`, an `[external]`
+//! package has no tag or position in the build order; requiring it only
+//! checks that it is declared and that the ranges can agree.
+
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+
+/// One `[external]` entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExternalPackage {
+    /// Accepted versions, a semver requirement such as `>=0.28`
+    pub version: String,
+}
+
+/// Whether some release version satisfies both `a` and `b`
+///
+/// The smallest version in an intersection is the lower bound of one of
+/// the comparators, so trying each comparator's version and the versions
+/// just above it is enough. Pre-release-only overlaps are not found.
+pub(crate) fn ranges_overlap(a: &VersionReq, b: &VersionReq) -> bool {
+    let mut candidates = vec![Version::new(0, 0, 0)];
+    for comparator in a.comparators.iter().chain(&b.comparators) {
+        let (major, minor, patch) = (
+            comparator.major,
+            comparator.minor.unwrap_or(0),
+            comparator.patch.unwrap_or(0),
+        );
+        candidates.extend([
+            Version::new(major, minor, patch),
+            Version::new(major, minor, patch + 1),
+            Version::new(major, minor + 1, 0),
+            Version::new(major + 1, 0, 0),
+        ]);
+    }
+    candidates
+        .iter()
+        .any(|version| a.matches(version) && b.matches(version))
+}
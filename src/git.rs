@@ -0,0 +1,562 @@
+//! Checks against git remotes (requires the `git` feature)
+
+use crate::latest::VersionResolver;
+use crate::lockfile::TagResolver;
+use crate::versions::VersionsManifest;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::process::Command;
+use std::time::Duration;
+
+/// Result of looking up one repo's `git_tag` on its remote
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagCheck {
+    /// Repository name
+    pub repo: String,
+
+    /// Tag that was looked up
+    pub git_tag: String,
+
+    /// Commit the tag points at, `None` if the remote has no such tag or
+    /// could not be asked
+    pub commit: Option<String>,
+
+    /// Last transient failure when the remote could still not be asked
+    /// after the final attempt; whether the tag exists is then unknown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unreachable: Option<String>,
+
+    /// How many times the remote was asked, more than 1 after transient
+    /// failures
+    pub attempts: u32,
+}
+
+impl TagCheck {
+    /// Whether the tag exists on the remote
+    pub fn exists(&self) -> bool {
+        self.commit.is_some()
+    }
+
+    /// Whether the remote answered that it has no such tag
+    pub fn is_missing(&self) -> bool {
+        self.commit.is_none() && self.unreachable.is_none()
+    }
+}
+
+impl fmt::Display for TagCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.commit, &self.unreachable) {
+            (Some(commit), _) => write!(f, "{} {}: {}", self.repo, self.git_tag, commit),
+            (None, Some(reason)) => {
+                write!(
+                    f,
+                    "{} {}: unreachable ({})",
+                    self.repo, self.git_tag, reason
+                )
+            }
+            (None, None) => write!(f, "{} {}: missing", self.repo, self.git_tag),
+        }?;
+        if self.attempts > 1 {
+            write!(f, " ({} attempts)", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of [`VersionsManifest::verify_tags`], one check per repo sorted
+/// by name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct TagReport {
+    pub checks: Vec<TagCheck>,
+}
+
+impl TagReport {
+    /// Checks whose tag was not found on the remote
+    pub fn missing(&self) -> Vec<&TagCheck> {
+        self.checks
+            .iter()
+            .filter(|check| check.is_missing())
+            .collect()
+    }
+
+    /// Checks whose remote could not be asked, see [`TagCheck::unreachable`]
+    pub fn unreachable(&self) -> Vec<&TagCheck> {
+        self.checks
+            .iter()
+            .filter(|check| check.unreachable.is_some())
+            .collect()
+    }
+
+    /// Whether every tag exists
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(TagCheck::exists)
+    }
+}
+
+impl fmt::Display for TagReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            writeln!(f, "{}", check)?;
+        }
+        Ok(())
+    }
+}
+
+/// Remote URL of each repo, the input of [`VersionsManifest::verify_tags`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RemoteMap {
+    urls: BTreeMap<String, String>,
+}
+
+impl RemoteMap {
+    /// Empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// [`git_url_for`](VersionsManifest::git_url_for) of every repo in
+    /// `manifest`
+    pub fn from_manifest(manifest: &VersionsManifest) -> anyhow::Result<Self> {
+        let mut remotes = Self::new();
+        for repo in manifest.versions.keys() {
+            remotes.insert(repo, manifest.git_url_for(repo)?);
+        }
+        Ok(remotes)
+    }
+
+    /// Set the remote of `repo`, replacing any previous one
+    pub fn insert(&mut self, repo: impl Into<String>, url: impl Into<String>) {
+        self.urls.insert(repo.into(), url.into());
+    }
+
+    /// Remote of `repo`, if known
+    pub fn get(&self, repo: &str) -> Option<&str> {
+        self.urls.get(repo).map(String::as_str)
+    }
+}
+
+/// How often to retry a git operation that failed for a transient reason
+///
+/// Retry `n` waits `base_delay * 2^(n-1)`. Only [transient](is_transient)
+/// failures are retried; a tag that does not exist is an answer, not a
+/// failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Tries in total, including the first (0 counts as 1)
+    pub attempts: u32,
+
+    /// Wait before the first retry
+    pub base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    /// 3 attempts, starting with a 1 second wait
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Try once, never retry
+    pub fn none() -> Self {
+        Self {
+            attempts: 1,
+            base_delay: Duration::ZERO,
+        }
+    }
+
+    /// Wait before retry number `retry` (starting at 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+    }
+
+    /// Run `op` until it succeeds, fails for a non-transient reason or runs
+    /// out of attempts; returns the last outcome and the attempts made
+    pub(crate) fn run<T>(
+        &self,
+        mut op: impl FnMut() -> anyhow::Result<T>,
+    ) -> (anyhow::Result<T>, u32) {
+        let mut attempt = 1;
+        loop {
+            let result = op();
+            match &result {
+                Err(e) if e.is::<TransientError>() && attempt < self.attempts => {
+                    std::thread::sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                _ => return (result, attempt),
+            }
+        }
+    }
+}
+
+/// A git operation failed in a way that may succeed when retried, e.g. a
+/// dropped connection or a timeout
+///
+/// [`TagChecker`]s return it (inside `anyhow::Error`) to have the lookup
+/// retried under the [`RetryPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransientError(pub String);
+
+impl fmt::Display for TransientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for TransientError {}
+
+/// Whether git's error output describes a network problem or timeout
+pub fn is_transient(stderr: &str) -> bool {
+    const TRANSIENT: &[&str] = &[
+        "could not resolve host",
+        "temporary failure in name resolution",
+        "connection timed out",
+        "operation timed out",
+        "connection reset",
+        "connection refused",
+        "remote end hung up unexpectedly",
+        "early eof",
+        "rpc failed",
+        "gnutls recv error",
+    ];
+    let stderr = stderr.to_lowercase();
+    TRANSIENT.iter().any(|pattern| stderr.contains(pattern))
+}
+
+/// Looks up which commit a tag points at on a remote
+///
+/// [`LsRemote`] asks the remote with `git ls-remote`; implement it to check
+/// tags without network access.
+pub trait TagChecker {
+    /// Commit `git_tag` points at on `url`, `None` if it does not exist there
+    ///
+    /// Return a [`TransientError`] for failures worth retrying.
+    fn tag_commit(&self, url: &str, git_tag: &str) -> anyhow::Result<Option<String>>;
+}
+
+impl<T: TagChecker + ?Sized> TagChecker for &T {
+    fn tag_commit(&self, url: &str, git_tag: &str) -> anyhow::Result<Option<String>> {
+        (**self).tag_commit(url, git_tag)
+    }
+}
+
+/// [`TagChecker`] running `git ls-remote --tags`
+///
+/// Only a successful lookup without the tag means it does not exist.
+/// Network problems and timeouts are a [`TransientError`]; any other failure
+/// (authentication, a remote that does not exist, a git binary that cannot
+/// be run) is an error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LsRemote;
+
+impl TagChecker for LsRemote {
+    fn tag_commit(&self, url: &str, git_tag: &str) -> anyhow::Result<Option<String>> {
+        let tag_ref = format!("refs/tags/{}", git_tag);
+        let output = Command::new("git")
+            .args(["ls-remote", "--tags", url, &tag_ref])
+            .arg(format!("{}^{{}}", tag_ref))
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run git ls-remote for '{}': {}", url, e))?;
+
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if output.status.success() {
+            Ok(parse_ls_remote(
+                &String::from_utf8_lossy(&output.stdout),
+                git_tag,
+            ))
+        } else if is_transient(&stderr) {
+            Err(TransientError(format!("git ls-remote {}: {}", url, stderr.trim())).into())
+        } else {
+            anyhow::bail!("git ls-remote {}: {}", url, stderr.trim())
+        }
+    }
+}
+
+impl VersionsManifest {
+    /// Check that every repo's `git_tag` exists on its remote in `remotes`
+    ///
+    /// Uses [`LsRemote`]; see [`verify_tags_with`](Self::verify_tags_with).
+    pub fn verify_tags(
+        &self,
+        remotes: &RemoteMap,
+        retry: RetryPolicy,
+    ) -> anyhow::Result<TagReport> {
+        self.verify_tags_with(remotes, LsRemote, retry)
+    }
+
+    /// Check every repo's `git_tag` with `checker`
+    ///
+    /// Missing tags are reported in the result rather than stopping the
+    /// check; a repo without an entry in `remotes` is an error, as is any
+    /// other failure of `checker`. Transient failures are retried under
+    /// `retry`, and a tag whose remote still fails after the last attempt is
+    /// reported as [`unreachable`](TagCheck::unreachable), not missing.
+    pub fn verify_tags_with<C: TagChecker>(
+        &self,
+        remotes: &RemoteMap,
+        checker: C,
+        retry: RetryPolicy,
+    ) -> anyhow::Result<TagReport> {
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+
+        let mut checks = Vec::new();
+        for repo in repos {
+            let Some(url) = remotes.get(repo) else {
+                anyhow::bail!("No remote for repository '{}'", repo);
+            };
+            let git_tag = &self.versions[repo].full_tag();
+            let (commit, unreachable, attempts) =
+                match retry.run(|| checker.tag_commit(url, git_tag)) {
+                    (Ok(commit), attempts) => (commit, None, attempts),
+                    (Err(e), attempts) if e.is::<TransientError>() => {
+                        (None, Some(e.to_string()), attempts)
+                    }
+                    (Err(e), _) => return Err(e),
+                };
+            checks.push(TagCheck {
+                repo: repo.clone(),
+                git_tag: git_tag.clone(),
+                commit,
+                unreachable,
+                attempts,
+            });
+        }
+
+        Ok(TagReport { checks })
+    }
+}
+
+/// Resolves tags by asking each repo's remote, see
+/// [`git_url_for`](VersionsManifest::git_url_for)
+///
+/// Use it with [`VersionsManifest::resolve_commits`] to pin commits without
+/// local checkouts.
+#[derive(Debug, Clone)]
+pub struct RemoteTagResolver<'a> {
+    manifest: &'a VersionsManifest,
+}
+
+impl<'a> RemoteTagResolver<'a> {
+    /// Resolve tags on the remotes of `manifest`'s repos
+    pub fn new(manifest: &'a VersionsManifest) -> Self {
+        Self { manifest }
+    }
+}
+
+impl TagResolver for RemoteTagResolver<'_> {
+    fn resolve(&self, repo: &str, git_tag: &str) -> anyhow::Result<String> {
+        let url = self.manifest.git_url_for(repo)?;
+        LsRemote.tag_commit(&url, git_tag)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "Tag '{}' of '{}' was not found on its remote",
+                git_tag,
+                repo
+            )
+        })
+    }
+}
+
+/// Lists tags on each repo's remote, see
+/// [`git_url_for`](VersionsManifest::git_url_for)
+///
+/// Use it with [`VersionsManifest::resolve_latest`] to pin
+/// `track = "latest"` repos without local checkouts.
+#[derive(Debug, Clone)]
+pub struct RemoteVersionResolver<'a> {
+    manifest: &'a VersionsManifest,
+}
+
+impl<'a> RemoteVersionResolver<'a> {
+    /// List tags on the remotes of `manifest`'s repos
+    pub fn new(manifest: &'a VersionsManifest) -> Self {
+        Self { manifest }
+    }
+}
+
+impl VersionResolver for RemoteVersionResolver<'_> {
+    fn tags(&self, repo: &str) -> anyhow::Result<Vec<String>> {
+        let url = self.manifest.git_url_for(repo)?;
+        let output = Command::new("git")
+            .args(["ls-remote", "--tags", "--refs", &url])
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run git ls-remote for '{}': {}", url, e))?;
+
+        if !output.status.success() {
+            anyhow::bail!("Failed to list tags of '{}' on {}", repo, url);
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(_, name)| name.strip_prefix("refs/tags/"))
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// Commit `git_tag` points at in `git ls-remote` output
+///
+/// Annotated tags are listed twice; the peeled `^{}` entry is the commit.
+fn parse_ls_remote(output: &str, git_tag: &str) -> Option<String> {
+    let tag_ref = format!("refs/tags/{}", git_tag);
+    let peeled_ref = format!("{}^{{}}", tag_ref);
+
+    let mut direct = None;
+    for line in output.lines() {
+        let Some((sha, name)) = line.split_once('\t') else {
+            continue;
+        };
+        if name == peeled_ref {
+            return Some(sha.to_string());
+        }
+        if name == tag_ref {
+            direct = Some(sha.to_string());
+        }
+    }
+    direct
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ls_remote() {
+        let lightweight = "1111111111111111111111111111111111111111\trefs/tags/v0.1.0\n";
+        assert_eq!(
+            parse_ls_remote(lightweight, "v0.1.0").as_deref(),
+            Some("1111111111111111111111111111111111111111")
+        );
+
+        let annotated = "2222222222222222222222222222222222222222\trefs/tags/v0.1.0\n\
+                         3333333333333333333333333333333333333333\trefs/tags/v0.1.0^{}\n";
+        assert_eq!(
+            parse_ls_remote(annotated, "v0.1.0").as_deref(),
+            Some("3333333333333333333333333333333333333333")
+        );
+
+        assert_eq!(parse_ls_remote("", "v0.1.0"), None);
+        assert_eq!(parse_ls_remote(lightweight, "v0.1"), None);
+    }
+
+    struct KnownTags(&'static [(&'static str, &'static str)]);
+
+    impl TagChecker for KnownTags {
+        fn tag_commit(&self, url: &str, git_tag: &str) -> anyhow::Result<Option<String>> {
+            Ok(self
+                .0
+                .iter()
+                .find(|(known_url, tag)| *known_url == url && *tag == git_tag)
+                .map(|_| "1111111111111111111111111111111111111111".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_verify_tags_with_checker() {
+        let manifest = VersionsManifest::from_str(
+            r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+        )
+        .unwrap();
+        let checker = KnownTags(&[("https://example.com/consensus", "v0.1.0")]);
+
+        let mut remotes = RemoteMap::new();
+        remotes.insert("bllvm-protocol", "https://example.com/protocol");
+        let err = manifest
+            .verify_tags_with(&remotes, &checker, RetryPolicy::none())
+            .unwrap_err();
+        assert!(err.to_string().contains("'bllvm-consensus'"));
+
+        remotes.insert("bllvm-consensus", "https://example.com/consensus");
+        let report = manifest
+            .verify_tags_with(&remotes, &checker, RetryPolicy::none())
+            .unwrap();
+        assert!(!report.is_ok());
+        assert_eq!(report.missing().len(), 1);
+        assert_eq!(report.missing()[0].repo, "bllvm-protocol");
+        assert!(report
+            .to_string()
+            .contains("bllvm-protocol v0.1.0: missing"));
+    }
+
+    /// Fails transiently a number of times before answering
+    struct Flaky {
+        failures: std::cell::Cell<u32>,
+    }
+
+    impl TagChecker for Flaky {
+        fn tag_commit(&self, _url: &str, git_tag: &str) -> anyhow::Result<Option<String>> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(TransientError("Connection reset by peer".to_string()).into());
+            }
+            Ok((git_tag == "v0.1.0")
+                .then(|| "1111111111111111111111111111111111111111".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_verify_tags_retry() {
+        let manifest = VersionsManifest::from_str(
+            r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.2.0", git_tag = "v0.2.0" }
+"#,
+        )
+        .unwrap();
+        let mut remotes = RemoteMap::new();
+        remotes.insert("bllvm-consensus", "https://example.com/consensus");
+        remotes.insert("bllvm-protocol", "https://example.com/protocol");
+        let retry = RetryPolicy {
+            attempts: 3,
+            base_delay: Duration::ZERO,
+        };
+
+        // Two dropped connections, then the answer; a missing tag is not retried
+        let checker = Flaky {
+            failures: std::cell::Cell::new(2),
+        };
+        let report = manifest
+            .verify_tags_with(&remotes, &checker, retry)
+            .unwrap();
+        assert!(report.checks[0].exists());
+        assert_eq!(report.checks[0].attempts, 3);
+        assert!(!report.checks[1].exists());
+        assert_eq!(report.checks[1].attempts, 1);
+
+        // Out of attempts: reported as unreachable, not missing
+        let checker = Flaky {
+            failures: std::cell::Cell::new(3),
+        };
+        let report = manifest
+            .verify_tags_with(&remotes, &checker, retry)
+            .unwrap();
+        assert!(!report.is_ok());
+        assert!(!report.checks[0].is_missing());
+        assert_eq!(report.unreachable().len(), 1);
+        assert_eq!(report.missing()[0].repo, "bllvm-protocol");
+        assert!(report
+            .to_string()
+            .contains("v0.1.0: unreachable (Connection reset by peer) (3 attempts)"));
+
+        assert!(is_transient(
+            "fatal: unable to access 'https://example.com/': Could not resolve host: example.com"
+        ));
+        assert!(!is_transient(
+            "fatal: '/tmp/nope' does not appear to be a git repository"
+        ));
+        assert_eq!(RetryPolicy::default().delay(3), Duration::from_secs(4));
+    }
+}
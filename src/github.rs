@@ -0,0 +1,98 @@
+//! GitHub Actions build matrix from the parallel build batches
+//!
+//! [`VersionsManifest::to_github_matrix`] prints one line of JSON:
+//!
+//! ```json
+//! {"include":[
+//!   {"batch":0,"name":"batch-0","repos":["bllvm-consensus","bllvm-sdk"],"repo_list":"bllvm-consensus bllvm-sdk","needs_batch":null,"last":false},
+//!   {"batch":1,"name":"batch-1","repos":["bllvm-protocol"],"repo_list":"bllvm-protocol","needs_batch":0,"last":true}
+//! ]}
+//! ```
+//!
+//! The shape is stable: fields are only ever added, so a workflow keeps
+//! working as repos come and go. A matrix cannot make one of its jobs wait
+//! for another, so a workflow either runs the batches one after another
+//! (`max-parallel: 1`, jobs are listed in batch order) or uses `needs_batch`
+//! to wire jobs generated per batch:
+//!
+//! ```yaml
+//! jobs:
+//!   plan:
+//!     runs-on: ubuntu-latest
+//!     outputs:
+//!       matrix: ${{ steps.matrix.outputs.matrix }}
+//!     steps:
+//!       - uses: actions/checkout@v4
+//!       - id: matrix
+//!         run: echo "matrix=$(bllvm matrix versions.toml)" >> "$GITHUB_OUTPUT"
+//!   build:
+//!     needs: plan
+//!     strategy:
+//!       max-parallel: 1
+//!       matrix: ${{ fromJSON(needs.plan.outputs.matrix) }}
+//!     name: ${{ matrix.name }}
+//!     runs-on: ubuntu-latest
+//!     steps:
+//!       - run: for repo in ${{ matrix.repo_list }}; do ./build.sh "$repo"; done
+//! ```
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+/// Matrix for `strategy.matrix`, one job per build batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GithubMatrix {
+    /// Jobs in batch order
+    pub include: Vec<MatrixJob>,
+}
+
+/// One matrix job: a batch of repos that can be built concurrently
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatrixJob {
+    /// Index of the batch, 0 for repos without dependencies
+    pub batch: usize,
+
+    /// Job name, `batch-<index>`
+    pub name: String,
+
+    /// Repos to build, sorted by name
+    pub repos: Vec<String>,
+
+    /// `repos` separated by spaces, for shell steps
+    pub repo_list: String,
+
+    /// Batch that has to finish first (`batch - 1`), `null` for batch 0
+    pub needs_batch: Option<usize>,
+
+    /// Whether this is the final batch
+    pub last: bool,
+}
+
+impl VersionsManifest {
+    /// Build batches as a GitHub Actions matrix, see [`crate::github`]
+    pub fn github_matrix(&self) -> Result<GithubMatrix, Error> {
+        let batches = self.build_batches()?;
+        let count = batches.len();
+        let include = batches
+            .into_iter()
+            .enumerate()
+            .map(|(batch, repos)| MatrixJob {
+                batch,
+                name: format!("batch-{}", batch),
+                repo_list: repos.join(" "),
+                repos,
+                needs_batch: batch.checked_sub(1),
+                last: batch + 1 == count,
+            })
+            .collect();
+        Ok(GithubMatrix { include })
+    }
+
+    /// [`github_matrix`](Self::github_matrix) as single-line JSON, ready
+    /// for `$GITHUB_OUTPUT`
+    pub fn to_github_matrix(&self) -> anyhow::Result<String> {
+        serde_json::to_string(&self.github_matrix()?)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize GitHub matrix: {}", e))
+    }
+}
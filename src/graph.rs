@@ -0,0 +1,223 @@
+//! Dependency graph of a manifest, built once and queried many times
+//!
+//! Every [`VersionsManifest`] query resolves `requires` afresh; a
+//! [`DependencyGraph`] resolves them once and answers from adjacency lists:
+//!
+//! ```
+//! use bllvm::versions::VersionsManifest;
+//!
+//! let manifest = VersionsManifest::from_str(
+//!     r#"
+//! [versions]
+//! bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+//! bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+//! bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+//! "#,
+//! )?;
+//! let graph = manifest.graph()?;
+//! assert_eq!(graph.topo_order(), ["bllvm-consensus", "bllvm-protocol", "bllvm-node"]);
+//! assert_eq!(graph.dependents("bllvm-consensus")?, ["bllvm-protocol"]);
+//! # Ok::<(), bllvm::Error>(())
+//! ```
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use std::collections::HashMap;
+
+/// Resolved `requires` edges of a manifest, see [`VersionsManifest::graph`]
+///
+/// Repos are numbered by name: node id 0 is the repo that sorts first. The
+/// graph borrows the manifest, so it has to be built again after the
+/// manifest changes.
+#[derive(Debug, Clone)]
+pub struct DependencyGraph<'a> {
+    manifest: &'a VersionsManifest,
+    /// Repo of each node id
+    names: Vec<&'a str>,
+    /// Node id of each repo
+    ids: HashMap<&'a str, usize>,
+    /// Node ids each node requires, sorted
+    dependencies: Vec<Vec<usize>>,
+    /// Node ids requiring each node, sorted
+    dependents: Vec<Vec<usize>>,
+    /// Node ids of each build stage, sorted
+    stages: Vec<Vec<usize>>,
+}
+
+impl VersionsManifest {
+    /// Resolve every `requires` entry into a [`DependencyGraph`]
+    ///
+    /// Fails like [`build_order`](Self::build_order): on a requirement that
+    /// does not resolve, or on a cycle. `optional_requires` are not edges.
+    pub fn graph(&self) -> Result<DependencyGraph<'_>, Error> {
+        let deps = self.acyclic_deps()?;
+
+        let mut names: Vec<&str> = deps.keys().copied().collect();
+        names.sort();
+        let ids: HashMap<&str, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(id, repo)| (*repo, id))
+            .collect();
+
+        let mut dependencies: Vec<Vec<usize>> = names
+            .iter()
+            .map(|repo| deps[repo].iter().map(|dep| ids[dep]).collect())
+            .collect();
+        let mut dependents = vec![Vec::new(); names.len()];
+        for (id, deps) in dependencies.iter_mut().enumerate() {
+            deps.sort_unstable();
+            for &dep in deps.iter() {
+                dependents[dep].push(id);
+            }
+        }
+
+        // Level-based topological sort, as in `build_stages`
+        let mut remaining: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+        let mut stage: Vec<usize> = (0..names.len()).filter(|&id| remaining[id] == 0).collect();
+        let mut stages = Vec::new();
+        while !stage.is_empty() {
+            let mut next = Vec::new();
+            for &id in &stage {
+                for &dependent in &dependents[id] {
+                    remaining[dependent] -= 1;
+                    if remaining[dependent] == 0 {
+                        next.push(dependent);
+                    }
+                }
+            }
+            next.sort_unstable();
+            stages.push(std::mem::replace(&mut stage, next));
+        }
+
+        Ok(DependencyGraph {
+            manifest: self,
+            names,
+            ids,
+            dependencies,
+            dependents,
+            stages,
+        })
+    }
+}
+
+impl<'a> DependencyGraph<'a> {
+    /// Number of repos
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether the manifest has no repos
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// Every repo, sorted by name, i.e. indexed by node id
+    pub fn repos(&self) -> &[&'a str] {
+        &self.names
+    }
+
+    /// Node id of `repo`, `None` if it is not in the manifest
+    pub fn id(&self, repo: &str) -> Option<usize> {
+        self.ids.get(repo).copied()
+    }
+
+    /// Repo with node id `id`
+    ///
+    /// Panics if `id` is not below [`len`](Self::len).
+    pub fn repo(&self, id: usize) -> &'a str {
+        self.names[id]
+    }
+
+    /// Same as [`VersionsManifest::build_order`]
+    pub fn topo_order(&self) -> Vec<&'a str> {
+        self.stages
+            .iter()
+            .flatten()
+            .map(|&id| self.names[id])
+            .collect()
+    }
+
+    /// Same as [`VersionsManifest::build_stages`]
+    pub fn stages(&self) -> Vec<Vec<&'a str>> {
+        self.stages
+            .iter()
+            .map(|stage| self.repos_of(stage))
+            .collect()
+    }
+
+    /// Repos `repo` requires directly, sorted by name
+    ///
+    /// Unknown repos are an error suggesting close matches.
+    pub fn dependencies(&self, repo: &str) -> Result<Vec<&'a str>, Error> {
+        Ok(self.repos_of(&self.dependencies[self.node(repo)?]))
+    }
+
+    /// Repos requiring `repo` directly, sorted by name
+    ///
+    /// Unknown repos are an error suggesting close matches.
+    pub fn dependents(&self, repo: &str) -> Result<Vec<&'a str>, Error> {
+        Ok(self.repos_of(&self.dependents[self.node(repo)?]))
+    }
+
+    /// Every distinct chain of requires edges from `from` down to `to`
+    ///
+    /// Same as [`VersionsManifest::explain_dependency`]: paths start with
+    /// `from`, end with `to` and are sorted shortest first, then by name.
+    pub fn paths_between(&self, from: &str, to: &str) -> Result<Vec<Vec<&'a str>>, Error> {
+        let from = self.node(from)?;
+        let to = self.node(to)?;
+
+        // Only walk into repos that lead to `to`
+        let mut leads_to = vec![false; self.len()];
+        leads_to[to] = true;
+        let mut queue = vec![to];
+        while let Some(id) = queue.pop() {
+            for &dependent in &self.dependents[id] {
+                if !leads_to[dependent] {
+                    leads_to[dependent] = true;
+                    queue.push(dependent);
+                }
+            }
+        }
+
+        let mut paths = Vec::new();
+        if from != to && leads_to[from] {
+            self.extend_paths(to, &leads_to, &mut vec![from], &mut paths);
+        }
+        paths.sort_by(|a: &Vec<&str>, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+        Ok(paths)
+    }
+
+    /// Extend `path` to `to` in every possible way, collecting the paths
+    fn extend_paths(
+        &self,
+        to: usize,
+        leads_to: &[bool],
+        path: &mut Vec<usize>,
+        paths: &mut Vec<Vec<&'a str>>,
+    ) {
+        let id = *path.last().expect("path starts with the first repo");
+        if id == to {
+            paths.push(self.repos_of(path));
+            return;
+        }
+        for &dep in &self.dependencies[id] {
+            if leads_to[dep] {
+                path.push(dep);
+                self.extend_paths(to, leads_to, path, paths);
+                path.pop();
+            }
+        }
+    }
+
+    /// Node id of `repo`, or the manifest's unknown repo error
+    fn node(&self, repo: &str) -> Result<usize, Error> {
+        self.id(repo)
+            .ok_or_else(|| self.manifest.unknown_repo(repo))
+    }
+
+    fn repos_of(&self, ids: &[usize]) -> Vec<&'a str> {
+        ids.iter().map(|&id| self.names[id]).collect()
+    }
+}
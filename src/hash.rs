@@ -0,0 +1,67 @@
+//! Content hashes of manifests (SHA-256)
+
+use crate::versions::{Dependency, VersionsManifest};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Version of the canonical form hashed by [`VersionsManifest::content_hash`]
+///
+/// Bump it whenever the set of hashed fields changes, so old cache keys are
+/// not mistaken for new ones.
+const CONTENT_HASH_FORMAT: &str = "bllvm-manifest-v1";
+
+impl VersionsManifest {
+    /// Hex SHA-256 of everything in the manifest that affects a build
+    ///
+    /// Covers each repo's name, version, full git tag, git commit, `requires`,
+    /// `optional_requires`, features and build command. Key order,
+    /// whitespace, comments and the order of list entries do not change the
+    /// hash, and an empty `git_commit` is the same as none. Metadata and
+    /// profiles are not covered; hash [`with_profile`](Self::with_profile)'s
+    /// result to include a profile.
+    pub fn content_hash(&self) -> String {
+        #[derive(Serialize)]
+        struct Canonical<'a> {
+            version: &'a str,
+            git_tag: String,
+            git_commit: Option<&'a str>,
+            requires: Vec<&'a Dependency>,
+            optional_requires: Vec<&'a String>,
+            features: Vec<&'a String>,
+            build: Option<&'a str>,
+        }
+
+        fn sorted<T: Ord>(list: &[T]) -> Vec<&T> {
+            let mut list: Vec<&T> = list.iter().collect();
+            list.sort_unstable();
+            list
+        }
+
+        let repos: BTreeMap<&str, Canonical> = self
+            .versions
+            .iter()
+            .map(|(name, info)| {
+                let canonical = Canonical {
+                    version: &info.version,
+                    git_tag: info.full_tag(),
+                    git_commit: info.git_commit.as_deref().filter(|c| !c.is_empty()),
+                    requires: sorted(&info.requires),
+                    optional_requires: sorted(&info.optional_requires),
+                    features: sorted(&info.features),
+                    build: info.build.as_deref(),
+                };
+                (name.as_str(), canonical)
+            })
+            .collect();
+
+        let mut content = format!("{}\n", CONTENT_HASH_FORMAT).into_bytes();
+        content.extend(serde_json::to_vec(&repos).expect("canonical manifest serializes"));
+        to_hex(&Sha256::digest(&content))
+    }
+}
+
+/// Lowercase hex encoding of `bytes`
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,241 @@
+//! Callbacks around each repo build, see [`BuildExecutor::hooks`](crate::executor::BuildExecutor::hooks)
+
+use crate::plan::BuildPlan;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The repo a hook is called for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepoContext {
+    /// Repository name
+    pub repo: String,
+
+    /// Version being built
+    pub version: String,
+
+    /// Full git tag being built
+    pub git_tag: String,
+
+    /// Build stage of the repo (0 for repos without dependencies)
+    pub stage: usize,
+
+    /// Directory the build command runs in
+    pub dir: PathBuf,
+}
+
+/// How one repo's build command ended
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum BuildResult {
+    Succeeded,
+
+    /// Failed with the given reason (exit status or spawn error)
+    Failed(String),
+}
+
+impl BuildResult {
+    pub fn is_success(&self) -> bool {
+        matches!(self, BuildResult::Succeeded)
+    }
+}
+
+/// Callbacks the build executor runs around each repo
+///
+/// All methods default to doing nothing. An error from
+/// [`on_plan`](Self::on_plan) stops the run before anything is built; an
+/// error from [`before_repo`](Self::before_repo) or
+/// [`after_repo`](Self::after_repo) fails that repo like a failing build
+/// command. Hooks that should only warn return a [`HookWarning`], which ends
+/// up in [`BuildReport::warnings`](crate::executor::BuildReport::warnings).
+pub trait BuildHooks {
+    /// Called once with the full plan before the first build
+    fn on_plan(&self, _plan: &BuildPlan) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called before a repo's build command runs
+    fn before_repo(&self, _repo: &RepoContext) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called after a repo's build command finished, successfully or not
+    fn after_repo(&self, _repo: &RepoContext, _result: &BuildResult) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// A hook failed but the build carries on
+///
+/// [`BuildHooks`] return it (inside `anyhow::Error`) to have the failure
+/// reported as a warning instead of failing the repo or the run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookWarning(pub String);
+
+impl std::fmt::Display for HookWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for HookWarning {}
+
+/// What a failing hook command does to the build
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnFailure {
+    /// Fail the repo (or, for `on_plan`, the whole run)
+    #[default]
+    Fail,
+
+    /// Report a [`HookWarning`] and carry on
+    Warn,
+}
+
+/// A shell command run as a hook
+///
+/// Written as just the command (`post_build = "./notify.sh"`) or as a table
+/// to set the failure policy
+/// (`post_build = { command = "./notify.sh", on_failure = "warn" }`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "HookSpec")]
+pub struct HookCommand {
+    /// Command run through the platform shell
+    pub command: String,
+
+    #[serde(default)]
+    pub on_failure: OnFailure,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HookSpec {
+    Command(String),
+    Detailed {
+        command: String,
+        #[serde(default)]
+        on_failure: OnFailure,
+    },
+}
+
+impl From<HookSpec> for HookCommand {
+    fn from(spec: HookSpec) -> Self {
+        match spec {
+            HookSpec::Command(command) => HookCommand {
+                command,
+                on_failure: OnFailure::default(),
+            },
+            HookSpec::Detailed {
+                command,
+                on_failure,
+            } => HookCommand {
+                command,
+                on_failure,
+            },
+        }
+    }
+}
+
+/// Shell hooks declared in versions.toml (`[hooks]` table)
+///
+/// Repo hooks run in the repo's build directory with `BLLVM_REPO`,
+/// `BLLVM_VERSION`, `BLLVM_GIT_TAG`, `BLLVM_STAGE` and `BLLVM_REPO_DIR` set;
+/// `post_build` also gets `BLLVM_BUILD_STATUS` (`succeeded` or `failed`).
+/// `on_plan` runs in the current directory with `BLLVM_REPOS`, the repos to
+/// build in order separated by spaces.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HooksConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_plan: Option<HookCommand>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pre_build: Option<HookCommand>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_build: Option<HookCommand>,
+}
+
+impl BuildHooks for HooksConfig {
+    fn on_plan(&self, plan: &BuildPlan) -> anyhow::Result<()> {
+        let Some(hook) = &self.on_plan else {
+            return Ok(());
+        };
+        let repos: Vec<&str> = plan.repos.iter().map(|repo| repo.name.as_str()).collect();
+        run_hook(
+            "on_plan",
+            hook,
+            None,
+            Path::new("."),
+            &[("BLLVM_REPOS", repos.join(" "))],
+        )
+    }
+
+    fn before_repo(&self, repo: &RepoContext) -> anyhow::Result<()> {
+        let Some(hook) = &self.pre_build else {
+            return Ok(());
+        };
+        run_hook(
+            "pre_build",
+            hook,
+            Some(&repo.repo),
+            &repo.dir,
+            &repo_env(repo),
+        )
+    }
+
+    fn after_repo(&self, repo: &RepoContext, result: &BuildResult) -> anyhow::Result<()> {
+        let Some(hook) = &self.post_build else {
+            return Ok(());
+        };
+        let mut env = repo_env(repo);
+        let status = if result.is_success() {
+            "succeeded"
+        } else {
+            "failed"
+        };
+        env.push(("BLLVM_BUILD_STATUS", status.to_string()));
+        run_hook("post_build", hook, Some(&repo.repo), &repo.dir, &env)
+    }
+}
+
+fn repo_env(repo: &RepoContext) -> Vec<(&'static str, String)> {
+    vec![
+        ("BLLVM_REPO", repo.repo.clone()),
+        ("BLLVM_VERSION", repo.version.clone()),
+        ("BLLVM_GIT_TAG", repo.git_tag.clone()),
+        ("BLLVM_STAGE", repo.stage.to_string()),
+        ("BLLVM_REPO_DIR", repo.dir.display().to_string()),
+    ]
+}
+
+/// Run `hook` in `dir`, applying its failure policy
+fn run_hook(
+    name: &str,
+    hook: &HookCommand,
+    repo: Option<&str>,
+    dir: &Path,
+    env: &[(&str, String)],
+) -> anyhow::Result<()> {
+    let mut command = crate::executor::shell(&hook.command, dir);
+    command.envs(env.iter().map(|(key, value)| (key, value)));
+    let result = run(&mut command);
+
+    let Err(reason) = result else {
+        return Ok(());
+    };
+    let target = repo
+        .map(|repo| format!(" for '{}'", repo))
+        .unwrap_or_default();
+    match hook.on_failure {
+        OnFailure::Fail => anyhow::bail!("{} hook{} {}", name, target, reason),
+        OnFailure::Warn => Err(HookWarning(format!("{} hook{} {}", name, target, reason)).into()),
+    }
+}
+
+fn run(command: &mut Command) -> Result<(), String> {
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(format!("exited with {}", status)),
+        Err(e) => Err(format!("failed to run: {}", e)),
+    }
+}
@@ -0,0 +1,72 @@
+//! Composing one manifest from several files with `include`
+//!
+//! ```toml
+//! include = ["teams/consensus.toml", "teams/node.toml"]
+//!
+//! [versions]
+//! governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+//! ```
+
+use crate::manifest_set::ManifestSet;
+use crate::versions::VersionsManifest;
+use crate::Error;
+use std::path::{Path, PathBuf};
+
+impl VersionsManifest {
+    /// Merge the manifests listed in [`include`](Self::include) into this one
+    ///
+    /// `path` is the file this manifest was read from. Included files may
+    /// include others; including a file that is already being loaded is an
+    /// error.
+    pub(crate) fn with_includes(self, path: &Path) -> Result<Self, Error> {
+        self.with_includes_from(path, &mut Vec::new())
+    }
+
+    fn with_includes_from(
+        mut self,
+        path: &Path,
+        loading: &mut Vec<PathBuf>,
+    ) -> Result<Self, Error> {
+        if self.include.is_empty() {
+            return Ok(self);
+        }
+
+        let source = path.display().to_string();
+        let key = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        loading.push(key);
+
+        let includes = std::mem::take(&mut self.include);
+        let schema = self.schema;
+        let bllvm_min_version = self.bllvm_min_version.clone();
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut set = ManifestSet::new();
+        set.add(source.clone(), self);
+        for include in &includes {
+            let included = base.join(include);
+            let key = included.canonicalize().unwrap_or_else(|_| included.clone());
+            if loading.contains(&key) {
+                let chain: Vec<String> = loading
+                    .iter()
+                    .chain([&key])
+                    .map(|path| path.display().to_string())
+                    .collect();
+                return Err(Error::parse(
+                    &source,
+                    None,
+                    format!("include cycle: {}", chain.join(" -> ")),
+                ));
+            }
+            let manifest =
+                VersionsManifest::read_file(&included)?.with_includes_from(&included, loading)?;
+            set.add(included.display().to_string(), manifest);
+        }
+        loading.pop();
+
+        let mut merged = set
+            .merged()
+            .map_err(|e| Error::parse(&source, None, e.to_string()))?;
+        merged.schema = schema;
+        merged.bllvm_min_version = bllvm_min_version;
+        Ok(merged)
+    }
+}
@@ -0,0 +1,185 @@
+//! `track = "latest"`: follow the newest release tag of a repo
+//!
+//! ```toml
+//! [versions]
+//! bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", tag_prefix = "consensus-", track = "latest" }
+//! bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1"] }
+//! ```
+//!
+//! `version` and `git_tag` stay the last known pin, so the manifest is valid
+//! as written. [`resolve_latest`](VersionsManifest::resolve_latest) moves
+//! the pin to the newest tag; `bllvm` does this when loading a manifest,
+//! asking each repo's remote for its tags.
+
+use crate::versions::{parse_requirement, RepoVersion, VersionsManifest};
+use crate::Error;
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
+
+/// `track` value that follows the newest release tag
+pub const LATEST: &str = "latest";
+
+/// Lists the release tags of a repo
+///
+/// `git::RemoteVersionResolver` (with the `git` feature) asks each repo's
+/// remote; [`MockVersionResolver`] serves fixed tags for tests and offline
+/// use.
+pub trait VersionResolver {
+    /// Every tag of `repo`, as git knows it (including any `tag_prefix`)
+    fn tags(&self, repo: &str) -> anyhow::Result<Vec<String>>;
+}
+
+impl<T: VersionResolver + ?Sized> VersionResolver for &T {
+    fn tags(&self, repo: &str) -> anyhow::Result<Vec<String>> {
+        (**self).tags(repo)
+    }
+}
+
+/// In-memory version resolver for tests and offline use
+#[derive(Debug, Clone, Default)]
+pub struct MockVersionResolver {
+    tags: HashMap<String, Vec<String>>,
+}
+
+impl MockVersionResolver {
+    /// Create a resolver with no known tags
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `repo` list `tags`
+    pub fn with_tags(mut self, repo: &str, tags: &[&str]) -> Self {
+        self.tags
+            .entry(repo.to_string())
+            .or_default()
+            .extend(tags.iter().map(|tag| tag.to_string()));
+        self
+    }
+}
+
+impl VersionResolver for MockVersionResolver {
+    fn tags(&self, repo: &str) -> anyhow::Result<Vec<String>> {
+        self.tags
+            .get(repo)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("No tags known for '{}'", repo))
+    }
+}
+
+impl RepoVersion {
+    /// Whether this entry follows the newest release, see [`crate::latest`]
+    pub fn is_latest(&self) -> bool {
+        self.track.as_deref() == Some(LATEST)
+    }
+
+    /// Version and `git_tag` of a tag, if it is a release of this repo
+    ///
+    /// The tag has to start with `tag_prefix`; the rest, without a leading
+    /// `v`, has to be a semver version without pre-release part.
+    fn release(&self, tag: &str) -> Option<(Version, String)> {
+        let git_tag = tag.strip_prefix(self.tag_prefix.as_deref().unwrap_or(""))?;
+        let version = Version::parse(git_tag.strip_prefix('v').unwrap_or(git_tag)).ok()?;
+        version
+            .pre
+            .is_empty()
+            .then(|| (version, git_tag.to_string()))
+    }
+}
+
+impl VersionsManifest {
+    /// Whether any repo is declared with `track = "latest"`
+    pub fn has_latest(&self) -> bool {
+        self.versions.values().any(|info| info.track.is_some())
+    }
+
+    /// Pin every `track = "latest"` repo to the newest release `resolver` lists
+    ///
+    /// Each such repo gets the highest released version accepted by the
+    /// `requires` and `optional_requires` of every repo depending on it,
+    /// with `git_tag` set to match and `track` removed, so the result is
+    /// fully pinned. A repo without such a tag is
+    /// [`Error::NoMatchingVersion`]; any other `track` value is an error.
+    /// Other entries are left as they are.
+    ///
+    /// Not to be confused with [`resolve`](Self::resolve), which chooses
+    /// between versions declared in the manifest.
+    pub fn resolve_latest(&self, resolver: impl VersionResolver) -> anyhow::Result<Self> {
+        let mut resolved = self.clone();
+        let mut tracked: Vec<(&String, &RepoVersion)> = self
+            .versions
+            .iter()
+            .filter(|(_, info)| info.track.is_some())
+            .collect();
+        tracked.sort_by_key(|(repo, _)| *repo);
+
+        for (repo, info) in tracked {
+            if !info.is_latest() {
+                anyhow::bail!(
+                    "Repository '{}' has unknown track '{}' (expected '{}')",
+                    repo,
+                    info.track.as_deref().unwrap_or_default(),
+                    LATEST
+                );
+            }
+            let required = self.constraints_on(repo)?;
+            let mut releases: Vec<(Version, String)> = resolver
+                .tags(repo)?
+                .iter()
+                .filter_map(|tag| info.release(tag))
+                .collect();
+            releases.sort();
+
+            let Some((version, git_tag)) = releases
+                .iter()
+                .rev()
+                .find(|(version, _)| required.iter().all(|(_, req)| req.matches(version)))
+            else {
+                let mut required_by: Vec<String> = required
+                    .into_iter()
+                    .map(|(requirement, _)| requirement)
+                    .collect();
+                required_by.sort();
+                return Err(Error::NoMatchingVersion {
+                    repo: repo.clone(),
+                    required_by,
+                    declared: releases.iter().map(|(v, _)| v.to_string()).collect(),
+                }
+                .into());
+            };
+
+            let entry = resolved
+                .versions
+                .get_mut(repo)
+                .expect("repo comes from the manifest");
+            entry.version = version.to_string();
+            entry.git_tag = git_tag.clone();
+            entry.track = None;
+        }
+
+        Ok(resolved)
+    }
+
+    /// Requirements on `repo` from every entry, as
+    /// (`"<dependent> requires '<spec>'"`, accepted versions)
+    fn constraints_on(&self, repo: &str) -> anyhow::Result<Vec<(String, VersionReq)>> {
+        let entries = self.versions.keys().flat_map(|dependent| {
+            self.entries(dependent)
+                .into_iter()
+                .map(move |info| (dependent, info))
+        });
+
+        let mut constraints = Vec::new();
+        for (dependent, info) in entries {
+            for spec in info.all_requires() {
+                let requirement = parse_requirement(spec).map_err(|e| {
+                    anyhow::anyhow!("Invalid requirement '{}' of '{}': {}", spec, dependent, e)
+                })?;
+                if requirement.name == repo {
+                    let required_by = format!("{} requires '{}'", dependent, spec);
+                    constraints.push((required_by, requirement.req));
+                }
+            }
+        }
+        Ok(constraints)
+    }
+}
@@ -2,4 +2,51 @@
 //!
 //! Library components for the BLLVM build orchestration system
 
+#[cfg(feature = "async-executor")]
+pub mod async_executor;
+pub mod builder;
+pub mod bump;
+pub mod channel;
+pub mod checksum;
+pub mod consistency;
+pub mod diff;
+pub mod discover;
+pub mod dot;
+mod duplicate_requires;
+pub mod error;
+pub mod executor;
+pub mod explain;
+pub mod external;
+#[cfg(feature = "git")]
+pub mod git;
+pub mod github;
+pub mod graph;
+pub mod hash;
+pub mod hooks;
+mod include;
+pub mod latest;
+pub mod lint;
+pub mod lockfile;
+pub mod manifest_set;
+pub mod multi_version;
+pub mod plan;
+pub mod progress;
+pub mod publish;
+pub mod replace;
+pub mod report;
+pub mod schedule;
+pub mod source;
+pub mod state;
+pub mod stats;
+#[cfg(feature = "git")]
+pub mod tagger;
+pub mod target;
+pub mod transitive;
+mod unknown_fields;
 pub mod versions;
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "git")]
+pub mod workspace;
+
+pub use error::{Error, ManifestError};
@@ -0,0 +1,281 @@
+//! Opinionated manifest checks beyond [`validate`](VersionsManifest::validate)
+//!
+//! Each [`Rule`] has a default [`Severity`] that a `[lint]` table (or the
+//! flags of `bllvm lint`) can change:
+//!
+//! ```toml
+//! [lint]
+//! allow = ["isolated-repo"]
+//! deny = ["tag-convention"]
+//! max_depth = 3
+//! release = true
+//! ```
+
+use crate::report::depth;
+use crate::versions::{dependency_name, VersionsManifest};
+use crate::Error;
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+/// Default for [`LintConfig::max_depth`]
+pub const DEFAULT_MAX_DEPTH: usize = 5;
+
+/// How much a finding matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Reported, but `bllvm lint` still succeeds
+    Warn,
+    /// Makes `bllvm lint` fail
+    Deny,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warn => write!(f, "warning"),
+            Severity::Deny => write!(f, "error"),
+        }
+    }
+}
+
+/// One lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    /// Name used in findings and in `allow`/`warn`/`deny`
+    pub id: &'static str,
+    /// Severity unless configured otherwise
+    pub default: Severity,
+    /// What the rule looks for
+    pub summary: &'static str,
+}
+
+/// Every rule [`lint`](VersionsManifest::lint) knows
+pub const RULES: &[Rule] = &[
+    Rule {
+        id: "tag-convention",
+        default: Severity::Warn,
+        summary: "git_tag is not v{version}",
+    },
+    Rule {
+        id: "isolated-repo",
+        default: Severity::Warn,
+        summary: "repo neither requires nor is required by another repo (possibly stale)",
+    },
+    Rule {
+        id: "unsorted-requires",
+        default: Severity::Warn,
+        summary: "requires or optional_requires not sorted by repo name",
+    },
+    Rule {
+        id: "prerelease-version",
+        default: Severity::Deny,
+        summary: "pre-release version in a release manifest (lint.release = true)",
+    },
+    Rule {
+        id: "deep-chain",
+        default: Severity::Warn,
+        summary: "dependency chain longer than lint.max_depth",
+    },
+];
+
+/// Lint settings (`[lint]` table)
+///
+/// A rule listed in several of `allow`, `warn` and `deny` gets the
+/// strictest of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LintConfig {
+    /// Rules not to run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+
+    /// Rules to report as warnings
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warn: Vec<String>,
+
+    /// Rules to report as errors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+
+    /// Longest dependency chain, in edges, before `deep-chain` fires
+    /// (default [`DEFAULT_MAX_DEPTH`])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+
+    /// Whether the manifest describes a release, enabling
+    /// `prerelease-version`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub release: bool,
+}
+
+impl LintConfig {
+    /// Severity of `rule`, `None` if it is allowed
+    pub fn level(&self, rule: &Rule) -> Option<Severity> {
+        let listed = |list: &[String]| list.iter().any(|id| id == rule.id);
+        if listed(&self.deny) {
+            Some(Severity::Deny)
+        } else if listed(&self.warn) {
+            Some(Severity::Warn)
+        } else if listed(&self.allow) {
+            None
+        } else {
+            Some(rule.default)
+        }
+    }
+
+    /// Set `rule` to `level` (`None` to allow it), replacing any earlier setting
+    ///
+    /// Used for command-line flags, which take precedence over the manifest.
+    pub fn set(&mut self, rule: &str, level: Option<Severity>) {
+        for list in [&mut self.allow, &mut self.warn, &mut self.deny] {
+            list.retain(|id| id != rule);
+        }
+        let list = match level {
+            None => &mut self.allow,
+            Some(Severity::Warn) => &mut self.warn,
+            Some(Severity::Deny) => &mut self.deny,
+        };
+        list.push(rule.to_string());
+    }
+
+    /// Fail on rule names that are not in [`RULES`]
+    fn check_rules(&self) -> Result<(), Error> {
+        for id in self.allow.iter().chain(&self.warn).chain(&self.deny) {
+            if !RULES.iter().any(|rule| rule.id == id) {
+                return Err(Error::UnknownLintRule {
+                    rule: id.clone(),
+                    known: RULES.iter().map(|rule| rule.id.to_string()).collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One problem found by [`VersionsManifest::lint`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LintFinding {
+    /// [`Rule::id`] of the rule that found it
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub repo: String,
+    pub message: String,
+}
+
+impl fmt::Display for LintFinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}[{}] {}: {}",
+            self.severity, self.rule, self.repo, self.message
+        )
+    }
+}
+
+impl VersionsManifest {
+    /// Run the [`RULES`] with `config`, e.g. the manifest's own `[lint]`
+    /// table (`manifest.lint`)
+    ///
+    /// Findings are sorted by repo, then rule. Unknown rule names in
+    /// `config` are an error. Problems [`validate`](Self::validate) reports
+    /// are not repeated here; `deep-chain` is skipped when there is a cycle.
+    pub fn lint(&self, config: &LintConfig) -> Result<Vec<LintFinding>, Error> {
+        config.check_rules()?;
+        let mut findings = Vec::new();
+        let mut report = |rule: &'static str, repo: &str, message: String| {
+            let rule = RULES
+                .iter()
+                .find(|r| r.id == rule)
+                .expect("rule is in RULES");
+            if let Some(severity) = config.level(rule) {
+                findings.push(LintFinding {
+                    rule: rule.id,
+                    severity,
+                    repo: repo.to_string(),
+                    message,
+                });
+            }
+        };
+
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+        for (repo, info) in repos.iter().flat_map(|repo| {
+            self.entries(repo)
+                .into_iter()
+                .map(move |info| (*repo, info))
+        }) {
+            let expected = format!("v{}", info.version);
+            if info.git_tag != expected {
+                report(
+                    "tag-convention",
+                    repo,
+                    format!("git_tag '{}' should be '{}'", info.git_tag, expected),
+                );
+            }
+
+            let requires: Vec<&str> = info.requires.iter().map(|dep| dep.spec.as_str()).collect();
+            let optional: Vec<&str> = info.optional_requires.iter().map(String::as_str).collect();
+            for (field, list) in [("requires", requires), ("optional_requires", optional)] {
+                if !list.is_sorted_by_key(|spec| dependency_name(spec)) {
+                    report(
+                        "unsorted-requires",
+                        repo,
+                        format!("{} is not sorted by repo name", field),
+                    );
+                }
+            }
+
+            if config.release
+                && Version::parse(&info.version).is_ok_and(|version| !version.pre.is_empty())
+            {
+                report(
+                    "prerelease-version",
+                    repo,
+                    format!("version {} is a pre-release", info.version),
+                );
+            }
+        }
+
+        let edges = self.dependency_edges();
+        let required: BTreeSet<&str> = edges
+            .iter()
+            .flat_map(|(repo, deps)| deps.iter().filter(move |dep| *dep != repo))
+            .copied()
+            .collect();
+        if edges.len() > 1 {
+            for (repo, deps) in &edges {
+                if deps.iter().all(|dep| dep == repo) && !required.contains(repo) {
+                    report(
+                        "isolated-repo",
+                        repo,
+                        "no other repo requires it and it requires none".to_string(),
+                    );
+                }
+            }
+        }
+
+        if self.find_cycles().is_empty() {
+            let max_depth = config.max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+            let mut depths = HashMap::new();
+            for repo in edges.keys() {
+                let chain = depth(&edges, repo, &mut depths);
+                // Only the top of a chain, its dependencies are part of it
+                if chain > max_depth && !required.contains(repo) {
+                    report(
+                        "deep-chain",
+                        repo,
+                        format!(
+                            "dependency chain of {} exceeds max_depth {}",
+                            chain, max_depth
+                        ),
+                    );
+                }
+            }
+        }
+
+        findings.sort_by(|a, b| a.repo.cmp(&b.repo).then_with(|| a.rule.cmp(b.rule)));
+        Ok(findings)
+    }
+}
@@ -0,0 +1,400 @@
+//! bllvm.lock / versions.lock generation and verification
+
+use crate::versions::{ValidationError, ValidationResult, VersionsManifest};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Header written at the top of every lockfile
+const LOCKFILE_HEADER: &str = "# This file is generated by bllvm. Do not edit it by hand.\n\n";
+
+/// File name of the lockfile written next to versions.toml by [`VersionsManifest::lock`]
+pub const LOCKFILE_NAME: &str = "versions.lock";
+
+/// Resolves a repo's git tag to the commit it currently points at
+///
+/// Resolvers run git or ask a remote, so they return `anyhow::Error`; the
+/// lockfile APIs turn a failure into [`Error::TagResolution`].
+pub trait TagResolver {
+    /// Return the commit SHA `git_tag` of `repo` points at
+    fn resolve(&self, repo: &str, git_tag: &str) -> anyhow::Result<String>;
+}
+
+impl<T: TagResolver + ?Sized> TagResolver for &T {
+    fn resolve(&self, repo: &str, git_tag: &str) -> anyhow::Result<String> {
+        (**self).resolve(repo, git_tag)
+    }
+}
+
+/// Resolves tags with the git CLI in local checkouts (`<root>/<repo>`)
+#[derive(Debug, Clone)]
+pub struct GitTagResolver {
+    root: PathBuf,
+}
+
+impl GitTagResolver {
+    /// Resolve tags in checkouts under `root`
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl TagResolver for GitTagResolver {
+    fn resolve(&self, repo: &str, git_tag: &str) -> anyhow::Result<String> {
+        let checkout = self.root.join(repo);
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&checkout)
+            .args(["rev-parse", "--verify", "--quiet"])
+            .arg(format!("refs/tags/{}^{{commit}}", git_tag))
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run git for '{}': {}", repo, e))?;
+
+        if !output.status.success() {
+            anyhow::bail!(
+                "Failed to resolve tag '{}' of '{}' in {}",
+                git_tag,
+                repo,
+                checkout.display()
+            );
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+/// In-memory tag resolver for tests and offline use
+#[derive(Debug, Clone, Default)]
+pub struct MockTagResolver {
+    tags: HashMap<(String, String), String>,
+}
+
+impl MockTagResolver {
+    /// Create a resolver with no known tags
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make `git_tag` of `repo` resolve to `commit`
+    pub fn with_tag(mut self, repo: &str, git_tag: &str, commit: &str) -> Self {
+        self.tags
+            .insert((repo.to_string(), git_tag.to_string()), commit.to_string());
+        self
+    }
+}
+
+/// `resolver.resolve(repo, git_tag)`, failures as [`Error::TagResolution`]
+fn resolve_tag(resolver: &impl TagResolver, repo: &str, git_tag: &str) -> Result<String, Error> {
+    resolver
+        .resolve(repo, git_tag)
+        .map_err(|e| Error::TagResolution {
+            repo: repo.to_string(),
+            git_tag: git_tag.to_string(),
+            message: format!("{:#}", e),
+        })
+}
+
+impl TagResolver for MockTagResolver {
+    fn resolve(&self, repo: &str, git_tag: &str) -> anyhow::Result<String> {
+        self.tags
+            .get(&(repo.to_string(), git_tag.to_string()))
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown tag '{}' of '{}'", git_tag, repo))
+    }
+}
+
+/// A single resolved repository in the lockfile
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LockedRepo {
+    /// Repository name
+    pub name: String,
+
+    /// Resolved version
+    pub version: String,
+
+    /// Resolved git tag
+    pub git_tag: String,
+
+    /// Resolved git commit hash (if known)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+}
+
+impl LockedRepo {
+    /// Whether `locked` is still what this manifest entry resolves to
+    ///
+    /// An entry without a `git_commit` accepts whatever commit was locked.
+    fn still_locks(&self, locked: &LockedRepo) -> bool {
+        self.version == locked.version
+            && self.git_tag == locked.git_tag
+            && (self.git_commit.is_none() || self.git_commit == locked.git_commit)
+    }
+
+    /// Short human-readable description, e.g. `0.1.0 (v0.1.0 @ abc123)`
+    fn describe(&self) -> String {
+        match &self.git_commit {
+            Some(commit) => format!("{} ({} @ {})", self.version, self.git_tag, commit),
+            None => format!("{} ({})", self.version, self.git_tag),
+        }
+    }
+}
+
+/// Lockfile structure (bllvm.lock / versions.lock)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Lockfile {
+    /// Locked repositories in build order
+    #[serde(default)]
+    pub locked: Vec<LockedRepo>,
+}
+
+impl Lockfile {
+    /// Build a lockfile from a manifest's resolved build order
+    pub fn from_manifest(manifest: &VersionsManifest) -> Result<Self, Error> {
+        let locked = manifest
+            .build_order()?
+            .into_iter()
+            .map(|name| {
+                let info = &manifest.versions[&name];
+                LockedRepo {
+                    version: info.version.clone(),
+                    git_tag: info.full_tag(),
+                    git_commit: info.git_commit.clone().filter(|c| !c.is_empty()),
+                    name,
+                }
+            })
+            .collect();
+
+        Ok(Self { locked })
+    }
+
+    /// Load a lockfile from file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let error = |message: String| Error::Lockfile {
+            path: path.as_ref().display().to_string(),
+            message,
+        };
+        let content = std::fs::read_to_string(path.as_ref()).map_err(|e| error(e.to_string()))?;
+
+        let lockfile: Lockfile = toml::from_str(&content).map_err(|e| error(e.to_string()))?;
+
+        Ok(lockfile)
+    }
+
+    /// Serialize the lockfile to TOML
+    ///
+    /// The output only depends on the locked contents, so re-writing an
+    /// unchanged lockfile produces byte-identical output.
+    pub fn to_toml_string(&self) -> Result<String, Error> {
+        let body = toml::to_string(self).map_err(|e| Error::serialize("lockfile", e))?;
+        Ok(format!("{}{}", LOCKFILE_HEADER, body))
+    }
+
+    /// Write the lockfile to file
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path.as_ref(), self.to_toml_string()?).map_err(|e| Error::Write {
+            path: path.as_ref().display().to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Path of the lockfile that belongs to the manifest at `manifest_path`
+    pub fn path_for<P: AsRef<Path>>(manifest_path: P) -> PathBuf {
+        manifest_path.as_ref().with_file_name(LOCKFILE_NAME)
+    }
+
+    /// Verify that the manifest's tags still point at the locked commits
+    ///
+    /// Returns an error listing every repo that was added, removed, changed
+    /// version or tag, or whose tag now resolves to a different commit.
+    /// Entries locked without a commit are only checked for version and
+    /// tag changes.
+    pub fn verify(
+        &self,
+        manifest: &VersionsManifest,
+        resolver: impl TagResolver,
+    ) -> Result<(), Error> {
+        let mut drift = Vec::new();
+
+        for locked in &self.locked {
+            let Some(info) = manifest.versions.get(&locked.name) else {
+                drift.push(format!(
+                    "Repository '{}' is locked but no longer in the manifest",
+                    locked.name
+                ));
+                continue;
+            };
+
+            let git_tag = info.full_tag();
+            if info.version != locked.version || git_tag != locked.git_tag {
+                drift.push(format!(
+                    "Repository '{}' changed: locked {} ({}), manifest has {} ({})",
+                    locked.name, locked.version, locked.git_tag, info.version, git_tag
+                ));
+                continue;
+            }
+
+            // Without a locked commit there is nothing to compare the tag against
+            let Some(locked_commit) = locked.git_commit.as_deref() else {
+                continue;
+            };
+            let commit = resolve_tag(&resolver, &locked.name, &locked.git_tag)?;
+            if locked_commit != commit {
+                drift.push(format!(
+                    "Repository '{}' tag '{}' moved: locked {}, now {}",
+                    locked.name, locked.git_tag, locked_commit, commit
+                ));
+            }
+        }
+
+        let mut added: Vec<&String> = manifest
+            .versions
+            .keys()
+            .filter(|name| !self.locked.iter().any(|r| &r.name == *name))
+            .collect();
+        added.sort();
+        for name in added {
+            drift.push(format!(
+                "Repository '{}' is in the manifest but not in the lockfile",
+                name
+            ));
+        }
+
+        if drift.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::LockfileOutOfDate { drift })
+        }
+    }
+
+    /// Verify that a manifest still matches this lockfile
+    ///
+    /// Returns an error listing every repo that was added, removed, or
+    /// changed in the manifest since the lockfile was written. A locked
+    /// commit is only compared when the manifest pins one, so a lockfile
+    /// from [`lock`](VersionsManifest::lock) matches the manifest it was
+    /// written from.
+    pub fn verify_against(&self, manifest: &VersionsManifest) -> Result<(), Error> {
+        let expected = Lockfile::from_manifest(manifest)?;
+        let mut drift = Vec::new();
+
+        for locked in &self.locked {
+            match expected.locked.iter().find(|r| r.name == locked.name) {
+                None => drift.push(format!(
+                    "Repository '{}' is locked but no longer in the manifest",
+                    locked.name
+                )),
+                Some(current) if !current.still_locks(locked) => drift.push(format!(
+                    "Repository '{}' changed: locked {}, manifest has {}",
+                    locked.name,
+                    locked.describe(),
+                    current.describe()
+                )),
+                Some(_) => {}
+            }
+        }
+
+        for current in &expected.locked {
+            if !self.locked.iter().any(|r| r.name == current.name) {
+                drift.push(format!(
+                    "Repository '{}' is in the manifest but not in the lockfile",
+                    current.name
+                ));
+            }
+        }
+
+        if drift.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::LockfileOutOfDate { drift })
+        }
+    }
+}
+
+impl VersionsManifest {
+    /// Lock every repo's git tag to the commit it currently points at
+    ///
+    /// Repos are locked in build order. Use [`Lockfile::path_for`] to get the
+    /// `versions.lock` path next to the manifest.
+    pub fn lock(&self, resolver: impl TagResolver) -> Result<Lockfile, Error> {
+        let locked = self
+            .build_order()?
+            .into_iter()
+            .map(|name| {
+                let info = &self.versions[&name];
+                Ok(LockedRepo {
+                    version: info.version.clone(),
+                    git_tag: info.full_tag(),
+                    git_commit: Some(resolve_tag(&resolver, &name, &info.full_tag())?),
+                    name,
+                })
+            })
+            .collect::<Result<_, Error>>()?;
+
+        Ok(Lockfile { locked })
+    }
+
+    /// Fill in `git_commit` for every repo that does not pin one yet
+    ///
+    /// Each missing commit is set to what the repo's `git_tag` currently
+    /// points at. Returns the repos that were filled in, sorted by name.
+    pub fn resolve_commits(&mut self, resolver: impl TagResolver) -> Result<Vec<String>, Error> {
+        let mut repos: Vec<String> = self
+            .versions
+            .iter()
+            .filter(|(_, info)| info.git_commit.as_deref().unwrap_or_default().is_empty())
+            .map(|(name, _)| name.clone())
+            .collect();
+        repos.sort();
+
+        for repo in &repos {
+            let info = self
+                .versions
+                .get_mut(repo)
+                .expect("repo is in the manifest");
+            info.git_commit = Some(resolve_tag(&resolver, repo, &info.full_tag())?);
+        }
+
+        Ok(repos)
+    }
+
+    /// [`validate`](Self::validate), and also check that every repo pinning a
+    /// `git_commit` has a `git_tag` that still points at it
+    ///
+    /// A tag pointing elsewhere is reported as
+    /// [`ValidationError::CommitMismatch`]; a tag that cannot be resolved at
+    /// all is an error.
+    pub fn validate_commits(&self, resolver: impl TagResolver) -> Result<ValidationResult, Error> {
+        let (mut errors, warnings) = self.validate().into_problems();
+
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+        for repo in repos {
+            let info = &self.versions[repo];
+            let expected = info.git_commit.as_deref().unwrap_or_default();
+            if expected.is_empty() || info.git_tag.trim().is_empty() {
+                continue;
+            }
+
+            let found = resolve_tag(&resolver, repo, &info.full_tag())?;
+            if found != expected {
+                errors.push(ValidationError::CommitMismatch {
+                    repo: repo.clone(),
+                    git_tag: info.full_tag(),
+                    expected: expected.to_string(),
+                    found,
+                });
+            }
+        }
+
+        Ok(ValidationResult::from_problems(errors, warnings))
+    }
+
+    /// Resolve the manifest and write the result to a lockfile
+    pub fn write_lockfile<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        Lockfile::from_manifest(self)?.to_file(path)
+    }
+}
@@ -0,0 +1,262 @@
+//! Resolving one fleet split across several versions.toml files
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Several manifests that are resolved together
+///
+/// `requires` entries may reference repos defined in any of the manifests.
+/// Use [`merged`](Self::merged) to get a single manifest that behaves exactly
+/// as if everything had been in one file.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestSet {
+    /// Manifests with the name of their source, in the order they were added
+    manifests: Vec<(String, VersionsManifest)>,
+}
+
+impl ManifestSet {
+    /// Create an empty set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load every file into a set
+    pub fn from_files<P: AsRef<Path>>(paths: &[P]) -> Result<Self, Error> {
+        let mut set = Self::new();
+        for path in paths {
+            set.add_file(path)?;
+        }
+        Ok(set)
+    }
+
+    /// Load a manifest file and add it to the set
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let manifest = VersionsManifest::from_file(path.as_ref())?;
+        self.add(path.as_ref().display().to_string(), manifest);
+        Ok(())
+    }
+
+    /// Add an already loaded manifest, naming its source for error messages
+    pub fn add(&mut self, source: impl Into<String>, manifest: VersionsManifest) {
+        self.manifests.push((source.into(), manifest));
+    }
+
+    /// Name of the source that defines `repo`, if any
+    pub fn source_of(&self, repo: &str) -> Option<&str> {
+        self.manifests
+            .iter()
+            .find(|(_, manifest)| manifest.versions.contains_key(repo))
+            .map(|(source, _)| source.as_str())
+    }
+
+    /// Name of the source that defines group `group`, if any
+    fn group_source(&self, group: &str) -> Option<&str> {
+        self.manifests
+            .iter()
+            .find(|(_, manifest)| manifest.groups.contains_key(group))
+            .map(|(source, _)| source.as_str())
+    }
+
+    /// Name of the source whose profile `profile` overrides `repo`, if any
+    fn profile_source(&self, profile: &str, repo: &str) -> Option<&str> {
+        self.manifests
+            .iter()
+            .find(|(_, manifest)| {
+                manifest
+                    .profiles
+                    .get(profile)
+                    .is_some_and(|p| p.versions.contains_key(repo))
+            })
+            .map(|(source, _)| source.as_str())
+    }
+
+    /// Combine all manifests into one
+    ///
+    /// A repo defined in several manifests must have identical entries, and
+    /// so must `[workspace]`, `[hooks]` and `[lint]` tables, groups of the same name, `[replace]`
+    /// entries of the same repo, `[external]` entries of the same package and overrides of the same repo in the same profile; otherwise the
+    /// error names both sources. Metadata keys are taken from the first manifest that sets them.
+    pub fn merged(&self) -> Result<VersionsManifest, Error> {
+        let mut merged = VersionsManifest::default();
+        let mut workspace_source = "";
+        let mut hooks_source = "";
+        let mut lint_source = "";
+        let mut replace_sources: HashMap<&str, &str> = HashMap::new();
+        let mut external_sources: HashMap<&str, &str> = HashMap::new();
+
+        for (source, manifest) in &self.manifests {
+            let mut repos: Vec<&String> = manifest.versions.keys().collect();
+            repos.sort();
+            for repo in repos {
+                let info = &manifest.versions[repo];
+                match merged.versions.get(repo) {
+                    Some(existing) if existing != info => {
+                        return Err(Error::ManifestConflict {
+                            item: format!("Repository '{}'", repo),
+                            first: format!(
+                                "{} (version {})",
+                                self.source_of(repo).unwrap_or_default(),
+                                existing.version
+                            ),
+                            second: format!("{} (version {})", source, info.version),
+                        })
+                    }
+                    Some(_) => {}
+                    None => {
+                        merged.versions.insert(repo.clone(), info.clone());
+                        if let Some(entries) = manifest.multi_versions.get(repo) {
+                            merged.multi_versions.insert(repo.clone(), entries.clone());
+                        }
+                        if let Some(channels) = manifest.channels.get(repo) {
+                            merged.channels.insert(repo.clone(), channels.clone());
+                        }
+                    }
+                }
+            }
+
+            merged
+                .parse_warnings
+                .extend(manifest.parse_warnings.iter().cloned());
+
+            if let Some(metadata) = &manifest.metadata {
+                let merged_metadata = merged.metadata.get_or_insert_with(Default::default);
+                for (key, value) in metadata {
+                    merged_metadata
+                        .entry(key.clone())
+                        .or_insert_with(|| value.clone());
+                }
+            }
+
+            for (group, members) in &manifest.groups {
+                match merged.groups.get(group) {
+                    Some(existing) if existing != members => {
+                        return Err(conflict(
+                            format!("Group '{}'", group),
+                            self.group_source(group).unwrap_or_default(),
+                            source,
+                        ))
+                    }
+                    Some(_) => {}
+                    None => {
+                        merged.groups.insert(group.clone(), members.clone());
+                    }
+                }
+            }
+
+            for (repo, replacement) in &manifest.replace {
+                match merged.replace.get(repo) {
+                    Some(existing) if existing != replacement => {
+                        return Err(conflict(
+                            format!("[replace] of '{}'", repo),
+                            replace_sources[repo.as_str()],
+                            source,
+                        ))
+                    }
+                    Some(_) => {}
+                    None => {
+                        merged.replace.insert(repo.clone(), replacement.clone());
+                        replace_sources.insert(repo, source);
+                    }
+                }
+            }
+
+            for (name, external) in &manifest.external {
+                match merged.external.get(name) {
+                    Some(existing) if existing != external => {
+                        return Err(conflict(
+                            format!("External package '{}'", name),
+                            external_sources[name.as_str()],
+                            source,
+                        ))
+                    }
+                    Some(_) => {}
+                    None => {
+                        merged.external.insert(name.clone(), external.clone());
+                        external_sources.insert(name, source);
+                    }
+                }
+            }
+
+            for (profile_name, profile) in &manifest.profiles {
+                let merged_profile = merged.profiles.entry(profile_name.clone()).or_default();
+                for (repo, overrides) in &profile.versions {
+                    match merged_profile.versions.get(repo) {
+                        Some(existing) if existing != overrides => {
+                            return Err(conflict(
+                                format!("Profile '{}' override of '{}'", profile_name, repo),
+                                self.profile_source(profile_name, repo).unwrap_or_default(),
+                                source,
+                            ))
+                        }
+                        Some(_) => {}
+                        None => {
+                            merged_profile
+                                .versions
+                                .insert(repo.clone(), overrides.clone());
+                        }
+                    }
+                }
+            }
+
+            match (&merged.workspace, &manifest.workspace) {
+                (Some(existing), Some(workspace)) if existing != workspace => {
+                    return Err(conflict(
+                        "[workspace]".to_string(),
+                        workspace_source,
+                        source,
+                    ))
+                }
+                (None, Some(workspace)) => {
+                    merged.workspace = Some(workspace.clone());
+                    workspace_source = source;
+                }
+                _ => {}
+            }
+
+            match (&merged.hooks, &manifest.hooks) {
+                (Some(existing), Some(hooks)) if existing != hooks => {
+                    return Err(conflict("[hooks]".to_string(), hooks_source, source))
+                }
+                (None, Some(hooks)) => {
+                    merged.hooks = Some(hooks.clone());
+                    hooks_source = source;
+                }
+                _ => {}
+            }
+
+            match (&merged.lint, &manifest.lint) {
+                (Some(existing), Some(lint)) if existing != lint => {
+                    return Err(conflict("[lint]".to_string(), lint_source, source))
+                }
+                (None, Some(lint)) => {
+                    merged.lint = Some(lint.clone());
+                    lint_source = source;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(merged)
+    }
+}
+
+/// [`Error::ManifestConflict`] of `item` between manifests `first` and `second`
+fn conflict(item: String, first: &str, second: &str) -> Error {
+    Error::ManifestConflict {
+        item,
+        first: first.to_string(),
+        second: second.to_string(),
+    }
+}
+
+impl VersionsManifest {
+    /// Combine this manifest with `other`, see [`ManifestSet::merged`]
+    pub fn merge(self, other: VersionsManifest) -> Result<VersionsManifest, Error> {
+        let mut set = ManifestSet::new();
+        set.add("first manifest", self);
+        set.add("second manifest", other);
+        set.merged()
+    }
+}
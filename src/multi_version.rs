@@ -0,0 +1,325 @@
+//! Several versions of one repo side by side in `[versions]`
+//!
+//! ```toml
+//! [versions]
+//! bllvm-consensus = [
+//!     { version = "0.1.4", git_tag = "v0.1.4" },
+//!     { version = "0.2.0", git_tag = "v0.2.0" },
+//! ]
+//! ```
+//!
+//! [`versions`](VersionsManifest::versions) holds the newest entry, so the
+//! repo-level API still sees one version per repo. A `requires` entry on such
+//! a repo must match exactly one of its versions, and
+//! [`versioned_build_order`](VersionsManifest::versioned_build_order) orders
+//! every entry.
+//!
+//! One repo can depend on several versions at once, e.g. during a
+//! migration, by giving each its own alias:
+//!
+//! ```toml
+//! bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = [
+//!     "consensus_old=bllvm-consensus@0.1.4",
+//!     "consensus_new=bllvm-consensus@0.2.0",
+//! ] }
+//! ```
+//!
+//! So the graph has two levels. Nodes of
+//! [`versioned_build_order`](VersionsManifest::versioned_build_order) are
+//! `(repo, version)` pairs and the aliased repo above is two of them, both
+//! ordered before `bllvm-node`. [`build_order`](VersionsManifest::build_order)
+//! and the rest of the repo-level API keep one node per repo name, so single
+//! version manifests work as before.
+
+use crate::versions::{
+    cycles_in, dependency_name, parse_requirement, stages_for, RepoVersion, ValidationError,
+    VersionsManifest,
+};
+use crate::Error;
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use toml::Value;
+
+/// How [`VersionsManifest::resolve`] picks among the versions of a repo
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResolveStrategy {
+    /// The newest version every dependent accepts
+    #[default]
+    Highest,
+
+    /// The oldest version every dependent accepts (minimal version selection)
+    MinimalVersion,
+}
+
+impl ResolveStrategy {
+    /// Pick from `candidates`, which are sorted oldest first
+    fn pick<'a>(self, candidates: &[&'a RepoVersion]) -> Option<&'a RepoVersion> {
+        match self {
+            ResolveStrategy::Highest => candidates.last().copied(),
+            ResolveStrategy::MinimalVersion => candidates.first().copied(),
+        }
+    }
+}
+
+impl VersionsManifest {
+    /// Every declared entry of `repo`, oldest first
+    ///
+    /// One entry unless the repo is declared with an array of versions; none
+    /// for an unknown repo.
+    pub fn entries(&self, repo: &str) -> Vec<&RepoVersion> {
+        match self.multi_versions.get(repo) {
+            Some(entries) => entries.iter().collect(),
+            None => self.versions.get(repo).into_iter().collect(),
+        }
+    }
+
+    /// Build order over every declared version, as `(repo, version)` pairs
+    ///
+    /// Like [`build_order`](Self::build_order), but a repo declared with
+    /// several versions appears once per version, after the versions of its
+    /// dependencies that its own `requires` match. A requirement matching
+    /// more than one version is an error, as is a cycle between versions
+    /// (reported as `repo@version` names). Pairs are ordered by stage, then
+    /// name, then version.
+    pub fn versioned_build_order(&self) -> Result<Vec<(String, Version)>, Error> {
+        let mut nodes: BTreeMap<String, (String, Version)> = BTreeMap::new();
+        let mut requires: HashMap<String, Vec<String>> = HashMap::new();
+        for repo in self.versions.keys() {
+            for entry in self.entries(repo) {
+                let version = Version::parse(&entry.version).map_err(|_| {
+                    ValidationError::InvalidVersion {
+                        repo: repo.clone(),
+                        version: entry.version.clone(),
+                    }
+                })?;
+                let id = node_id(repo, &entry.version);
+                let mut deps = Vec::new();
+                for spec in &entry.requires {
+                    if let Some((dep, dep_entry)) = self.resolve_entry(repo, spec)? {
+                        deps.push(node_id(dep, &dep_entry.version));
+                    }
+                }
+                deps.sort();
+                deps.dedup();
+                requires.insert(id.clone(), deps);
+                nodes.insert(id, (repo.clone(), version));
+            }
+        }
+
+        let edges: BTreeMap<&str, Vec<&str>> = requires
+            .iter()
+            .map(|(id, deps)| (id.as_str(), deps.iter().map(String::as_str).collect()))
+            .collect();
+        if let Some(cycle) = cycles_in(&edges).into_iter().next() {
+            return Err(Error::CircularDependency { cycle });
+        }
+
+        let pending: HashMap<&str, HashSet<&str>> = edges
+            .iter()
+            .map(|(id, deps)| (*id, deps.iter().copied().collect()))
+            .collect();
+        let mut order = Vec::new();
+        for stage in stages_for(pending) {
+            let mut stage: Vec<(String, Version)> =
+                stage.iter().map(|id| nodes[id].clone()).collect();
+            stage.sort();
+            order.extend(stage);
+        }
+        Ok(order)
+    }
+}
+
+impl VersionsManifest {
+    /// Narrow every repo declared with several versions down to one
+    ///
+    /// Each such repo gets the entry accepted by the `requires` and
+    /// `optional_requires` of every repo depending on it: with
+    /// [`ResolveStrategy::Highest`] the newest such entry, with
+    /// [`ResolveStrategy::MinimalVersion`] the oldest. Dependents are decided
+    /// before their dependencies, so only the chosen entries' requirements
+    /// count; cycles between repos are an error. The result declares one
+    /// version per repo, so
+    /// [`build_order`](Self::build_order) and friends build the chosen
+    /// versions. A repo with no entry every dependent accepts is
+    /// [`Error::NoMatchingVersion`]; manifests without version arrays are
+    /// returned unchanged.
+    ///
+    /// For example, given
+    ///
+    /// ```toml
+    /// [versions]
+    /// bllvm-consensus = [
+    ///     { version = "0.1.4", git_tag = "v0.1.4" },
+    ///     { version = "0.1.6", git_tag = "v0.1.6" },
+    ///     { version = "0.2.0", git_tag = "v0.2.0" },
+    /// ]
+    /// bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus>=0.1.4, <0.2.0"] }
+    /// bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1.2"] }
+    /// ```
+    ///
+    /// both dependents accept 0.1.4 and 0.1.6 but not 0.2.0. `Highest` picks
+    /// 0.1.6, the newest compatible release. `MinimalVersion` picks 0.1.4,
+    /// the oldest version anybody asked for, so publishing a 0.1.7 does not
+    /// change what gets built until a dependent raises its requirement. Had
+    /// bllvm-node required `bllvm-consensus>=0.2.0`, both strategies would
+    /// fail: no single version satisfies both dependents.
+    pub fn resolve(&self, strategy: ResolveStrategy) -> Result<VersionsManifest, Error> {
+        // Repo-level graph over every entry, so each repo comes after all of
+        // its possible dependents once reversed
+        let mut edges: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for repo in self.versions.keys() {
+            let mut deps: Vec<&str> = self
+                .entries(repo)
+                .into_iter()
+                .flat_map(|entry| entry.all_requires())
+                .filter_map(|spec| {
+                    let dep = dependency_name(spec);
+                    self.versions
+                        .get_key_value(dep)
+                        .map(|(dep, _)| dep.as_str())
+                })
+                .collect();
+            deps.sort();
+            deps.dedup();
+            edges.insert(repo, deps);
+        }
+        if let Some(cycle) = cycles_in(&edges).into_iter().next() {
+            return Err(Error::CircularDependency { cycle });
+        }
+        let pending: HashMap<&str, HashSet<&str>> = edges
+            .iter()
+            .map(|(repo, deps)| (*repo, deps.iter().copied().collect()))
+            .collect();
+        let order: Vec<String> = stages_for(pending).into_iter().flatten().collect();
+
+        let mut resolved = VersionsManifest {
+            multi_versions: HashMap::new(),
+            ..self.clone()
+        };
+        // Requirements on each multi-version repo from its chosen dependents
+        let mut constraints: HashMap<&str, Vec<(&str, &str, VersionReq)>> = HashMap::new();
+        for repo in order.iter().rev() {
+            let entry = match self.multi_versions.get(repo) {
+                None => &self.versions[repo],
+                Some(entries) => {
+                    let required = constraints
+                        .get(repo.as_str())
+                        .map_or(&[][..], Vec::as_slice);
+                    let candidates: Vec<&RepoVersion> = entries
+                        .iter()
+                        .filter(|entry| {
+                            let version = Version::parse(&entry.version).ok();
+                            required.iter().all(|(_, _, req)| {
+                                version.as_ref().is_some_and(|version| req.matches(version))
+                            })
+                        })
+                        .collect();
+                    let Some(entry) = strategy.pick(&candidates) else {
+                        let mut required_by: Vec<String> = required
+                            .iter()
+                            .map(|(dependent, spec, _)| {
+                                format!("{} requires '{}'", dependent, spec)
+                            })
+                            .collect();
+                        required_by.sort();
+                        return Err(Error::NoMatchingVersion {
+                            repo: repo.clone(),
+                            required_by,
+                            declared: entries.iter().map(|e| e.version.clone()).collect(),
+                        });
+                    };
+                    resolved.versions.insert(repo.clone(), entry.clone());
+                    entry
+                }
+            };
+
+            for spec in entry.all_requires() {
+                let requirement =
+                    parse_requirement(spec).map_err(|e| ValidationError::InvalidRequirement {
+                        repo: repo.clone(),
+                        requirement: spec.to_string(),
+                        reason: e.to_string(),
+                    })?;
+                if let Some((dep, _)) = self.multi_versions.get_key_value(&requirement.name) {
+                    constraints.entry(dep.as_str()).or_default().push((
+                        repo.as_str(),
+                        spec,
+                        requirement.req,
+                    ));
+                }
+            }
+        }
+
+        Ok(resolved)
+    }
+}
+
+fn node_id(repo: &str, version: &str) -> String {
+    format!("{}@{}", repo, version)
+}
+
+/// Take the repos declared with an array of versions out of `[versions]`
+///
+/// Every entry needs a distinct, valid semver version. Entries are returned
+/// oldest first.
+pub(crate) fn take_multi_versions(
+    versions: &mut toml::Table,
+    source: &str,
+) -> Result<HashMap<String, Vec<RepoVersion>>, Error> {
+    let repos: Vec<String> = versions
+        .iter()
+        .filter(|(_, entry)| entry.is_array())
+        .map(|(repo, _)| repo.clone())
+        .collect();
+
+    let mut multi_versions = HashMap::new();
+    for repo in repos {
+        let Some(Value::Array(array)) = versions.remove(&repo) else {
+            continue;
+        };
+        if array.is_empty() {
+            return Err(Error::parse(
+                source,
+                Some(&repo),
+                "version array is empty".to_string(),
+            ));
+        }
+
+        let mut entries: Vec<(Version, RepoVersion)> = Vec::new();
+        for (index, value) in array.into_iter().enumerate() {
+            let entry: RepoVersion = value.try_into().map_err(|e: toml::de::Error| {
+                Error::parse(
+                    source,
+                    Some(&repo),
+                    format!("entry {}: {}", index + 1, e.message().trim()),
+                )
+            })?;
+            let version = Version::parse(&entry.version).map_err(|e| {
+                Error::parse(
+                    source,
+                    Some(&repo),
+                    format!(
+                        "entry {}: invalid version '{}': {}",
+                        index + 1,
+                        entry.version,
+                        e
+                    ),
+                )
+            })?;
+            if entries.iter().any(|(existing, _)| *existing == version) {
+                return Err(Error::parse(
+                    source,
+                    Some(&repo),
+                    format!("version {} is listed more than once", entry.version),
+                ));
+            }
+            entries.push((version, entry));
+        }
+
+        entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        multi_versions.insert(repo, entries.into_iter().map(|(_, entry)| entry).collect());
+    }
+    Ok(multi_versions)
+}
@@ -0,0 +1,306 @@
+//! Build plans and JSON export for external tooling
+
+use crate::lockfile::Lockfile;
+use crate::versions::{dependency_name, VersionsManifest};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A single repo in a [`BuildPlan`]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlannedRepo {
+    /// Repository name
+    pub name: String,
+
+    /// Version to build
+    pub version: String,
+
+    /// Git tag to build
+    pub git_tag: String,
+
+    /// Git commit hash (if pinned)
+    #[serde(default)]
+    pub git_commit: Option<String>,
+
+    /// Names of the repos this repo depends on, sorted
+    pub dependencies: Vec<String>,
+
+    /// Index of the build stage this repo belongs to
+    pub stage: usize,
+
+    /// Cargo features to build with (see [`VersionsManifest::features_for`])
+    #[serde(default)]
+    pub features: Vec<String>,
+
+    /// Whether this repo needs to be (re)built
+    #[serde(default = "default_rebuild")]
+    pub rebuild: bool,
+
+    /// The repo's `metadata` table, passed through for downstream tooling
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+/// An `[external]` package in a [`BuildPlan`], not built or ordered
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct PlannedExternal {
+    /// Package name
+    pub name: String,
+
+    /// Accepted version range, as declared
+    pub version: String,
+
+    /// Names of the repos requiring it, sorted
+    #[serde(default)]
+    pub required_by: Vec<String>,
+}
+
+fn default_rebuild() -> bool {
+    true
+}
+
+/// Resolved build plan
+///
+/// Serializes to JSON as:
+///
+/// ```json
+/// {
+///   "stages": [["bllvm-consensus"], ["bllvm-protocol"]],
+///   "repos": [
+///     { "name": "bllvm-consensus", "version": "0.1.0", "git_tag": "v0.1.0",
+///       "git_commit": null, "dependencies": [], "stage": 0, "features": [],
+///       "rebuild": true }
+///   ],
+///   "externals": [
+///     { "name": "secp256k1", "version": ">=0.28", "required_by": ["bllvm-consensus"] }
+///   ]
+/// }
+/// ```
+///
+/// `externals` is left out when the manifest declares none.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuildPlan {
+    /// Repo names grouped by parallel build stage
+    pub stages: Vec<Vec<String>>,
+
+    /// Repos in build order
+    pub repos: Vec<PlannedRepo>,
+
+    /// `[external]` packages, sorted by name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub externals: Vec<PlannedExternal>,
+}
+
+/// Build order for machine consumption, see [`VersionsManifest::build_order_json`]
+///
+/// Serializes to JSON as:
+///
+/// ```json
+/// {
+///   "order": ["bllvm-consensus", "bllvm-sdk", "bllvm-protocol"],
+///   "batches": [["bllvm-consensus", "bllvm-sdk"], ["bllvm-protocol"]]
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BuildOrder {
+    /// Repos in build order ([`VersionsManifest::build_order`])
+    pub order: Vec<String>,
+
+    /// Repos grouped into parallel batches ([`VersionsManifest::build_batches`])
+    pub batches: Vec<Vec<String>>,
+}
+
+impl BuildPlan {
+    /// Compute a plan that only rebuilds repos changed since `lockfile`
+    ///
+    /// `commits` maps repo names to their current resolved commit; repos
+    /// missing from it use the manifest's `git_commit`. A repo is rebuilt if
+    /// it is not in the lockfile, its version, tag or commit differs from the
+    /// locked one, or any of its transitive dependencies is rebuilt.
+    pub fn incremental(
+        manifest: &VersionsManifest,
+        lockfile: &Lockfile,
+        commits: &HashMap<String, String>,
+    ) -> Result<Self, Error> {
+        let mut plan = manifest.build_plan()?;
+        let mut dirty: HashSet<String> = HashSet::new();
+
+        // Repos are in build order, so dependencies are decided first
+        for repo in &mut plan.repos {
+            if let Some(commit) = commits.get(&repo.name).filter(|c| !c.is_empty()) {
+                repo.git_commit = Some(commit.clone());
+            }
+
+            let changed = match lockfile.locked.iter().find(|l| l.name == repo.name) {
+                None => true,
+                Some(locked) => {
+                    locked.version != repo.version
+                        || locked.git_tag != repo.git_tag
+                        || locked.git_commit != repo.git_commit
+                }
+            };
+
+            repo.rebuild = changed || repo.dependencies.iter().any(|dep| dirty.contains(dep));
+            if repo.rebuild {
+                dirty.insert(repo.name.clone());
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Names of the repos that need rebuilding, in build order
+    pub fn to_rebuild(&self) -> Vec<String> {
+        self.repos
+            .iter()
+            .filter(|repo| repo.rebuild)
+            .map(|repo| repo.name.clone())
+            .collect()
+    }
+
+    /// Human-readable description of what building this plan would do
+    ///
+    /// Lists each batch with its repos, the tag (and commit, if pinned) each
+    /// builds from and whether it is dirty (will be built) or fresh (its
+    /// previous build is reused, see [`incremental`](Self::incremental)).
+    /// The summary gives the number of batches and the estimated parallel
+    /// depth, i.e. how many of them contain something to build. Only looks at
+    /// the plan itself.
+    pub fn explain(&self) -> String {
+        let dirty = self.repos.iter().filter(|repo| repo.rebuild).count();
+        let depth = self
+            .stages
+            .iter()
+            .filter(|stage| {
+                self.repos
+                    .iter()
+                    .any(|repo| repo.rebuild && stage.contains(&repo.name))
+            })
+            .count();
+
+        let mut out = format!(
+            "Build plan: {} batch(es), {} repo(s), {} to build, {} fresh\n",
+            self.stages.len(),
+            self.repos.len(),
+            dirty,
+            self.repos.len() - dirty
+        );
+        for (index, stage) in self.stages.iter().enumerate() {
+            out.push_str(&format!("\nBatch {}:\n", index));
+            for repo in self.repos.iter().filter(|repo| stage.contains(&repo.name)) {
+                let source = match &repo.git_commit {
+                    Some(commit) => format!("{} @ {}", repo.git_tag, short_commit(commit)),
+                    None => repo.git_tag.clone(),
+                };
+                out.push_str(&format!(
+                    "  {} {} ({}) {}\n",
+                    repo.name,
+                    repo.version,
+                    source,
+                    if repo.rebuild { "dirty" } else { "fresh" }
+                ));
+            }
+        }
+        out.push_str(&format!(
+            "\nEstimated parallel depth: {} of {} batch(es)\n",
+            depth,
+            self.stages.len()
+        ));
+        out
+    }
+
+    /// Serialize the plan as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::serialize("build plan", e))
+    }
+}
+
+/// First 12 characters of a commit hash, as `git log --oneline` would show
+fn short_commit(commit: &str) -> &str {
+    commit.get(..12).unwrap_or(commit)
+}
+
+impl VersionsManifest {
+    /// Compute the build plan (stages plus per-repo details)
+    pub fn build_plan(&self) -> Result<BuildPlan, Error> {
+        let stages = self.build_stages()?;
+
+        let mut repos = Vec::new();
+        for (stage, names) in stages.iter().enumerate() {
+            for name in names {
+                let info = &self.versions[name];
+                let mut dependencies: Vec<String> = info
+                    .requires
+                    .iter()
+                    .map(|dep| dependency_name(dep))
+                    .filter(|dep| self.versions.contains_key(*dep))
+                    .map(String::from)
+                    .collect();
+                dependencies.sort();
+                dependencies.dedup();
+
+                repos.push(PlannedRepo {
+                    name: name.clone(),
+                    version: info.version.clone(),
+                    git_tag: info.full_tag(),
+                    git_commit: info.git_commit.clone().filter(|c| !c.is_empty()),
+                    dependencies,
+                    stage,
+                    features: self.features_for(name)?,
+                    rebuild: true,
+                    metadata: info.metadata.clone(),
+                });
+            }
+        }
+
+        let mut externals: Vec<PlannedExternal> = self
+            .external
+            .iter()
+            .map(|(name, external)| {
+                let mut required_by: Vec<String> = self
+                    .versions
+                    .iter()
+                    .filter(|(_, info)| {
+                        info.all_requires()
+                            .any(|dep| dependency_name(dep) == name.as_str())
+                    })
+                    .map(|(repo, _)| repo.clone())
+                    .collect();
+                required_by.sort();
+                PlannedExternal {
+                    name: name.clone(),
+                    version: external.version.clone(),
+                    required_by,
+                }
+            })
+            .collect();
+        externals.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(BuildPlan {
+            stages,
+            repos,
+            externals,
+        })
+    }
+
+    /// Serialize the build order and batches as pretty-printed JSON
+    ///
+    /// See [`BuildOrder`] for the format. Meant for generating CI matrices
+    /// from the batches.
+    pub fn build_order_json(&self) -> Result<String, Error> {
+        let batches = self.build_batches()?;
+        let order = BuildOrder {
+            order: batches.iter().flatten().cloned().collect(),
+            batches,
+        };
+        serde_json::to_string_pretty(&order).map_err(|e| Error::serialize("build order", e))
+    }
+
+    /// Serialize the whole manifest as pretty-printed JSON
+    ///
+    /// Repos are emitted sorted by name so the output is stable.
+    pub fn to_json(&self) -> Result<String, Error> {
+        let value = serde_json::to_value(self).map_err(|e| Error::serialize("manifest", e))?;
+        serde_json::to_string_pretty(&value).map_err(|e| Error::serialize("manifest", e))
+    }
+}
@@ -0,0 +1,215 @@
+//! Progress reporting for long builds, see
+//! [`BuildExecutor::progress`](crate::executor::BuildExecutor::progress)
+//!
+//! [`LogReporter`] writes one line per event to stderr; `BarReporter` (with
+//! the `progress` feature) draws progress bars instead. `bllvm build` uses
+//! the bars when stderr is a terminal.
+
+use crate::executor::BuildReport;
+use crate::hooks::BuildResult;
+use crate::plan::BuildPlan;
+use std::time::Duration;
+
+/// How far a run has come: repos done (built, failed, skipped or up to
+/// date) out of all repos with a build command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    pub done: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    /// Share of the run that is done, 0 to 100
+    pub fn percent(&self) -> u8 {
+        if self.total == 0 {
+            return 100;
+        }
+        (self.done * 100 / self.total) as u8
+    }
+}
+
+/// Receives the progress of a build run
+///
+/// All methods default to doing nothing and are called on the thread (or
+/// task) driving the run, also with
+/// [`run_parallel`](crate::executor::BuildExecutor::run_parallel).
+pub trait ProgressReporter {
+    /// Called once with the full plan before the first build
+    fn plan_computed(&self, _plan: &BuildPlan, _progress: Progress) {}
+
+    /// Called when the build command of `repo` starts
+    fn repo_started(&self, _repo: &str, _progress: Progress) {}
+
+    /// Called when `repo` is done, with how long its build command ran
+    fn repo_finished(
+        &self,
+        _repo: &str,
+        _result: &BuildResult,
+        _duration: Duration,
+        _progress: Progress,
+    ) {
+    }
+
+    /// Called for a repo that is not built because a dependency failed or
+    /// the run stopped
+    fn repo_skipped(&self, _repo: &str, _progress: Progress) {}
+
+    /// Called once the run is over
+    fn run_finished(&self, _report: &BuildReport) {}
+}
+
+/// Plain log lines on stderr, for CI logs and other non-terminals
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogReporter;
+
+impl ProgressReporter for LogReporter {
+    fn plan_computed(&self, plan: &BuildPlan, progress: Progress) {
+        eprintln!(
+            "Building {} repo(s) in {} batch(es), {} up to date",
+            progress.total - progress.done,
+            plan.stages.len(),
+            progress.done
+        );
+    }
+
+    fn repo_started(&self, repo: &str, progress: Progress) {
+        eprintln!("[{:>3}%] Building {}", progress.percent(), repo);
+    }
+
+    fn repo_finished(
+        &self,
+        repo: &str,
+        result: &BuildResult,
+        duration: Duration,
+        progress: Progress,
+    ) {
+        match result {
+            BuildResult::Succeeded => eprintln!(
+                "[{:>3}%] Built {} in {:.1}s",
+                progress.percent(),
+                repo,
+                duration.as_secs_f64()
+            ),
+            BuildResult::Failed(reason) => eprintln!(
+                "[{:>3}%] Failed {} after {:.1}s: {}",
+                progress.percent(),
+                repo,
+                duration.as_secs_f64(),
+                reason
+            ),
+        }
+    }
+
+    fn repo_skipped(&self, repo: &str, progress: Progress) {
+        eprintln!("[{:>3}%] Skipped {}", progress.percent(), repo);
+    }
+}
+
+#[cfg(feature = "progress")]
+pub use bar::BarReporter;
+
+#[cfg(feature = "progress")]
+mod bar {
+    use super::{Progress, ProgressReporter};
+    use crate::executor::BuildReport;
+    use crate::hooks::BuildResult;
+    use crate::plan::BuildPlan;
+    use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// An overall progress bar plus a spinner per running build, on stderr
+    pub struct BarReporter {
+        bars: MultiProgress,
+        overall: ProgressBar,
+        running: Mutex<HashMap<String, ProgressBar>>,
+    }
+
+    impl Default for BarReporter {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl BarReporter {
+        pub fn new() -> Self {
+            let bars = MultiProgress::new();
+            let overall = bars.add(ProgressBar::new(0));
+            overall.set_style(
+                ProgressStyle::with_template("{bar:40} {pos}/{len} ({percent}%) {elapsed}")
+                    .expect("template is valid"),
+            );
+            Self {
+                bars,
+                overall,
+                running: Mutex::new(HashMap::new()),
+            }
+        }
+
+        fn set(&self, progress: Progress) {
+            self.overall.set_length(progress.total as u64);
+            self.overall.set_position(progress.done as u64);
+        }
+
+        fn stop(&self, repo: &str) {
+            let spinner = self
+                .running
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(repo);
+            if let Some(spinner) = spinner {
+                spinner.finish_and_clear();
+                self.bars.remove(&spinner);
+            }
+        }
+    }
+
+    impl ProgressReporter for BarReporter {
+        fn plan_computed(&self, _plan: &BuildPlan, progress: Progress) {
+            self.set(progress);
+        }
+
+        fn repo_started(&self, repo: &str, progress: Progress) {
+            let spinner = self.bars.add(ProgressBar::new_spinner());
+            spinner.set_style(
+                ProgressStyle::with_template("{spinner} {msg} {elapsed}")
+                    .expect("template is valid"),
+            );
+            spinner.set_message(repo.to_string());
+            spinner.enable_steady_tick(Duration::from_millis(120));
+            self.running
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(repo.to_string(), spinner);
+            self.set(progress);
+        }
+
+        fn repo_finished(
+            &self,
+            repo: &str,
+            result: &BuildResult,
+            duration: Duration,
+            progress: Progress,
+        ) {
+            self.stop(repo);
+            let line = match result {
+                BuildResult::Succeeded => {
+                    format!("Built {} in {:.1}s", repo, duration.as_secs_f64())
+                }
+                BuildResult::Failed(reason) => format!("Failed {}: {}", repo, reason),
+            };
+            let _ = self.bars.println(line);
+            self.set(progress);
+        }
+
+        fn repo_skipped(&self, repo: &str, progress: Progress) {
+            let _ = self.bars.println(format!("Skipped {}", repo));
+            self.set(progress);
+        }
+
+        fn run_finished(&self, _report: &BuildReport) {
+            self.overall.finish_and_clear();
+        }
+    }
+}
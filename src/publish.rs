@@ -0,0 +1,109 @@
+//! Release ordering for publishing to a registry
+
+use crate::versions::{BuildOrderOptions, ValidationError, VersionsManifest};
+use crate::Error;
+use semver::Version;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Tells which versions a registry (e.g. crates.io) already has
+pub trait RegistryChecker {
+    /// Whether `version` of `repo` has been published
+    fn is_published(&self, repo: &str, version: &str) -> anyhow::Result<bool>;
+}
+
+impl<T: RegistryChecker + ?Sized> RegistryChecker for &T {
+    fn is_published(&self, repo: &str, version: &str) -> anyhow::Result<bool> {
+        (**self).is_published(repo, version)
+    }
+}
+
+/// In-memory registry for tests and offline use
+#[derive(Debug, Clone, Default)]
+pub struct MockRegistry {
+    published: HashSet<(String, String)>,
+}
+
+impl MockRegistry {
+    /// Create a registry with nothing published
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark `version` of `repo` as published
+    pub fn with_published(mut self, repo: &str, version: &str) -> Self {
+        self.published
+            .insert((repo.to_string(), version.to_string()));
+        self
+    }
+}
+
+impl RegistryChecker for MockRegistry {
+    fn is_published(&self, repo: &str, version: &str) -> anyhow::Result<bool> {
+        Ok(self
+            .published
+            .contains(&(repo.to_string(), version.to_string())))
+    }
+}
+
+/// Outcome of [`VersionsManifest::publish_order_skipping`]
+///
+/// Both lists are in publish order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PublishOrder {
+    /// Repos still to be tagged and published
+    pub pending: Vec<String>,
+
+    /// Repos whose manifest version the registry already has
+    pub published: Vec<String>,
+}
+
+impl VersionsManifest {
+    /// Order in which repos must be tagged and published
+    ///
+    /// The build order, with stricter checks since a published release
+    /// cannot be fixed afterwards: every version must be valid semver, every
+    /// `requires` entry and every `optional_requires` entry on a repo in the
+    /// manifest must accept the dependency's current version (otherwise the
+    /// repo would be published against a stale release), and no yanked
+    /// version may be required.
+    pub fn publish_order(&self) -> Result<Vec<String>, Error> {
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+        for repo in repos {
+            let info = &self.versions[repo];
+            if Version::parse(&info.version).is_err() {
+                return Err(ValidationError::InvalidVersion {
+                    repo: repo.clone(),
+                    version: info.version.clone(),
+                }
+                .into());
+            }
+            for spec in info.all_requires() {
+                self.resolve_requirement(repo, spec)?;
+            }
+        }
+
+        self.build_order_with_options(&BuildOrderOptions {
+            strict: true,
+            ..Default::default()
+        })
+    }
+
+    /// [`publish_order`](Self::publish_order), setting aside repos that
+    /// `registry` already has at their manifest version
+    pub fn publish_order_skipping<R: RegistryChecker>(
+        &self,
+        registry: R,
+    ) -> anyhow::Result<PublishOrder> {
+        let mut order = PublishOrder::default();
+        for repo in self.publish_order()? {
+            if registry.is_published(&repo, &self.versions[&repo].version)? {
+                order.published.push(repo);
+            } else {
+                order.pending.push(repo);
+            }
+        }
+        Ok(order)
+    }
+}
@@ -0,0 +1,63 @@
+//! Local path overrides (`[replace]` table) for cross-repo development
+//!
+//! ```toml
+//! [replace]
+//! bllvm-consensus = { path = "../bllvm-consensus" }
+//! ```
+//!
+//! A replaced repo is resolved and ordered as before; only where its
+//! source lives changes, see [`VersionsManifest::source_of`].
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::PathBuf;
+
+/// One `[replace]` entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Replacement {
+    /// Checkout to use instead of cloning, relative to the manifest's directory
+    pub path: PathBuf,
+}
+
+/// Where a repo's source comes from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Cloned from `url` at `tag`
+    Git { url: String, tag: String },
+    /// A local checkout from `[replace]`, as written there
+    Path(PathBuf),
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Git { url, tag } => write!(f, "{} at {}", url, tag),
+            Source::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl VersionsManifest {
+    /// Where the source of `name` comes from: its `[replace]` path, or its
+    /// [`git_url_for`](Self::git_url_for) at its
+    /// [`resolved_tag`](Self::resolved_tag)
+    ///
+    /// Unknown names are an error suggesting close matches, as is a repo
+    /// without a clone URL that is not replaced.
+    pub fn source_of(&self, name: &str) -> Result<Source, Error> {
+        if !self.versions.contains_key(name) {
+            return Err(self.unknown_repo(name));
+        }
+        if let Some(replacement) = self.replace.get(name) {
+            return Ok(Source::Path(replacement.path.clone()));
+        }
+        Ok(Source::Git {
+            url: self.git_url_for(name)?,
+            tag: self.resolved_tag(name)?,
+        })
+    }
+}
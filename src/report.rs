@@ -0,0 +1,151 @@
+//! At-a-glance health summary of a manifest, for CI logs
+//!
+//! ```text
+//! Repositories: 6
+//! Roots (no dependents): 3 (bllvm-node, docs, governance-app)
+//! Leaves (no dependencies): 3 (bllvm-consensus, bllvm-sdk, docs)
+//! Max depth: 2
+//! Cycles: none
+//! Warnings: none
+//! ```
+
+use crate::versions::{VersionsManifest, Warning};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+/// Summary of a manifest's dependency graph, see [`VersionsManifest::report`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ManifestReport {
+    /// Number of repos
+    pub repos: usize,
+
+    /// Repos no other repo requires, by name
+    pub roots: Vec<String>,
+
+    /// Repos that require no other repo, by name
+    pub leaves: Vec<String>,
+
+    /// Longest chain of dependencies, counted in edges; `None` when there
+    /// is a cycle
+    pub max_depth: Option<usize>,
+
+    /// One cycle per group of repos that depend on each other, as in
+    /// [`find_cycles`](VersionsManifest::find_cycles)
+    pub cycles: Vec<Vec<String>>,
+
+    /// Same as [`warnings`](VersionsManifest::warnings)
+    pub warnings: Vec<Warning>,
+}
+
+impl ManifestReport {
+    /// Whether there is neither a cycle nor a warning
+    pub fn is_healthy(&self) -> bool {
+        self.cycles.is_empty() && self.warnings.is_empty()
+    }
+}
+
+impl VersionsManifest {
+    /// Repo counts, roots and leaves, depth, cycles and warnings in one go
+    ///
+    /// Only `requires` edges between repos in the manifest are counted, as
+    /// in [`build_order`](Self::build_order), and requirements are not
+    /// checked against versions. Unlike the build order this never fails:
+    /// cycles are reported instead.
+    pub fn report(&self) -> ManifestReport {
+        let edges = self.dependency_edges();
+        let required: BTreeSet<&str> = edges
+            .iter()
+            .flat_map(|(repo, deps)| deps.iter().filter(move |dep| *dep != repo))
+            .copied()
+            .collect();
+
+        let roots = edges
+            .keys()
+            .filter(|repo| !required.contains(*repo))
+            .map(|repo| repo.to_string())
+            .collect();
+        let leaves = edges
+            .iter()
+            .filter(|(repo, deps)| deps.iter().all(|dep| dep == *repo))
+            .map(|(repo, _)| repo.to_string())
+            .collect();
+
+        let cycles = self.find_cycles();
+        let max_depth = cycles.is_empty().then(|| {
+            let mut depths = HashMap::new();
+            edges
+                .keys()
+                .map(|repo| depth(&edges, repo, &mut depths))
+                .max()
+                .unwrap_or_default()
+        });
+
+        ManifestReport {
+            repos: self.versions.len(),
+            roots,
+            leaves,
+            max_depth,
+            cycles,
+            warnings: self.warnings(),
+        }
+    }
+}
+
+/// Longest chain of dependencies below `repo`; `edges` must be acyclic
+pub(crate) fn depth<'a>(
+    edges: &BTreeMap<&'a str, Vec<&'a str>>,
+    repo: &'a str,
+    depths: &mut HashMap<&'a str, usize>,
+) -> usize {
+    if let Some(&known) = depths.get(repo) {
+        return known;
+    }
+    let below = edges
+        .get(repo)
+        .into_iter()
+        .flatten()
+        .map(|dep| depth(edges, dep, depths) + 1)
+        .max()
+        .unwrap_or_default();
+    depths.insert(repo, below);
+    below
+}
+
+impl fmt::Display for ManifestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Repositories: {}", self.repos)?;
+        writeln!(f, "Roots (no dependents): {}", counted(&self.roots))?;
+        writeln!(f, "Leaves (no dependencies): {}", counted(&self.leaves))?;
+        match self.max_depth {
+            Some(depth) => writeln!(f, "Max depth: {}", depth)?,
+            None => writeln!(f, "Max depth: unknown (dependency cycle)")?,
+        }
+        if self.cycles.is_empty() {
+            writeln!(f, "Cycles: none")?;
+        } else {
+            writeln!(f, "Cycles: {}", self.cycles.len())?;
+            for cycle in &self.cycles {
+                writeln!(f, "  {}", cycle.join(" -> "))?;
+            }
+        }
+        if self.warnings.is_empty() {
+            writeln!(f, "Warnings: none")
+        } else {
+            writeln!(f, "Warnings: {}", self.warnings.len())?;
+            for warning in &self.warnings {
+                writeln!(f, "  {}", warning)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `2 (a, b)`, or just `0`
+fn counted(repos: &[String]) -> String {
+    if repos.is_empty() {
+        "0".to_string()
+    } else {
+        format!("{} ({})", repos.len(), repos.join(", "))
+    }
+}
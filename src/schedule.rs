@@ -0,0 +1,182 @@
+//! Predicted parallel build schedule from per-repo `build_weight`s
+//!
+//! Unlike [`build_batches`](VersionsManifest::build_batches), where every
+//! repo of a batch waits for the whole previous batch, a worker starts a
+//! repo as soon as the repos it requires are built.
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Simulated build with a fixed number of workers, see
+/// [`VersionsManifest::schedule`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Schedule {
+    /// When the last repo is done, in `build_weight` units (seconds)
+    pub makespan: u64,
+
+    /// What each worker builds, in the order it builds them
+    pub workers: Vec<Vec<ScheduledRepo>>,
+}
+
+/// One repo on a worker's timeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ScheduledRepo {
+    pub repo: String,
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Schedule {
+    /// Share of worker time spent building, 0 to 100
+    pub fn utilization(&self) -> u8 {
+        let busy: u64 = self
+            .workers
+            .iter()
+            .flatten()
+            .map(|slot| slot.end - slot.start)
+            .sum();
+        let available = self.makespan * self.workers.len() as u64;
+        if available == 0 {
+            return 100;
+        }
+        (busy * 100 / available) as u8
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Makespan: {}s on {} worker(s) ({}% busy)",
+            self.makespan,
+            self.workers.len(),
+            self.utilization()
+        )?;
+        for (index, slots) in self.workers.iter().enumerate() {
+            let timeline: Vec<String> = slots
+                .iter()
+                .map(|slot| format!("{} [{}-{}]", slot.repo, slot.start, slot.end))
+                .collect();
+            writeln!(f, "  worker {}: {}", index + 1, timeline.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl VersionsManifest {
+    /// Simulate building every repo with `workers` workers
+    ///
+    /// Each repo takes its [`build_weight`](crate::versions::RepoVersion::build_weight)
+    /// (1 if unset); `external` repos take no time and no worker. Whenever a
+    /// worker is free it starts the ready repo with the heaviest chain of
+    /// dependents still ahead of it (ties go to the name that sorts first),
+    /// a list schedule that is close to, but not always, the optimum.
+    /// Only required dependencies are followed; cycles are an error.
+    pub fn schedule(&self, workers: usize) -> Result<Schedule, Error> {
+        if workers == 0 {
+            return Err(Error::NoWorkers);
+        }
+        let graph = self.graph()?;
+        let weight = |repo: &str| {
+            let info = &self.versions[repo];
+            if info.external {
+                0
+            } else {
+                info.build_weight.unwrap_or(1)
+            }
+        };
+
+        // Weight of each repo plus its heaviest chain of dependents
+        let mut priority: HashMap<&str, u64> = HashMap::new();
+        for repo in graph.topo_order().into_iter().rev() {
+            let ahead = graph
+                .dependents(repo)?
+                .into_iter()
+                .map(|dependent| priority[dependent])
+                .max()
+                .unwrap_or_default();
+            priority.insert(repo, weight(repo) + ahead);
+        }
+
+        let mut waiting: HashMap<&str, usize> = HashMap::new();
+        let mut ready: Vec<&str> = Vec::new();
+        for &repo in graph.repos() {
+            let deps = graph.dependencies(repo)?.len();
+            if deps == 0 {
+                ready.push(repo);
+            }
+            waiting.insert(repo, deps);
+        }
+
+        let mut timelines: Vec<Vec<ScheduledRepo>> = vec![Vec::new(); workers];
+        // (end, worker, repo) of the builds in progress
+        let mut running: Vec<(u64, usize, &str)> = Vec::new();
+        let mut idle: Vec<usize> = (0..workers).rev().collect();
+        let mut now = 0;
+        let mut makespan = 0;
+
+        loop {
+            // External repos are done as soon as they are ready
+            while let Some(index) = ready.iter().position(|repo| self.versions[*repo].external) {
+                let repo = ready.remove(index);
+                for dependent in graph.dependents(repo)? {
+                    let count = waiting.get_mut(dependent).expect("every repo is counted");
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(dependent);
+                    }
+                }
+            }
+
+            while !ready.is_empty() {
+                let Some(worker) = idle.pop() else {
+                    break;
+                };
+                let index = (0..ready.len())
+                    .max_by(|&a, &b| {
+                        priority[ready[a]]
+                            .cmp(&priority[ready[b]])
+                            .then_with(|| ready[b].cmp(ready[a]))
+                    })
+                    .expect("ready is not empty");
+                let repo = ready.swap_remove(index);
+                let end = now + weight(repo);
+                timelines[worker].push(ScheduledRepo {
+                    repo: repo.to_string(),
+                    start: now,
+                    end,
+                });
+                running.push((end, worker, repo));
+            }
+
+            let Some(next) = running.iter().map(|(end, _, _)| *end).min() else {
+                break;
+            };
+            now = next;
+            makespan = makespan.max(now);
+            let (done, still_running): (Vec<_>, Vec<_>) =
+                running.into_iter().partition(|(end, _, _)| *end == now);
+            running = still_running;
+            for (_, worker, repo) in done {
+                idle.push(worker);
+                for dependent in graph.dependents(repo)? {
+                    let count = waiting.get_mut(dependent).expect("every repo is counted");
+                    *count -= 1;
+                    if *count == 0 {
+                        ready.push(dependent);
+                    }
+                }
+            }
+            // Lowest-numbered free worker first
+            idle.sort_unstable_by(|a, b| b.cmp(a));
+        }
+
+        Ok(Schedule {
+            makespan,
+            workers: timelines,
+        })
+    }
+}
@@ -0,0 +1,106 @@
+//! Manifest file formats
+//!
+//! Every format deserializes into the same [`VersionsManifest`]; only the
+//! front-end differs. TOML is the native format and the only one whose errors
+//! name the repo they occur in.
+
+use crate::unknown_fields::EntryKeys;
+use crate::versions::{SchemaOnly, VersionsManifest};
+use crate::Error;
+use std::path::Path;
+
+/// Deserialization front-end for one manifest format
+pub trait ManifestSource {
+    /// Parse manifest `content`
+    ///
+    /// Errors name the source `<inline>`;
+    /// [`from_file`](VersionsManifest::from_file) replaces it with the path.
+    fn parse(&self, content: &str) -> Result<VersionsManifest, Error>;
+}
+
+/// versions.toml, see [`VersionsManifest::from_str`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TomlSource;
+
+impl ManifestSource for TomlSource {
+    fn parse(&self, content: &str) -> Result<VersionsManifest, Error> {
+        VersionsManifest::from_str(content)
+    }
+}
+
+/// JSON with the same structure as versions.toml
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonSource;
+
+impl ManifestSource for JsonSource {
+    fn parse(&self, content: &str) -> Result<VersionsManifest, Error> {
+        if let Ok(schema) = serde_json::from_str::<SchemaOnly>(content) {
+            schema.check(INLINE)?;
+        }
+        let mut manifest: VersionsManifest =
+            serde_json::from_str(content).map_err(|e| Error::Parse {
+                file: INLINE.to_string(),
+                line: Some(e.line()),
+                column: Some(e.column()),
+                repo: None,
+                message: e.to_string(),
+            })?;
+        if let Ok(keys) = serde_json::from_str::<EntryKeys>(content) {
+            manifest.parse_warnings = keys.warnings();
+        }
+        manifest.finish_parse(INLINE, |_, _| None)
+    }
+}
+
+/// YAML with the same structure as versions.toml (requires the `yaml` feature)
+#[cfg(feature = "yaml")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct YamlSource;
+
+#[cfg(feature = "yaml")]
+impl ManifestSource for YamlSource {
+    fn parse(&self, content: &str) -> Result<VersionsManifest, Error> {
+        if let Ok(schema) = serde_yaml::from_str::<SchemaOnly>(content) {
+            schema.check(INLINE)?;
+        }
+        let mut manifest: VersionsManifest =
+            serde_yaml::from_str(content).map_err(|e| Error::Parse {
+                file: INLINE.to_string(),
+                line: e.location().map(|location| location.line()),
+                column: e.location().map(|location| location.column()),
+                repo: None,
+                message: e.to_string(),
+            })?;
+        if let Ok(keys) = serde_yaml::from_str::<EntryKeys>(content) {
+            manifest.parse_warnings = keys.warnings();
+        }
+        manifest.finish_parse(INLINE, |_, _| None)
+    }
+}
+
+const INLINE: &str = "<inline>";
+
+/// Format of the manifest at `path`, from its extension
+///
+/// `.json` is JSON, `.yaml` and `.yml` are YAML and anything else is TOML.
+/// YAML without the `yaml` feature is an error.
+pub fn source_for_path(path: &Path) -> Result<Box<dyn ManifestSource>, Error> {
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("json") => Ok(Box::new(JsonSource)),
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => Ok(Box::new(YamlSource)),
+        #[cfg(not(feature = "yaml"))]
+        Some("yaml" | "yml") => Err(Error::Parse {
+            file: path.display().to_string(),
+            line: None,
+            column: None,
+            repo: None,
+            message: "YAML manifests need bllvm built with the `yaml` feature".to_string(),
+        }),
+        _ => Ok(Box::new(TomlSource)),
+    }
+}
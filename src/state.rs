@@ -0,0 +1,242 @@
+//! Record of successful builds for incremental `bllvm build`
+//!
+//! ```toml
+//! [built.bllvm-protocol]
+//! version = "0.1.0"
+//! git_tag = "v0.1.0"
+//!
+//! [built.bllvm-protocol.dependencies]
+//! bllvm-consensus = "0.1.0 v0.1.0"
+//! ```
+//!
+//! A repo counts as up to date while its version, full git tag and the
+//! version and tag of each required dependency still match what was recorded
+//! when it last built, and every dependency is up to date itself. So a
+//! changed repo is rebuilt together with everything depending on it.
+
+use crate::versions::{dependency_name, VersionsManifest};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Header written at the top of every state file
+const STATE_HEADER: &str =
+    "# Written by bllvm build. Delete it (or run `bllvm clean-state`) to rebuild everything.\n\n";
+
+/// File name of the state file written next to versions.toml by `bllvm build`
+pub const STATE_FILE_NAME: &str = ".bllvm-state.toml";
+
+/// Repos that built successfully, and what they were built from
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildState {
+    /// Last successful build of each repo, by name
+    #[serde(default)]
+    pub built: BTreeMap<String, BuiltRepo>,
+}
+
+/// What one repo was built from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuiltRepo {
+    pub version: String,
+
+    /// Full git tag (including any `tag_prefix`)
+    pub git_tag: String,
+
+    /// `"<version> <git_tag>"` of each required dependency at build time
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub dependencies: BTreeMap<String, String>,
+}
+
+impl BuiltRepo {
+    /// Entry for building `repo` as currently declared in `manifest`
+    fn current(manifest: &VersionsManifest, repo: &str) -> Option<Self> {
+        let info = manifest.versions.get(repo)?;
+        let dependencies = info
+            .requires
+            .iter()
+            .filter_map(|spec| manifest.versions.get_key_value(dependency_name(spec)))
+            .map(|(dep, dep_info)| {
+                let built_from = format!("{} {}", dep_info.version, dep_info.full_tag());
+                (dep.clone(), built_from)
+            })
+            .collect();
+        Some(Self {
+            version: info.version.clone(),
+            git_tag: info.full_tag(),
+            dependencies,
+        })
+    }
+}
+
+impl BuildState {
+    /// Load a state file, or an empty state if it does not exist
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => anyhow::bail!("Failed to read build state {}: {}", path.display(), e),
+        };
+
+        toml::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Failed to parse build state {}: {}", path.display(), e))
+    }
+
+    /// Write the state file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let body = toml::to_string(self)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize build state: {}", e))?;
+        std::fs::write(path, format!("{}{}", STATE_HEADER, body))
+            .map_err(|e| anyhow::anyhow!("Failed to write build state {}: {}", path.display(), e))
+    }
+
+    /// Path of the state file that belongs to the manifest at `manifest_path`
+    pub fn path_for<P: AsRef<Path>>(manifest_path: P) -> PathBuf {
+        manifest_path.as_ref().with_file_name(STATE_FILE_NAME)
+    }
+
+    /// Record that `repo` built successfully from its current manifest entry
+    pub fn record(&mut self, manifest: &VersionsManifest, repo: &str) {
+        if let Some(built) = BuiltRepo::current(manifest, repo) {
+            self.built.insert(repo.to_string(), built);
+        }
+    }
+
+    /// Repos that do not need to be rebuilt
+    ///
+    /// Repos without a `build` command have nothing to record and count as
+    /// up to date unless one of their dependencies is not.
+    pub fn up_to_date(&self, manifest: &VersionsManifest) -> anyhow::Result<HashSet<String>> {
+        let mut up_to_date = HashSet::new();
+        for repo in manifest.build_order()? {
+            let info = &manifest.versions[&repo];
+            let unchanged = info.build.is_none()
+                || self.built.get(&repo) == BuiltRepo::current(manifest, &repo).as_ref();
+            let deps_up_to_date = info.requires.iter().all(|spec| {
+                let dep = dependency_name(spec);
+                !manifest.versions.contains_key(dep) || up_to_date.contains(dep)
+            });
+            if unchanged && deps_up_to_date {
+                up_to_date.insert(repo);
+            }
+        }
+        Ok(up_to_date)
+    }
+
+    /// Drop the entries of repos that are no longer up to date
+    ///
+    /// Covers repos whose version, tag or dependencies changed, their
+    /// transitive dependents, and repos removed from the manifest. Returns
+    /// the dropped repos by name.
+    pub fn invalidate(&mut self, manifest: &VersionsManifest) -> anyhow::Result<Vec<String>> {
+        let up_to_date = self.up_to_date(manifest)?;
+        let stale: Vec<String> = self
+            .built
+            .keys()
+            .filter(|repo| !up_to_date.contains(*repo))
+            .cloned()
+            .collect();
+        for repo in &stale {
+            self.built.remove(repo);
+        }
+        Ok(stale)
+    }
+}
+
+impl VersionsManifest {
+    /// [`build_order`](Self::build_order) without the repos `state` has as up to date
+    ///
+    /// See [`BuildState::up_to_date`].
+    pub fn build_order_with_state(&self, state: &BuildState) -> anyhow::Result<Vec<String>> {
+        let up_to_date = state.up_to_date(self)?;
+        Ok(self
+            .build_order()?
+            .into_iter()
+            .filter(|repo| !up_to_date.contains(repo))
+            .collect())
+    }
+
+    /// [`build_order`](Self::build_order) without the repos in `done`
+    ///
+    /// For resuming an interrupted build from a known list of finished
+    /// repos. Every repo in `done` must be in the manifest and have all of
+    /// its required dependencies in `done` as well: a repo cannot have been
+    /// built while something it needs is still to be built. Anything else
+    /// is an error naming the first offending repo.
+    pub fn remaining_order(&self, done: &HashSet<String>) -> Result<Vec<String>, Error> {
+        let mut sorted: Vec<&String> = done.iter().collect();
+        sorted.sort();
+        if let Some(unknown) = sorted
+            .iter()
+            .find(|repo| !self.versions.contains_key(**repo))
+        {
+            return Err(self.unknown_repo(unknown));
+        }
+
+        let order = self.build_order()?;
+        let deps = self.required_deps()?;
+        for repo in order.iter().filter(|repo| done.contains(*repo)) {
+            let mut pending: Vec<&str> = deps[repo.as_str()]
+                .iter()
+                .copied()
+                .filter(|dep| !done.contains(*dep))
+                .collect();
+            pending.sort();
+            if let Some(dep) = pending.first() {
+                return Err(Error::PendingDependency {
+                    repo: repo.clone(),
+                    dependency: dep.to_string(),
+                });
+            }
+        }
+
+        Ok(order
+            .into_iter()
+            .filter(|repo| !done.contains(repo))
+            .collect())
+    }
+}
+
+/// Exclusive lock on a state file, released when dropped
+///
+/// Held as `<state file>.lock`, created with the process id inside. A lock
+/// left behind by a crashed run has to be removed by hand or with
+/// `bllvm clean-state`.
+#[derive(Debug)]
+pub struct StateLock {
+    path: PathBuf,
+}
+
+impl StateLock {
+    /// Lock the state file at `state_path`, failing if another run holds it
+    pub fn acquire<P: AsRef<Path>>(state_path: P) -> anyhow::Result<Self> {
+        let path = Self::path_for(state_path);
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => anyhow::bail!(
+                "Build state is locked by another build ({} exists; remove it if no build is running)",
+                path.display()
+            ),
+            Err(e) => anyhow::bail!("Failed to create {}: {}", path.display(), e),
+        };
+        let _ = writeln!(file, "{}", std::process::id());
+        Ok(Self { path })
+    }
+
+    /// Path of the lock file for the state file at `state_path`
+    pub fn path_for<P: AsRef<Path>>(state_path: P) -> PathBuf {
+        let mut path = state_path.as_ref().as_os_str().to_owned();
+        path.push(".lock");
+        PathBuf::from(path)
+    }
+}
+
+impl Drop for StateLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
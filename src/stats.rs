@@ -0,0 +1,137 @@
+//! Dependency graph statistics and critical-path analysis
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Shape of the dependency graph, see [`VersionsManifest::graph_stats`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphStats {
+    /// Number of repos
+    pub nodes: usize,
+
+    /// Number of required dependencies between repos
+    pub edges: usize,
+
+    /// Chain with the most repos, starting with the repo built first
+    pub longest_chain: Vec<String>,
+
+    /// Per-repo figures, by name
+    pub repos: BTreeMap<String, RepoStats>,
+
+    /// Set by [`graph_stats_with_durations`](VersionsManifest::graph_stats_with_durations)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timing: Option<Timing>,
+}
+
+/// Where one repo sits in the dependency graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct RepoStats {
+    /// Longest chain of dependencies below the repo (0 without dependencies),
+    /// which is also its build stage
+    pub depth: usize,
+
+    /// Number of repos that require it
+    pub fan_in: usize,
+
+    /// Number of repos it requires
+    pub fan_out: usize,
+}
+
+/// Build-time analysis from measured per-repo durations
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Timing {
+    /// Chain that takes longest to build one after another
+    pub critical_path: Vec<String>,
+
+    /// Total duration of the critical path: the shortest possible wall-clock
+    /// time with unlimited parallelism
+    #[serde(serialize_with = "seconds")]
+    pub min_wall_clock: Duration,
+
+    /// Sum of all durations, i.e. the time for a fully serial build
+    #[serde(serialize_with = "seconds")]
+    pub serial: Duration,
+
+    /// Repos without a duration, counted as taking no time
+    pub unmeasured: Vec<String>,
+}
+
+impl VersionsManifest {
+    /// Node and edge counts, the longest chain, and depth and fan-in/fan-out per repo
+    ///
+    /// Only required dependencies are counted, as in
+    /// [`build_order`](Self::build_order). Cycles are an error.
+    pub fn graph_stats(&self) -> Result<GraphStats, Error> {
+        let deps = self.acyclic_deps()?;
+
+        let mut repos: BTreeMap<String, RepoStats> = deps
+            .iter()
+            .map(|(repo, repo_deps)| {
+                let stats = RepoStats {
+                    depth: 0,
+                    fan_in: 0,
+                    fan_out: repo_deps.len(),
+                };
+                (repo.to_string(), stats)
+            })
+            .collect();
+        for dep in deps.values().flatten() {
+            if let Some(stats) = repos.get_mut(*dep) {
+                stats.fan_in += 1;
+            }
+        }
+        for (depth, stage) in self.build_stages()?.iter().enumerate() {
+            for repo in stage {
+                if let Some(stats) = repos.get_mut(repo) {
+                    stats.depth = depth;
+                }
+            }
+        }
+
+        Ok(GraphStats {
+            nodes: deps.len(),
+            edges: deps.values().map(|repo_deps| repo_deps.len()).sum(),
+            longest_chain: self.heaviest_chain(|_| 1u64)?.0,
+            repos,
+            timing: None,
+        })
+    }
+
+    /// [`graph_stats`](Self::graph_stats) plus the weighted critical path
+    ///
+    /// `durations` holds how long each repo took to build, e.g. from previous
+    /// runs. Repos missing from it count as taking no time and are listed in
+    /// [`Timing::unmeasured`]; entries for repos not in the manifest are
+    /// ignored.
+    pub fn graph_stats_with_durations(
+        &self,
+        durations: &HashMap<String, Duration>,
+    ) -> Result<GraphStats, Error> {
+        let mut stats = self.graph_stats()?;
+
+        let duration = |repo: &str| durations.get(repo).copied().unwrap_or_default();
+        let (critical_path, min_wall_clock) = self.heaviest_chain(duration)?;
+        let mut unmeasured: Vec<String> = self
+            .versions
+            .keys()
+            .filter(|repo| !durations.contains_key(*repo))
+            .cloned()
+            .collect();
+        unmeasured.sort();
+
+        stats.timing = Some(Timing {
+            critical_path,
+            min_wall_clock,
+            serial: self.versions.keys().map(|repo| duration(repo)).sum(),
+            unmeasured,
+        });
+        Ok(stats)
+    }
+}
+
+fn seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
@@ -0,0 +1,424 @@
+//! Tagging every repo for a release, in dependency order (requires the `git` feature)
+//!
+//! Tag-triggered CI of a repo expects the tags of its dependencies to be
+//! there already, so [`ReleaseTagger::tag_all`] tags in
+//! [`publish_order`](VersionsManifest::publish_order). All repos are checked
+//! before the first tag is created: working trees must be clean, and a tag
+//! that already exists has to point at the checked-out commit, which makes
+//! reruns after a partial failure safe.
+//!
+//! ```no_run
+//! # fn main() -> anyhow::Result<()> {
+//! use bllvm::tagger::{repo_paths, GitCli, ReleaseTagger, TagOptions};
+//! use bllvm::versions::VersionsManifest;
+//!
+//! let manifest = VersionsManifest::from_file("versions.toml")?;
+//! let options = TagOptions {
+//!     push: Some("origin".to_string()),
+//!     dry_run: true,
+//!     ..TagOptions::default()
+//! };
+//! for tagged in ReleaseTagger::new(GitCli).tag_all(&manifest, &repo_paths(&manifest, ".".as_ref()), &options)? {
+//!     print!("{}", tagged);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::versions::VersionsManifest;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The git operations tagging needs, all on the working tree at `dir`
+///
+/// [`GitCli`] runs git; [`MockGit`] keeps everything in memory for tests.
+pub trait GitOps {
+    /// Whether there are no uncommitted changes
+    fn is_clean(&self, dir: &Path) -> anyhow::Result<bool>;
+
+    /// Commit `HEAD` points at
+    fn head_commit(&self, dir: &Path) -> anyhow::Result<String>;
+
+    /// Commit `tag` points at, `None` if there is no such tag
+    fn tag_commit(&self, dir: &Path, tag: &str) -> anyhow::Result<Option<String>>;
+
+    /// Create annotated `tag` on `HEAD`
+    fn create_tag(&self, dir: &Path, tag: &str, message: &str) -> anyhow::Result<()>;
+
+    /// Push `tag` to `remote`
+    fn push_tag(&self, dir: &Path, remote: &str, tag: &str) -> anyhow::Result<()>;
+}
+
+impl<T: GitOps + ?Sized> GitOps for &T {
+    fn is_clean(&self, dir: &Path) -> anyhow::Result<bool> {
+        (**self).is_clean(dir)
+    }
+
+    fn head_commit(&self, dir: &Path) -> anyhow::Result<String> {
+        (**self).head_commit(dir)
+    }
+
+    fn tag_commit(&self, dir: &Path, tag: &str) -> anyhow::Result<Option<String>> {
+        (**self).tag_commit(dir, tag)
+    }
+
+    fn create_tag(&self, dir: &Path, tag: &str, message: &str) -> anyhow::Result<()> {
+        (**self).create_tag(dir, tag, message)
+    }
+
+    fn push_tag(&self, dir: &Path, remote: &str, tag: &str) -> anyhow::Result<()> {
+        (**self).push_tag(dir, remote, tag)
+    }
+}
+
+/// Arguments of the `git tag` command creating `tag`
+fn tag_args<'a>(tag: &'a str, message: &'a str) -> [&'a str; 5] {
+    ["tag", "-a", tag, "-m", message]
+}
+
+/// Arguments of the `git push` command pushing `tag`
+fn push_args(remote: &str, tag: &str) -> [String; 3] {
+    [
+        "push".to_string(),
+        remote.to_string(),
+        format!("refs/tags/{}", tag),
+    ]
+}
+
+/// `git -C <dir> <args>` as it would be typed into a shell
+fn command_line<S: AsRef<str>>(dir: &Path, args: &[S]) -> String {
+    let mut line = format!("git -C {}", quote(&dir.display().to_string()));
+    for arg in args {
+        line.push(' ');
+        line.push_str(&quote(arg.as_ref()));
+    }
+    line
+}
+
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:@^{}".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// [`GitOps`] running the git CLI
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GitCli;
+
+impl GitCli {
+    fn run<S: AsRef<std::ffi::OsStr>>(dir: &Path, args: &[S]) -> anyhow::Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .output()
+            .map_err(|e| anyhow::anyhow!("Failed to run git in {}: {}", dir.display(), e))?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "git failed in {}: {}",
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+}
+
+impl GitOps for GitCli {
+    fn is_clean(&self, dir: &Path) -> anyhow::Result<bool> {
+        Ok(Self::run(dir, &["status", "--porcelain"])?.is_empty())
+    }
+
+    fn head_commit(&self, dir: &Path) -> anyhow::Result<String> {
+        Self::run(dir, &["rev-parse", "HEAD"])
+    }
+
+    fn tag_commit(&self, dir: &Path, tag: &str) -> anyhow::Result<Option<String>> {
+        let tag_ref = format!("refs/tags/{}", tag);
+        if Self::run(dir, &["tag", "--list", tag])?.is_empty() {
+            return Ok(None);
+        }
+        Self::run(dir, &["rev-parse", &format!("{}^{{commit}}", tag_ref)]).map(Some)
+    }
+
+    fn create_tag(&self, dir: &Path, tag: &str, message: &str) -> anyhow::Result<()> {
+        Self::run(dir, &tag_args(tag, message)).map(|_| ())
+    }
+
+    fn push_tag(&self, dir: &Path, remote: &str, tag: &str) -> anyhow::Result<()> {
+        Self::run(dir, &push_args(remote, tag)).map(|_| ())
+    }
+}
+
+/// In-memory [`GitOps`] for tests
+#[derive(Debug, Default)]
+pub struct MockGit {
+    repos: RefCell<HashMap<PathBuf, MockRepo>>,
+    pushed: RefCell<Vec<(PathBuf, String, String)>>,
+}
+
+#[derive(Debug, Default)]
+struct MockRepo {
+    head: String,
+    dirty: bool,
+    tags: HashMap<String, (String, String)>,
+}
+
+impl MockGit {
+    /// Create a fake without any repos
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a clean working tree at `dir` with `HEAD` at `head`
+    pub fn with_repo(self, dir: impl Into<PathBuf>, head: &str) -> Self {
+        self.repos.borrow_mut().insert(
+            dir.into(),
+            MockRepo {
+                head: head.to_string(),
+                ..MockRepo::default()
+            },
+        );
+        self
+    }
+
+    /// Give the working tree at `dir` uncommitted changes
+    pub fn with_changes(self, dir: impl AsRef<Path>) -> Self {
+        self.repo(dir.as_ref(), |repo| repo.dirty = true)
+            .expect("repo added with with_repo");
+        self
+    }
+
+    /// Add `tag` at `commit` to the repo at `dir`
+    pub fn with_tag(self, dir: impl AsRef<Path>, tag: &str, commit: &str) -> Self {
+        self.repo(dir.as_ref(), |repo| {
+            repo.tags
+                .insert(tag.to_string(), (commit.to_string(), String::new()));
+        })
+        .expect("repo added with with_repo");
+        self
+    }
+
+    /// Message of `tag` in the repo at `dir`
+    pub fn tag_message(&self, dir: impl AsRef<Path>, tag: &str) -> Option<String> {
+        self.repos
+            .borrow()
+            .get(dir.as_ref())
+            .and_then(|repo| repo.tags.get(tag))
+            .map(|(_, message)| message.clone())
+    }
+
+    /// Every `(dir, remote, tag)` pushed so far, in order
+    pub fn pushed(&self) -> Vec<(PathBuf, String, String)> {
+        self.pushed.borrow().clone()
+    }
+
+    fn repo<T>(&self, dir: &Path, f: impl FnOnce(&mut MockRepo) -> T) -> anyhow::Result<T> {
+        match self.repos.borrow_mut().get_mut(dir) {
+            Some(repo) => Ok(f(repo)),
+            None => anyhow::bail!("Not a git repository: {}", dir.display()),
+        }
+    }
+}
+
+impl GitOps for MockGit {
+    fn is_clean(&self, dir: &Path) -> anyhow::Result<bool> {
+        self.repo(dir, |repo| !repo.dirty)
+    }
+
+    fn head_commit(&self, dir: &Path) -> anyhow::Result<String> {
+        self.repo(dir, |repo| repo.head.clone())
+    }
+
+    fn tag_commit(&self, dir: &Path, tag: &str) -> anyhow::Result<Option<String>> {
+        self.repo(dir, |repo| {
+            repo.tags.get(tag).map(|(commit, _)| commit.clone())
+        })
+    }
+
+    fn create_tag(&self, dir: &Path, tag: &str, message: &str) -> anyhow::Result<()> {
+        self.repo(dir, |repo| {
+            let head = repo.head.clone();
+            repo.tags
+                .insert(tag.to_string(), (head, message.to_string()));
+        })
+    }
+
+    fn push_tag(&self, dir: &Path, remote: &str, tag: &str) -> anyhow::Result<()> {
+        self.repo(dir, |_| ())?;
+        self.pushed
+            .borrow_mut()
+            .push((dir.to_path_buf(), remote.to_string(), tag.to_string()));
+        Ok(())
+    }
+}
+
+/// How [`ReleaseTagger::tag_all`] tags
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagOptions {
+    /// Tag message, with `{repo}`, `{version}` and `{tag}` filled in
+    pub message: String,
+
+    /// Remote to push the tags to, `None` to only tag locally
+    pub push: Option<String>,
+
+    /// Check everything and report the git commands, without running them
+    pub dry_run: bool,
+}
+
+impl Default for TagOptions {
+    fn default() -> Self {
+        Self {
+            message: "Release {repo} {version}".to_string(),
+            push: None,
+            dry_run: false,
+        }
+    }
+}
+
+/// What [`ReleaseTagger::tag_all`] did to one repo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaggedRepo {
+    pub repo: String,
+
+    /// Tag as git knows it, see [`full_tag`](crate::versions::RepoVersion::full_tag)
+    pub tag: String,
+
+    /// Commit the tag points at
+    pub commit: String,
+
+    /// The tag was already there, at `commit`
+    pub existed: bool,
+
+    /// git commands run, or that would be run in a dry run
+    pub commands: Vec<String>,
+}
+
+impl fmt::Display for TaggedRepo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.existed {
+            writeln!(f, "# {} already tagged {}", self.repo, self.tag)?;
+        }
+        for command in &self.commands {
+            writeln!(f, "{}", command)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tags repos for a release through a [`GitOps`], see [`crate::tagger`]
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseTagger<G> {
+    git: G,
+}
+
+impl<G: GitOps> ReleaseTagger<G> {
+    pub fn new(git: G) -> Self {
+        Self { git }
+    }
+
+    /// Tag every repo of `manifest` with its `git_tag`, in publish order
+    ///
+    /// `repo_paths` gives each repo's checkout (see [`repo_paths`]);
+    /// `external` repos are not ours to tag and are skipped. Nothing is
+    /// tagged unless every repo has a path and a clean working tree, and
+    /// every existing tag points at the checkout's `HEAD`. Existing tags
+    /// are pushed again with [`TagOptions::push`], which git treats as a
+    /// no-op if the remote has them.
+    pub fn tag_all(
+        &self,
+        manifest: &VersionsManifest,
+        repo_paths: &HashMap<String, PathBuf>,
+        options: &TagOptions,
+    ) -> anyhow::Result<Vec<TaggedRepo>> {
+        let mut planned = Vec::new();
+        for repo in manifest.publish_order()? {
+            let info = &manifest.versions[&repo];
+            if info.external {
+                continue;
+            }
+            let Some(dir) = repo_paths.get(&repo) else {
+                anyhow::bail!("No checkout path for repository '{}'", repo);
+            };
+            if !self.git.is_clean(dir)? {
+                anyhow::bail!(
+                    "Working tree of '{}' ({}) has uncommitted changes",
+                    repo,
+                    dir.display()
+                );
+            }
+            let tag = info.full_tag();
+            let commit = self.git.head_commit(dir)?;
+            let existed = match self.git.tag_commit(dir, &tag)? {
+                Some(tagged) if tagged == commit => true,
+                Some(tagged) => anyhow::bail!(
+                    "Tag '{}' of '{}' already exists at {}, but HEAD is {}",
+                    tag,
+                    repo,
+                    tagged,
+                    commit
+                ),
+                None => false,
+            };
+            let message = options
+                .message
+                .replace("{repo}", &repo)
+                .replace("{version}", &info.version)
+                .replace("{tag}", &tag);
+            planned.push((
+                dir,
+                message,
+                TaggedRepo {
+                    repo,
+                    tag,
+                    commit,
+                    existed,
+                    commands: Vec::new(),
+                },
+            ));
+        }
+
+        let mut tagged = Vec::new();
+        for (dir, message, mut repo) in planned {
+            if !repo.existed {
+                repo.commands
+                    .push(command_line(dir, &tag_args(&repo.tag, &message)));
+                if !options.dry_run {
+                    self.git.create_tag(dir, &repo.tag, &message)?;
+                }
+            }
+            if let Some(remote) = &options.push {
+                repo.commands
+                    .push(command_line(dir, &push_args(remote, &repo.tag)));
+                if !options.dry_run {
+                    self.git.push_tag(dir, remote, &repo.tag)?;
+                }
+            }
+            tagged.push(repo);
+        }
+        Ok(tagged)
+    }
+}
+
+/// Checkout of each repo below `root`: its `path`, or a directory named
+/// after it
+pub fn repo_paths(manifest: &VersionsManifest, root: &Path) -> HashMap<String, PathBuf> {
+    manifest
+        .versions
+        .iter()
+        .map(|(repo, info)| {
+            (
+                repo.clone(),
+                root.join(info.path.as_deref().unwrap_or(repo)),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+//! Target-platform conditional `requires`
+//!
+//! ```toml
+//! [versions]
+//! bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = [
+//!     "bllvm-protocol=0.1.0",
+//!     { dep = "bllvm-ffi=0.1.0", target = "windows" },
+//!     "bllvm-winsupport=0.1.0; cfg(windows)",
+//! ] }
+//! ```
+//!
+//! A `target` is either a platform name (see `std::env::consts::OS`) or, as
+//! in Cargo's `[target.'cfg(...)'.dependencies]`, a `cfg(...)` expression
+//! such as `cfg(all(unix, target_arch = "aarch64"))`. The expression can
+//! also follow the requirement after a `;`.
+//!
+//! Conditional entries are validated, ordered and cycle-checked like any
+//! other, so [`build_order`](VersionsManifest::build_order) is correct for
+//! every target at once. [`build_order_for_target`](VersionsManifest::build_order_for_target)
+//! drops the edges that do not apply to one target.
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use cfg_expr::targets::{get_builtin_target_by_triple, TargetInfo};
+use cfg_expr::{Expression, Predicate};
+
+impl VersionsManifest {
+    /// Manifest without the `requires` entries that only apply to other targets
+    ///
+    /// `target` is a target triple such as `x86_64-pc-windows-msvc`, or a
+    /// platform name (`linux`, `windows`, `macos`), see
+    /// [`Dependency::applies_to`](crate::versions::Dependency::applies_to).
+    pub fn for_target(&self, target: &str) -> VersionsManifest {
+        let mut filtered = self.clone();
+        let entries = filtered
+            .versions
+            .values_mut()
+            .chain(filtered.multi_versions.values_mut().flatten());
+        for info in entries {
+            info.requires.retain(|dep| dep.applies_to(target));
+        }
+        filtered
+    }
+
+    /// [`build_order`](Self::build_order) when building for `target`
+    ///
+    /// Conditional dependencies for other targets neither order builds nor
+    /// form cycles; see [`for_target`](Self::for_target). Entries without a
+    /// target are always included. A malformed `cfg(...)` is an error, as is
+    /// a `target` that is not a known triple when any entry uses `cfg(...)`.
+    pub fn build_order_for_target(&self, target: &str) -> Result<Vec<String>, Error> {
+        let entries = self
+            .versions
+            .keys()
+            .flat_map(|repo| self.entries(repo).into_iter().map(move |info| (repo, info)));
+        for (repo, info) in entries {
+            for dep in &info.requires {
+                let Some(cfg) = dep.target.as_deref().filter(|only| is_cfg(only)) else {
+                    continue;
+                };
+                parse_cfg(cfg).map_err(|reason| Error::InvalidRequirement {
+                    repo: repo.clone(),
+                    requirement: dep.to_string(),
+                    reason,
+                })?;
+                if get_builtin_target_by_triple(target).is_none() {
+                    return Err(Error::UnknownTarget {
+                        target: target.to_string(),
+                    });
+                }
+            }
+        }
+        self.for_target(target).build_order()
+    }
+}
+
+/// Whether a `target` is a `cfg(...)` expression rather than a platform name
+pub(crate) fn is_cfg(target: &str) -> bool {
+    target.starts_with("cfg(")
+}
+
+/// Split `spec; cfg(...)` into the requirement and the expression
+pub(crate) fn split_cfg(spec: &str) -> Option<(&str, &str)> {
+    let (spec, cfg) = spec.split_once(';')?;
+    let cfg = cfg.trim();
+    is_cfg(cfg).then(|| (spec.trim_end(), cfg))
+}
+
+pub(crate) fn parse_cfg(cfg: &str) -> Result<Expression, String> {
+    Expression::parse(cfg).map_err(|e| format!("invalid {}: {}", cfg, e))
+}
+
+/// Whether a `target` applies to the triple (or platform name) `target`
+///
+/// Unknown triples match plain platform names by their value only, and no
+/// `cfg(...)` at all; neither does a malformed `cfg(...)`.
+pub(crate) fn target_matches(only: &str, target: &str) -> bool {
+    let info = get_builtin_target_by_triple(target);
+    if !is_cfg(only) {
+        return only == target || info.and_then(os).is_some_and(|os| os == only);
+    }
+    match (parse_cfg(only), info) {
+        (Ok(expression), Some(info)) => expression.eval(|predicate| match predicate {
+            Predicate::Target(predicate) => predicate.matches(info),
+            _ => false,
+        }),
+        _ => false,
+    }
+}
+
+fn os(info: &TargetInfo) -> Option<&str> {
+    info.os.as_ref().map(|os| os.as_str())
+}
@@ -0,0 +1,128 @@
+//! Memoized transitive dependency sets
+//!
+//! [`transitive_deps`](VersionsManifest::transitive_deps) keeps every set it
+//! computes. The cache is tagged with a fingerprint of the versions,
+//! `requires` and external packages it was computed from, so changing the
+//! manifest in any way (overrides, bumps, editing `versions` directly)
+//! starts it over.
+
+use crate::versions::{RepoVersion, VersionsManifest};
+use crate::Error;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+
+/// Transitive dependency sets by repo, see [`crate::transitive`]
+///
+/// Clones start empty, and every cache compares equal, so the cache never
+/// changes how manifests compare.
+#[derive(Default)]
+pub(crate) struct DepsCache(Mutex<Cached>);
+
+#[derive(Default)]
+struct Cached {
+    fingerprint: u64,
+    sets: HashMap<String, BTreeSet<String>>,
+}
+
+impl Clone for DepsCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl PartialEq for DepsCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for DepsCache {}
+
+impl std::fmt::Debug for DepsCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DepsCache").finish_non_exhaustive()
+    }
+}
+
+impl VersionsManifest {
+    /// Every repo `name` requires, directly or through other repos
+    ///
+    /// Follows the same `requires` edges as the build order, so the result
+    /// is what has to be built before `name`; `name` itself is left out even
+    /// if it is part of a cycle. Results are memoized, see
+    /// [`crate::transitive`]. Unknown names are an error suggesting close
+    /// matches.
+    pub fn transitive_deps(&self, name: &str) -> Result<BTreeSet<String>, Error> {
+        if !self.versions.contains_key(name) {
+            return Err(self.unknown_repo(name));
+        }
+
+        let fingerprint = self.deps_fingerprint();
+        let mut cache = self
+            .deps_cache
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if cache.fingerprint != fingerprint {
+            *cache = Cached {
+                fingerprint,
+                sets: HashMap::new(),
+            };
+        }
+        if let Some(found) = cache.sets.get(name) {
+            return Ok(found.clone());
+        }
+
+        let deps = self.required_deps()?;
+        let mut found = BTreeSet::new();
+        let mut queue = VecDeque::from([name]);
+        while let Some(repo) = queue.pop_front() {
+            for &dep in deps.get(repo).into_iter().flatten() {
+                if !found.insert(dep.to_string()) {
+                    continue;
+                }
+                match cache.sets.get(dep) {
+                    Some(known) => found.extend(known.iter().cloned()),
+                    None => queue.push_back(dep),
+                }
+            }
+        }
+        found.remove(name);
+
+        cache.sets.insert(name.to_string(), found.clone());
+        Ok(found)
+    }
+
+    /// Hash of everything the dependency edges are resolved from
+    ///
+    /// Entries are hashed one by one and summed, so the result does not
+    /// depend on map iteration order.
+    fn deps_fingerprint(&self) -> u64 {
+        let entry = |repo: &str, info: &RepoVersion| {
+            let mut hasher = DefaultHasher::new();
+            repo.hash(&mut hasher);
+            info.version.hash(&mut hasher);
+            for dep in &info.requires {
+                dep.spec.hash(&mut hasher);
+            }
+            hasher.finish()
+        };
+        let versions = self.versions.iter().map(|(repo, info)| entry(repo, info));
+        let multi_versions = self
+            .multi_versions
+            .iter()
+            .flat_map(|(repo, entries)| entries.iter().map(move |info| entry(repo, info)));
+        let external = self.external.iter().map(|(name, package)| {
+            let mut hasher = DefaultHasher::new();
+            name.hash(&mut hasher);
+            package.version.hash(&mut hasher);
+            hasher.finish()
+        });
+        versions
+            .chain(multi_versions)
+            .chain(external)
+            .fold(0, u64::wrapping_add)
+    }
+}
@@ -0,0 +1,129 @@
+//! Warnings for keys in `[versions]` entries that bllvm does not know
+//!
+//! serde ignores unknown keys, so a typo like `require = [...]` would
+//! otherwise silently drop the dependencies. The keys are read in a second,
+//! format-independent pass and compared with the fields [`RepoVersion`]
+//! deserializes.
+
+use crate::versions::{edit_distance, RepoVersion, Warning};
+use serde::de::{self, IgnoredAny, Visitor};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Just the keys of every `[versions]` entry
+#[derive(Deserialize)]
+pub(crate) struct EntryKeys {
+    #[serde(default)]
+    versions: BTreeMap<String, Entry>,
+}
+
+/// The shapes a `[versions]` entry can take
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Entry {
+    /// Array of versions, see [`crate::multi_version`]
+    Versions(Vec<BTreeMap<String, IgnoredAny>>),
+
+    /// Release channels, see [`crate::channel`]
+    Channels(BTreeMap<String, BTreeMap<String, IgnoredAny>>),
+
+    Single(BTreeMap<String, IgnoredAny>),
+}
+
+impl EntryKeys {
+    /// One [`Warning::UnknownField`] per unknown key, by repo then key
+    pub(crate) fn warnings(&self) -> Vec<Warning> {
+        let known = repo_version_fields();
+        let mut warnings = Vec::new();
+        for (repo, entry) in &self.versions {
+            let tables: Vec<&BTreeMap<String, IgnoredAny>> = match entry {
+                Entry::Versions(tables) => tables.iter().collect(),
+                Entry::Channels(channels) => channels.values().collect(),
+                Entry::Single(table) => vec![table],
+            };
+            let mut unknown: Vec<&str> = tables
+                .into_iter()
+                .flat_map(BTreeMap::keys)
+                .map(String::as_str)
+                .filter(|key| !known.contains(key))
+                .collect();
+            unknown.sort();
+            unknown.dedup();
+
+            for field in unknown {
+                warnings.push(Warning::UnknownField {
+                    repo: repo.clone(),
+                    field: field.to_string(),
+                    suggestion: closest(field, known),
+                });
+            }
+        }
+        warnings
+    }
+}
+
+/// Known key within a third of `field`'s length in edits, if any
+fn closest(field: &str, known: &[&str]) -> Option<String> {
+    let max_distance = (field.len() / 3).max(1);
+    known
+        .iter()
+        .map(|key| (edit_distance(field, key), *key))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, key)| key.to_string())
+}
+
+/// Keys [`RepoVersion`]'s derived `Deserialize` accepts
+///
+/// Read from the field list serde passes to `deserialize_struct`, so it
+/// cannot drift from the struct.
+fn repo_version_fields() -> &'static [&'static str] {
+    match RepoVersion::deserialize(FieldNames) {
+        Err(Fields(fields)) => fields,
+        Ok(_) => &[],
+    }
+}
+
+/// Deserializer that only records the field names of the struct asked for
+struct FieldNames;
+
+#[derive(Debug)]
+struct Fields(&'static [&'static str]);
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "fields: {}", self.0.join(", "))
+    }
+}
+
+impl std::error::Error for Fields {}
+
+impl de::Error for Fields {
+    fn custom<T: fmt::Display>(_msg: T) -> Self {
+        Fields(&[])
+    }
+}
+
+impl<'de> de::Deserializer<'de> for FieldNames {
+    type Error = Fields;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Fields> {
+        Err(Fields(&[]))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Fields> {
+        Err(Fields(fields))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map enum identifier ignored_any
+    }
+}
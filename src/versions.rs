@@ -1,11 +1,24 @@
 //! versions.toml parsing and validation
 
+use crate::channel::{take_channels, DEFAULT_CHANNEL};
+use crate::external::{ranges_overlap, ExternalPackage};
+use crate::hooks::HooksConfig;
+use crate::lint::LintConfig;
+use crate::multi_version::take_multi_versions;
+use crate::replace::Replacement;
+use crate::source::source_for_path;
+use crate::transitive::DepsCache;
+use crate::unknown_fields::EntryKeys;
+use crate::Error;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::Read;
 use std::path::Path;
+use std::str::FromStr;
 
 /// Version information for a repository
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct RepoVersion {
     /// Semantic version (e.g., "0.1.0")
     pub version: String,
@@ -17,164 +30,2094 @@ pub struct RepoVersion {
     #[serde(default)]
     pub git_commit: Option<String>,
 
+    /// Prepended to `git_tag` to form the actual tag, for repos tagged like
+    /// `consensus-v0.1.0`; `git_tag` can then be just `0.1.0`
+    ///
+    /// See [`full_tag`](Self::full_tag).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_prefix: Option<String>,
+
     /// Required dependencies with version constraints
+    ///
+    /// Each entry is a repo name followed by a semver requirement, e.g.
+    /// `bllvm-consensus=0.1.0` (exact pin), `bllvm-consensus>=0.1.0, <0.2.0`
+    /// or `bllvm-consensus=^0.1`. Cargo features can be requested on the
+    /// dependency with a trailing list, e.g. `bllvm-consensus=0.1.0[bignum]`,
+    /// and a final `?` marks the dependency as optional (`bllvm-node=0.1.0?`).
+    /// An entry can be limited to some target platforms by writing it as a
+    /// table or adding `; cfg(...)`, see [`Dependency`].
     #[serde(default)]
-    pub requires: Vec<String>,
+    pub requires: Vec<Dependency>,
+
+    /// Dev- or example-only dependencies, in the same format as `requires`
+    ///
+    /// These are checked for existence and version like `requires`, but are
+    /// left out of cycle detection and build ordering unless requested with
+    /// [`BuildOrderOptions::include_optional`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub optional_requires: Vec<String>,
 
     /// Binary names produced by this repo
     #[serde(default)]
     pub binaries: Vec<String>,
+
+    /// Cargo features to build this repo with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+
+    /// Clone URL, overriding the `[workspace]` git host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_url: Option<String>,
+
+    /// Shell command that builds this repo, e.g. `cargo build --release`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<String>,
+
+    /// Checkout directory, relative to the manifest (defaults to the repo name)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+
+    /// Marks this version as broken
+    ///
+    /// Requiring it is a [`Warning`], or an error with
+    /// [`BuildOrderOptions::strict`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub yanked: bool,
+
+    /// Deprecation notice, e.g. `use 0.2.1 instead`; requiring it is a [`Warning`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<String>,
+
+    /// Estimated build cost, used instead of 1 per repo by
+    /// [`VersionsManifest::critical_path`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build_weight: Option<u64>,
+
+    /// Hex SHA-256 of the repo's source artifact, see
+    /// [`VersionsManifest::verify_checksums`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    /// `latest` to follow the newest release tag instead of the pinned
+    /// `version` and `git_tag`, see [`crate::latest`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track: Option<String>,
+
+    /// Pulled in prebuilt (e.g. from crates.io) instead of built from source
+    ///
+    /// External repos are still ordered and version-checked, but are left
+    /// out of [`VersionsManifest::buildable_order`], so they are never
+    /// cloned or built.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub external: bool,
+
+    /// Free-form fields for downstream tooling, e.g.
+    /// `metadata = { team = "core", image = "bllvm/node" }`
+    ///
+    /// Kept as written and never validated; see
+    /// [`VersionsManifest::metadata_for`].
+    #[serde(default, skip_serializing_if = "serde_json::Map::is_empty")]
+    pub metadata: serde_json::Map<String, serde_json::Value>,
+
+    /// String labels for slicing the workspace, e.g.
+    /// `labels = { team = "consensus", tier = "core" }`
+    ///
+    /// Any key is accepted; see [`VersionsManifest::labels_for`] and
+    /// [`VersionsManifest::filter_by_label`].
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+
+    /// Set when `git_tag`/`git_commit` come from an environment override
+    /// instead of the file, see [`VersionsManifest::apply_env_overrides`]
+    #[serde(skip)]
+    pub overridden: bool,
+}
+
+impl RepoVersion {
+    /// `requires` followed by `optional_requires`, as requirement strings
+    pub fn all_requires(&self) -> impl Iterator<Item = &str> {
+        self.requires
+            .iter()
+            .map(|dep| dep.spec.as_str())
+            .chain(self.optional_requires.iter().map(String::as_str))
+    }
+
+    /// Sort `requires` and `optional_requires`, the order
+    /// [`VersionsManifest::to_toml_string`] writes them in
+    pub fn sort_requires(&mut self) {
+        self.requires.sort();
+        self.optional_requires.sort();
+    }
+
+    /// The tag as git knows it: `tag_prefix` followed by `git_tag`
+    pub fn full_tag(&self) -> String {
+        format!(
+            "{}{}",
+            self.tag_prefix.as_deref().unwrap_or(""),
+            self.git_tag
+        )
+    }
+}
+
+/// Workspace-wide defaults (`[workspace]` table)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WorkspaceConfig {
+    /// Base URL repos are cloned from, e.g. `https://github.com/BTCDecoded`
+    #[serde(default)]
+    pub git_host: Option<String>,
+
+    /// Branch to use for repos that don't specify one, e.g. `main`
+    #[serde(default)]
+    pub default_branch: Option<String>,
+}
+
+/// Newest versions.toml schema this version of bllvm understands
+pub const LATEST_SCHEMA: u32 = 1;
+
+fn default_schema() -> u32 {
+    1
+}
+
+/// Just the `schema` of a manifest, read before the rest of it
+#[derive(Deserialize)]
+pub(crate) struct SchemaOnly {
+    schema: Option<u64>,
+    bllvm_min_version: Option<String>,
+}
+
+impl SchemaOnly {
+    /// Reject a schema newer than [`LATEST_SCHEMA`] or a `bllvm_min_version`
+    /// above this bllvm
+    pub(crate) fn check(&self, source: &str) -> Result<(), Error> {
+        if let Some(required) = &self.bllvm_min_version {
+            let current = env!("CARGO_PKG_VERSION");
+            let req = VersionReq::parse(&format!(">={}", required)).map_err(|e| {
+                Error::parse(
+                    source,
+                    None,
+                    format!("invalid bllvm_min_version '{}': {}", required, e),
+                )
+            })?;
+            let too_old = Version::parse(current).is_ok_and(|current| !req.matches(&current));
+            if too_old {
+                return Err(Error::BllvmTooOld {
+                    file: source.to_string(),
+                    required: required.clone(),
+                    current: current.to_string(),
+                });
+            }
+        }
+
+        match self.schema {
+            Some(schema) if schema > u64::from(LATEST_SCHEMA) => Err(Error::UnsupportedSchema {
+                file: source.to_string(),
+                schema,
+                latest: LATEST_SCHEMA,
+            }),
+            _ => Ok(()),
+        }
+    }
 }
 
 /// Versions manifest structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct VersionsManifest {
+    /// Format version of the file (`schema = 1`); files without one are schema 1
+    ///
+    /// See [`migrate_to_latest`](Self::migrate_to_latest).
+    #[serde(default = "default_schema")]
+    pub schema: u32,
+
+    /// Oldest bllvm that can read this file (`bllvm_min_version = "0.3"`)
+    ///
+    /// Older versions refuse to parse it with [`Error::BllvmTooOld`] instead
+    /// of misreading or ignoring newer fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bllvm_min_version: Option<String>,
+
+    /// Other manifests to merge into this one (`include = ["team-a.toml"]`)
+    ///
+    /// Relative paths are resolved against the directory of the including
+    /// file. [`from_file`](Self::from_file) merges them and leaves this
+    /// empty; content parsed with [`from_str`](Self::from_str) keeps the
+    /// list unresolved.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+
     /// Repository versions
-    #[serde(rename = "versions")]
+    ///
+    /// May be left out by a manifest that only includes others.
+    #[serde(rename = "versions", default)]
     pub versions: HashMap<String, RepoVersion>,
 
     /// Metadata
     #[serde(default)]
     pub metadata: Option<HashMap<String, String>>,
+
+    /// Workspace-wide defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace: Option<WorkspaceConfig>,
+
+    /// Shell commands run around builds (`[hooks]` table)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hooks: Option<HooksConfig>,
+
+    /// Opinionated checks to run (`[lint]` table), see [`lint`](Self::lint)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lint: Option<LintConfig>,
+
+    /// Named overlays (`[profiles.<name>.versions]`), see [`with_profile`](Self::with_profile)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub profiles: HashMap<String, Profile>,
+
+    /// Named sets of repos (`[groups]`), see [`group_members`](Self::group_members)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub groups: HashMap<String, Vec<String>>,
+
+    /// Local checkouts used instead of git (`[replace]`), see
+    /// [`source_of`](Self::source_of)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub replace: HashMap<String, Replacement>,
+
+    /// Packages `requires` may name without building them (`[external]`),
+    /// see [`crate::external`]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub external: HashMap<String, ExternalPackage>,
+
+    /// Every channel of the repos that declare channels, by repo then channel
+    ///
+    /// `versions` holds the selected one, see
+    /// [`resolve_channel`](Self::resolve_channel). Not written back by
+    /// [`to_toml_string`](Self::to_toml_string).
+    #[serde(skip)]
+    pub channels: HashMap<String, BTreeMap<String, RepoVersion>>,
+
+    /// Every entry of the repos declared with an array of versions, oldest first
+    ///
+    /// `versions` holds the newest one, see [`entries`](Self::entries). Not
+    /// written back by [`to_toml_string`](Self::to_toml_string).
+    #[serde(skip)]
+    pub multi_versions: HashMap<String, Vec<RepoVersion>>,
+
+    /// Unknown keys in `[versions]` entries found while parsing, as
+    /// [`Warning::UnknownField`] sorted by repo
+    #[serde(skip)]
+    pub parse_warnings: Vec<Warning>,
+
+    /// Memoized [`transitive_deps`](Self::transitive_deps) results
+    #[serde(skip)]
+    pub(crate) deps_cache: DepsCache,
+}
+
+impl Default for VersionsManifest {
+    fn default() -> Self {
+        Self {
+            schema: LATEST_SCHEMA,
+            bllvm_min_version: None,
+            include: Vec::new(),
+            versions: HashMap::new(),
+            metadata: None,
+            workspace: None,
+            hooks: None,
+            lint: None,
+            profiles: HashMap::new(),
+            groups: HashMap::new(),
+            replace: HashMap::new(),
+            external: HashMap::new(),
+            channels: HashMap::new(),
+            multi_versions: HashMap::new(),
+            parse_warnings: Vec::new(),
+            deps_cache: DepsCache::default(),
+        }
+    }
+}
+
+/// What [`VersionsManifest::to_toml_string`] and
+/// [`to_json_string`](VersionsManifest::to_json_string) write
+#[derive(Serialize)]
+struct SortedManifest<'a> {
+    schema: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bllvm_min_version: Option<&'a String>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    include: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    workspace: Option<&'a WorkspaceConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hooks: Option<&'a HooksConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lint: Option<&'a LintConfig>,
+    versions: BTreeMap<&'a String, RepoVersion>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<BTreeMap<&'a String, &'a String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    groups: BTreeMap<&'a String, &'a Vec<String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    replace: BTreeMap<&'a String, &'a Replacement>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    external: BTreeMap<&'a String, &'a ExternalPackage>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    profiles: BTreeMap<&'a String, BTreeMap<&'a str, BTreeMap<&'a String, RepoOverride>>>,
+}
+
+/// A named set of overrides on top of `[versions]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Profile {
+    /// Per-repo overrides
+    #[serde(default)]
+    pub versions: HashMap<String, RepoOverride>,
 }
 
-impl VersionsManifest {
-    /// Load versions.toml from file
-    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let content = std::fs::read_to_string(path.as_ref())
-            .map_err(|e| anyhow::anyhow!("Failed to read versions.toml: {}", e))?;
+/// Fields a profile overrides on a `[versions]` entry
+///
+/// Unset fields keep the base value. A repo that is not in the base
+/// `[versions]` can only be overridden with `add = true`, and then needs at
+/// least `version` and `git_tag`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RepoOverride {
+    /// Version override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Git tag override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_tag: Option<String>,
+
+    /// Git commit override
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+
+    /// Replacement `requires` list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requires: Option<Vec<Dependency>>,
+
+    /// Add the repo if it is not in the base `[versions]`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub add: bool,
+}
+
+impl VersionsManifest {
+    /// Load versions.toml from file
+    ///
+    /// The format is picked from the extension, see
+    /// [`source_for_path`](crate::source::source_for_path); anything but
+    /// `.json`, `.yaml` and `.yml` is read as TOML. Manifests listed in
+    /// [`include`](Self::include) are loaded (with their own includes) and
+    /// merged as by [`ManifestSet::merged`](crate::manifest_set::ManifestSet::merged).
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::read_file(path.as_ref())?.with_includes(path.as_ref())
+    }
+
+    /// Load one manifest file, leaving its `include` list unresolved
+    pub(crate) fn read_file(path: &Path) -> Result<Self, Error> {
+        let source = path.display().to_string();
+        let format = source_for_path(path)?;
+        let content = std::fs::read_to_string(path).map_err(|e| Error::Io {
+            path: source.clone(),
+            message: e.to_string(),
+        })?;
+
+        format.parse(&content).map_err(|e| e.with_source(&source))
+    }
+
+    /// Parse versions.toml content that did not come from a file
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(content: &str) -> Result<Self, Error> {
+        Self::parse(content, "<inline>")
+    }
+
+    /// [`from_str`](Self::from_str) for content read from `reader`, e.g. an
+    /// HTTP response body
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Self, Error> {
+        let mut content = String::new();
+        reader.read_to_string(&mut content).map_err(|e| Error::Io {
+            path: "<inline>".to_string(),
+            message: e.to_string(),
+        })?;
+        Self::from_str(&content)
+    }
+
+    /// Parse versions.toml content, naming `source` in errors
+    ///
+    /// Errors carry the line and column of the offending value and, when it is
+    /// inside a `[versions]` entry, the repo it belongs to. `requires` entries
+    /// are checked here too, so a malformed one is reported where it is
+    /// written rather than when the build order is computed.
+    fn parse(content: &str, source: &str) -> Result<Self, Error> {
+        Self::parse_channel(content, source, DEFAULT_CHANNEL)
+    }
+
+    /// [`parse`](Self::parse), selecting `channel` for repos that have channels
+    pub(crate) fn parse_channel(content: &str, source: &str, channel: &str) -> Result<Self, Error> {
+        // Checked first so a newer file is reported as such rather than as
+        // whatever it no longer has in common with this schema
+        if let Ok(schema) = toml::from_str::<SchemaOnly>(content) {
+            schema.check(source)?;
+        }
+
+        let from_table = match toml::from_str::<toml::Table>(content) {
+            Ok(table) => Self::from_table(table, source, channel)?,
+            Err(_) => None,
+        };
+        let mut manifest: Self = match from_table {
+            Some(manifest) => manifest,
+            None => toml::from_str(content).map_err(|e| match duplicate_repo_key(e.message()) {
+                Some(repo) => Error::DuplicateRepo {
+                    file: source.to_string(),
+                    repo: repo.to_string(),
+                    line: e.span().map(|span| line_column(content, span.start).0),
+                    versions: declared_versions(content, repo),
+                },
+                None => match e.span() {
+                    Some(span) => {
+                        let (line, column) = line_column(content, span.start);
+                        Error::Parse {
+                            file: source.to_string(),
+                            line: Some(line),
+                            column: Some(column),
+                            repo: repo_at(content, span.start),
+                            message: e.message().trim().to_string(),
+                        }
+                    }
+                    None => Error::Parse {
+                        file: source.to_string(),
+                        line: None,
+                        column: None,
+                        repo: None,
+                        message: e.to_string(),
+                    },
+                },
+            })?,
+        };
+
+        if let Ok(keys) = toml::from_str::<EntryKeys>(content) {
+            manifest.parse_warnings = keys.warnings();
+        }
+        manifest.finish_parse(source, |repo, spec| {
+            let quoted = format!("\"{}\"", spec);
+            content
+                .match_indices(&quoted)
+                .map(|(offset, _)| offset + 1)
+                .find(|&offset| repo_at(content, offset).as_deref() == Some(repo))
+                .map(|offset| line_column(content, offset))
+        })
+    }
+
+    /// Deserialize a manifest with channel or version-array entries
+    ///
+    /// Those entries don't match [`RepoVersion`], so they are taken out of
+    /// `[versions]` and the rest deserialized from the parsed table, without
+    /// positions in errors. Returns `None` when there are none, so the caller
+    /// can deserialize the text directly instead.
+    fn from_table(
+        mut table: toml::Table,
+        source: &str,
+        channel: &str,
+    ) -> Result<Option<Self>, Error> {
+        let Some(toml::Value::Table(versions)) = table.get_mut("versions") else {
+            return Ok(None);
+        };
+        let channels = take_channels(versions, source)?;
+        let multi_versions = take_multi_versions(versions, source)?;
+        if channels.is_empty() && multi_versions.is_empty() {
+            return Ok(None);
+        }
+
+        let mut manifest: Self =
+            toml::Value::Table(table)
+                .try_into()
+                .map_err(|e: toml::de::Error| {
+                    Error::parse(source, None, e.message().trim().to_string())
+                })?;
+        for (repo, entries) in &multi_versions {
+            if let Some(newest) = entries.last() {
+                manifest.versions.insert(repo.clone(), newest.clone());
+            }
+        }
+        manifest.multi_versions = multi_versions;
+        manifest.channels = channels;
+        manifest
+            .select_channel(channel)
+            .map_err(|(repo, message)| Error::parse(source, Some(&repo), message))?;
+        Ok(Some(manifest))
+    }
+
+    /// Checks every manifest format runs once deserialized
+    ///
+    /// Rejects schema 0 and malformed `requires`/`optional_requires` entries.
+    /// `locate` finds the line and column of a repo's requirement in the
+    /// source text, if the format can tell.
+    pub(crate) fn finish_parse(
+        self,
+        source: &str,
+        locate: impl Fn(&str, &str) -> Option<(usize, usize)>,
+    ) -> Result<Self, Error> {
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+        for (repo, info) in repos
+            .into_iter()
+            .flat_map(|repo| self.entries(repo).into_iter().map(move |info| (repo, info)))
+        {
+            for spec in info.all_requires() {
+                if let Err(e) = parse_requirement(spec) {
+                    let position = locate(repo, spec);
+                    return Err(Error::Parse {
+                        file: source.to_string(),
+                        line: position.map(|(line, _)| line),
+                        column: position.map(|(_, column)| column),
+                        repo: Some(repo.clone()),
+                        message: format!(
+                            "invalid requirement '{}' (expected `name=version`): {}",
+                            spec, e
+                        ),
+                    });
+                }
+            }
+        }
+
+        if self.schema == 0 {
+            return Err(Error::Parse {
+                file: source.to_string(),
+                line: None,
+                column: None,
+                repo: None,
+                message: "schema 0 does not exist, the first schema is 1".to_string(),
+            });
+        }
+
+        Ok(self)
+    }
+
+    /// Upgrade a manifest read from an older schema to [`LATEST_SCHEMA`]
+    ///
+    /// Parsing keeps the schema the file declares, so
+    /// [`to_toml_string`](Self::to_toml_string) writes back the same schema
+    /// unless this is called first.
+    pub fn migrate_to_latest(&mut self) {
+        // Schema 1 is the first; steps for later schemas go here, each
+        // upgrading from the previous one
+        self.schema = LATEST_SCHEMA;
+    }
+
+    /// Serialize the manifest to TOML
+    ///
+    /// Repos are written sorted by name and `requires` (and
+    /// `optional_requires`) sorted too, so the output is canonical: comments,
+    /// the original key order and the order of requirements are not
+    /// preserved. [`from_file`](Self::from_file) reads the output back to a
+    /// manifest that only differs in that order, and writing that out again
+    /// gives the same text.
+    pub fn to_toml_string(&self) -> Result<String, Error> {
+        toml::to_string(&self.sorted()).map_err(|e| Error::serialize("versions.toml", e))
+    }
+
+    /// Serialize the manifest to pretty-printed JSON
+    ///
+    /// Same content and order as [`to_toml_string`](Self::to_toml_string);
+    /// [`JsonSource`](crate::source::JsonSource) reads it back to an
+    /// identical manifest.
+    pub fn to_json_string(&self) -> Result<String, Error> {
+        serde_json::to_string_pretty(&self.sorted())
+            .map_err(|e| Error::serialize("manifest as JSON", e))
+    }
+
+    /// The serialized form, with every map sorted by key and every
+    /// `requires` list sorted
+    fn sorted(&self) -> SortedManifest<'_> {
+        SortedManifest {
+            schema: self.schema,
+            bllvm_min_version: self.bllvm_min_version.as_ref(),
+            include: &self.include,
+            workspace: self.workspace.as_ref(),
+            hooks: self.hooks.as_ref(),
+            lint: self.lint.as_ref(),
+            versions: self
+                .versions
+                .iter()
+                .map(|(repo, info)| {
+                    let mut info = info.clone();
+                    info.sort_requires();
+                    (repo, info)
+                })
+                .collect(),
+            metadata: self.metadata.as_ref().map(|m| m.iter().collect()),
+            groups: self.groups.iter().collect(),
+            replace: self.replace.iter().collect(),
+            external: self.external.iter().collect(),
+            profiles: self
+                .profiles
+                .iter()
+                .map(|(name, profile)| {
+                    (
+                        name,
+                        BTreeMap::from([(
+                            "versions",
+                            profile
+                                .versions
+                                .iter()
+                                .map(|(repo, overrides)| {
+                                    let mut overrides = overrides.clone();
+                                    if let Some(requires) = &mut overrides.requires {
+                                        requires.sort();
+                                    }
+                                    (repo, overrides)
+                                })
+                                .collect(),
+                        )]),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Sort every `requires` list, including profile overrides, into the
+    /// order [`to_toml_string`](Self::to_toml_string) writes
+    ///
+    /// Reading the written manifest back gives the manifest as it is after
+    /// this call.
+    pub fn sort_requires(&mut self) {
+        for info in self.versions.values_mut() {
+            info.sort_requires();
+        }
+        for profile in self.profiles.values_mut() {
+            for overrides in profile.versions.values_mut() {
+                if let Some(requires) = &mut overrides.requires {
+                    requires.sort();
+                }
+            }
+        }
+    }
+
+    /// Write the manifest to file
+    pub fn to_file<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        std::fs::write(path.as_ref(), self.to_toml_string()?).map_err(|e| Error::Write {
+            path: path.as_ref().display().to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Set the version and git tag of `repo`
+    ///
+    /// With `cascade` set, exact-pin `requires` entries in other repos that
+    /// pointed at the old version (`repo=0.1.0`) are updated to the new one.
+    /// Returns the repos whose `requires` were updated, sorted by name.
+    pub fn set_version(
+        &mut self,
+        repo: &str,
+        version: &str,
+        git_tag: &str,
+        cascade: bool,
+    ) -> Result<Vec<String>, Error> {
+        if !is_valid_semver(version) {
+            return Err(Error::InvalidVersion {
+                repo: repo.to_string(),
+                version: version.to_string(),
+            });
+        }
+        let Some(info) = self.versions.get_mut(repo) else {
+            return Err(self.unknown_repo(repo));
+        };
+
+        let old_version = std::mem::replace(&mut info.version, version.to_string());
+        info.git_tag = git_tag.to_string();
+
+        let mut updated = Vec::new();
+        if cascade {
+            for (name, version_info) in &mut self.versions {
+                let mut changed = false;
+                for dep in &mut version_info.requires {
+                    // Aliased entries name one of several versions on purpose
+                    if dependency_name(dep) != repo || split_alias(dep).is_some() {
+                        continue;
+                    }
+                    let (spec, features, optional) = split_requirement(dep);
+                    if spec[repo.len()..].trim().strip_prefix('=').map(str::trim)
+                        == Some(old_version.as_str())
+                    {
+                        dep.spec = format!(
+                            "{}={}{}{}",
+                            repo,
+                            version,
+                            features.map(|f| format!("[{}]", f)).unwrap_or_default(),
+                            if optional { "?" } else { "" }
+                        );
+                        changed = true;
+                    }
+                }
+                if changed {
+                    updated.push(name.clone());
+                }
+            }
+        }
+        updated.sort();
+
+        Ok(updated)
+    }
+
+    /// Override git refs from `BLLVM_OVERRIDE_<REPO>=<git_ref>` environment variables
+    ///
+    /// `<REPO>` is the repo name uppercased with `-` turned into `_`, e.g.
+    /// `BLLVM_OVERRIDE_BLLVM_NODE=my-branch`. The ref replaces `git_tag`; a full
+    /// commit SHA also replaces `git_commit`, anything else clears it. Overridden
+    /// repos get [`RepoVersion::overridden`] set.
+    ///
+    /// Env overrides win over the file contents, so apply them last (after
+    /// [`with_profile`](Self::with_profile)). Variables naming no repo in the
+    /// manifest are ignored. Returns the overridden repos, sorted by name.
+    pub fn apply_env_overrides(&mut self) -> Vec<String> {
+        self.apply_overrides(std::env::vars())
+    }
+
+    /// [`apply_env_overrides`](Self::apply_env_overrides) reading variables from `vars`
+    pub fn apply_overrides<I, K, V>(&mut self, vars: I) -> Vec<String>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        let keys: HashMap<String, String> = self
+            .versions
+            .keys()
+            .map(|repo| (repo.to_uppercase().replace('-', "_"), repo.clone()))
+            .collect();
+
+        let mut overridden = Vec::new();
+        for (key, value) in vars {
+            let Some(repo) = key
+                .as_ref()
+                .strip_prefix(ENV_OVERRIDE_PREFIX)
+                .and_then(|key| keys.get(key))
+            else {
+                continue;
+            };
+
+            let git_ref = value.as_ref().trim();
+            let info = self
+                .versions
+                .get_mut(repo)
+                .expect("repo is in the manifest");
+            // The override is a complete ref, not a suffix of the prefix
+            info.git_tag = git_ref.to_string();
+            info.tag_prefix = None;
+            info.git_commit = is_commit_sha(git_ref).then(|| git_ref.to_string());
+            info.overridden = true;
+            overridden.push(repo.clone());
+        }
+
+        overridden.sort();
+        overridden.dedup();
+        overridden
+    }
+
+    /// Resolve the manifest with the overrides of profile `name` applied
+    ///
+    /// The result has no profiles of its own, so [`validate`](Self::validate)
+    /// and [`build_order`](Self::build_order) on it check the effective
+    /// versions. Unknown profiles, and overrides of repos that are not in the
+    /// base `[versions]` without `add = true`, are errors.
+    pub fn with_profile(&self, name: &str) -> Result<VersionsManifest, Error> {
+        let Some(profile) = self.profiles.get(name) else {
+            let mut available: Vec<String> = self.profiles.keys().cloned().collect();
+            available.sort();
+            return Err(Error::UnknownProfile {
+                profile: name.to_string(),
+                available,
+            });
+        };
+
+        let mut resolved = VersionsManifest {
+            profiles: HashMap::new(),
+            ..self.clone()
+        };
+
+        let mut repos: Vec<&String> = profile.versions.keys().collect();
+        repos.sort();
+        for repo in repos {
+            let overrides = &profile.versions[repo];
+            let info = match resolved.versions.get_mut(repo) {
+                Some(info) => info,
+                None if overrides.add => {
+                    let (Some(version), Some(git_tag)) = (&overrides.version, &overrides.git_tag)
+                    else {
+                        return Err(Error::ProfileIncompleteRepo {
+                            profile: name.to_string(),
+                            repo: repo.clone(),
+                        });
+                    };
+                    resolved
+                        .versions
+                        .entry(repo.clone())
+                        .or_insert(RepoVersion {
+                            version: version.clone(),
+                            git_tag: git_tag.clone(),
+                            ..Default::default()
+                        })
+                }
+                None => {
+                    return Err(Error::ProfileUnknownRepo {
+                        profile: name.to_string(),
+                        repo: repo.clone(),
+                    })
+                }
+            };
+
+            if let Some(version) = &overrides.version {
+                info.version = version.clone();
+            }
+            if let Some(git_tag) = &overrides.git_tag {
+                info.git_tag = git_tag.clone();
+            }
+            if let Some(git_commit) = &overrides.git_commit {
+                info.git_commit = Some(git_commit.clone());
+            }
+            if let Some(requires) = &overrides.requires {
+                info.requires = requires.clone();
+            }
+        }
+
+        Ok(resolved)
+    }
+
+    /// Load versions.toml from file and reject it if [`validate`](Self::validate) finds any errors
+    ///
+    /// The error lists every problem found, one per line.
+    pub fn from_file_strict<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_file(path)?.strict()
+    }
+
+    /// This manifest if it [validates](Self::validate), else every error
+    pub(crate) fn strict(self) -> Result<Self, Error> {
+        if let ValidationResult::Invalid { errors, .. } = self.validate() {
+            return Err(Error::InvalidManifest { errors });
+        }
+
+        Ok(self)
+    }
+
+    /// Validate the manifest
+    ///
+    /// Collects every problem found rather than stopping at the first one.
+    /// Pinned `git_commit`s are not checked here since that needs git; see
+    /// [`validate_commits`](Self::validate_commits).
+    pub fn validate(&self) -> ValidationResult {
+        let mut errors = Vec::new();
+        let warnings: Vec<String> = self.warnings().iter().map(Warning::to_string).collect();
+
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+
+        // Check for repo names that only differ by case or `-`/`_`
+        let mut normalized: HashMap<String, &str> = HashMap::new();
+        for repo in &repos {
+            let key = repo.to_lowercase().replace('_', "-");
+            if let Some(other) = normalized.insert(key, repo) {
+                errors.push(ValidationError::DuplicateRepo {
+                    repo: repo.to_string(),
+                    other: other.to_string(),
+                });
+            }
+        }
+
+        for (repo, version_info) in repos
+            .into_iter()
+            .flat_map(|repo| self.entries(repo).into_iter().map(move |info| (repo, info)))
+        {
+            // Check all versions are valid semver
+            if !is_valid_semver(&version_info.version) {
+                errors.push(ValidationError::InvalidVersion {
+                    repo: repo.clone(),
+                    version: version_info.version.clone(),
+                });
+            }
+
+            if version_info.git_tag.trim().is_empty() {
+                errors.push(ValidationError::EmptyGitTag { repo: repo.clone() });
+            }
+
+            for duplicate in version_info.duplicate_requirements() {
+                if !duplicate.identical {
+                    errors.push(ValidationError::ConflictingRequirements {
+                        repo: repo.clone(),
+                        dependency: duplicate.dependency,
+                        first: duplicate.first.to_string(),
+                        second: duplicate.second.to_string(),
+                    });
+                }
+            }
+
+            for dep in &version_info.requires {
+                let Some(cfg) = dep
+                    .target
+                    .as_deref()
+                    .filter(|only| crate::target::is_cfg(only))
+                else {
+                    continue;
+                };
+                if let Err(reason) = crate::target::parse_cfg(cfg) {
+                    errors.push(ValidationError::InvalidRequirement {
+                        repo: repo.clone(),
+                        requirement: dep.to_string(),
+                        reason,
+                    });
+                }
+            }
+
+            // Check dependencies exist and satisfy their version constraints
+            for dep in version_info.all_requires() {
+                if dependency_name(dep) == repo.as_str() {
+                    errors.push(ValidationError::SelfRequirement { repo: repo.clone() });
+                } else if let Err(e) = self.resolve_requirement(repo, dep) {
+                    errors.push(e);
+                }
+            }
+        }
+
+        let mut replaced: Vec<&String> = self.replace.keys().collect();
+        replaced.sort();
+        for repo in replaced {
+            if !self.versions.contains_key(repo) {
+                errors.push(ValidationError::UnknownReplacement {
+                    repo: repo.clone(),
+                    suggestion: self.closest_repo(repo).map(String::from),
+                });
+            }
+        }
+
+        let mut externals: Vec<(&String, &ExternalPackage)> = self.external.iter().collect();
+        externals.sort_by_key(|(name, _)| *name);
+        for (name, external) in externals {
+            let reason = if self.versions.contains_key(name) {
+                "is also in [versions]".to_string()
+            } else if let Err(e) = VersionReq::parse(&external.version) {
+                format!("has invalid version range '{}': {}", external.version, e)
+            } else {
+                continue;
+            };
+            errors.push(ValidationError::InvalidExternal {
+                name: name.clone(),
+                reason,
+            });
+        }
+
+        // Check for circular dependencies (one cycle per strongly connected component).
+        // A repo requiring itself was already reported above.
+        for cycle in self.find_cycles() {
+            if cycle.len() == 2 && cycle[0] == cycle[1] {
+                continue;
+            }
+            errors.push(ValidationError::CircularDependency { cycle });
+        }
+
+        ValidationResult::from_problems(errors, warnings)
+    }
+
+    /// Every `requires` and `optional_requires` entry that the declared
+    /// version of its dependency does not satisfy
+    ///
+    /// An exact pin has to name the declared version; a range has to
+    /// include it. These are the [`ValidationError::VersionMismatch`]es of
+    /// [`validate`](Self::validate), by requirer and entry.
+    pub fn check_pin_consistency(&self) -> Vec<PinDrift> {
+        self.validate()
+            .errors()
+            .iter()
+            .filter_map(|error| match error {
+                ValidationError::VersionMismatch {
+                    repo,
+                    requirement,
+                    dependency,
+                    found,
+                } => Some(PinDrift {
+                    requirer: repo.clone(),
+                    dependency: dependency.clone(),
+                    pinned: requirement.clone(),
+                    actual: found.clone(),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Resolve a `requires` entry of `repo` against the declared versions
+    ///
+    /// Returns the dependency name as stored in the manifest, `None` for an
+    /// optional dependency that is not in the manifest, or an error if the
+    /// entry is malformed, names an undefined repo, or is not satisfied by the
+    /// dependency's declared version. Declared versions that are not valid
+    /// semver are reported by [`validate`](Self::validate) and are not checked
+    /// here.
+    pub(crate) fn resolve_requirement(
+        &self,
+        repo: &str,
+        spec: &str,
+    ) -> Result<Option<&str>, ValidationError> {
+        Ok(self.resolve_entry(repo, spec)?.map(|(dep, _)| dep))
+    }
+
+    /// [`resolve_requirement`](Self::resolve_requirement), with the entry of
+    /// the dependency that satisfies it
+    ///
+    /// For a dependency declared with several versions this is the one entry
+    /// the requirement matches; matching none or more than one is an error.
+    pub(crate) fn resolve_entry(
+        &self,
+        repo: &str,
+        spec: &str,
+    ) -> Result<Option<(&str, &RepoVersion)>, ValidationError> {
+        let requirement =
+            parse_requirement(spec).map_err(|e| ValidationError::InvalidRequirement {
+                repo: repo.to_string(),
+                requirement: spec.to_string(),
+                reason: e.to_string(),
+            })?;
+
+        let Some((dep_key, dep_info)) = self.versions.get_key_value(&requirement.name) else {
+            if let Some(external) = self.external.get(&requirement.name) {
+                // Not built, so never an ordering edge; an unparsable range
+                // is reported once by `validate`
+                return match VersionReq::parse(&external.version) {
+                    Ok(declared) if !ranges_overlap(&requirement.req, &declared) => {
+                        Err(ValidationError::VersionMismatch {
+                            repo: repo.to_string(),
+                            requirement: spec.to_string(),
+                            dependency: requirement.name,
+                            found: external.version.clone(),
+                        })
+                    }
+                    _ => Ok(None),
+                };
+            }
+            if requirement.optional {
+                return Ok(None);
+            }
+            return Err(ValidationError::UnknownDependency {
+                repo: repo.to_string(),
+                suggestion: self.closest_repo(&requirement.name).map(String::from),
+                dependency: requirement.name,
+            });
+        };
+
+        if let Some(entries) = self.multi_versions.get(dep_key) {
+            let matching: Vec<&RepoVersion> = entries
+                .iter()
+                .filter(|entry| {
+                    Version::parse(&entry.version).is_ok_and(|v| requirement.req.matches(&v))
+                })
+                .collect();
+            let declared = || {
+                entries
+                    .iter()
+                    .map(|e| e.version.clone())
+                    .collect::<Vec<_>>()
+            };
+            return match matching[..] {
+                [entry] => Ok(Some((dep_key, entry))),
+                [] => Err(ValidationError::VersionMismatch {
+                    repo: repo.to_string(),
+                    requirement: spec.to_string(),
+                    dependency: requirement.name,
+                    found: declared().join(", "),
+                }),
+                _ => Err(ValidationError::AmbiguousRequirement {
+                    repo: repo.to_string(),
+                    requirement: spec.to_string(),
+                    dependency: requirement.name,
+                    matches: matching.iter().map(|e| e.version.clone()).collect(),
+                }),
+            };
+        }
+
+        if let Ok(dep_version) = Version::parse(&dep_info.version) {
+            if !requirement.req.matches(&dep_version) {
+                return Err(ValidationError::VersionMismatch {
+                    repo: repo.to_string(),
+                    requirement: spec.to_string(),
+                    dependency: requirement.name,
+                    found: dep_info.version.clone(),
+                });
+            }
+        }
+
+        Ok(Some((dep_key, dep_info)))
+    }
+
+    /// Detect circular dependencies
+    pub fn detect_circular_dependencies(&self) -> Option<String> {
+        self.find_cycle().map(|cycle| cycle.join(" -> "))
+    }
+
+    /// Find a dependency cycle, if any
+    ///
+    /// Returns the first of [`find_cycles`](Self::find_cycles).
+    fn find_cycle(&self) -> Option<Vec<String>> {
+        self.find_cycles().into_iter().next()
+    }
+
+    /// Find one cycle per strongly connected component of the dependency graph
+    ///
+    /// Each cycle is the shortest path from the component's first repo (by
+    /// name) back to itself, e.g. `[a, b, a]`. Cycles are sorted by their
+    /// first repo, so the same manifest always reports the same cycles.
+    pub fn find_cycles(&self) -> Vec<Vec<String>> {
+        cycles_in(&self.dependency_edges())
+    }
+
+    /// Strongly connected components that contain at least one cycle
+    pub(crate) fn cyclic_components(&self) -> Vec<Vec<String>> {
+        cyclic_components(&self.dependency_edges())
+            .into_iter()
+            .map(|component| component.into_iter().map(String::from).collect())
+            .collect()
+    }
+
+    /// Get every repo that requires `name`
+    ///
+    /// With `transitive` set, repos that require it indirectly are included
+    /// too, so the result can be used as a rebuild list. Dependents are
+    /// returned in build order; if the manifest has no build order (because of
+    /// a cycle), they are sorted by name instead. Unknown names are an error
+    /// suggesting close matches.
+    pub fn dependents_of(&self, name: &str, transitive: bool) -> Result<Vec<String>, Error> {
+        if !self.versions.contains_key(name) {
+            return Err(self.unknown_repo(name));
+        }
+
+        let mut reverse: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (repo, deps) in self.dependency_edges() {
+            for dep in deps {
+                reverse.entry(dep).or_default().push(repo);
+            }
+        }
+
+        let mut found = HashSet::new();
+        let mut queue = VecDeque::from([name]);
+        while let Some(repo) = queue.pop_front() {
+            for &dependent in reverse.get(repo).into_iter().flatten() {
+                if dependent != name && found.insert(dependent) && transitive {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        Ok(match self.build_order() {
+            Ok(order) => order
+                .into_iter()
+                .filter(|repo| found.contains(repo.as_str()))
+                .collect(),
+            Err(_) => {
+                let mut dependents: Vec<String> = found.into_iter().map(String::from).collect();
+                dependents.sort();
+                dependents
+            }
+        })
+    }
+
+    /// Get the features `name` should be built with
+    ///
+    /// This is the union of the repo's own `features` and every feature
+    /// requested on it by other repos' `requires` entries, sorted by name.
+    pub fn features_for(&self, name: &str) -> Result<Vec<String>, Error> {
+        let Some(info) = self.versions.get(name) else {
+            return Err(self.unknown_repo(name));
+        };
+
+        let mut features: BTreeSet<String> = info.features.iter().cloned().collect();
+        for version_info in self.versions.values() {
+            for dep in &version_info.requires {
+                if dependency_name(dep) == name {
+                    features.extend(requirement_features(dep));
+                }
+            }
+        }
+
+        Ok(features.into_iter().collect())
+    }
+
+    /// Get the clone URL of `name`
+    ///
+    /// The repo's own `git_url` wins; otherwise the URL is the workspace
+    /// `git_host` followed by the repo name.
+    pub fn git_url_for(&self, name: &str) -> Result<String, Error> {
+        let Some(info) = self.versions.get(name) else {
+            return Err(self.unknown_repo(name));
+        };
+
+        if let Some(url) = &info.git_url {
+            return Ok(url.clone());
+        }
+
+        match self.workspace.as_ref().and_then(|w| w.git_host.as_deref()) {
+            Some(host) => Ok(format!("{}/{}", host.trim_end_matches('/'), name)),
+            None => Err(Error::NoGitUrl {
+                repo: name.to_string(),
+            }),
+        }
+    }
+
+    /// Get the `metadata` table of `name` (empty if it has none)
+    pub fn metadata_for(
+        &self,
+        name: &str,
+    ) -> Result<&serde_json::Map<String, serde_json::Value>, Error> {
+        match self.versions.get(name) {
+            Some(info) => Ok(&info.metadata),
+            None => Err(self.unknown_repo(name)),
+        }
+    }
+
+    /// Get the `labels` of `name` (empty if it has none)
+    pub fn labels_for(&self, name: &str) -> Result<&BTreeMap<String, String>, Error> {
+        match self.versions.get(name) {
+            Some(info) => Ok(&info.labels),
+            None => Err(self.unknown_repo(name)),
+        }
+    }
+
+    /// Repos labelled `key = value`, sorted by name
+    ///
+    /// A label nobody has just matches no repo.
+    pub fn filter_by_label(&self, key: &str, value: &str) -> Vec<String> {
+        let mut repos: Vec<String> = self
+            .versions
+            .iter()
+            .filter(|(_, info)| info.labels.get(key).is_some_and(|label| label == value))
+            .map(|(repo, _)| repo.clone())
+            .collect();
+        repos.sort();
+        repos
+    }
+
+    /// Get the git tag of `name` with its `tag_prefix` applied
+    ///
+    /// See [`RepoVersion::full_tag`].
+    pub fn resolved_tag(&self, name: &str) -> Result<String, Error> {
+        match self.versions.get(name) {
+            Some(info) => Ok(info.full_tag()),
+            None => Err(self.unknown_repo(name)),
+        }
+    }
+
+    /// Error for a repo name that is not in the manifest
+    ///
+    /// Suggests the [`closest_repo`](Self::closest_repo) and lists the valid
+    /// names.
+    pub(crate) fn unknown_repo(&self, name: &str) -> Error {
+        let mut valid: Vec<String> = self.versions.keys().cloned().collect();
+        valid.sort();
+
+        Error::UnknownRepo {
+            repo: name.to_string(),
+            suggestion: self.closest_repo(name).map(str::to_string),
+            valid,
+        }
+    }
+
+    /// Repo name to suggest for the unknown `name`: the closest one within
+    /// an edit distance of [`SUGGESTION_DISTANCE`], ties going to the first
+    /// by name
+    pub(crate) fn closest_repo(&self, name: &str) -> Option<&str> {
+        self.versions
+            .keys()
+            .map(|repo| (edit_distance(name, repo), repo.as_str()))
+            .filter(|(distance, _)| *distance <= SUGGESTION_DISTANCE)
+            .min()
+            .map(|(_, repo)| repo)
+    }
+
+    /// Dependency edges of every repo, with dependencies sorted by name
+    ///
+    /// Dependencies that are not defined in the manifest are left out.
+    pub(crate) fn dependency_edges(&self) -> BTreeMap<&str, Vec<&str>> {
+        self.versions
+            .iter()
+            .map(|(repo, version_info)| {
+                let mut deps: Vec<&str> = version_info
+                    .requires
+                    .iter()
+                    .map(|dep| dependency_name(dep))
+                    .filter(|dep| self.versions.contains_key(*dep))
+                    .collect();
+                deps.sort();
+                deps.dedup();
+                (repo.as_str(), deps)
+            })
+            .collect()
+    }
+
+    /// Get build order (topological sort)
+    ///
+    /// Equivalent to flattening [`build_stages`](Self::build_stages). Repos
+    /// that become buildable at the same time are emitted sorted by name, so
+    /// the same manifest always yields the same order, independent of hash
+    /// map iteration.
+    ///
+    /// The order is also stable across edits: a repo's stage only depends on
+    /// what it requires, so adding (or removing) a repo that no other repo
+    /// requires inserts (or removes) just that repo, and every other repo
+    /// keeps its stage and its relative order. Build caches keyed on
+    /// position stay valid for them.
+    pub fn build_order(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .graph()?
+            .topo_order()
+            .into_iter()
+            .map(String::from)
+            .collect())
+    }
+
+    /// [`build_order`](Self::build_order) without the repos marked
+    /// `external = true`, i.e. the repos to clone and build
+    pub fn buildable_order(&self) -> Result<Vec<String>, Error> {
+        Ok(self
+            .build_order()?
+            .into_iter()
+            .filter(|repo| !self.versions[repo].external)
+            .collect())
+    }
+
+    /// [`build_order`](Self::build_order) together with the [`warnings`](Self::warnings)
+    pub fn build_order_with_warnings(&self) -> Result<(Vec<String>, Vec<Warning>), Error> {
+        Ok((self.build_order()?, self.warnings()))
+    }
+
+    /// Yanked or deprecated versions that other repos require, then
+    /// [`parse_warnings`](Self::parse_warnings)
+    ///
+    /// Both `requires` and `optional_requires` count. Each warning lists every
+    /// repo requiring the version; warnings are sorted by repo name.
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut required_by: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (repo, info) in &self.versions {
+            for dep in info.all_requires() {
+                let dep = dependency_name(dep);
+                if dep != repo.as_str() && self.versions.contains_key(dep) {
+                    required_by.entry(dep).or_default().insert(repo.as_str());
+                }
+            }
+        }
+
+        let mut warnings = Vec::new();
+        for (repo, by) in required_by {
+            let info = &self.versions[repo];
+            let required_by: Vec<String> = by.into_iter().map(String::from).collect();
+            if info.yanked {
+                warnings.push(Warning::Yanked {
+                    repo: repo.to_string(),
+                    version: info.version.clone(),
+                    required_by: required_by.clone(),
+                });
+            }
+            if let Some(message) = &info.deprecated {
+                warnings.push(Warning::Deprecated {
+                    repo: repo.to_string(),
+                    version: info.version.clone(),
+                    message: message.clone(),
+                    required_by,
+                });
+            }
+        }
+        let mut repos: Vec<&String> = self.versions.keys().collect();
+        repos.sort();
+        for repo in repos {
+            for info in self.entries(repo) {
+                for duplicate in info.duplicate_requirements() {
+                    if duplicate.identical {
+                        warnings.push(Warning::DuplicateRequirement {
+                            repo: repo.clone(),
+                            requirement: duplicate.second.to_string(),
+                        });
+                    }
+                }
+            }
+        }
+        warnings.extend(self.parse_warnings.iter().cloned());
+        warnings
+    }
+
+    /// Repos in group `name` (`[groups]`), in the order listed
+    ///
+    /// An unknown group, or a group listing a repo that is not in
+    /// `[versions]`, is an error.
+    pub fn group_members(&self, name: &str) -> Result<Vec<String>, Error> {
+        let Some(members) = self.groups.get(name) else {
+            let mut valid: Vec<String> = self.groups.keys().cloned().collect();
+            valid.sort();
+            return Err(Error::UnknownGroup {
+                group: name.to_string(),
+                valid,
+            });
+        };
+        if let Some(missing) = members.iter().find(|m| !self.versions.contains_key(*m)) {
+            return Err(Error::UnknownGroupMember {
+                group: name.to_string(),
+                repo: missing.clone(),
+            });
+        }
+        Ok(members.clone())
+    }
+
+    /// Get build order for the members of group `name` and their dependencies
+    pub fn build_order_for_group(&self, name: &str) -> Result<Vec<String>, Error> {
+        self.build_order_for(&[name])
+    }
+
+    /// Get build order for `targets` and their transitive dependencies
+    ///
+    /// Repos outside the targets' dependency closure are left out, and repos
+    /// shared by several targets appear once. A target may also name a group,
+    /// which stands for all its members; a repo of the same name takes
+    /// precedence. Unknown target names are an error listing the valid repo
+    /// names.
+    pub fn build_order_for(&self, targets: &[&str]) -> Result<Vec<String>, Error> {
+        self.closure_manifest(targets)?.build_order()
+    }
+
+    /// Get parallel build stages for `targets` and their transitive dependencies
+    ///
+    /// Targets are resolved as in [`build_order_for`](Self::build_order_for).
+    pub fn build_stages_for(&self, targets: &[&str]) -> Result<Vec<Vec<String>>, Error> {
+        self.closure_manifest(targets)?.build_stages()
+    }
+
+    /// Manifest with only the repos in the dependency closure of `targets`
+    ///
+    /// Every other table (`[external]`, the versions of repos declared with
+    /// several, ...) is kept, so requirements resolve as in the full manifest.
+    fn closure_manifest(&self, targets: &[&str]) -> Result<VersionsManifest, Error> {
+        let closure = self.dependency_closure(targets)?;
+        let mut manifest = self.clone();
+        manifest
+            .versions
+            .retain(|repo, _| closure.contains(repo.as_str()));
+        manifest
+            .multi_versions
+            .retain(|repo, _| closure.contains(repo.as_str()));
+        manifest
+            .channels
+            .retain(|repo, _| closure.contains(repo.as_str()));
+        Ok(manifest)
+    }
+
+    /// `targets` plus every repo they transitively depend on
+    fn dependency_closure<'a>(&'a self, targets: &[&str]) -> Result<HashSet<&'a str>, Error> {
+        let mut closure = HashSet::new();
+        let mut queue = VecDeque::new();
+        for target in targets {
+            let members =
+                if self.groups.contains_key(*target) && !self.versions.contains_key(*target) {
+                    self.group_members(target)?
+                } else {
+                    vec![target.to_string()]
+                };
+            for member in members {
+                let Some((repo, _)) = self.versions.get_key_value(&member) else {
+                    return Err(self.unknown_repo(&member));
+                };
+                if closure.insert(repo.as_str()) {
+                    queue.push_back(repo.as_str());
+                }
+            }
+        }
+
+        while let Some(repo) = queue.pop_front() {
+            for dep in &self.versions[repo].requires {
+                if let Some((dep, _)) = self.versions.get_key_value(dependency_name(dep)) {
+                    if closure.insert(dep.as_str()) {
+                        queue.push_back(dep.as_str());
+                    }
+                }
+            }
+        }
+
+        Ok(closure)
+    }
+
+    /// Copy of the manifest with only `roots` and what they transitively need
+    ///
+    /// Follows `requires` and `optional_requires`, so the result still
+    /// validates. Group members, profile overrides, channels and `[replace]`
+    /// entries of dropped repos go too (as do groups left empty); `[workspace]`, `[hooks]`,
+    /// `[external]` and metadata are kept. Names that are not in `[versions]` are ignored.
+    pub fn pruned(&self, roots: &[&str]) -> VersionsManifest {
+        let mut keep: HashSet<&str> = HashSet::new();
+        let mut queue: VecDeque<&str> = roots
+            .iter()
+            .filter_map(|root| self.versions.get_key_value(*root))
+            .map(|(repo, _)| repo.as_str())
+            .collect();
+        while let Some(repo) = queue.pop_front() {
+            if !keep.insert(repo) {
+                continue;
+            }
+            for spec in self
+                .entries(repo)
+                .into_iter()
+                .flat_map(RepoVersion::all_requires)
+            {
+                if let Some((dep, _)) = self.versions.get_key_value(dependency_name(spec)) {
+                    queue.push_back(dep.as_str());
+                }
+            }
+        }
+
+        let mut pruned = self.clone();
+        pruned
+            .versions
+            .retain(|repo, _| keep.contains(repo.as_str()));
+        pruned
+            .multi_versions
+            .retain(|repo, _| keep.contains(repo.as_str()));
+        pruned
+            .channels
+            .retain(|repo, _| keep.contains(repo.as_str()));
+        pruned
+            .replace
+            .retain(|repo, _| keep.contains(repo.as_str()));
+        for members in pruned.groups.values_mut() {
+            members.retain(|member| keep.contains(member.as_str()));
+        }
+        pruned.groups.retain(|_, members| !members.is_empty());
+        for profile in pruned.profiles.values_mut() {
+            profile
+                .versions
+                .retain(|repo, _| keep.contains(repo.as_str()));
+        }
+        pruned
+    }
+
+    /// Get the repos to rebuild after `changed` changed, in build order
+    ///
+    /// This is the changed repos plus everything that transitively depends on
+    /// them, each listed once. Equivalent to flattening
+    /// [`rebuild_stages`](Self::rebuild_stages).
+    pub fn rebuild_plan(&self, changed: &[&str]) -> Result<Vec<String>, Error> {
+        Ok(self
+            .rebuild_stages(changed)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+
+    /// Get the repos to rebuild after `changed` changed, as parallel stages
+    ///
+    /// Stages only count dependencies that are rebuilt too, so a repo is not
+    /// held back by unchanged repos that come earlier in the full build order.
+    /// Unknown repo names are an error.
+    pub fn rebuild_stages(&self, changed: &[&str]) -> Result<Vec<Vec<String>>, Error> {
+        let mut rebuild = HashSet::new();
+        for repo in changed {
+            rebuild.extend(self.dependents_of(repo, true)?);
+            rebuild.insert(repo.to_string());
+        }
+
+        let edges = self.dependency_edges();
+        let mut level: HashMap<String, usize> = HashMap::new();
+        let mut stages: Vec<Vec<String>> = Vec::new();
+        for repo in self.build_order()? {
+            if !rebuild.contains(&repo) {
+                continue;
+            }
+            let stage = edges[repo.as_str()]
+                .iter()
+                .filter_map(|dep| level.get(*dep))
+                .map(|dep_level| dep_level + 1)
+                .max()
+                .unwrap_or(0);
+            if stage == stages.len() {
+                stages.push(Vec::new());
+            }
+            stages[stage].push(repo.clone());
+            level.insert(repo, stage);
+        }
+
+        for stage in &mut stages {
+            stage.sort();
+        }
+        Ok(stages)
+    }
+
+    /// Get parallel build batches
+    ///
+    /// Alias for [`build_stages`](Self::build_stages).
+    pub fn build_batches(&self) -> Result<Vec<Vec<String>>, Error> {
+        self.build_stages()
+    }
+
+    /// Longest chain of dependencies, starting with the repo built first
+    ///
+    /// Each repo counts [`build_weight`](RepoVersion::build_weight) (1 if
+    /// unset), so without weights this is the chain with the most repos and
+    /// with weights the one that takes longest to build one after another.
+    /// Only required dependencies are followed. Ties go to the name that sorts
+    /// first, so the result is deterministic.
+    pub fn critical_path(&self) -> Result<Vec<String>, Error> {
+        let (path, _) =
+            self.heaviest_chain(|repo| self.versions[repo].build_weight.unwrap_or(1))?;
+        Ok(path)
+    }
+
+    /// Chain of required dependencies with the largest total `weight`, and that total
+    ///
+    /// The chain starts with the repo built first; ties go to the name that
+    /// sorts first. See [`critical_path`](Self::critical_path).
+    pub(crate) fn heaviest_chain<W>(
+        &self,
+        weight: impl Fn(&str) -> W,
+    ) -> Result<(Vec<String>, W), Error>
+    where
+        W: Copy + Ord + Default + std::ops::Add<Output = W>,
+    {
+        let deps = self.acyclic_deps()?;
+        let order = self.build_order()?;
+
+        // Heaviest chain ending at each repo, and the dependency it comes from
+        let mut cost: HashMap<&str, W> = HashMap::new();
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        for repo in &order {
+            let repo = repo.as_str();
+            let mut heaviest: Option<(&str, W)> = None;
+            for &dep in &deps[repo] {
+                let dep_cost = cost[dep];
+                if heaviest
+                    .is_none_or(|(name, best)| dep_cost > best || (dep_cost == best && dep < name))
+                {
+                    heaviest = Some((dep, dep_cost));
+                }
+            }
+
+            cost.insert(
+                repo,
+                weight(repo) + heaviest.map_or(W::default(), |(_, c)| c),
+            );
+            if let Some((dep, _)) = heaviest {
+                previous.insert(repo, dep);
+            }
+        }
+
+        let Some(mut repo) = order
+            .iter()
+            .map(String::as_str)
+            .max_by(|a, b| cost[a].cmp(&cost[b]).then_with(|| b.cmp(a)))
+        else {
+            return Ok((Vec::new(), W::default()));
+        };
 
-        let manifest: VersionsManifest = toml::from_str(&content)
-            .map_err(|e| anyhow::anyhow!("Failed to parse versions.toml: {}", e))?;
+        let total = cost[repo];
+        let mut path = vec![repo.to_string()];
+        while let Some(dep) = previous.get(repo) {
+            path.push(dep.to_string());
+            repo = dep;
+        }
+        path.reverse();
+        Ok((path, total))
+    }
 
-        Ok(manifest)
+    /// Get parallel build stages (level-based topological sort)
+    ///
+    /// Each stage contains the repos whose dependencies are all satisfied by
+    /// earlier stages, so the repos within a stage can be built concurrently.
+    /// Repos are sorted by name within each stage so the result is deterministic.
+    /// See [`graph`](Self::graph) for asking several questions of one manifest.
+    pub fn build_stages(&self) -> Result<Vec<Vec<String>>, Error> {
+        Ok(self
+            .graph()?
+            .stages()
+            .into_iter()
+            .map(|stage| stage.into_iter().map(String::from).collect())
+            .collect())
     }
 
-    /// Validate the manifest
-    pub fn validate(&self) -> ValidationResult {
-        let mut errors = Vec::new();
-        let mut warnings = Vec::new();
+    /// Check that every `requires` entry resolves and there is no cycle
+    ///
+    /// Fails with the same error [`build_order`](Self::build_order) would,
+    /// without computing the order, so it is a cheap pre-commit check.
+    pub fn check(&self) -> Result<(), Error> {
+        self.acyclic_deps().map(|_| ())
+    }
 
-        // Check all versions are valid semver
-        for (repo, version_info) in &self.versions {
-            if !is_valid_semver(&version_info.version) {
-                errors.push(format!(
-                    "Repository '{}' has invalid version '{}' (must be X.Y.Z)",
-                    repo, version_info.version
-                ));
+    /// Whether [`check`](Self::check) passes, i.e. the dependency graph is a
+    /// resolvable DAG
+    pub fn is_valid_dag(&self) -> bool {
+        self.check().is_ok()
+    }
+
+    /// Get build order, optionally ordering by `optional_requires` too
+    ///
+    /// With [`BuildOrderOptions::include_optional`], optional dependencies are
+    /// considered repo by repo, both sorted by name, and each one is honoured
+    /// unless it would create a cycle. Required dependencies always win.
+    pub fn build_order_with_options(
+        &self,
+        options: &BuildOrderOptions,
+    ) -> Result<Vec<String>, Error> {
+        if options.strict {
+            if let Some(Warning::Yanked {
+                repo,
+                version,
+                required_by,
+            }) = self
+                .warnings()
+                .into_iter()
+                .find(|w| matches!(w, Warning::Yanked { .. }))
+            {
+                return Err(Error::YankedDependency {
+                    repo,
+                    version,
+                    required_by,
+                });
+            }
+        }
+
+        let mut deps = self.acyclic_deps()?;
+
+        if options.include_optional {
+            let mut repos: Vec<&String> = self.versions.keys().collect();
+            repos.sort();
+            for repo in repos {
+                let mut optional = Vec::new();
+                for dep in &self.versions[repo].optional_requires {
+                    if let Some(dep_name) = self.resolve_requirement(repo, dep)? {
+                        optional.push(dep_name);
+                    }
+                }
+                optional.sort();
+                for dep in optional {
+                    if dep != repo.as_str() && !reaches(&deps, dep, repo) {
+                        deps.entry(repo.as_str()).or_default().insert(dep);
+                    }
+                }
             }
+        }
+
+        Ok(stages_for(deps).into_iter().flatten().collect())
+    }
 
-            // Check dependencies exist
+    /// Dependencies of every repo from `requires`, resolved against the manifest
+    pub(crate) fn required_deps(&self) -> Result<HashMap<&str, HashSet<&str>>, Error> {
+        let mut pending: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for (repo, version_info) in &self.versions {
+            let mut deps = HashSet::new();
             for dep in &version_info.requires {
-                let dep_name = dep.split('=').next().unwrap_or(dep);
-                if !self.versions.contains_key(dep_name) {
-                    errors.push(format!(
-                        "Repository '{}' requires '{}' which is not defined",
-                        repo, dep_name
-                    ));
+                if let Some(dep_name) = self.resolve_requirement(repo, dep)? {
+                    deps.insert(dep_name);
                 }
             }
+            pending.insert(repo.as_str(), deps);
         }
+        Ok(pending)
+    }
 
-        // Check for circular dependencies
-        if let Some(circular) = self.detect_circular_dependencies() {
-            errors.push(format!("Circular dependency detected: {}", circular));
+    /// [`required_deps`](Self::required_deps), failing if they form a cycle
+    ///
+    /// The one cycle check behind [`check`](Self::check) and every build order.
+    pub(crate) fn acyclic_deps(&self) -> Result<HashMap<&str, HashSet<&str>>, Error> {
+        let deps = self.required_deps()?;
+        if has_cycle(&deps) {
+            let cycle = self.find_cycle().unwrap_or_default();
+            return Err(Error::CircularDependency { cycle });
         }
+        Ok(deps)
+    }
+}
 
-        if errors.is_empty() && warnings.is_empty() {
-            ValidationResult::Valid
-        } else if errors.is_empty() {
-            ValidationResult::ValidWithWarnings(warnings)
-        } else {
-            ValidationResult::Invalid { errors, warnings }
+/// One cycle per strongly connected component of `edges`, see
+/// [`VersionsManifest::find_cycles`]
+pub(crate) fn cycles_in(edges: &BTreeMap<&str, Vec<&str>>) -> Vec<Vec<String>> {
+    let mut cycles: Vec<Vec<String>> = cyclic_components(edges)
+        .iter()
+        .map(|component| {
+            let members: HashSet<&str> = component.iter().copied().collect();
+            let start = component.iter().min().copied().unwrap_or_default();
+            shortest_cycle(edges, &members, start)
+        })
+        .collect();
+    cycles.sort();
+    cycles
+}
+
+/// Level-based topological sort of `pending` (repo -> dependencies)
+///
+/// Kahn's algorithm, so O(V + E) apart from sorting each stage by name.
+/// `pending` must be acyclic, see [`VersionsManifest::acyclic_deps`].
+pub(crate) fn stages_for<'a>(pending: HashMap<&'a str, HashSet<&'a str>>) -> Vec<Vec<String>> {
+    let mut remaining: HashMap<&str, usize> = pending
+        .iter()
+        .map(|(repo, deps)| (*repo, deps.len()))
+        .collect();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (repo, deps) in &pending {
+        for dep in deps {
+            dependents.entry(dep).or_default().push(repo);
         }
     }
 
-    /// Detect circular dependencies
-    pub fn detect_circular_dependencies(&self) -> Option<String> {
-        for (repo, _) in &self.versions {
-            let mut visited = std::collections::HashSet::new();
-            let mut path = Vec::new();
-            if self.has_circular_dependency(repo, &mut visited, &mut path) {
-                return Some(path.join(" -> "));
+    let mut stage: Vec<&str> = remaining
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(repo, _)| *repo)
+        .collect();
+    let mut stages = Vec::new();
+    let mut placed = 0;
+    while !stage.is_empty() {
+        stage.sort();
+        placed += stage.len();
+        let mut next = Vec::new();
+        for repo in &stage {
+            for dependent in dependents.get(repo).into_iter().flatten() {
+                let count = remaining
+                    .get_mut(dependent)
+                    .expect("dependents are in pending");
+                *count -= 1;
+                if *count == 0 {
+                    next.push(*dependent);
+                }
             }
         }
-        None
+        stages.push(stage.into_iter().map(String::from).collect());
+        stage = next;
     }
+    assert_eq!(placed, pending.len(), "cycles are rejected before ordering");
 
-    fn has_circular_dependency(
-        &self,
-        repo: &str,
-        visited: &mut std::collections::HashSet<String>,
-        path: &mut Vec<String>,
-    ) -> bool {
-        if path.contains(&repo.to_string()) {
-            path.push(repo.to_string());
-            return true;
-        }
+    stages
+}
 
-        if visited.contains(repo) {
-            return false;
+/// Whether `deps` (repo -> dependencies) contains a cycle
+///
+/// Peels off repos whose dependencies are all resolved; anything left over
+/// is on or behind a cycle.
+fn has_cycle<'a>(deps: &HashMap<&'a str, HashSet<&'a str>>) -> bool {
+    let mut remaining: HashMap<&str, usize> = deps
+        .iter()
+        .map(|(repo, deps)| (*repo, deps.len()))
+        .collect();
+    let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (repo, repo_deps) in deps {
+        for dep in repo_deps {
+            dependents.entry(dep).or_default().push(repo);
         }
+    }
 
-        visited.insert(repo.to_string());
-        path.push(repo.to_string());
-
-        if let Some(version_info) = self.versions.get(repo) {
-            for dep in &version_info.requires {
-                let dep_name = dep.split('=').next().unwrap_or(dep);
-                if self.has_circular_dependency(dep_name, visited, path) {
-                    return true;
+    let mut ready: Vec<&str> = remaining
+        .iter()
+        .filter(|(_, count)| **count == 0)
+        .map(|(repo, _)| *repo)
+        .collect();
+    let mut resolved = 0;
+    while let Some(repo) = ready.pop() {
+        resolved += 1;
+        for dependent in dependents.get(repo).into_iter().flatten() {
+            if let Some(count) = remaining.get_mut(dependent) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(dependent);
                 }
             }
         }
+    }
+    resolved < deps.len()
+}
+
+/// Options for [`VersionsManifest::build_order_with_options`]
+#[derive(Debug, Clone, Default)]
+pub struct BuildOrderOptions {
+    /// Also order repos after their `optional_requires` where that doesn't
+    /// create a cycle
+    pub include_optional: bool,
+
+    /// Fail with [`Error::YankedDependency`] if a yanked version is required
+    pub strict: bool,
+}
 
-        path.pop();
-        false
+/// Whether `to` can be reached from `from` by following dependencies
+fn reaches(deps: &HashMap<&str, HashSet<&str>>, from: &str, to: &str) -> bool {
+    let mut seen = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(repo) = queue.pop_front() {
+        if repo == to {
+            return true;
+        }
+        for &dep in deps.get(repo).into_iter().flatten() {
+            if seen.insert(dep) {
+                queue.push_back(dep);
+            }
+        }
     }
+    false
+}
 
-    /// Get build order (topological sort)
-    pub fn build_order(&self) -> anyhow::Result<Vec<String>> {
-        let mut result = Vec::new();
-        let mut visited = std::collections::HashSet::new();
-        let mut visiting = std::collections::HashSet::new();
+/// Tarjan's strongly connected components algorithm over dependency edges
+struct Tarjan<'a, 'g> {
+    edges: &'g BTreeMap<&'a str, Vec<&'a str>>,
+    next_index: usize,
+    index: HashMap<&'a str, usize>,
+    lowlink: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: HashSet<&'a str>,
+    components: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Tarjan<'a, '_> {
+    fn visit(&mut self, repo: &'a str) {
+        self.index.insert(repo, self.next_index);
+        self.lowlink.insert(repo, self.next_index);
+        self.next_index += 1;
+        self.stack.push(repo);
+        self.on_stack.insert(repo);
 
-        for repo in self.versions.keys() {
-            if !visited.contains(repo) {
-                self.dfs(repo, &mut visited, &mut visiting, &mut result)?;
+        for &dep in &self.edges[repo] {
+            if !self.index.contains_key(dep) {
+                self.visit(dep);
+                let low = self.lowlink[repo].min(self.lowlink[dep]);
+                self.lowlink.insert(repo, low);
+            } else if self.on_stack.contains(dep) {
+                let low = self.lowlink[repo].min(self.index[dep]);
+                self.lowlink.insert(repo, low);
             }
         }
 
-        Ok(result)
+        if self.lowlink[repo] == self.index[repo] {
+            let mut component = Vec::new();
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(member);
+                component.push(member);
+                if member == repo {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
     }
+}
 
-    fn dfs(
-        &self,
-        repo: &str,
-        visited: &mut std::collections::HashSet<String>,
-        visiting: &mut std::collections::HashSet<String>,
-        result: &mut Vec<String>,
-    ) -> anyhow::Result<()> {
-        if visiting.contains(repo) {
-            anyhow::bail!("Circular dependency detected involving {}", repo);
-        }
-        if visited.contains(repo) {
-            return Ok(());
+/// Strongly connected components of `edges` that contain at least one cycle
+fn cyclic_components<'a>(edges: &BTreeMap<&'a str, Vec<&'a str>>) -> Vec<Vec<&'a str>> {
+    let mut tarjan = Tarjan {
+        edges,
+        next_index: 0,
+        index: HashMap::new(),
+        lowlink: HashMap::new(),
+        stack: Vec::new(),
+        on_stack: HashSet::new(),
+        components: Vec::new(),
+    };
+    for repo in edges.keys() {
+        if !tarjan.index.contains_key(repo) {
+            tarjan.visit(repo);
         }
+    }
 
-        visiting.insert(repo.to_string());
+    tarjan
+        .components
+        .into_iter()
+        .filter(|component| component.len() > 1 || edges[component[0]].contains(&component[0]))
+        .collect()
+}
 
-        if let Some(version_info) = self.versions.get(repo) {
-            for dep in &version_info.requires {
-                let dep_name = dep.split('=').next().unwrap_or(dep);
-                self.dfs(dep_name, visited, visiting, result)?;
+/// Shortest path from `start` back to itself using only edges within `members`
+fn shortest_cycle(
+    edges: &BTreeMap<&str, Vec<&str>>,
+    members: &HashSet<&str>,
+    start: &str,
+) -> Vec<String> {
+    let mut parent: HashMap<&str, &str> = HashMap::new();
+    let mut queue = VecDeque::from([start]);
+
+    while let Some(repo) = queue.pop_front() {
+        for &dep in &edges[repo] {
+            if dep == start {
+                let mut path = vec![repo];
+                let mut current = repo;
+                while current != start {
+                    current = parent[current];
+                    path.push(current);
+                }
+                path.reverse();
+                path.push(start);
+                return path.into_iter().map(String::from).collect();
+            }
+            if members.contains(dep) && !parent.contains_key(dep) {
+                parent.insert(dep, repo);
+                queue.push_back(dep);
             }
         }
+    }
+
+    Vec::new()
+}
+
+/// A problem that does not stop resolution, see [`VersionsManifest::warnings`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Warning {
+    /// A required version is marked `yanked = true`
+    Yanked {
+        repo: String,
+        version: String,
+        required_by: Vec<String>,
+    },
+    /// A required version is marked `deprecated = "..."`
+    Deprecated {
+        repo: String,
+        version: String,
+        message: String,
+        required_by: Vec<String>,
+    },
+    /// A `[versions]` entry has a key bllvm does not know, which is ignored
+    ///
+    /// `suggestion` is the closest known key, for typos like `require`.
+    UnknownField {
+        repo: String,
+        field: String,
+        suggestion: Option<String>,
+    },
+    /// The same requirement is listed twice in one entry
+    DuplicateRequirement { repo: String, requirement: String },
+}
 
-        visiting.remove(repo);
-        visited.insert(repo.to_string());
-        result.push(repo.to_string());
-        Ok(())
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::Yanked {
+                repo,
+                version,
+                required_by,
+            } => write!(
+                f,
+                "Repository '{}' {} is yanked but required by {}",
+                repo,
+                version,
+                required_by.join(", ")
+            ),
+            Warning::Deprecated {
+                repo,
+                version,
+                message,
+                required_by,
+            } => write!(
+                f,
+                "Repository '{}' {} is deprecated ({}) but required by {}",
+                repo,
+                version,
+                message,
+                required_by.join(", ")
+            ),
+            Warning::UnknownField {
+                repo,
+                field,
+                suggestion,
+            } => {
+                write!(f, "Repository '{}' has unknown field '{}'", repo, field)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, ", did you mean '{}'?", suggestion)?;
+                }
+                write!(f, " (ignored)")
+            }
+            Warning::DuplicateRequirement { repo, requirement } => write!(
+                f,
+                "Repository '{}' lists requirement '{}' more than once",
+                repo, requirement
+            ),
+        }
     }
 }
 
@@ -184,12 +2127,32 @@ pub enum ValidationResult {
     Valid,
     ValidWithWarnings(Vec<String>),
     Invalid {
-        errors: Vec<String>,
+        errors: Vec<ValidationError>,
         warnings: Vec<String>,
     },
 }
 
 impl ValidationResult {
+    /// Result for the given problems
+    pub(crate) fn from_problems(errors: Vec<ValidationError>, warnings: Vec<String>) -> Self {
+        if errors.is_empty() && warnings.is_empty() {
+            ValidationResult::Valid
+        } else if errors.is_empty() {
+            ValidationResult::ValidWithWarnings(warnings)
+        } else {
+            ValidationResult::Invalid { errors, warnings }
+        }
+    }
+
+    /// Errors and warnings found
+    pub(crate) fn into_problems(self) -> (Vec<ValidationError>, Vec<String>) {
+        match self {
+            ValidationResult::Valid => (Vec::new(), Vec::new()),
+            ValidationResult::ValidWithWarnings(warnings) => (Vec::new(), warnings),
+            ValidationResult::Invalid { errors, warnings } => (errors, warnings),
+        }
+    }
+
     pub fn is_valid(&self) -> bool {
         matches!(
             self,
@@ -197,7 +2160,7 @@ impl ValidationResult {
         )
     }
 
-    pub fn errors(&self) -> &[String] {
+    pub fn errors(&self) -> &[ValidationError] {
         match self {
             ValidationResult::Invalid { errors, .. } => errors,
             _ => &[],
@@ -205,6 +2168,242 @@ impl ValidationResult {
     }
 }
 
+impl FromStr for VersionsManifest {
+    type Err = Error;
+
+    /// Same as [`VersionsManifest::from_str`]
+    fn from_str(content: &str) -> Result<Self, Error> {
+        VersionsManifest::from_str(content)
+    }
+}
+
+/// A single problem found by [`VersionsManifest::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValidationError {
+    /// Version string is not valid semver
+    InvalidVersion { repo: String, version: String },
+    /// `git_tag` is empty
+    EmptyGitTag { repo: String },
+    /// Two repo names only differ by case or `-`/`_`
+    DuplicateRepo { repo: String, other: String },
+    /// `requires` entry could not be parsed
+    InvalidRequirement {
+        repo: String,
+        requirement: String,
+        reason: String,
+    },
+    /// `requires` entry names the repo itself
+    SelfRequirement { repo: String },
+    /// `requires` entry names a repo that is not in `[versions]`, with the
+    /// closest defined name if there is one
+    UnknownDependency {
+        repo: String,
+        dependency: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suggestion: Option<String>,
+    },
+    /// Dependency's declared version does not satisfy a `requires` entry
+    VersionMismatch {
+        repo: String,
+        requirement: String,
+        dependency: String,
+        found: String,
+    },
+    /// `requires` entry matches more than one version of a repo declared
+    /// with several
+    AmbiguousRequirement {
+        repo: String,
+        requirement: String,
+        dependency: String,
+        matches: Vec<String>,
+    },
+    /// One entry requires the same dependency twice with different
+    /// constraints
+    ConflictingRequirements {
+        repo: String,
+        dependency: String,
+        first: String,
+        second: String,
+    },
+    /// Dependencies form a cycle
+    CircularDependency { cycle: Vec<String> },
+    /// `[replace]` names a repo that is not in `[versions]`, with the closest
+    /// defined name if there is one
+    UnknownReplacement {
+        repo: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        suggestion: Option<String>,
+    },
+    /// `[external]` entry that is also a repo or whose version range does
+    /// not parse
+    InvalidExternal { name: String, reason: String },
+    /// `git_tag` does not point at the pinned `git_commit`
+    CommitMismatch {
+        repo: String,
+        git_tag: String,
+        expected: String,
+        found: String,
+    },
+}
+
+impl ValidationError {
+    /// Repository the problem belongs to (the first repo of a cycle, the
+    /// package of an `[external]` entry)
+    pub fn repo(&self) -> &str {
+        match self {
+            ValidationError::InvalidVersion { repo, .. }
+            | ValidationError::EmptyGitTag { repo }
+            | ValidationError::DuplicateRepo { repo, .. }
+            | ValidationError::InvalidRequirement { repo, .. }
+            | ValidationError::SelfRequirement { repo }
+            | ValidationError::UnknownDependency { repo, .. }
+            | ValidationError::VersionMismatch { repo, .. }
+            | ValidationError::AmbiguousRequirement { repo, .. }
+            | ValidationError::ConflictingRequirements { repo, .. }
+            | ValidationError::UnknownReplacement { repo, .. }
+            | ValidationError::CommitMismatch { repo, .. } => repo,
+            ValidationError::InvalidExternal { name, .. } => name,
+            ValidationError::CircularDependency { cycle } => {
+                cycle.first().map(String::as_str).unwrap_or_default()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::InvalidVersion { repo, version } => write!(
+                f,
+                "Repository '{}' has invalid version '{}' (must be semver, e.g. X.Y.Z)",
+                repo, version
+            ),
+            ValidationError::EmptyGitTag { repo } => {
+                write!(f, "Repository '{}' has an empty git_tag", repo)
+            }
+            ValidationError::DuplicateRepo { repo, other } => write!(
+                f,
+                "Repository '{}' duplicates '{}' (names differ only by case or '-'/'_')",
+                repo, other
+            ),
+            ValidationError::InvalidRequirement {
+                repo,
+                requirement,
+                reason,
+            } => write!(
+                f,
+                "Repository '{}' has invalid requirement '{}': {}",
+                repo, requirement, reason
+            ),
+            ValidationError::SelfRequirement { repo } => {
+                write!(f, "Repository '{}' requires itself", repo)
+            }
+            ValidationError::UnknownDependency {
+                repo,
+                dependency,
+                suggestion,
+            } => {
+                write!(
+                    f,
+                    "Repository '{}' requires '{}' which is not defined",
+                    repo, dependency
+                )?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean '{}'?", suggestion)?;
+                }
+                Ok(())
+            }
+            ValidationError::VersionMismatch {
+                repo,
+                requirement,
+                dependency,
+                found,
+            } => write!(
+                f,
+                "Repository '{}' requires '{}' but '{}' is at version '{}'",
+                repo, requirement, dependency, found
+            ),
+            ValidationError::AmbiguousRequirement {
+                repo,
+                requirement,
+                dependency,
+                matches,
+            } => write!(
+                f,
+                "Repository '{}' requires '{}' which matches several versions of '{}': {}",
+                repo,
+                requirement,
+                dependency,
+                matches.join(", ")
+            ),
+            ValidationError::ConflictingRequirements {
+                repo,
+                dependency,
+                first,
+                second,
+            } => write!(
+                f,
+                "Repository '{}' requires '{}' twice with different constraints: '{}' and '{}'",
+                repo, dependency, first, second
+            ),
+            ValidationError::CircularDependency { cycle } => {
+                write!(f, "Circular dependency detected: {}", cycle.join(" -> "))
+            }
+            ValidationError::UnknownReplacement { repo, suggestion } => {
+                write!(f, "[replace] names '{}' which is not in [versions]", repo)?;
+                if let Some(suggestion) = suggestion {
+                    write!(f, "; did you mean '{}'?", suggestion)?;
+                }
+                Ok(())
+            }
+            ValidationError::InvalidExternal { name, reason } => {
+                write!(f, "[external] package '{}' {}", name, reason)
+            }
+            ValidationError::CommitMismatch {
+                repo,
+                git_tag,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Repository '{}' pins git_commit {} but tag '{}' points at {}",
+                repo, expected, git_tag, found
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// A requirement that drifted from the version its dependency is declared
+/// at, see [`VersionsManifest::check_pin_consistency`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PinDrift {
+    /// Repo with the `requires` entry
+    pub requirer: String,
+
+    /// Repo the entry is on
+    pub dependency: String,
+
+    /// The entry as written, e.g. `bllvm-consensus=0.1.0`
+    pub pinned: String,
+
+    /// Version `dependency` is declared at (every version, for a repo
+    /// declared with several)
+    pub actual: String,
+}
+
+impl std::fmt::Display for PinDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{}' requires '{}' but '{}' is declared at {}",
+            self.requirer, self.pinned, self.dependency, self.actual
+        )
+    }
+}
+
 /// Check if a version string is valid semantic versioning (X.Y.Z)
 fn is_valid_semver(version: &str) -> bool {
     let parts: Vec<&str> = version.split('.').collect();
@@ -214,6 +2413,396 @@ fn is_valid_semver(version: &str) -> bool {
     parts.iter().all(|part| part.parse::<u32>().is_ok())
 }
 
+/// Repo name from a toml "duplicate key `x` in table `versions`" error message
+fn duplicate_repo_key(message: &str) -> Option<&str> {
+    let rest = message.split("duplicate key `").nth(1)?;
+    let (key, rest) = rest.split_once('`')?;
+    rest.starts_with(" in table `versions`").then_some(key)
+}
+
+/// 1-based line and column (in characters) of byte `offset` in `content`
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Best-effort scan of the raw TOML for the `[versions]` entry containing byte `offset`
+///
+/// Understands both `repo = { ... }` lines (including arrays continued on
+/// later lines) and `[versions.repo]` tables.
+fn repo_at(content: &str, offset: usize) -> Option<String> {
+    let is_bare_key = |key: &str| {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+    let key_name = |key: &str| {
+        let key = key.trim();
+        match key.strip_prefix('"').and_then(|k| k.strip_suffix('"')) {
+            Some(quoted) => Some(quoted.to_string()),
+            None => is_bare_key(key).then(|| key.to_string()),
+        }
+    };
+
+    // Include the rest of the line, in case `offset` is at a key or header
+    let end = content[offset..]
+        .find('\n')
+        .map_or(content.len(), |i| offset + i);
+
+    let mut in_versions = false;
+    let mut repo = None;
+    for line in content[..end].split('\n').map(str::trim) {
+        if let Some(header) = line.strip_prefix('[').filter(|h| !h.starts_with('[')) {
+            let header = header.split(']').next().unwrap_or_default().trim();
+            in_versions = header == "versions";
+            repo = header.strip_prefix("versions.").and_then(key_name);
+        } else if in_versions {
+            if let Some(key) = line.split_once('=').and_then(|(key, _)| key_name(key)) {
+                repo = Some(key);
+            }
+        }
+    }
+    repo
+}
+
+/// Best-effort scan of the raw TOML for every `version` declared for `repo`
+///
+/// Used to describe duplicate definitions, which toml refuses to parse.
+/// Understands both `repo = { version = "..." }` and `[versions.repo]` tables.
+fn declared_versions(content: &str, repo: &str) -> Vec<String> {
+    let quoted_version = |line: &str| {
+        let rest = line
+            .split("version")
+            .nth(1)?
+            .trim_start()
+            .strip_prefix('=')?;
+        let rest = rest.trim_start().strip_prefix('"')?;
+        rest.split('"').next().map(String::from)
+    };
+    let is_key = |key: &str| key.trim().trim_matches('"') == repo;
+
+    let mut versions = Vec::new();
+    let mut in_repo_table = false;
+    for line in content.lines().map(str::trim) {
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_end_matches(']').trim();
+            in_repo_table = header.strip_prefix("versions.").is_some_and(is_key);
+        } else if in_repo_table {
+            if line.starts_with("version") {
+                versions.extend(quoted_version(line));
+            }
+        } else if let Some((key, value)) = line.split_once('=') {
+            if is_key(key) && value.trim_start().starts_with('{') {
+                versions.extend(quoted_version(value));
+            }
+        }
+    }
+    versions
+}
+
+/// Largest edit distance at which an unknown repo name gets a "did you
+/// mean" suggestion
+const SUGGESTION_DISTANCE: usize = 2;
+
+/// Levenshtein distance between two strings
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// Characters that start the version constraint part of a `requires` entry
+const CONSTRAINT_START: [char; 6] = ['=', '>', '<', '^', '~', '*'];
+
+/// Prefix of the environment variables read by [`VersionsManifest::apply_env_overrides`]
+pub const ENV_OVERRIDE_PREFIX: &str = "BLLVM_OVERRIDE_";
+
+/// Whether a git ref is a full commit SHA (SHA-1 or SHA-256) rather than a tag or branch
+fn is_commit_sha(git_ref: &str) -> bool {
+    matches!(git_ref.len(), 40 | 64) && git_ref.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Extract the repo name from a `requires` entry
+pub(crate) fn dependency_name(spec: &str) -> &str {
+    if let Some((_, name, _)) = split_alias(spec) {
+        return name;
+    }
+    let end = spec
+        .find(|c| CONSTRAINT_START.contains(&c) || c == '[' || c == '?')
+        .unwrap_or(spec.len());
+    spec[..end].trim()
+}
+
+/// Alias, repo name and the rest of an `alias=repo@version` entry
+fn split_alias(spec: &str) -> Option<(&str, &str, &str)> {
+    let (head, constraint) = spec.split_once('@')?;
+    let (alias, name) = head.split_once('=')?;
+    Some((alias.trim(), name.trim(), constraint.trim()))
+}
+
+/// Whether a `requires` entry is optional (trailing `?`)
+pub(crate) fn is_optional(spec: &str) -> bool {
+    split_requirement(spec).2
+}
+
+/// Split a `requires` entry into name and constraint, feature list and
+/// optional marker
+pub(crate) fn split_requirement(spec: &str) -> (&str, Option<&str>, bool) {
+    let spec = spec.trim();
+    let (spec, optional) = match spec.strip_suffix('?') {
+        Some(rest) => (rest.trim_end(), true),
+        None => (spec, false),
+    };
+    match (spec.strip_suffix(']'), spec.rfind('[')) {
+        (Some(body), Some(start)) => (&spec[..start], Some(&body[start + 1..]), optional),
+        _ => (spec, None, optional),
+    }
+}
+
+/// Features requested on the dependency by a `requires` entry
+///
+/// `bllvm-consensus=0.1.0[bignum, serde]` requests `bignum` and `serde`.
+pub fn requirement_features(spec: &str) -> Vec<String> {
+    feature_list(split_requirement(spec).1)
+}
+
+/// Split a comma separated feature list, dropping empty entries
+fn feature_list(list: Option<&str>) -> Vec<String> {
+    list.into_iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|feature| !feature.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// One `requires` entry, optionally only for some target platforms
+///
+/// Written as just the requirement (`"bllvm-consensus=0.1.0"`), followed by
+/// a Cargo-style `cfg(...)` (`"bllvm-winsupport=0.1.0; cfg(windows)"`), or as
+/// a table naming the target (`{ dep = "bllvm-ffi=0.1.0", target = "windows" }`).
+/// It derefs to the requirement string, so it can be used wherever a
+/// requirement is expected. Conditional entries count like any other except
+/// in [`VersionsManifest::build_order_for_target`] and
+/// [`VersionsManifest::for_target`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(from = "DependencySpec", into = "DependencySpec")]
+pub struct Dependency {
+    /// Requirement string, e.g. `bllvm-ffi=0.1.0`
+    pub spec: String,
+
+    /// Target platform (as in `std::env::consts::OS`) or `cfg(...)`
+    /// expression the entry applies to; `None` for every target
+    pub target: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum DependencySpec {
+    Spec(String),
+    Conditional { dep: String, target: String },
+}
+
+impl From<DependencySpec> for Dependency {
+    fn from(spec: DependencySpec) -> Self {
+        match spec {
+            DependencySpec::Spec(spec) => match crate::target::split_cfg(&spec) {
+                Some((dep, cfg)) => Dependency {
+                    spec: dep.to_string(),
+                    target: Some(cfg.to_string()),
+                },
+                None => Dependency { spec, target: None },
+            },
+            DependencySpec::Conditional { dep, target } => Dependency {
+                spec: dep,
+                target: Some(target),
+            },
+        }
+    }
+}
+
+impl From<Dependency> for DependencySpec {
+    fn from(dependency: Dependency) -> Self {
+        match dependency.target {
+            None => DependencySpec::Spec(dependency.spec),
+            Some(target) if crate::target::is_cfg(&target) => {
+                DependencySpec::Spec(format!("{}; {}", dependency.spec, target))
+            }
+            Some(target) => DependencySpec::Conditional {
+                dep: dependency.spec,
+                target,
+            },
+        }
+    }
+}
+
+impl Dependency {
+    /// Whether the entry applies when building for `target`
+    ///
+    /// `target` is a target triple (`x86_64-unknown-linux-gnu`) or a platform
+    /// name (`linux`). A platform name `target` on the entry matches either
+    /// that name or a triple for that OS; a `cfg(...)` is evaluated like
+    /// Cargo does and only matches triples rustc knows.
+    pub fn applies_to(&self, target: &str) -> bool {
+        self.target
+            .as_deref()
+            .is_none_or(|only| crate::target::target_matches(only, target))
+    }
+}
+
+impl From<String> for Dependency {
+    fn from(spec: String) -> Self {
+        Dependency { spec, target: None }
+    }
+}
+
+impl From<&str> for Dependency {
+    fn from(spec: &str) -> Self {
+        spec.to_string().into()
+    }
+}
+
+impl std::ops::Deref for Dependency {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.spec
+    }
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.target {
+            None => write!(f, "{}", self.spec),
+            Some(target) if crate::target::is_cfg(target) => {
+                write!(f, "{}; {}", self.spec, target)
+            }
+            Some(target) => write!(f, "{} (target {})", self.spec, target),
+        }
+    }
+}
+
+impl PartialEq<str> for Dependency {
+    fn eq(&self, other: &str) -> bool {
+        self.target.is_none() && self.spec == other
+    }
+}
+
+impl PartialEq<&str> for Dependency {
+    fn eq(&self, other: &&str) -> bool {
+        *self == **other
+    }
+}
+
+/// A parsed `requires` entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// Name of the required repo
+    pub name: String,
+
+    /// Accepted versions of the required repo
+    pub req: VersionReq,
+
+    /// Features requested on the required repo
+    pub features: Vec<String>,
+
+    /// Whether the required repo may be absent from the manifest
+    ///
+    /// Optional dependencies that are present are still built first.
+    pub optional: bool,
+
+    /// Name the dependent knows this version by, for `alias=repo@version`
+    /// entries
+    pub alias: Option<String>,
+}
+
+/// Parse a `requires` entry
+///
+/// `name=X.Y.Z` is an exact pin. Any other semver requirement may follow the
+/// name directly (`name>=0.1.0, <0.2.0`) or after an `=` (`name=^0.1`).
+/// A bare name accepts any version. The version may be followed by a feature
+/// list (see [`requirement_features`]) and then a `?` marking the dependency
+/// as optional, e.g. `bllvm-node=0.1.0[rpc]?`.
+///
+/// `alias=repo@version` requires `repo` under the name `alias`, so one entry
+/// can require several versions of a repo declared with an array of
+/// versions (see [`crate::multi_version`]), e.g.
+/// `consensus_old=bllvm-consensus@0.1.0`.
+pub fn parse_requirement(spec: &str) -> Result<Requirement, Error> {
+    let malformed = |reason: String| Error::MalformedRequirement {
+        requirement: spec.to_string(),
+        reason,
+    };
+    let (spec, features, optional) = split_requirement(spec);
+    let (alias, name, mut constraint) = match split_alias(spec) {
+        Some((alias, name, constraint)) => {
+            if alias.is_empty() {
+                return Err(malformed(format!("missing alias before '=' in '{}'", spec)));
+            }
+            (Some(alias.to_string()), name, constraint)
+        }
+        None if spec.contains('@') => {
+            return Err(malformed(
+                "'@' is only allowed in aliased entries, alias=repo@version".to_string(),
+            ))
+        }
+        None => {
+            let name = dependency_name(spec);
+            (None, name, spec[name.len()..].trim())
+        }
+    };
+    if name.is_empty() {
+        return Err(malformed("missing repository name".to_string()));
+    }
+
+    // `alias=repo@0.1.0` pins like `repo=0.1.0`
+    let pinned;
+    if alias.is_some() && constraint.starts_with(|c: char| c.is_ascii_digit()) {
+        pinned = format!("={}", constraint);
+        constraint = &pinned;
+    }
+    if let Some(rest) = constraint.strip_prefix('=') {
+        if rest.trim_start().starts_with(CONSTRAINT_START) {
+            constraint = rest;
+        }
+    }
+
+    let req = if constraint.is_empty() {
+        VersionReq::STAR
+    } else {
+        VersionReq::parse(constraint).map_err(|e| {
+            malformed(format!(
+                "invalid version constraint '{}': {}",
+                constraint, e
+            ))
+        })?
+    };
+
+    Ok(Requirement {
+        name: name.to_string(),
+        req,
+        features: feature_list(features),
+        optional,
+        alias,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -228,6 +2817,59 @@ mod tests {
         assert!(!is_valid_semver("1.2.3.4"));
     }
 
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("bllvm-protocol", "bllvm-protocol"), 0);
+        assert_eq!(edit_distance("bllvm-protcol", "bllvm-protocol"), 1);
+        assert_eq!(edit_distance("bllvm-node", "bllvm-sdk"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_parse_requirement() {
+        let parsed = parse_requirement("bllvm-consensus=0.1.0").unwrap();
+        assert_eq!(parsed.name, "bllvm-consensus");
+        assert!(parsed.req.matches(&Version::parse("0.1.0").unwrap()));
+        assert!(!parsed.req.matches(&Version::parse("0.1.1").unwrap()));
+        assert!(!parsed.optional);
+
+        let parsed = parse_requirement("bllvm-consensus>=0.1.0, <0.2.0").unwrap();
+        assert_eq!(parsed.name, "bllvm-consensus");
+        assert!(parsed.req.matches(&Version::parse("0.1.5").unwrap()));
+        assert!(!parsed.req.matches(&Version::parse("0.2.0").unwrap()));
+
+        let parsed = parse_requirement("bllvm-consensus=^0.1").unwrap();
+        assert!(parsed.req.matches(&Version::parse("0.1.9").unwrap()));
+        assert!(!parsed.req.matches(&Version::parse("0.2.0").unwrap()));
+
+        let parsed = parse_requirement("bllvm-consensus").unwrap();
+        assert_eq!(parsed.name, "bllvm-consensus");
+        assert_eq!(parsed.req, VersionReq::STAR);
+
+        let parsed = parse_requirement("bllvm-consensus=0.1.0[bignum]").unwrap();
+        assert_eq!(parsed.name, "bllvm-consensus");
+        assert!(parsed.req.matches(&Version::parse("0.1.0").unwrap()));
+        assert_eq!(parsed.features, vec!["bignum"]);
+
+        let parsed = parse_requirement("bllvm-node=0.1.0[rpc]?").unwrap();
+        assert_eq!(parsed.name, "bllvm-node");
+        assert!(parsed.req.matches(&Version::parse("0.1.0").unwrap()));
+        assert_eq!(parsed.features, vec!["rpc"]);
+        assert!(parsed.optional);
+        assert_eq!(dependency_name("bllvm-node?"), "bllvm-node");
+        assert_eq!(
+            requirement_features("bllvm-consensus=0.1.0[bignum, serde]"),
+            vec!["bignum", "serde"]
+        );
+        assert_eq!(
+            dependency_name("bllvm-consensus[bignum]"),
+            "bllvm-consensus"
+        );
+
+        assert!(parse_requirement("=0.1.0").is_err());
+        assert!(parse_requirement("bllvm-consensus=not-a-version").is_err());
+    }
+
     #[test]
     fn test_parse_versions_toml() {
         let content = r#"
@@ -236,12 +2878,20 @@ bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
 bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
 "#;
 
-        let manifest: VersionsManifest = toml::from_str(content).unwrap();
+        let manifest = VersionsManifest::from_str(content).unwrap();
         assert_eq!(manifest.versions.len(), 2);
         assert!(manifest.versions.contains_key("bllvm-consensus"));
         assert!(manifest.versions.contains_key("bllvm-protocol"));
     }
 
+    #[test]
+    fn test_from_str_error_mentions_inline_source() {
+        let err = VersionsManifest::from_str("[versions]\nbllvm-consensus = 1")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Failed to parse versions.toml (<inline>)"));
+    }
+
     #[test]
     fn test_build_order() {
         let content = r#"
@@ -251,7 +2901,7 @@ bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-con
 bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
 "#;
 
-        let manifest: VersionsManifest = toml::from_str(content).unwrap();
+        let manifest = VersionsManifest::from_str(content).unwrap();
         let order = manifest.build_order().unwrap();
 
         let consensus_pos = order.iter().position(|r| r == "bllvm-consensus").unwrap();
@@ -270,7 +2920,7 @@ A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
 B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
 "#;
 
-        let manifest: VersionsManifest = toml::from_str(content).unwrap();
+        let manifest = VersionsManifest::from_str(content).unwrap();
         assert!(manifest.detect_circular_dependencies().is_some());
     }
 }
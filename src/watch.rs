@@ -0,0 +1,298 @@
+//! Live build order for local development (`watch` feature)
+//!
+//! ```no_run
+//! use std::ops::ControlFlow;
+//!
+//! bllvm::watch::watch("versions.toml", |order| {
+//!     match order {
+//!         Ok(order) => println!("{}", order.join(" -> ")),
+//!         Err(e) => eprintln!("{}", e),
+//!     }
+//!     ControlFlow::Continue(())
+//! })
+//! .unwrap();
+//! ```
+//!
+//! [`ManifestWatcher`] also validates each save and reports how the build
+//! order changed; it is what `bllvm watch` runs.
+
+use crate::versions::VersionsManifest;
+use crate::Error;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+/// How long the file has to stay untouched before it is read again
+///
+/// Editors often save in several steps (truncate, write, rename), which
+/// would otherwise be reported as several changes.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Call `callback` with the build order of the manifest at `path`, then
+/// again every time the file changes
+///
+/// The callback gets the new [`build_order`](VersionsManifest::build_order),
+/// or the error if the manifest no longer parses or resolves; watching goes
+/// on either way until the callback returns [`ControlFlow::Break`]. Writes
+/// closer together than [`DEBOUNCE`] count as one change. Files brought in
+/// with `include` are read on every change but not watched themselves.
+pub fn watch<P, F>(path: P, mut callback: F) -> anyhow::Result<()>
+where
+    P: AsRef<Path>,
+    F: FnMut(Result<Vec<String>, Error>) -> ControlFlow<()>,
+{
+    ManifestWatcher::new(path.as_ref())
+        .each_load(|manifest| callback(manifest.and_then(|manifest| manifest.build_order())))
+}
+
+/// How the build order changed between two saves, see [`WatchUpdate::Plan`]
+///
+/// Each list is in the order of the build order it comes from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct OrderDiff {
+    /// Repos only in the new order
+    pub added: Vec<String>,
+    /// Repos only in the old order
+    pub removed: Vec<String>,
+    /// Repos in both whose position relative to the other repos in both
+    /// changed
+    pub moved: Vec<String>,
+}
+
+impl OrderDiff {
+    /// Differences from `old` to `new`
+    pub fn between(old: &[String], new: &[String]) -> Self {
+        let position = |order: &[String]| -> HashMap<String, usize> {
+            order
+                .iter()
+                .enumerate()
+                .map(|(index, repo)| (repo.clone(), index))
+                .collect()
+        };
+        let (in_old, in_new) = (position(old), position(new));
+
+        let kept_old: Vec<&String> = old.iter().filter(|r| in_new.contains_key(*r)).collect();
+        let kept_new: Vec<&String> = new.iter().filter(|r| in_old.contains_key(*r)).collect();
+        let kept_position: HashMap<&String, usize> = kept_old
+            .iter()
+            .enumerate()
+            .map(|(index, repo)| (*repo, index))
+            .collect();
+
+        OrderDiff {
+            added: new
+                .iter()
+                .filter(|r| !in_old.contains_key(*r))
+                .cloned()
+                .collect(),
+            removed: old
+                .iter()
+                .filter(|r| !in_new.contains_key(*r))
+                .cloned()
+                .collect(),
+            moved: kept_new
+                .iter()
+                .enumerate()
+                .filter(|(index, repo)| kept_position[*repo] != *index)
+                .map(|(_, repo)| repo.to_string())
+                .collect(),
+        }
+    }
+
+    /// Whether the order is the same
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+}
+
+impl fmt::Display for OrderDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for repo in &self.added {
+            writeln!(f, "+ {}", repo)?;
+        }
+        for repo in &self.removed {
+            writeln!(f, "- {}", repo)?;
+        }
+        for repo in &self.moved {
+            writeln!(f, "~ {}", repo)?;
+        }
+        Ok(())
+    }
+}
+
+/// What [`ManifestWatcher::run`] reports after each save
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WatchUpdate {
+    /// The manifest is valid and has this build order
+    Plan {
+        order: Vec<String>,
+        /// Changes since the last valid plan; `None` for the first one
+        changes: Option<OrderDiff>,
+    },
+    /// The manifest does not parse, or [`validate`](VersionsManifest::validate)
+    /// found errors (cycles included); the last valid plan stays the one
+    /// the next is compared with
+    Invalid { errors: Vec<String> },
+}
+
+/// Re-plan a manifest every time it is saved
+///
+/// Like [`watch`], but validates each save and reports [`WatchUpdate`]s,
+/// and can be stopped from another thread with a [`StopHandle`].
+#[derive(Debug)]
+pub struct ManifestWatcher {
+    path: PathBuf,
+    debounce: Duration,
+    sender: Sender<Message>,
+    receiver: Receiver<Message>,
+}
+
+/// Stops a [`ManifestWatcher`], see [`ManifestWatcher::stop_handle`]
+#[derive(Debug, Clone)]
+pub struct StopHandle(Sender<Message>);
+
+impl StopHandle {
+    /// Make [`run`](ManifestWatcher::run) return; does nothing once it has
+    pub fn stop(&self) {
+        let _ = self.0.send(Message::Stop);
+    }
+}
+
+#[derive(Debug)]
+enum Message {
+    Changed(notify::Result<Event>),
+    Stop,
+}
+
+impl ManifestWatcher {
+    /// Watch the manifest at `path`, with [`DEBOUNCE`]
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            path: path.as_ref().to_path_buf(),
+            debounce: DEBOUNCE,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Treat writes closer together than `debounce` as one save
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Handle to stop [`run`](Self::run) from another thread, e.g. on Ctrl-C
+    pub fn stop_handle(&self) -> StopHandle {
+        StopHandle(self.sender.clone())
+    }
+
+    /// Call `callback` with the state of the manifest, then after every save
+    ///
+    /// Returns once the callback returns [`ControlFlow::Break`] or the
+    /// watcher is stopped. An invalid manifest, e.g. one an editor is still
+    /// writing, is reported and watching goes on.
+    pub fn run<F>(self, mut callback: F) -> anyhow::Result<()>
+    where
+        F: FnMut(WatchUpdate) -> ControlFlow<()>,
+    {
+        let mut last: Option<Vec<String>> = None;
+        self.each_load(|manifest| {
+            let update = match plan(manifest) {
+                Ok(order) => {
+                    let changes = last.as_deref().map(|last| OrderDiff::between(last, &order));
+                    last = Some(order.clone());
+                    WatchUpdate::Plan { order, changes }
+                }
+                Err(errors) => WatchUpdate::Invalid { errors },
+            };
+            callback(update)
+        })
+    }
+
+    /// Call `callback` with the loaded manifest, then again after every save
+    fn each_load<F>(self, mut callback: F) -> anyhow::Result<()>
+    where
+        F: FnMut(Result<VersionsManifest, Error>) -> ControlFlow<()>,
+    {
+        let path = self.path.as_path();
+        let Some(file_name) = path.file_name() else {
+            anyhow::bail!("Not a manifest file: {}", path.display());
+        };
+        // Watch the directory: saving by renaming a new file over the old one
+        // would end a watch on the file itself
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+
+        let sender = self.sender.clone();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(Message::Changed(event));
+        })
+        .map_err(|e| anyhow::anyhow!("Failed to watch {}: {}", path.display(), e))?;
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| anyhow::anyhow!("Failed to watch {}: {}", dir.display(), e))?;
+
+        let is_change = |event: &Event| {
+            !matches!(event.kind, EventKind::Access(_))
+                && event
+                    .paths
+                    .iter()
+                    .any(|changed| changed.file_name() == Some(file_name))
+        };
+
+        loop {
+            if callback(VersionsManifest::from_file(path)).is_break() {
+                return Ok(());
+            }
+            if !self.next_change(is_change)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Wait for a change, then until none has come in for the debounce time
+    ///
+    /// `false` once the watcher has been stopped.
+    fn next_change(&self, is_change: impl Fn(&Event) -> bool) -> anyhow::Result<bool> {
+        loop {
+            match self.receiver.recv() {
+                Ok(Message::Changed(event)) => {
+                    if is_change(&event.map_err(|e| anyhow::anyhow!("Watch failed: {}", e))?) {
+                        break;
+                    }
+                }
+                Ok(Message::Stop) | Err(_) => return Ok(false),
+            }
+        }
+        loop {
+            match self.receiver.recv_timeout(self.debounce) {
+                Ok(Message::Changed(_)) => continue,
+                Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => return Ok(false),
+                Err(RecvTimeoutError::Timeout) => return Ok(true),
+            }
+        }
+    }
+}
+
+/// Build order of a loaded manifest, or everything wrong with it
+fn plan(manifest: Result<VersionsManifest, Error>) -> Result<Vec<String>, Vec<String>> {
+    let manifest = manifest.map_err(|e| vec![e.to_string()])?;
+    let validation = manifest.validate();
+    if !validation.is_valid() {
+        return Err(validation
+            .errors()
+            .iter()
+            .map(ToString::to_string)
+            .collect());
+    }
+    manifest.build_order().map_err(|e| vec![e.to_string()])
+}
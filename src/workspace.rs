@@ -0,0 +1,293 @@
+//! Cloning a manifest's repos side by side (requires the `git` feature)
+
+use crate::git::{is_transient, RetryPolicy};
+use crate::versions::VersionsManifest;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::process::Command;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// What [`Workspace::clone_all`] did with one repo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", content = "reason", rename_all = "snake_case")]
+pub enum CloneStatus {
+    /// Freshly cloned
+    Cloned,
+
+    /// Already present at a different commit, fetched and checked out
+    Updated,
+
+    /// Already present at the pinned commit, left alone
+    UpToDate,
+
+    /// Could not be cloned or checked out
+    Failed(String),
+}
+
+/// Outcome for one repo, see [`Workspace::clone_all`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CloneResult {
+    /// Repository name
+    pub repo: String,
+
+    /// Checkout directory (`<dest_dir>/<path>`, or `<dest_dir>/<repo>`
+    /// without a `path`)
+    pub dir: PathBuf,
+
+    pub status: CloneStatus,
+
+    /// How many times the clone or fetch was tried, more than 1 after
+    /// transient failures
+    pub attempts: u32,
+}
+
+impl CloneResult {
+    /// Whether the repo is now checked out at its pinned ref
+    pub fn is_success(&self) -> bool {
+        !matches!(self.status, CloneStatus::Failed(_))
+    }
+}
+
+impl fmt::Display for CloneResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.status {
+            CloneStatus::Cloned => write!(f, "{}: cloned", self.repo),
+            CloneStatus::Updated => write!(f, "{}: updated", self.repo),
+            CloneStatus::UpToDate => write!(f, "{}: up to date", self.repo),
+            CloneStatus::Failed(reason) => write!(f, "{}: failed ({})", self.repo, reason),
+        }?;
+        if self.attempts > 1 {
+            write!(f, " after {} attempts", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+/// The repos of a manifest checked out next to each other
+#[derive(Debug, Clone)]
+pub struct Workspace<'a> {
+    manifest: &'a VersionsManifest,
+}
+
+impl<'a> Workspace<'a> {
+    /// Workspace of `manifest`'s repos
+    pub fn new(manifest: &'a VersionsManifest) -> Self {
+        Self { manifest }
+    }
+
+    /// Clone every repo into `<dest_dir>/<path>` (`<dest_dir>/<repo>` when it
+    /// has no `path`) at its pinned ref, where the build and
+    /// [`orphans`](Self::orphans) expect it
+    ///
+    /// Repos marked `external` are not cloned.
+    /// Repos are cloned one [build batch](VersionsManifest::build_batches) at
+    /// a time, so dependencies are in place before their dependents, with at
+    /// most `concurrency` clones running at once. The pinned ref is
+    /// `git_commit` when set, otherwise the [full tag](crate::versions::RepoVersion::full_tag). A checkout already at that
+    /// commit is left alone and one at another commit is fetched and checked
+    /// out. Network problems and timeouts are retried under `retry`. A
+    /// failing repo is reported in its result without stopping the others;
+    /// the results are in build order.
+    pub async fn clone_all<P: AsRef<Path>>(
+        &self,
+        dest_dir: P,
+        concurrency: usize,
+        retry: RetryPolicy,
+    ) -> anyhow::Result<Vec<CloneResult>> {
+        if concurrency == 0 {
+            anyhow::bail!("Clone concurrency must be at least 1");
+        }
+        let dest_dir = dest_dir.as_ref();
+        std::fs::create_dir_all(dest_dir)
+            .map_err(|e| anyhow::anyhow!("Failed to create {}: {}", dest_dir.display(), e))?;
+
+        let permits = Arc::new(Semaphore::new(concurrency));
+        let mut results = Vec::new();
+        for batch in self.manifest.build_batches()? {
+            let mut tasks = JoinSet::new();
+            let batch: Vec<&String> = batch
+                .iter()
+                .filter(|repo| !self.manifest.versions[*repo].external)
+                .collect();
+            for (index, repo) in batch.iter().enumerate() {
+                let info = &self.manifest.versions[*repo];
+                let dir = dest_dir.join(info.path.as_deref().unwrap_or(repo.as_str()));
+                let url = self.manifest.git_url_for(repo);
+                let git_ref = info
+                    .git_commit
+                    .clone()
+                    .filter(|commit| !commit.is_empty())
+                    .unwrap_or_else(|| info.full_tag());
+                let git_tag = info.full_tag();
+                let permits = Arc::clone(&permits);
+                tasks.spawn(async move {
+                    let _permit = permits.acquire_owned().await;
+                    let (status, attempts) = match url {
+                        Ok(url) => checkout_with_retry(&url, &git_tag, &git_ref, &dir, retry).await,
+                        Err(e) => (CloneStatus::Failed(e.to_string()), 1),
+                    };
+                    (index, dir, status, attempts)
+                });
+            }
+
+            let mut finished = Vec::new();
+            while let Some(joined) = tasks.join_next().await {
+                finished.push(joined.map_err(|e| anyhow::anyhow!("Clone task failed: {}", e))?);
+            }
+            finished.sort_by_key(|(index, _, _, _)| *index);
+            results.extend(finished.into_iter().map(|(index, dir, status, attempts)| {
+                CloneResult {
+                    repo: batch[index].to_string(),
+                    dir,
+                    status,
+                    attempts,
+                }
+            }));
+        }
+
+        Ok(results)
+    }
+
+    /// Git checkouts directly under `dir` that are not in the manifest, sorted
+    ///
+    /// A repo is checked out at `<dir>/<path>`, or `<dir>/<repo>` when it has
+    /// no `path`. Subdirectories that are not git repos are ignored.
+    pub fn orphans<P: AsRef<Path>>(&self, dir: P) -> anyhow::Result<Vec<String>> {
+        let dir = dir.as_ref();
+        let declared: HashSet<PathBuf> = self
+            .manifest
+            .versions
+            .iter()
+            .map(|(repo, info)| dir.join(info.path.as_deref().unwrap_or(repo)))
+            .collect();
+
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?;
+        let mut orphans = Vec::new();
+        for entry in entries {
+            let entry =
+                entry.map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?;
+            let path = entry.path();
+            if is_checkout(&path) && !declared.contains(&path) {
+                orphans.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        orphans.sort();
+        Ok(orphans)
+    }
+
+    /// Repos in the manifest without a git checkout under `dir`, sorted
+    ///
+    /// Checkout directories are found as in [`orphans`](Self::orphans).
+    pub fn missing<P: AsRef<Path>>(&self, dir: P) -> Vec<String> {
+        let dir = dir.as_ref();
+        let mut missing: Vec<String> = self
+            .manifest
+            .versions
+            .iter()
+            .filter(|(repo, info)| !is_checkout(&dir.join(info.path.as_deref().unwrap_or(repo))))
+            .map(|(repo, _)| repo.clone())
+            .collect();
+        missing.sort();
+        missing
+    }
+}
+
+/// Whether `dir` is a git working tree (`.git` is a directory, or a file for worktrees)
+fn is_checkout(dir: &Path) -> bool {
+    dir.join(".git").exists()
+}
+
+/// [`checkout`], retrying transient failures; returns the status and the
+/// attempts made
+async fn checkout_with_retry(
+    url: &str,
+    git_tag: &str,
+    git_ref: &str,
+    dir: &Path,
+    retry: RetryPolicy,
+) -> (CloneStatus, u32) {
+    let mut attempt = 1;
+    loop {
+        match checkout(url, git_tag, git_ref, dir).await {
+            Ok(status) => return (status, attempt),
+            Err(reason) if is_transient(&reason) && attempt < retry.attempts => {
+                tokio::time::sleep(retry.delay(attempt)).await;
+                attempt += 1;
+            }
+            Err(reason) => return (CloneStatus::Failed(reason), attempt),
+        }
+    }
+}
+
+/// Get `dir` checked out at `git_ref`, cloning `url` if it does not exist
+async fn checkout(
+    url: &str,
+    git_tag: &str,
+    git_ref: &str,
+    dir: &Path,
+) -> Result<CloneStatus, String> {
+    if dir.join(".git").exists() {
+        let head = git(dir, &["rev-parse", "HEAD"]).await?;
+        if resolve(dir, git_ref).await.as_deref() == Some(head.as_str()) {
+            return Ok(CloneStatus::UpToDate);
+        }
+        git(dir, &["fetch", "--quiet", "--tags", "origin"]).await?;
+        git(dir, &["checkout", "--quiet", "--detach", git_ref]).await?;
+        return Ok(CloneStatus::Updated);
+    }
+
+    let dir_arg = dir.to_string_lossy();
+    run(
+        Command::new("git").args(["clone", "--quiet", "--branch", git_tag, url, &dir_arg]),
+        "git clone",
+    )
+    .await?;
+    if git_ref != git_tag {
+        git(dir, &["checkout", "--quiet", "--detach", git_ref]).await?;
+    }
+    Ok(CloneStatus::Cloned)
+}
+
+/// Commit `git_ref` names in the checkout at `dir`, if it exists there
+async fn resolve(dir: &Path, git_ref: &str) -> Option<String> {
+    git(
+        dir,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", git_ref),
+        ],
+    )
+    .await
+    .ok()
+}
+
+/// Run git in `dir`, returning its trimmed stdout
+async fn git(dir: &Path, args: &[&str]) -> Result<String, String> {
+    let description = format!("git {}", args[0]);
+    run(
+        Command::new("git").arg("-C").arg(dir).args(args),
+        &description,
+    )
+    .await
+}
+
+async fn run(command: &mut Command, description: &str) -> Result<String, String> {
+    let output = command
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .output()
+        .await
+        .map_err(|e| format!("failed to run {}: {}", description, e))?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("{} failed: {}", description, stderr.trim()))
+    }
+}
@@ -1,7 +1,17 @@
 //! Tests for build order and dependency resolution
 
-use bllvm::versions::VersionsManifest;
+use bllvm::bump::BumpPolicy;
+use bllvm::executor::BuildExecutor;
+use bllvm::explain::OrderExplanation;
+use bllvm::latest::MockVersionResolver;
+use bllvm::multi_version::ResolveStrategy;
+use bllvm::versions::{
+    parse_requirement, BuildOrderOptions, PinDrift, ValidationError, ValidationResult,
+    VersionsManifest, Warning,
+};
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
+use std::time::Duration;
 use tempfile::TempDir;
 
 /// Test that build order respects dependencies
@@ -65,11 +75,10 @@ B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
     let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
     let result = manifest.build_order();
 
-    assert!(result.is_err(), "Should fail with circular dependency");
-    assert!(result
-        .unwrap_err()
-        .to_string()
-        .contains("Circular dependency"));
+    assert!(
+        matches!(result, Err(bllvm::Error::CircularDependency { .. })),
+        "Should fail with circular dependency"
+    );
 }
 
 /// Test parallel builds (repos with no dependencies can be built in parallel)
@@ -86,36 +95,1661 @@ bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-con
     let versions_path = temp_dir.path().join("versions.toml");
     fs::write(&versions_path, content).unwrap();
 
+    // bllvm-consensus and bllvm-sdk have no dependencies, so they can be built in parallel;
+    // ties are broken by name, so protocol (which needs consensus) comes after both.
+    // Parse twice so the two runs don't share a hash map.
+    for _ in 0..2 {
+        let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+        let build_order = manifest
+            .build_order()
+            .expect("Should calculate build order");
+        assert_eq!(
+            build_order,
+            vec!["bllvm-consensus", "bllvm-sdk", "bllvm-protocol"]
+        );
+    }
+}
+
+/// Test that independent repos are grouped into the same build batch
+#[test]
+fn test_build_batches() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
     let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
-    let build_order = manifest
+    let batches = manifest
+        .build_batches()
+        .expect("Should calculate build batches");
+
+    assert_eq!(
+        batches,
+        vec![
+            vec!["bllvm-consensus".to_string(), "bllvm-sdk".to_string()],
+            vec!["bllvm-protocol".to_string()],
+            vec!["bllvm-node".to_string()],
+        ]
+    );
+
+    // Batches must be stable across repeated calls
+    assert_eq!(batches, manifest.build_batches().unwrap());
+}
+
+/// Test that build batches fail on circular dependencies
+#[test]
+fn test_build_batches_circular() {
+    let content = r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    match manifest.build_batches() {
+        Err(bllvm::ManifestError::CircularDependency { cycle }) => {
+            assert_eq!(cycle, vec!["A", "B", "A"]);
+        }
+        other => panic!("expected a circular dependency, got {:?}", other),
+    }
+}
+
+/// Test that the flat build order is the flattened build stages
+#[test]
+fn test_build_order_matches_stages() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let stages = manifest.build_stages().expect("Should calculate stages");
+    let order = manifest.build_order().expect("Should calculate order");
+
+    assert_eq!(stages.len(), 2);
+    assert_eq!(order, stages.into_iter().flatten().collect::<Vec<_>>());
+    assert_eq!(
+        order,
+        vec!["bllvm-consensus", "bllvm-sdk", "bllvm-protocol"]
+    );
+}
+
+/// Test that build order fails when a dependency's version doesn't satisfy a requirement
+#[test]
+fn test_build_order_unsatisfied_requirement() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let err = manifest
+        .build_order()
+        .expect_err("Should fail with unsatisfied requirement")
+        .to_string();
+
+    assert!(err.contains("bllvm-protocol"));
+    assert!(err.contains("bllvm-consensus=0.1.0"));
+    assert!(err.contains("0.2.0"));
+}
+
+/// Test that range requirements resolve against declared versions
+#[test]
+fn test_build_order_range_requirement() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.4", git_tag = "v0.1.4" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let order = manifest
         .build_order()
         .expect("Should calculate build order");
+    assert_eq!(order, vec!["bllvm-consensus", "bllvm-protocol"]);
+}
 
-    // bllvm-consensus and bllvm-sdk have no dependencies, so they can be built in parallel
-    // bllvm-protocol depends on bllvm-consensus, so consensus must come before protocol
-    // bllvm-sdk has no dependencies, so its position relative to protocol is non-deterministic
-    let consensus_pos = build_order
-        .iter()
-        .position(|r| r == "bllvm-consensus")
-        .unwrap();
-    let protocol_pos = build_order
+/// Test that the circular dependency error reports the cycle path
+#[test]
+fn test_circular_dependency_path() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-node=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0", "bllvm-node=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let err = manifest
+        .build_order()
+        .expect_err("Should fail with circular dependency")
+        .to_string();
+
+    assert_eq!(
+        err,
+        "Circular dependency detected: bllvm-node -> bllvm-protocol -> bllvm-node"
+    );
+    assert_eq!(
+        manifest.detect_circular_dependencies().as_deref(),
+        Some("bllvm-node -> bllvm-protocol -> bllvm-node")
+    );
+}
+
+/// Test that every independent cycle is reported and the error is structured
+#[test]
+fn test_multiple_cycles() {
+    let content = r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["C=0.1.0"] }
+C = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
+X = { version = "0.1.0", git_tag = "v0.1.0", requires = ["Y=0.1.0", "A=0.1.0"] }
+Y = { version = "0.1.0", git_tag = "v0.1.0", requires = ["X=0.1.0"] }
+Z = { version = "0.1.0", git_tag = "v0.1.0", requires = ["Z=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    assert_eq!(
+        manifest.find_cycles(),
+        vec![
+            vec!["A", "B", "C", "A"],
+            vec!["X", "Y", "X"],
+            vec!["Z", "Z"],
+        ]
+    );
+
+    let validation = manifest.validate();
+    let cycles = validation
+        .errors()
         .iter()
-        .position(|r| r == "bllvm-protocol")
-        .unwrap();
+        .filter(|e| matches!(e, ValidationError::CircularDependency { .. }))
+        .count();
+    assert_eq!(cycles, 2);
+    assert!(validation
+        .errors()
+        .contains(&ValidationError::SelfRequirement {
+            repo: "Z".to_string()
+        }));
 
-    // Consensus must come before protocol (it's a dependency)
-    assert!(
-        consensus_pos < protocol_pos,
-        "bllvm-consensus should come before bllvm-protocol (it's a dependency)"
+    let err = manifest.build_order().expect_err("Should fail with cycle");
+    assert_eq!(
+        err,
+        bllvm::Error::CircularDependency {
+            cycle: vec![
+                "A".to_string(),
+                "B".to_string(),
+                "C".to_string(),
+                "A".to_string()
+            ],
+        }
     );
+}
+
+/// Test reverse-dependency queries
+#[test]
+fn test_dependents_of() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    assert_eq!(
+        manifest.dependents_of("bllvm-consensus", true).unwrap(),
+        vec!["bllvm-protocol", "bllvm-node"]
+    );
+    assert_eq!(
+        manifest.dependents_of("bllvm-consensus", false).unwrap(),
+        vec!["bllvm-protocol"]
+    );
+    assert_eq!(
+        manifest.dependents_of("bllvm-node", true).unwrap(),
+        Vec::<String>::new()
+    );
+
+    let err = manifest
+        .dependents_of("bllvm-protcol", true)
+        .expect_err("Unknown repo should fail")
+        .to_string();
+    assert!(err.contains("did you mean 'bllvm-protocol'?"));
+
+    let err = manifest
+        .dependents_of("governance-app", true)
+        .expect_err("Unknown repo should fail")
+        .to_string();
+    assert!(!err.contains("did you mean"));
+}
+
+/// Test build order restricted to targets and their dependencies
+#[test]
+fn test_build_order_for_targets() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    assert_eq!(
+        manifest.build_order_for(&["bllvm-node"]).unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+
+    // Overlapping closures are deduplicated
+    assert_eq!(
+        manifest
+            .build_order_for(&["bllvm-node", "bllvm-protocol", "governance-app"])
+            .unwrap(),
+        vec![
+            "bllvm-consensus",
+            "bllvm-sdk",
+            "bllvm-protocol",
+            "governance-app",
+            "bllvm-node"
+        ]
+    );
+
+    let err = manifest
+        .build_order_for(&["bllvm-nod"])
+        .expect_err("Unknown target should fail")
+        .to_string();
+    assert!(err.contains("bllvm-nod"));
+    assert!(err.contains("bllvm-consensus, bllvm-node, bllvm-protocol, bllvm-sdk, governance-app"));
+}
+
+/// Test `[groups]` members and group names as build targets
+#[test]
+fn test_build_order_for_group() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+
+[groups]
+core = ["bllvm-protocol", "bllvm-consensus"]
+broken = ["bllvm-consensus", "bllvm-wallet"]
+"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        manifest.group_members("core").unwrap(),
+        vec!["bllvm-protocol", "bllvm-consensus"]
+    );
+    assert_eq!(
+        manifest.build_order_for_group("core").unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol"]
+    );
+    assert_eq!(
+        manifest
+            .build_order_for(&["core", "governance-app"])
+            .unwrap(),
+        vec![
+            "bllvm-consensus",
+            "bllvm-sdk",
+            "bllvm-protocol",
+            "governance-app"
+        ]
+    );
+
+    let err = manifest.group_members("tools").unwrap_err().to_string();
+    assert!(err.contains("valid groups: broken, core"), "{}", err);
+    let err = manifest
+        .build_order_for_group("broken")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("'bllvm-wallet'"), "{}", err);
+
+    // Groups survive a round trip
+    let reparsed = VersionsManifest::from_str(&manifest.to_toml_string().unwrap()).unwrap();
+    assert_eq!(reparsed.groups, manifest.groups);
+}
 
-    // Verify all repos are present (order between independent repos is non-deterministic)
-    assert!(build_order.contains(&"bllvm-consensus".to_string()));
-    assert!(build_order.contains(&"bllvm-sdk".to_string()));
-    assert!(build_order.contains(&"bllvm-protocol".to_string()));
+/// Test the rebuild set for changed repos
+#[test]
+fn test_rebuild_plan() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+bllvm = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-node=0.1.0", "bllvm-sdk=0.1.0"] }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).expect("Should parse");
+    assert_eq!(
+        manifest.rebuild_plan(&["bllvm-protocol"]).unwrap(),
+        vec!["bllvm-protocol", "bllvm-node", "bllvm"]
+    );
+
+    // Shared dependents are listed once, and stages only wait on rebuilt repos
+    assert_eq!(
+        manifest
+            .rebuild_stages(&["bllvm-node", "bllvm-sdk"])
+            .unwrap(),
+        vec![
+            vec!["bllvm-node", "bllvm-sdk"],
+            vec!["bllvm", "governance-app"]
+        ]
+    );
+    assert_eq!(
+        manifest.rebuild_plan(&["bllvm-node", "bllvm-sdk"]).unwrap(),
+        vec!["bllvm-node", "bllvm-sdk", "bllvm", "governance-app"]
+    );
+
+    let err = manifest
+        .rebuild_plan(&["bllvm-consensus", "bllvm-protocl"])
+        .expect_err("Unknown repo should fail");
+    assert!(matches!(
+        &err,
+        bllvm::Error::UnknownRepo { repo, suggestion, .. }
+            if repo == "bllvm-protocl" && suggestion.as_deref() == Some("bllvm-protocol")
+    ));
+    assert!(err
+        .to_string()
+        .contains("Unknown repository 'bllvm-protocl'"));
+}
+
+/// Test that optional dependencies order builds only when present
+#[test]
+fn test_optional_dependency() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-explorer = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-node=0.1.0?", "bllvm-consensus=0.1.0"] }
+"#;
+
+    // Absent optional dependency is neither an error nor a build step
+    let manifest = VersionsManifest::from_str(content).expect("Should parse");
+    assert!(manifest.validate().is_valid());
+    assert_eq!(
+        manifest.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-explorer"]
+    );
+    assert!(!manifest.to_dot().contains("undefined"));
+
+    // Present optional dependency is built first and its version is checked
+    let with_node = format!(
+        "{}{}",
+        content,
+        r#"bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }"#
+    );
+    let manifest = VersionsManifest::from_str(&with_node).expect("Should parse");
+    assert_eq!(
+        manifest.build_stages().unwrap(),
+        vec![
+            vec!["bllvm-consensus"],
+            vec!["bllvm-node"],
+            vec!["bllvm-explorer"]
+        ]
+    );
+
+    let manifest = VersionsManifest::from_str(&with_node.replace("0.1.0?", "0.2.0?")).unwrap();
+    assert!(manifest.build_order().is_err());
+}
+
+/// Test that optional_requires don't order builds or create cycles by default
+#[test]
+fn test_optional_requires() {
+    // bllvm-sdk only uses bllvm-node in its examples; as a hard requirement
+    // that would be a cycle
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", optional_requires = ["bllvm-node=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0", "bllvm-consensus=0.1.0"] }
+bllvm-explorer = { version = "0.1.0", git_tag = "v0.1.0", optional_requires = ["bllvm-node=0.1.0"] }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).expect("Should parse");
+    assert!(manifest.validate().is_valid());
+    assert!(manifest.find_cycles().is_empty());
+    assert_eq!(
+        manifest.build_order().unwrap(),
+        vec![
+            "bllvm-consensus",
+            "bllvm-explorer",
+            "bllvm-sdk",
+            "bllvm-node"
+        ]
+    );
+
+    // Included optional edges are honoured unless they would close a cycle
+    let options = BuildOrderOptions {
+        include_optional: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        manifest.build_order_with_options(&options).unwrap(),
+        vec![
+            "bllvm-consensus",
+            "bllvm-sdk",
+            "bllvm-node",
+            "bllvm-explorer"
+        ]
+    );
+    assert_eq!(
+        manifest
+            .build_order_with_options(&BuildOrderOptions::default())
+            .unwrap(),
+        manifest.build_order().unwrap()
+    );
+
+    // Optional entries are still checked for existence and version
+    let manifest = VersionsManifest::from_str(&content.replace(
+        "optional_requires = [\"bllvm-node=0.1.0\"] }\nbllvm-node",
+        "optional_requires = [\"bllvm-node=0.2.0\"] }\nbllvm-node",
+    ))
+    .unwrap();
+    assert!(matches!(
+        manifest.validate().errors(),
+        [ValidationError::VersionMismatch { repo, .. }] if repo == "bllvm-sdk"
+    ));
+}
+
+/// Test target filtering against the sample versions.toml
+#[test]
+fn test_build_order_for_sample_manifest() {
+    let manifest =
+        VersionsManifest::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/versions.toml"))
+            .expect("Should parse sample manifest");
+
+    assert_eq!(
+        manifest.build_order_for(&["bllvm-protocol"]).unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol"]
+    );
+    assert!(manifest.build_order_for(&["bllvm-nonexistent"]).is_err());
+}
+
+/// Test the longest dependency chain, by repo count and by build weight
+#[test]
+fn test_critical_path() {
+    let manifest =
+        VersionsManifest::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/versions.toml")).unwrap();
+    assert_eq!(
+        manifest.critical_path().unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node", "bllvm"]
+    );
+
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", build_weight = 5 }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", build_weight = 20 }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+"#;
+    let manifest = VersionsManifest::from_str(content).unwrap();
+    // Two repos at 21 outweigh three at 7
+    assert_eq!(
+        manifest.critical_path().unwrap(),
+        vec!["bllvm-sdk", "governance-app"]
+    );
+
+    assert!(VersionsManifest::default()
+        .critical_path()
+        .unwrap()
+        .is_empty());
+}
+
+/// Test warnings for yanked and deprecated dependencies, and strict resolution
+#[test]
+fn test_yanked_and_deprecated() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", yanked = true }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], deprecated = "use 0.2.1 instead" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", yanked = true }
+"#;
+    let manifest = VersionsManifest::from_str(content).unwrap();
+
+    // bllvm-sdk is yanked but nothing requires it
+    let (order, warnings) = manifest.build_order_with_warnings().unwrap();
+    assert_eq!(order, manifest.build_order().unwrap());
+    assert_eq!(
+        warnings,
+        vec![
+            Warning::Yanked {
+                repo: "bllvm-consensus".to_string(),
+                version: "0.1.0".to_string(),
+                required_by: vec!["bllvm-node".to_string(), "bllvm-protocol".to_string()],
+            },
+            Warning::Deprecated {
+                repo: "bllvm-protocol".to_string(),
+                version: "0.1.0".to_string(),
+                message: "use 0.2.1 instead".to_string(),
+                required_by: vec!["bllvm-node".to_string()],
+            },
+        ]
+    );
+
+    let validation = manifest.validate();
+    assert!(validation.is_valid());
+    assert!(matches!(
+        &validation,
+        ValidationResult::ValidWithWarnings(w) if w[0]
+            == "Repository 'bllvm-consensus' 0.1.0 is yanked but required by bllvm-node, bllvm-protocol"
+    ));
+
+    let strict = BuildOrderOptions {
+        strict: true,
+        ..Default::default()
+    };
+    assert!(matches!(
+        manifest.build_order_with_options(&strict),
+        Err(bllvm::Error::YankedDependency { repo, .. }) if repo == "bllvm-consensus"
+    ));
+}
+
+/// Test that declaration order never changes the build order (ties are broken by name)
+#[test]
+fn test_build_order_independent_of_declaration_order() {
+    let mut entries: Vec<String> = (0..20)
+        .map(|i| {
+            format!(
+                r#"repo-{:02} = {{ version = "0.1.0", git_tag = "v0.1.0" }}"#,
+                i
+            )
+        })
+        .collect();
+    entries.push(
+        r#"app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["repo-07=0.1.0", "lib=0.1.0"] }"#
+            .to_string(),
+    );
+    entries.push(
+        r#"lib = { version = "0.1.0", git_tag = "v0.1.0", requires = ["repo-13=0.1.0"] }"#
+            .to_string(),
+    );
+
+    let mut expected: Vec<String> = (0..20).map(|i| format!("repo-{:02}", i)).collect();
+    expected.push("lib".to_string());
+    expected.push("app".to_string());
+
+    // Shuffle with a fixed-seed LCG (Fisher-Yates) so failures are reproducible
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    for _ in 0..50 {
+        for i in (1..entries.len()).rev() {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            entries.swap(i, (seed >> 33) as usize % (i + 1));
+        }
+
+        let content = format!("[versions]\n{}\n", entries.join("\n"));
+        let manifest = VersionsManifest::from_str(&content).unwrap();
+        assert_eq!(manifest.build_order().unwrap(), expected, "{}", content);
+    }
+}
+
+/// Test that adding a repo nothing requires leaves the order of the others alone
+#[test]
+fn test_build_order_stable_when_adding_repo() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+"#;
+    let before = VersionsManifest::from_str(content)
+        .unwrap()
+        .build_order()
+        .unwrap();
+
+    for added in [
+        // Independent, sorting first
+        r#"aaa = { version = "0.1.0", git_tag = "v0.1.0" }"#,
+        // Leaf requiring existing repos from different stages
+        r#"bllvm-cli = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-node=0.1.0", "bllvm-sdk=0.1.0"] }"#,
+    ] {
+        let name = added.split(' ').next().unwrap();
+        let after = VersionsManifest::from_str(&format!("{}{}\n", content, added))
+            .unwrap()
+            .build_order()
+            .unwrap();
+        assert_eq!(after.len(), before.len() + 1);
+        let others: Vec<String> = after.into_iter().filter(|repo| repo != name).collect();
+        assert_eq!(others, before, "adding {}", name);
+    }
+}
+
+/// Test that ordering a 5,000 repo layered manifest is linear, not quadratic
+#[test]
+fn test_build_order_large_manifest() {
+    let (layers, width) = (50, 100);
+    let mut content = String::from("[versions]\n");
+    for layer in 0..layers {
+        for i in 0..width {
+            let requires: Vec<String> = if layer == 0 {
+                Vec::new()
+            } else {
+                (0..3)
+                    .map(|k| format!("\"l{:02}-{:03}>=0.1.0\"", layer - 1, (i + k * 37) % width))
+                    .collect()
+            };
+            content.push_str(&format!(
+                "l{:02}-{:03} = {{ version = \"0.1.0\", git_tag = \"v0.1.0\", requires = [{}] }}\n",
+                layer,
+                i,
+                requires.join(", ")
+            ));
+        }
+    }
+    let manifest = VersionsManifest::from_str(&content).unwrap();
+
+    let start = std::time::Instant::now();
+    let order = manifest.build_order().unwrap();
+    let with_options = manifest
+        .build_order_with_options(&BuildOrderOptions::default())
+        .unwrap();
+    let stages = manifest.build_stages().unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed < Duration::from_secs(1), "took {:?}", elapsed);
+
+    // One stage per layer, names sorted within each
+    let mut expected: Vec<String> = manifest.versions.keys().cloned().collect();
+    expected.sort();
+    assert_eq!(order, expected);
+    assert_eq!(with_options, order);
+    assert_eq!(stages.len(), layers);
+}
+
+/// Test the simulated schedule with one and with four workers
+#[test]
+fn test_schedule() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", build_weight = 10 }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", build_weight = 2 }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], build_weight = 5 }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"], build_weight = 8 }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"], build_weight = 3 }
+docs = { version = "0.1.0", git_tag = "v0.1.0", build_weight = 4 }
+secp256k1 = { version = "0.1.0", git_tag = "v0.1.0", external = true }
+"#,
+    )
+    .unwrap();
+
+    let serial = manifest.schedule(1).unwrap();
+    assert_eq!(serial.makespan, 32);
+    assert_eq!(serial.workers.len(), 1);
+    assert_eq!(serial.workers[0].len(), 6);
+    assert_eq!(serial.utilization(), 100);
+
+    let parallel = manifest.schedule(4).unwrap();
+    // Bounded by the critical path bllvm-consensus -> bllvm-protocol -> bllvm-node
+    assert_eq!(parallel.makespan, 23);
+    assert!(parallel.makespan < serial.makespan);
+    assert_eq!(
+        parallel.workers[0]
+            .iter()
+            .map(|slot| slot.repo.as_str())
+            .collect::<Vec<_>>(),
+        ["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    // governance-app starts when bllvm-sdk is done, not when the first batch is
+    let app = parallel
+        .workers
+        .iter()
+        .flatten()
+        .find(|slot| slot.repo == "governance-app")
+        .unwrap();
+    assert_eq!((app.start, app.end), (2, 5));
+    assert!(parallel
+        .workers
+        .iter()
+        .flatten()
+        .all(|slot| slot.repo != "secp256k1"));
+    assert!(parallel
+        .to_string()
+        .starts_with("Makespan: 23s on 4 worker(s)"));
+
+    assert!(manifest.schedule(0).is_err());
+}
+
+/// Test the order-free graph check agrees with build_order
+#[test]
+fn test_check_and_is_valid_dag() {
+    let manifest =
+        VersionsManifest::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/versions.toml")).unwrap();
+    assert!(manifest.is_valid_dag());
+    assert!(manifest.check().is_ok());
+
+    let cyclic = VersionsManifest::from_str(
+        r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
+C = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    assert!(!cyclic.is_valid_dag());
+    assert_eq!(cyclic.check(), cyclic.build_order().map(|_| ()));
+    assert!(matches!(
+        cyclic.check(),
+        Err(bllvm::Error::CircularDependency { cycle }) if cycle == vec!["A", "B", "A"]
+    ));
+
+    let unresolved = VersionsManifest::from_str(
+        r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.2.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    assert!(!unresolved.is_valid_dag());
+    assert!(matches!(
+        unresolved.check(),
+        Err(bllvm::Error::VersionMismatch { .. })
+    ));
+}
+
+/// Test ordering a repo declared with several concurrent versions
+#[test]
+fn test_versioned_build_order() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = [
+    { version = "0.2.0", git_tag = "v0.2.0" },
+    { version = "0.1.4", git_tag = "v0.1.4" },
+]
+bllvm-protocol = [
+    { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1"] },
+    { version = "0.2.0", git_tag = "v0.2.0", requires = ["bllvm-consensus=^0.2"] },
+]
+bllvm-node = { version = "0.2.0", git_tag = "v0.2.0", requires = ["bllvm-protocol=^0.2"] }
+"#,
+    )
+    .unwrap();
+    assert_eq!(manifest.versions["bllvm-consensus"].version, "0.2.0");
+    assert_eq!(manifest.entries("bllvm-consensus").len(), 2);
+    assert!(manifest.validate().is_valid());
+
+    let order: Vec<String> = manifest
+        .versioned_build_order()
+        .unwrap()
+        .into_iter()
+        .map(|(repo, version)| format!("{}@{}", repo, version))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            "bllvm-consensus@0.1.4",
+            "bllvm-consensus@0.2.0",
+            "bllvm-protocol@0.1.0",
+            "bllvm-protocol@0.2.0",
+            "bllvm-node@0.2.0",
+        ]
+    );
+
+    let ambiguous = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = [
+    { version = "0.1.4", git_tag = "v0.1.4" },
+    { version = "0.2.0", git_tag = "v0.2.0" },
+]
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus>=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    let err = ambiguous.versioned_build_order().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Repository 'bllvm-node' requires 'bllvm-consensus>=0.1.0' which matches several versions of 'bllvm-consensus': 0.1.4, 0.2.0"
+    );
+    assert!(!ambiguous.validate().is_valid());
+}
+
+/// Test that a target subset resolves against every declared version
+#[test]
+fn test_build_order_for_versioned_dependency() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = [
+    { version = "0.2.0", git_tag = "v0.2.0" },
+    { version = "0.1.4", git_tag = "v0.1.4" },
+]
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        manifest.build_order_for(&["bllvm-node"]).unwrap(),
+        vec!["bllvm-consensus", "bllvm-node"]
+    );
+    assert_eq!(
+        manifest.build_stages_for(&["bllvm-node"]).unwrap(),
+        vec![vec!["bllvm-consensus"], vec!["bllvm-node"]]
+    );
+}
+
+/// Test that aliases let one repo require two versions of another
+#[test]
+fn test_aliased_requires() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = [
+    { version = "0.1.4", git_tag = "v0.1.4" },
+    { version = "0.2.0", git_tag = "v0.2.0" },
+]
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = [
+    "consensus_old=bllvm-consensus@0.1.4",
+    "consensus_new=bllvm-consensus@^0.2[bignum]",
+] }
+"#,
+    )
+    .unwrap();
+    assert!(manifest.validate().is_valid());
+
+    let requirement = parse_requirement("consensus_old=bllvm-consensus@0.1.4").unwrap();
+    assert_eq!(requirement.name, "bllvm-consensus");
+    assert_eq!(requirement.alias.as_deref(), Some("consensus_old"));
+    assert!(!requirement.req.matches(&semver::Version::new(0, 1, 5)));
+    assert_eq!(
+        manifest.features_for("bllvm-consensus").unwrap(),
+        vec!["bignum"]
+    );
+
+    let order: Vec<String> = manifest
+        .versioned_build_order()
+        .unwrap()
+        .into_iter()
+        .map(|(repo, version)| format!("{}@{}", repo, version))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            "bllvm-consensus@0.1.4",
+            "bllvm-consensus@0.2.0",
+            "bllvm-node@0.1.0"
+        ]
+    );
+    assert_eq!(
+        manifest.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-node"]
+    );
+
+    assert!(VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus@0.1.0"] }
+"#
+    )
+    .is_err());
+}
+
+/// Test graph statistics with and without build durations
+#[test]
+fn test_graph_stats() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+
+    let stats = manifest.graph_stats().unwrap();
+    assert_eq!(stats.nodes, 4);
+    assert_eq!(stats.edges, 4);
+    assert_eq!(
+        stats.longest_chain,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    let consensus = stats.repos["bllvm-consensus"];
+    assert_eq!(
+        (consensus.depth, consensus.fan_in, consensus.fan_out),
+        (0, 3, 0)
+    );
+    let node = stats.repos["bllvm-node"];
+    assert_eq!((node.depth, node.fan_in, node.fan_out), (2, 0, 2));
+    assert!(stats.timing.is_none());
+
+    let durations: HashMap<String, Duration> = [
+        ("bllvm-consensus", 10),
+        ("bllvm-protocol", 5),
+        ("bllvm-sdk", 30),
+    ]
+    .into_iter()
+    .map(|(repo, secs)| (repo.to_string(), Duration::from_secs(secs)))
+    .collect();
+    let timing = manifest
+        .graph_stats_with_durations(&durations)
+        .unwrap()
+        .timing
+        .unwrap();
+    assert_eq!(timing.critical_path, vec!["bllvm-consensus", "bllvm-sdk"]);
+    assert_eq!(timing.min_wall_clock, Duration::from_secs(40));
+    assert_eq!(timing.serial, Duration::from_secs(45));
+    assert_eq!(timing.unmeasured, vec!["bllvm-node"]);
+}
+
+/// Test picking the newest or oldest version every dependent accepts
+#[test]
+fn test_resolve_strategy() {
+    let content = r#"
+[versions]
+bllvm-consensus = [
+    { version = "0.1.4", git_tag = "v0.1.4" },
+    { version = "0.1.6", git_tag = "v0.1.6" },
+    { version = "0.2.0", git_tag = "v0.2.0" },
+]
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus>=0.1.4, <0.2.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1.2", "bllvm-protocol=0.1.0"] }
+"#;
+    let manifest = VersionsManifest::from_str(content).unwrap();
+
+    let highest = manifest.resolve(ResolveStrategy::Highest).unwrap();
+    assert_eq!(highest.versions["bllvm-consensus"].version, "0.1.6");
+    assert!(highest.multi_versions.is_empty());
+    assert!(highest.validate().is_valid());
+
+    let minimal = manifest.resolve(ResolveStrategy::MinimalVersion).unwrap();
+    assert_eq!(minimal.versions["bllvm-consensus"].git_tag, "v0.1.4");
+    assert_eq!(
+        minimal.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+
+    let conflicting = VersionsManifest::from_str(&content.replace("=^0.1.2", ">=0.2.0")).unwrap();
+    match conflicting.resolve(ResolveStrategy::MinimalVersion) {
+        Err(bllvm::Error::NoMatchingVersion {
+            repo, required_by, ..
+        }) => {
+            assert_eq!(repo, "bllvm-consensus");
+            assert_eq!(
+                required_by,
+                vec![
+                    "bllvm-node requires 'bllvm-consensus>=0.2.0'",
+                    "bllvm-protocol requires 'bllvm-consensus>=0.1.4, <0.2.0'",
+                ]
+            );
+        }
+        other => panic!("expected no matching version, got {:?}", other),
+    }
+}
+
+/// Test remaining_order skips done repos and rejects inconsistent ones
+#[test]
+fn test_remaining_order() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    let done = |repos: &[&str]| repos.iter().map(|repo| repo.to_string()).collect();
+
+    assert_eq!(
+        manifest.remaining_order(&done(&[])).unwrap(),
+        manifest.build_order().unwrap()
+    );
+    assert_eq!(
+        manifest
+            .remaining_order(&done(&["bllvm-consensus", "bllvm-sdk"]))
+            .unwrap(),
+        vec!["bllvm-protocol", "bllvm-node"]
+    );
+    assert!(manifest
+        .remaining_order(&done(&[
+            "bllvm-consensus",
+            "bllvm-protocol",
+            "bllvm-node",
+            "bllvm-sdk"
+        ]))
+        .unwrap()
+        .is_empty());
+
+    let err = manifest
+        .remaining_order(&done(&["bllvm-consensus", "bllvm-node"]))
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Cannot skip 'bllvm-node' as done: it requires 'bllvm-protocol', which is not done"
+    );
+    let err = manifest
+        .remaining_order(&done(&["bllvm-consesus"]))
+        .unwrap_err();
+    assert!(err.to_string().contains("did you mean 'bllvm-consensus'?"));
+}
+
+/// Test that the dependency graph answers like the manifest queries
+#[test]
+fn test_dependency_graph() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", optional_requires = ["bllvm-node"] }
+"#,
+    )
+    .unwrap();
+    let graph = manifest.graph().unwrap();
+
+    assert_eq!(graph.len(), 4);
+    assert_eq!(
+        graph.repos(),
+        [
+            "bllvm-consensus",
+            "bllvm-node",
+            "bllvm-protocol",
+            "bllvm-sdk"
+        ]
+    );
+    assert_eq!(graph.id("bllvm-node"), Some(1));
+    assert_eq!(graph.repo(1), "bllvm-node");
+    assert_eq!(graph.id("bllvm"), None);
+
+    assert_eq!(graph.topo_order(), manifest.build_order().unwrap());
+    assert_eq!(graph.stages(), manifest.build_stages().unwrap());
+    assert_eq!(
+        graph.dependencies("bllvm-node").unwrap(),
+        ["bllvm-consensus", "bllvm-protocol"]
+    );
+    assert_eq!(
+        graph.dependents("bllvm-consensus").unwrap(),
+        ["bllvm-node", "bllvm-protocol"]
+    );
+    // Optional requirements are not edges
+    assert!(graph.dependencies("bllvm-sdk").unwrap().is_empty());
+    assert_eq!(
+        graph
+            .paths_between("bllvm-node", "bllvm-consensus")
+            .unwrap(),
+        vec![
+            vec!["bllvm-node", "bllvm-consensus"],
+            vec!["bllvm-node", "bllvm-protocol", "bllvm-consensus"],
+        ]
+    );
+    assert!(graph
+        .paths_between("bllvm-consensus", "bllvm-node")
+        .unwrap()
+        .is_empty());
+
+    let err = graph.dependents("bllvm-nod").unwrap_err().to_string();
+    assert!(err.contains("did you mean 'bllvm-node'?"), "{}", err);
+
+    let cyclic = VersionsManifest::from_str(
+        r#"
+[versions]
+a = { version = "0.1.0", git_tag = "v0.1.0", requires = ["b"] }
+b = { version = "0.1.0", git_tag = "v0.1.0", requires = ["a"] }
+"#,
+    )
+    .unwrap();
+    assert!(matches!(
+        cyclic.graph(),
+        Err(bllvm::Error::CircularDependency { .. })
+    ));
+}
+
+/// Test the manifest report summarizes the graph, also with a cycle
+#[test]
+fn test_manifest_report() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+docs = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    let report = manifest.report();
+    assert_eq!(report.repos, 6);
+    assert_eq!(report.roots, vec!["bllvm-node", "docs", "governance-app"]);
+    assert_eq!(report.leaves, vec!["bllvm-consensus", "bllvm-sdk", "docs"]);
+    assert_eq!(report.max_depth, Some(2));
+    assert!(report.is_healthy());
+    assert_eq!(
+        report.to_string(),
+        "Repositories: 6\n\
+         Roots (no dependents): 3 (bllvm-node, docs, governance-app)\n\
+         Leaves (no dependencies): 3 (bllvm-consensus, bllvm-sdk, docs)\n\
+         Max depth: 2\n\
+         Cycles: none\n\
+         Warnings: none\n"
+    );
+
+    let cyclic = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-node=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+docs = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    let report = cyclic.report();
+    assert_eq!(report.roots, vec!["docs"]);
+    assert_eq!(report.leaves, vec!["docs"]);
+    assert_eq!(report.max_depth, None);
+    assert_eq!(report.cycles.len(), 1);
+    assert!(!report.is_healthy());
+    let text = report.to_string();
+    assert!(text.contains("Max depth: unknown (dependency cycle)"));
+    assert!(text.contains("Cycles: 1\n  bllvm-consensus -> bllvm-node -> bllvm-consensus\n"));
+}
+
+/// Test that build_order_for_target drops conditional edges for other targets
+#[test]
+fn test_build_order_for_target() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-ffi = { version = "0.1.0", git_tag = "v0.1.0", requires = [{ dep = "bllvm-node", target = "linux" }] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", requires = [{ dep = "bllvm-ffi=0.1.0", target = "windows" }] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk"] }
+"#,
+    )
+    .unwrap();
+
+    // Across all targets the conditional edges form a cycle
+    assert!(matches!(
+        manifest.build_order(),
+        Err(bllvm::Error::CircularDependency { .. })
+    ));
+    assert_eq!(
+        manifest.build_order_for_target("windows").unwrap(),
+        vec!["bllvm-ffi", "bllvm-sdk", "bllvm-node"]
+    );
+    assert_eq!(
+        manifest.build_order_for_target("linux").unwrap(),
+        vec!["bllvm-sdk", "bllvm-node", "bllvm-ffi"]
+    );
+    assert_eq!(
+        manifest.build_order_for_target("macos").unwrap(),
+        vec!["bllvm-ffi", "bllvm-sdk", "bllvm-node"]
+    );
+}
+
+/// Test `; cfg(...)` requirements are evaluated against target triples
+#[test]
+fn test_build_order_for_target_cfg() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-winsupport = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-node=0.1.0; cfg(unix)"] }
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = [
+    "bllvm-consensus=0.1.0",
+    "bllvm-winsupport=0.1.0; cfg(windows)",
+    { dep = "bllvm-consensus=0.1.0", target = "cfg(target_arch = \"aarch64\")" },
+] }
+"#,
+    )
+    .unwrap();
+    // Only the cycle across all targets is a problem
+    let result = manifest.validate();
+    assert!(result
+        .errors()
+        .iter()
+        .all(|e| matches!(e, ValidationError::CircularDependency { .. })));
+    let winsupport = &manifest.versions["bllvm-node"].requires[1];
+    assert_eq!(winsupport.spec, "bllvm-winsupport=0.1.0");
+    assert_eq!(winsupport.target.as_deref(), Some("cfg(windows)"));
+    assert!(winsupport.applies_to("x86_64-pc-windows-msvc"));
+    assert!(!winsupport.applies_to("x86_64-unknown-linux-gnu"));
+
+    assert_eq!(
+        manifest
+            .build_order_for_target("x86_64-pc-windows-msvc")
+            .unwrap(),
+        vec!["bllvm-consensus", "bllvm-winsupport", "bllvm-node"]
+    );
+    assert_eq!(
+        manifest
+            .build_order_for_target("aarch64-unknown-linux-gnu")
+            .unwrap(),
+        vec!["bllvm-consensus", "bllvm-node", "bllvm-winsupport"]
+    );
+    assert!(matches!(
+        manifest.build_order_for_target("linux"),
+        Err(bllvm::Error::UnknownTarget { target }) if target == "linux"
+    ));
+
+    // The string form is kept when writing the manifest back
+    let written = manifest.to_toml_string().unwrap();
+    assert!(written.contains("bllvm-winsupport=0.1.0; cfg(windows)"));
+    let mut normalized = manifest.clone();
+    normalized.sort_requires();
+    assert_eq!(VersionsManifest::from_str(&written).unwrap(), normalized);
+
+    let broken = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0; cfg(all(windows)"] }
+"#,
+    )
+    .unwrap();
+    assert!(!broken.validate().is_valid());
+    assert!(matches!(
+        broken.build_order_for_target("x86_64-pc-windows-msvc"),
+        Err(bllvm::Error::InvalidRequirement { repo, .. }) if repo == "bllvm-node"
+    ));
+}
+
+/// Test that pruned keeps only the roots and their transitive dependencies
+#[test]
+fn test_pruned() {
+    let manifest = VersionsManifest::from_str(include_str!("../versions.toml")).unwrap();
+
+    let pruned = manifest.pruned(&["bllvm-protocol"]);
+    let mut repos: Vec<&String> = pruned.versions.keys().collect();
+    repos.sort();
+    assert_eq!(repos, vec!["bllvm-consensus", "bllvm-protocol"]);
+    assert!(!pruned.versions.contains_key("bllvm-node"));
+    assert_eq!(pruned.metadata, manifest.metadata);
+    assert!(pruned.validate().is_valid());
+
+    let pruned = manifest.pruned(&["bllvm", "governance-app", "not-a-repo"]);
+    assert_eq!(pruned.versions.len(), 6);
+}
+
+/// Test that resolve_latest picks the highest tag every dependent accepts
+#[test]
+fn test_resolve_latest() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", tag_prefix = "consensus-", track = "latest" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1"] }
+"#,
+    )
+    .unwrap();
+    assert!(manifest.has_latest());
+
+    let resolver = MockVersionResolver::new().with_tags(
+        "bllvm-consensus",
+        &[
+            "consensus-v0.1.0",
+            "consensus-v0.1.2",
+            "consensus-v0.2.0",
+            "consensus-v0.1.3-rc.1",
+            "v0.1.9",
+        ],
+    );
+    let resolved = manifest.resolve_latest(&resolver).unwrap();
+    let consensus = &resolved.versions["bllvm-consensus"];
+    assert_eq!(consensus.version, "0.1.2");
+    assert_eq!(consensus.full_tag(), "consensus-v0.1.2");
+    assert_eq!(consensus.track, None);
+    assert!(!resolved.has_latest());
+    assert!(resolved.validate().is_valid());
+    assert_eq!(
+        resolved.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol"]
+    );
+
+    let resolver = MockVersionResolver::new().with_tags("bllvm-consensus", &["consensus-v0.2.0"]);
+    match manifest.resolve_latest(&resolver) {
+        Err(e) => match e.downcast_ref::<bllvm::Error>() {
+            Some(bllvm::Error::NoMatchingVersion {
+                repo, required_by, ..
+            }) => {
+                assert_eq!(repo, "bllvm-consensus");
+                assert_eq!(
+                    required_by,
+                    &vec!["bllvm-protocol requires 'bllvm-consensus=^0.1'".to_string()]
+                );
+            }
+            other => panic!("Expected NoMatchingVersion, got {:?}", other),
+        },
+        Ok(_) => panic!("Expected no tag to satisfy ^0.1"),
+    }
+}
+
+/// Test that simulate_bump reports broken pins and the rebuild set
+#[test]
+fn test_simulate_bump() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=^0.1"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+
+    let impact = manifest.simulate_bump("bllvm-consensus", "0.1.1").unwrap();
+    assert_eq!(impact.broken.len(), 1);
+    assert_eq!(impact.broken[0].dependent, "bllvm-protocol");
+    assert!(impact.broken[0].exact);
+    assert_eq!(
+        impact.rebuild,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    assert_eq!(manifest.versions["bllvm-consensus"].version, "0.1.0");
+
+    let impact = manifest.simulate_bump("bllvm-consensus", "0.2.0").unwrap();
+    let broken: Vec<(&str, bool)> = impact
+        .broken
+        .iter()
+        .map(|b| (b.dependent.as_str(), b.exact))
+        .collect();
+    assert_eq!(
+        broken,
+        vec![("bllvm-node", false), ("bllvm-protocol", true)]
+    );
+    assert!(impact
+        .to_string()
+        .contains("- bllvm-node requires 'bllvm-consensus=^0.1' (range)"));
+
+    assert!(manifest
+        .simulate_bump("bllvm-sdk", "0.3.0")
+        .unwrap()
+        .is_ok());
+    assert!(manifest.simulate_bump("bllvm-sdk", "next").is_err());
+    assert!(manifest.simulate_bump("bllvm-unknown", "0.2.0").is_err());
+}
+
+/// Test that bump carries a version bump through every dependent once
+#[test]
+fn test_bump() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "abc" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0[bignum]"] }
+bllvm-node = { version = "0.1.4", git_tag = "node-v0.1.4", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=^0.1"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+
+    let (bumped, report) = manifest
+        .bump("bllvm-consensus", "0.1.1", BumpPolicy::Patch)
+        .unwrap();
+    let consensus = &bumped.versions["bllvm-consensus"];
+    assert_eq!(
+        (consensus.version.as_str(), consensus.git_tag.as_str()),
+        ("0.1.1", "v0.1.1")
+    );
+    assert_eq!(consensus.git_commit, None);
+    assert_eq!(
+        bumped.versions["bllvm-protocol"].requires[0].spec,
+        "bllvm-consensus=0.1.1[bignum]"
+    );
+    // Reached through protocol and directly, bumped once; the range still matches
+    let node = &bumped.versions["bllvm-node"];
+    assert_eq!(
+        (node.version.as_str(), node.git_tag.as_str()),
+        ("0.1.5", "node-v0.1.5")
+    );
+    assert_eq!(node.requires[0].spec, "bllvm-protocol=0.1.1");
+    assert_eq!(node.requires[1].spec, "bllvm-consensus=^0.1");
+    assert_eq!(bumped.versions["bllvm-sdk"], manifest.versions["bllvm-sdk"]);
+    let repos: Vec<&str> = report.changes.iter().map(|c| c.repo.as_str()).collect();
+    assert_eq!(
+        repos,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    assert!(report
+        .to_string()
+        .contains("- bllvm-node 0.1.4 -> 0.1.5 (git_tag node-v0.1.4 -> node-v0.1.5); requires bllvm-protocol=0.1.0 -> bllvm-protocol=0.1.1"));
+    assert!(serde_json::to_string(&report)
+        .unwrap()
+        .contains("\"repo\":\"bllvm-node\""));
+
+    // Minor bumps, and the range ^0.1 no longer matching gets repinned
+    let (bumped, _) = manifest
+        .bump("bllvm-consensus", "0.2.0", BumpPolicy::Minor)
+        .unwrap();
+    let node = &bumped.versions["bllvm-node"];
+    assert_eq!(node.version, "0.2.0");
+    assert_eq!(node.requires[1].spec, "bllvm-consensus=0.2.0");
+
+    // Without bumping dependents only the direct pins move
+    let (bumped, report) = manifest
+        .bump("bllvm-consensus", "0.1.1", BumpPolicy::None)
+        .unwrap();
+    assert_eq!(bumped.versions["bllvm-protocol"].version, "0.1.0");
+    assert_eq!(bumped.versions["bllvm-node"].version, "0.1.4");
+    assert_eq!(report.changes.len(), 2);
+
+    let invalid = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    let err = invalid
+        .bump("bllvm-consensus", "0.3.0", BumpPolicy::Patch)
+        .unwrap_err();
+    assert!(err.to_string().starts_with("Refusing to bump"), "{}", err);
+}
+
+/// Test explaining the build order between two repos
+#[test]
+fn test_explain_order() {
+    let manifest = VersionsManifest::from_str(include_str!("../versions.toml")).unwrap();
+
+    let explanation = manifest.explain_order("bllvm", "bllvm-consensus").unwrap();
+    assert_eq!(
+        explanation,
+        OrderExplanation::Required {
+            before: "bllvm-consensus".to_string(),
+            after: "bllvm".to_string(),
+            path: vec![
+                "bllvm".to_string(),
+                "bllvm-node".to_string(),
+                "bllvm-consensus".to_string()
+            ],
+        }
+    );
+    assert_eq!(
+        explanation.to_string(),
+        "bllvm-consensus < bllvm because bllvm requires bllvm-node which requires bllvm-consensus"
+    );
+
+    let explanation = manifest
+        .explain_order("bllvm-sdk", "bllvm-consensus")
+        .unwrap();
+    assert!(matches!(explanation, OrderExplanation::Arbitrary { .. }));
+    assert_eq!(
+        explanation.to_string(),
+        "bllvm-consensus < bllvm-sdk is arbitrary tie-breaking: neither requires the other"
+    );
+
+    assert_eq!(
+        manifest
+            .explain_dependency("bllvm", "bllvm-consensus")
+            .unwrap(),
+        vec![
+            vec!["bllvm", "bllvm-node", "bllvm-consensus"],
+            vec!["bllvm", "bllvm-node", "bllvm-protocol", "bllvm-consensus"],
+        ]
+    );
+    assert!(manifest
+        .explain_dependency("bllvm-consensus", "bllvm")
+        .unwrap()
+        .is_empty());
+
+    let err = manifest
+        .explain_order("bllvm-nod", "bllvm")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("did you mean 'bllvm-node'?"), "{}", err);
+}
+
+/// Test transitive dependency sets, also after the manifest changes
+#[test]
+fn test_transitive_deps() {
+    let mut manifest = VersionsManifest::from_str(include_str!("../versions.toml")).unwrap();
+    let expected: BTreeSet<String> = ["bllvm-consensus", "bllvm-protocol"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    assert_eq!(manifest.transitive_deps("bllvm-node").unwrap(), expected);
+    // Memoized
+    assert_eq!(manifest.transitive_deps("bllvm-node").unwrap(), expected);
+    assert_eq!(manifest.transitive_deps("bllvm").unwrap().len(), 3);
+    assert!(manifest.transitive_deps("bllvm-sdk").unwrap().is_empty());
+
+    // Changing the manifest starts the cache over
+    manifest
+        .set_version("bllvm-protocol", "0.2.0", "v0.2.0", false)
+        .unwrap();
+    assert!(manifest.transitive_deps("bllvm-node").is_err());
+    manifest
+        .set_version("bllvm-protocol", "0.1.0", "v0.1.0", false)
+        .unwrap();
+    assert_eq!(manifest.transitive_deps("bllvm").unwrap().len(), 3);
+
+    manifest.versions.get_mut("bllvm").unwrap().requires.clear();
+    assert!(manifest.transitive_deps("bllvm").unwrap().is_empty());
+    assert_eq!(
+        manifest.clone().transitive_deps("bllvm-node").unwrap(),
+        expected
+    );
+
+    let err = manifest
+        .transitive_deps("bllvm-nod")
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("did you mean 'bllvm-node'?"), "{}", err);
+}
+
+/// Test that external repos are ordered but not built
+#[test]
+fn test_external_repos() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", external = true, build = "cargo build" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], build = "cargo build" }
+"#;
+    let manifest = VersionsManifest::from_str(content).unwrap();
+    assert!(manifest.versions["bllvm-consensus"].external);
+    assert_eq!(
+        manifest.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol"]
+    );
+    assert_eq!(manifest.buildable_order().unwrap(), vec!["bllvm-protocol"]);
+
+    let steps = BuildExecutor::new(&manifest).steps().unwrap();
+    assert_eq!(steps.len(), 1);
+    assert_eq!(steps[0].repo, "bllvm-protocol");
+
+    // Versions are still checked
+    let content = content.replace(
+        "requires = [\"bllvm-consensus=0.1.0\"]",
+        "requires = [\"bllvm-consensus=0.2.0\"]",
+    );
+    let manifest = VersionsManifest::from_str(&content).unwrap();
+    assert!(!manifest.validate().is_valid());
+}
+
+/// Test that requirements drifting from the declared versions are reported
+#[test]
+fn test_check_pin_consistency() {
+    let manifest = VersionsManifest::from_str(include_str!("../versions.toml")).unwrap();
+    assert!(manifest.check_pin_consistency().is_empty());
+
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=^0.2", "bllvm-sdk=0.1.0?"], optional_requires = ["bllvm-consensus>=0.1, <0.2"] }
+"#;
+    let manifest = VersionsManifest::from_str(content).unwrap();
     assert_eq!(
-        build_order.len(),
-        3,
-        "Should have exactly 3 repos in build order"
+        manifest.check_pin_consistency(),
+        vec![
+            PinDrift {
+                requirer: "bllvm-node".to_string(),
+                dependency: "bllvm-consensus".to_string(),
+                pinned: "bllvm-consensus>=0.1, <0.2".to_string(),
+                actual: "0.2.0".to_string(),
+            },
+            PinDrift {
+                requirer: "bllvm-protocol".to_string(),
+                dependency: "bllvm-consensus".to_string(),
+                pinned: "bllvm-consensus=0.1.0".to_string(),
+                actual: "0.2.0".to_string(),
+            },
+        ]
     );
 }
@@ -0,0 +1,155 @@
+//! Tests for build plans and JSON export
+
+mod common;
+
+use bllvm::lockfile::Lockfile;
+use bllvm::plan::{BuildOrder, BuildPlan};
+use common::load;
+use std::collections::HashMap;
+
+const MANIFEST: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#;
+
+/// Test that the build plan carries stage indices and dependencies
+#[test]
+fn test_build_plan() {
+    let plan = load(MANIFEST).build_plan().expect("Should build plan");
+
+    assert_eq!(plan.stages.len(), 2);
+    let names: Vec<&str> = plan.repos.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["bllvm-consensus", "bllvm-sdk", "bllvm-protocol"]
+    );
+
+    let protocol = &plan.repos[2];
+    assert_eq!(protocol.stage, 1);
+    assert_eq!(protocol.dependencies, vec!["bllvm-consensus"]);
+    assert_eq!(protocol.git_tag, "v0.1.0");
+}
+
+/// Test that the plan and manifest JSON can be consumed by other tools
+#[test]
+fn test_json_export() {
+    let manifest = load(MANIFEST);
+    let plan = manifest.build_plan().unwrap();
+
+    let json = plan.to_json().unwrap();
+    let parsed: BuildPlan = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, plan);
+
+    let value: serde_json::Value = serde_json::from_str(&manifest.to_json().unwrap()).unwrap();
+    assert_eq!(
+        value["versions"]["bllvm-protocol"]["requires"][0],
+        "bllvm-consensus=0.1.0"
+    );
+}
+
+/// Test that a changed repo cascades to everything that depends on it
+#[test]
+fn test_incremental_plan() {
+    let manifest = load(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "aaa" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "bbb" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "ccc", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "ddd", requires = ["bllvm-protocol=0.1.0"] }
+"#,
+    );
+    let lockfile = Lockfile::from_manifest(&manifest).unwrap();
+
+    let unchanged = BuildPlan::incremental(&manifest, &lockfile, &HashMap::new()).unwrap();
+    assert!(unchanged.to_rebuild().is_empty());
+
+    let commits = HashMap::from([("bllvm-consensus".to_string(), "eee".to_string())]);
+    let plan = BuildPlan::incremental(&manifest, &lockfile, &commits).unwrap();
+    assert_eq!(
+        plan.to_rebuild(),
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    assert_eq!(plan.repos[0].git_commit.as_deref(), Some("eee"));
+
+    // A full plan rebuilds everything
+    assert_eq!(
+        manifest.build_plan().unwrap().to_rebuild(),
+        manifest.build_order().unwrap()
+    );
+}
+
+/// Test the dry-run explanation of an incremental plan
+#[test]
+fn test_explain_plan() {
+    let manifest = load(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "0123456789abcdef0123456789abcdef01234567" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "ccc", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+"#,
+    );
+    let lockfile = Lockfile::from_manifest(&manifest).unwrap();
+    let commits = HashMap::from([("bllvm-protocol".to_string(), "ddd".to_string())]);
+    let plan = BuildPlan::incremental(&manifest, &lockfile, &commits).unwrap();
+
+    assert_eq!(
+        plan.explain(),
+        "Build plan: 3 batch(es), 4 repo(s), 2 to build, 2 fresh
+
+Batch 0:
+  bllvm-consensus 0.1.0 (v0.1.0 @ 0123456789ab) fresh
+  bllvm-sdk 0.1.0 (v0.1.0) fresh
+
+Batch 1:
+  bllvm-protocol 0.1.0 (v0.1.0 @ ddd) dirty
+
+Batch 2:
+  bllvm-node 0.1.0 (v0.1.0) dirty
+
+Estimated parallel depth: 2 of 3 batch(es)
+"
+    );
+}
+
+/// Test the build order JSON format
+#[test]
+fn test_build_order_json() {
+    let manifest = load(MANIFEST);
+    let json = manifest.build_order_json().unwrap();
+
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        value,
+        serde_json::json!({
+            "order": ["bllvm-consensus", "bllvm-sdk", "bllvm-protocol"],
+            "batches": [["bllvm-consensus", "bllvm-sdk"], ["bllvm-protocol"]]
+        })
+    );
+
+    let parsed: BuildOrder = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.order, manifest.build_order().unwrap());
+}
+
+/// Test the GitHub Actions matrix shape, which workflows depend on
+#[test]
+fn test_github_matrix() {
+    let manifest = load(MANIFEST);
+    assert_eq!(
+        manifest.to_github_matrix().unwrap(),
+        concat!(
+            r#"{"include":["#,
+            r#"{"batch":0,"name":"batch-0","repos":["bllvm-consensus","bllvm-sdk"],"repo_list":"bllvm-consensus bllvm-sdk","needs_batch":null,"last":false},"#,
+            r#"{"batch":1,"name":"batch-1","repos":["bllvm-protocol"],"repo_list":"bllvm-protocol","needs_batch":0,"last":true}"#,
+            r#"]}"#
+        )
+    );
+
+    let matrix = manifest.github_matrix().unwrap();
+    let batches: Vec<Vec<String>> = matrix.include.into_iter().map(|job| job.repos).collect();
+    assert_eq!(batches, manifest.build_batches().unwrap());
+}
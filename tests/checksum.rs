@@ -0,0 +1,66 @@
+//! Tests for verifying artifact checksums
+
+use bllvm::checksum::{ChecksumMismatch, FileArtifactSource, MockArtifactSource};
+use bllvm::versions::VersionsManifest;
+use std::fs;
+use tempfile::TempDir;
+
+/// sha256 of "consensus source"
+const CONSENSUS_SHA256: &str = "947341c2c0ef0c9d9f4786f1480a87575232424cd94e8d3203ee8bd56484e919";
+
+const MANIFEST: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", sha256 = "947341C2C0EF0C9D9F4786F1480A87575232424CD94E8D3203EE8BD56484E919" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", sha256 = "0000000000000000000000000000000000000000000000000000000000000000" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0" }
+"#;
+
+/// Test verified, unverified and mismatched repos
+#[test]
+fn test_verify_checksums() {
+    let manifest = VersionsManifest::from_str(MANIFEST).unwrap();
+    let source = MockArtifactSource::new()
+        .with_artifact("bllvm-consensus", "consensus source")
+        .with_artifact("bllvm-protocol", "tampered source");
+
+    let report = manifest.verify_checksums(&source).unwrap();
+    assert_eq!(report.verified, vec!["bllvm-consensus"]);
+    assert_eq!(report.unverified, vec!["bllvm-node"]);
+    assert_eq!(report.mismatched.len(), 1);
+    let ChecksumMismatch {
+        repo,
+        expected,
+        actual,
+    } = &report.mismatched[0];
+    assert_eq!(repo, "bllvm-protocol");
+    assert_eq!(expected, &"0".repeat(64));
+    assert_eq!(actual.len(), 64);
+    assert!(!report.is_ok());
+
+    // A missing artifact is an error, not a mismatch
+    assert!(manifest
+        .verify_checksums(MockArtifactSource::new())
+        .is_err());
+}
+
+/// Test reading artifacts from files
+#[test]
+fn test_file_artifact_source() {
+    let temp_dir = TempDir::new().unwrap();
+    fs::write(
+        temp_dir.path().join("bllvm-consensus-v0.1.0.tar"),
+        "consensus source",
+    )
+    .unwrap();
+
+    let manifest = VersionsManifest::from_str(&format!(
+        "[versions]\nbllvm-consensus = {{ version = \"0.1.0\", git_tag = \"v0.1.0\", sha256 = \"{}\" }}\n",
+        CONSENSUS_SHA256
+    ))
+    .unwrap();
+    let source = FileArtifactSource::new(temp_dir.path()).pattern("{repo}-{git_tag}.tar");
+
+    let report = manifest.verify_checksums(source).unwrap();
+    assert!(report.is_complete());
+    assert_eq!(report.verified, vec!["bllvm-consensus"]);
+}
@@ -0,0 +1,20 @@
+//! Fixtures shared by the integration tests
+
+// Each test crate uses only some of these
+#![allow(dead_code)]
+
+use bllvm::versions::VersionsManifest;
+use std::fs;
+use std::path::Path;
+
+/// Write `content` to `path` under `root`, creating parent directories
+pub fn write(root: &Path, path: &str, content: &str) {
+    let path = root.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, content).unwrap();
+}
+
+/// Parse a versions.toml fixture
+pub fn load(content: &str) -> VersionsManifest {
+    VersionsManifest::from_str(content).expect("Should parse")
+}
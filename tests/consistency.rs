@@ -0,0 +1,81 @@
+//! Tests for checking versions.toml against the repos' Cargo.toml files
+
+mod common;
+
+use bllvm::consistency::Inconsistency;
+use bllvm::versions::VersionsManifest;
+use common::write;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// Test version mismatches, conflicting requirements and missing crates
+#[test]
+fn test_check_against_workspaces() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    write(
+        root,
+        "bllvm-consensus/Cargo.toml",
+        "[package]\nname = \"bllvm-consensus\"\nversion = \"0.1.0\"\n",
+    );
+    write(
+        root,
+        "bllvm-protocol/Cargo.toml",
+        r#"[package]
+name = "bllvm-protocol"
+version = "0.2.0-dev"
+
+[dependencies]
+bllvm-consensus = { version = "0.2", path = "../bllvm-consensus" }
+serde = "1"
+"#,
+    );
+    // Workspace checkout: the crate lives in a member
+    write(
+        root,
+        "bllvm-node/Cargo.toml",
+        "[workspace]\nmembers = [\"node\"]\n",
+    );
+    write(
+        root,
+        "bllvm-node/node/Cargo.toml",
+        "[package]\nname = \"bllvm-node\"\nversion = \"0.1.0\"\n\n[dependencies]\nbllvm-protocol = \"0.1\"\n",
+    );
+    fs::create_dir(root.join("bllvm-sdk")).unwrap();
+
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    let paths: HashMap<String, PathBuf> = manifest
+        .versions
+        .keys()
+        .map(|repo| (repo.clone(), root.join(repo)))
+        .collect();
+
+    let found = manifest.check_against_workspaces(&paths).unwrap();
+    let messages: Vec<String> = found.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages[..2],
+        [
+            "bllvm-protocol: versions.toml declares 0.1.0 but Cargo.toml has 0.2.0-dev",
+            "bllvm-protocol: Cargo.toml requires bllvm-consensus 0.2 but versions.toml pins 0.1.0",
+        ]
+    );
+    assert_eq!(found.len(), 3, "{:?}", messages);
+    assert!(matches!(
+        &found[2],
+        Inconsistency::CrateNotFound { repo, .. } if repo == "bllvm-sdk"
+    ));
+
+    let unknown = HashMap::from([("bllvm-wallet".to_string(), root.to_path_buf())]);
+    assert!(manifest.check_against_workspaces(&unknown).is_err());
+}
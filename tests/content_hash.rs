@@ -0,0 +1,60 @@
+//! Tests for manifest content hashes
+
+use bllvm::versions::VersionsManifest;
+
+const MANIFEST: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#;
+
+/// Test that formatting and ordering don't change the hash but content does
+#[test]
+fn test_content_hash() {
+    let manifest = VersionsManifest::from_str(MANIFEST).unwrap();
+    let hash = manifest.content_hash();
+    assert_eq!(hash.len(), 64);
+    assert!(hash.chars().all(|c| c.is_ascii_hexdigit()));
+
+    // Reordered repos, keys and requires, different whitespace and comments
+    let reformatted = VersionsManifest::from_str(
+        r#"
+# Same manifest
+[versions.bllvm-protocol]
+requires = [ "bllvm-consensus=0.1.0" ]
+git_tag = "v0.1.0"
+version = "0.1.0"
+
+[versions]
+bllvm-node = { git_tag = "v0.1.0", version = "0.1.0", git_commit = "", requires = ["bllvm-consensus=0.1.0", "bllvm-protocol=0.1.0"] }
+bllvm-consensus   =   { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    assert_eq!(reformatted.content_hash(), hash);
+
+    let changes = [
+        MANIFEST.replace(
+            r#"bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }"#,
+            r#"bllvm-consensus = { version = "0.1.1", git_tag = "v0.1.0" }"#,
+        ),
+        MANIFEST.replace(
+            r#"bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }"#,
+            r#"bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0-rc1" }"#,
+        ),
+        MANIFEST.replace(
+            r#"bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }"#,
+            r#"bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "abc123" }"#,
+        ),
+        MANIFEST.replace(r#""bllvm-protocol=0.1.0", "#, ""),
+    ];
+    for changed in &changes {
+        assert_ne!(
+            VersionsManifest::from_str(changed).unwrap().content_hash(),
+            hash,
+            "{}",
+            changed
+        );
+    }
+}
@@ -0,0 +1,88 @@
+//! Tests for generating a manifest from Cargo crates
+
+mod common;
+
+use bllvm::versions::VersionsManifest;
+use common::write;
+use tempfile::TempDir;
+
+/// Test crates, dependency edges and inherited versions
+#[test]
+fn test_discover() {
+    let temp_dir = TempDir::new().unwrap();
+    let root = temp_dir.path();
+    write(
+        root,
+        "bllvm-consensus/Cargo.toml",
+        "[package]\nname = \"bllvm-consensus\"\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1\"\n",
+    );
+    write(
+        root,
+        "protocol/Cargo.toml",
+        r#"[package]
+name = "bllvm-protocol"
+version = "0.2.0"
+
+[dependencies]
+consensus = { package = "bllvm-consensus", path = "../bllvm-consensus" }
+outside = { path = "../../outside" }
+
+[dev-dependencies]
+bllvm-node = { path = "../node/crates/bllvm-node" }
+"#,
+    );
+    write(
+        root,
+        "node/Cargo.toml",
+        "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nversion = \"0.3.0\"\n",
+    );
+    write(
+        root,
+        "node/crates/bllvm-node/Cargo.toml",
+        r#"[package]
+name = "bllvm-node"
+version.workspace = true
+
+[target.'cfg(unix)'.dependencies]
+bllvm-protocol = { git = "https://github.com/BTCDecoded/bllvm-protocol", tag = "v0.2.0" }
+"#,
+    );
+    write(
+        root,
+        "node/target/debug/Cargo.toml",
+        "[package]\nname = \"bllvm-node\"\nversion = \"9.9.9\"\n",
+    );
+
+    let manifest = VersionsManifest::discover(root).unwrap();
+    assert_eq!(
+        manifest.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+
+    let protocol = &manifest.versions["bllvm-protocol"];
+    assert_eq!(protocol.git_tag, "v0.2.0");
+    assert_eq!(protocol.requires, vec!["bllvm-consensus=0.1.0"]);
+    assert_eq!(protocol.path.as_deref(), Some("protocol"));
+
+    let node = &manifest.versions["bllvm-node"];
+    assert_eq!(node.version, "0.3.0");
+    assert_eq!(node.requires, vec!["bllvm-protocol=0.2.0"]);
+    assert_eq!(node.path.as_deref(), Some("node/crates/bllvm-node"));
+    assert_eq!(manifest.versions["bllvm-consensus"].path, None);
+
+    // Output is a loadable manifest
+    let reparsed = VersionsManifest::from_str(&manifest.to_toml_string().unwrap()).unwrap();
+    assert_eq!(reparsed, manifest);
+
+    write(
+        root,
+        "copy/Cargo.toml",
+        "[package]\nname = \"bllvm-consensus\"\nversion = \"0.1.1\"\n",
+    );
+    let err = VersionsManifest::discover(root).unwrap_err().to_string();
+    assert!(
+        err.contains("Crate 'bllvm-consensus' is declared in both"),
+        "{}",
+        err
+    );
+}
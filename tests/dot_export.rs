@@ -0,0 +1,82 @@
+//! Tests for Graphviz DOT export
+
+mod common;
+
+use bllvm::dot::DotOptions;
+use common::load;
+
+/// Test nodes and edges of the DOT output
+#[test]
+fn test_to_dot() {
+    let manifest = load(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    );
+
+    let dot = manifest.to_dot();
+    assert!(dot.starts_with("digraph dependencies {"));
+    assert!(dot.contains(r#""bllvm-consensus" [label="bllvm-consensus@0.1.0"];"#));
+    assert!(dot.contains(r#""bllvm-protocol" -> "bllvm-consensus";"#));
+    assert!(!dot.contains("color=red"));
+    assert!(!dot.contains("cluster"));
+}
+
+/// Test that cycle edges are highlighted and stages can be clustered
+#[test]
+fn test_to_dot_options() {
+    let cyclic = load(
+        r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0", "C=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
+C = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    );
+    let dot = cyclic.to_dot();
+    assert!(dot.contains(r#""A" -> "B" [color=red];"#));
+    assert!(dot.contains(r#""B" -> "A" [color=red];"#));
+    assert!(dot.contains(r#""A" -> "C";"#));
+
+    let acyclic = load(
+        r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    );
+    let dot = acyclic.to_dot_with(&DotOptions {
+        rankdir: Some("LR".to_string()),
+        cluster_by_stage: true,
+        ..Default::default()
+    });
+    assert!(dot.contains(r#"rankdir="LR";"#));
+    assert!(dot.contains("subgraph cluster_stage_0 {"));
+    assert!(dot.contains("subgraph cluster_stage_1 {"));
+}
+
+/// Test that repos in a cycle can be highlighted
+#[test]
+fn test_to_dot_highlight_cycle_nodes() {
+    let manifest = load(
+        r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
+C = { version = "0.2.0", git_tag = "v0.2.0", requires = ["A=0.1.0"] }
+"#,
+    );
+
+    assert!(!manifest.to_dot().contains("fontcolor=red"));
+
+    let dot = manifest.to_dot_with(&DotOptions {
+        highlight_cycle_nodes: true,
+        ..Default::default()
+    });
+    assert!(dot.contains(r#""A" [label="A@0.1.0", color=red, fontcolor=red];"#));
+    assert!(dot.contains(r#""B" [label="B@0.1.0", color=red, fontcolor=red];"#));
+    assert!(dot.contains(r#""C" [label="C@0.2.0"];"#));
+    assert!(dot.contains(r#""C" -> "A";"#));
+}
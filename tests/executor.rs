@@ -0,0 +1,539 @@
+//! Tests for running per-repo build commands
+#![cfg(unix)]
+
+use bllvm::executor::{BuildExecutor, BuildReport};
+use bllvm::hooks::{BuildHooks, BuildResult, RepoContext};
+use bllvm::plan::BuildPlan;
+use bllvm::progress::{Progress, ProgressReporter};
+use bllvm::state::{BuildState, StateLock, STATE_FILE_NAME};
+use bllvm::versions::VersionsManifest;
+use std::cell::RefCell;
+use std::fs;
+use std::time::Duration;
+use tempfile::TempDir;
+
+const MANIFEST: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", build = "touch built" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", build = "touch built", path = "repos/sdk" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], build = "exit 3" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"], build = "touch built" }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"], build = "touch built" }
+docs = { version = "0.1.0", git_tag = "v0.1.0" }
+"#;
+
+fn setup() -> (TempDir, VersionsManifest) {
+    let temp_dir = TempDir::new().unwrap();
+    for dir in [
+        "bllvm-consensus",
+        "repos/sdk",
+        "bllvm-protocol",
+        "bllvm-node",
+        "governance-app",
+    ] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+    }
+    (temp_dir, VersionsManifest::from_str(MANIFEST).unwrap())
+}
+
+/// Test that the first failure stops the build
+#[test]
+fn test_executor_stops_on_failure() {
+    let (temp_dir, manifest) = setup();
+    let executor = BuildExecutor::new(&manifest).root(temp_dir.path());
+
+    let steps: Vec<String> = executor
+        .steps()
+        .unwrap()
+        .into_iter()
+        .map(|s| s.repo)
+        .collect();
+    assert_eq!(
+        steps,
+        vec![
+            "bllvm-consensus",
+            "bllvm-sdk",
+            "bllvm-protocol",
+            "governance-app",
+            "bllvm-node"
+        ]
+    );
+
+    let report = executor.run().unwrap();
+    assert!(!report.is_success());
+    assert_eq!(report.succeeded, vec!["bllvm-consensus", "bllvm-sdk"]);
+    assert_eq!(report.failed[0].repo, "bllvm-protocol");
+    assert_eq!(report.skipped, vec!["governance-app", "bllvm-node"]);
+    assert!(temp_dir.path().join("repos/sdk/built").exists());
+    assert!(!temp_dir.path().join("governance-app/built").exists());
+}
+
+/// Test that keep-going only skips dependents of the failed repo
+#[test]
+fn test_executor_keep_going() {
+    let (temp_dir, manifest) = setup();
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .keep_going(true)
+        .run()
+        .unwrap();
+
+    assert_eq!(
+        report.succeeded,
+        vec!["bllvm-consensus", "bllvm-sdk", "governance-app"]
+    );
+    assert_eq!(report.skipped, vec!["bllvm-node"]);
+    assert!(report
+        .to_string()
+        .contains("Failed: bllvm-protocol (exited with"));
+}
+
+/// Test that keep-going skips repos depending on a failure through a repo without a build command
+#[test]
+fn test_keep_going_skips_through_unbuilt_repo() {
+    let temp_dir = TempDir::new().unwrap();
+    for dir in ["a", "c"] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+    }
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+a = { version = "0.1.0", git_tag = "v0.1.0", build = "exit 1" }
+b = { version = "0.1.0", git_tag = "v0.1.0", requires = ["a"] }
+c = { version = "0.1.0", git_tag = "v0.1.0", requires = ["b"], build = "touch built" }
+"#,
+    )
+    .unwrap();
+    let executor = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .keep_going(true);
+
+    for report in [executor.run().unwrap(), executor.run_parallel(2).unwrap()] {
+        assert_eq!(report.failed[0].repo, "a");
+        assert!(report.succeeded.is_empty(), "{}", report);
+        assert_eq!(report.skipped, vec!["c"]);
+    }
+    assert!(!temp_dir.path().join("c/built").exists());
+}
+
+/// Test that a repo starts as soon as its own dependencies are built
+#[test]
+fn test_run_parallel_does_not_wait_for_stage() {
+    let temp_dir = TempDir::new().unwrap();
+    for dir in ["slow", "fast", "after-fast"] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+    }
+    // `slow` only succeeds if `after-fast` (a later stage) finished while it ran
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+slow = { version = "0.1.0", git_tag = "v0.1.0", build = "sleep 1; test -e ../after-fast/built" }
+fast = { version = "0.1.0", git_tag = "v0.1.0", build = "touch built" }
+after-fast = { version = "0.1.0", git_tag = "v0.1.0", requires = ["fast"], build = "touch built" }
+"#,
+    )
+    .unwrap();
+
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .run_parallel(2)
+        .unwrap();
+    assert!(report.is_success(), "{}", report);
+    assert_eq!(report.succeeded, vec!["fast", "slow", "after-fast"]);
+}
+
+/// Test that a failure skips unstarted builds but lets running ones finish
+#[test]
+fn test_run_parallel_failure() {
+    let (temp_dir, manifest) = setup();
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .run_parallel(4)
+        .unwrap();
+    assert_eq!(report.failed[0].repo, "bllvm-protocol");
+    assert!(report.skipped.contains(&"bllvm-node".to_string()));
+    assert!(report.succeeded.contains(&"bllvm-sdk".to_string()));
+
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .keep_going(true)
+        .run_parallel(4)
+        .unwrap();
+    assert_eq!(
+        report.succeeded,
+        vec!["bllvm-consensus", "bllvm-sdk", "governance-app"]
+    );
+    assert_eq!(report.skipped, vec!["bllvm-node"]);
+
+    assert!(BuildExecutor::new(&manifest).run_parallel(0).is_err());
+}
+
+/// Records every hook call
+#[derive(Default)]
+struct Recorder {
+    calls: RefCell<Vec<String>>,
+}
+
+impl BuildHooks for Recorder {
+    fn on_plan(&self, plan: &BuildPlan) -> anyhow::Result<()> {
+        self.calls
+            .borrow_mut()
+            .push(format!("plan {}", plan.repos.len()));
+        Ok(())
+    }
+
+    fn before_repo(&self, repo: &RepoContext) -> anyhow::Result<()> {
+        self.calls
+            .borrow_mut()
+            .push(format!("before {} stage {}", repo.repo, repo.stage));
+        if repo.repo == "governance-app" {
+            anyhow::bail!("not today");
+        }
+        Ok(())
+    }
+
+    fn after_repo(&self, repo: &RepoContext, result: &BuildResult) -> anyhow::Result<()> {
+        self.calls
+            .borrow_mut()
+            .push(format!("after {} {}", repo.repo, result.is_success()));
+        Ok(())
+    }
+}
+
+/// Test hooks are called around each build and can fail a repo
+#[test]
+fn test_build_hooks() {
+    let (temp_dir, manifest) = setup();
+    let recorder = Recorder::default();
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .keep_going(true)
+        .hooks(&recorder)
+        .run()
+        .unwrap();
+
+    assert_eq!(report.succeeded, vec!["bllvm-consensus", "bllvm-sdk"]);
+    let failed: Vec<(&str, &str)> = report
+        .failed
+        .iter()
+        .map(|f| (f.repo.as_str(), f.reason.as_str()))
+        .collect();
+    assert_eq!(
+        failed,
+        vec![
+            ("bllvm-protocol", "exited with exit status: 3"),
+            ("governance-app", "pre-build hook failed: not today"),
+        ]
+    );
+    assert!(!temp_dir.path().join("governance-app/built").exists());
+    assert_eq!(
+        recorder.calls.into_inner(),
+        vec![
+            "plan 6",
+            "before bllvm-consensus stage 0",
+            "after bllvm-consensus true",
+            "before bllvm-sdk stage 0",
+            "after bllvm-sdk true",
+            "before bllvm-protocol stage 1",
+            "after bllvm-protocol false",
+            "before governance-app stage 1",
+            "after governance-app false",
+        ]
+    );
+}
+
+/// Test `[hooks]` shell commands get the repo environment and failure policy
+#[test]
+fn test_shell_hooks() {
+    let (temp_dir, _) = setup();
+    let log = temp_dir.path().join("hooks.log");
+    let manifest = VersionsManifest::from_str(&format!(
+        r#"
+[hooks]
+pre_build = {{ command = "test $BLLVM_REPO != bllvm-sdk", on_failure = "warn" }}
+post_build = "echo $BLLVM_REPO $BLLVM_VERSION $BLLVM_STAGE $BLLVM_BUILD_STATUS >> {}"
+
+[versions]
+bllvm-consensus = {{ version = "0.1.0", git_tag = "v0.1.0", build = "true" }}
+bllvm-sdk = {{ version = "0.1.0", git_tag = "v0.1.0", build = "true", path = "repos/sdk" }}
+bllvm-protocol = {{ version = "0.2.0", git_tag = "v0.2.0", requires = ["bllvm-consensus=0.1.0"], build = "exit 1" }}
+"#,
+        log.display()
+    ))
+    .unwrap();
+    let hooks = manifest.hooks.clone().unwrap();
+
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .keep_going(true)
+        .hooks(&hooks)
+        .run()
+        .unwrap();
+    assert_eq!(report.succeeded, vec!["bllvm-consensus", "bllvm-sdk"]);
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].starts_with("pre_build hook for 'bllvm-sdk' exited with"));
+    assert_eq!(
+        fs::read_to_string(&log).unwrap(),
+        "bllvm-consensus 0.1.0 0 succeeded\nbllvm-sdk 0.1.0 0 succeeded\nbllvm-protocol 0.2.0 1 failed\n"
+    );
+
+    // The same failure fails the repo when the policy is left at its default
+    let mut strict = hooks.clone();
+    strict.pre_build.as_mut().unwrap().on_failure = Default::default();
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .keep_going(true)
+        .hooks(&strict)
+        .run()
+        .unwrap();
+    assert_eq!(report.failed[0].repo, "bllvm-sdk");
+    assert!(report.failed[0]
+        .reason
+        .starts_with("pre-build hook failed: pre_build hook for 'bllvm-sdk' exited with"));
+}
+
+/// Test that a state file skips unchanged repos and rebuilds changed ones with their dependents
+#[test]
+fn test_incremental_build_state() {
+    let (temp_dir, _) = setup();
+    let state_path = temp_dir.path().join(STATE_FILE_NAME);
+    let manifest = |consensus: &str| {
+        VersionsManifest::from_str(&format!(
+            r#"
+[versions]
+bllvm-consensus = {{ version = "{0}", git_tag = "v{0}", build = "true" }}
+bllvm-sdk = {{ version = "0.1.0", git_tag = "v0.1.0", build = "true", path = "repos/sdk" }}
+bllvm-protocol = {{ version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus"], build = "true" }}
+bllvm-node = {{ version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol"], build = "true" }}
+"#,
+            consensus
+        ))
+        .unwrap()
+    };
+    let build = |manifest: &VersionsManifest, force: bool| {
+        BuildExecutor::new(manifest)
+            .root(temp_dir.path())
+            .state_file(&state_path)
+            .force(force)
+            .run_parallel(2)
+            .unwrap()
+    };
+
+    let old = manifest("0.1.0");
+    assert_eq!(build(&old, false).succeeded.len(), 4);
+    let report = build(&old, false);
+    assert!(report.succeeded.is_empty());
+    assert_eq!(
+        report.up_to_date,
+        vec![
+            "bllvm-consensus",
+            "bllvm-sdk",
+            "bllvm-protocol",
+            "bllvm-node"
+        ]
+    );
+
+    // A new consensus release invalidates everything built on top of it
+    let new = manifest("0.1.1");
+    let state = BuildState::load(&state_path).unwrap();
+    assert_eq!(
+        new.build_order_with_state(&state).unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    let report = build(&new, false);
+    assert_eq!(
+        report.succeeded,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    assert_eq!(report.up_to_date, vec!["bllvm-sdk"]);
+    assert_eq!(
+        BuildState::load(&state_path).unwrap().built["bllvm-protocol"].dependencies
+            ["bllvm-consensus"],
+        "0.1.1 v0.1.1"
+    );
+    assert_eq!(build(&new, true).succeeded.len(), 4);
+
+    // A second run on a locked state file does not start
+    let _lock = StateLock::acquire(&state_path).unwrap();
+    let err = BuildExecutor::new(&new)
+        .root(temp_dir.path())
+        .state_file(&state_path)
+        .run()
+        .unwrap_err();
+    assert!(err.to_string().contains("locked by another build"));
+}
+
+/// Test that a state file that cannot be written is reported, not fatal
+#[test]
+fn test_unwritable_build_state() {
+    let (temp_dir, _) = setup();
+    let state_path = temp_dir.path().join(STATE_FILE_NAME);
+    // The build turns the state file's path into a directory
+    let manifest = VersionsManifest::from_str(&format!(
+        r#"
+[versions]
+bllvm-consensus = {{ version = "0.1.0", git_tag = "v0.1.0", build = "mkdir {}" }}
+"#,
+        state_path.display()
+    ))
+    .unwrap();
+
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .state_file(&state_path)
+        .run()
+        .unwrap();
+    assert!(report.is_success());
+    assert_eq!(report.warnings.len(), 1);
+    assert!(report.warnings[0].starts_with("Failed to write build state"));
+}
+
+/// Records progress events as strings
+#[derive(Default)]
+struct ProgressRecorder {
+    events: RefCell<Vec<String>>,
+}
+
+impl ProgressReporter for ProgressRecorder {
+    fn plan_computed(&self, plan: &BuildPlan, progress: Progress) {
+        self.events.borrow_mut().push(format!(
+            "plan {} {}%",
+            plan.stages.len(),
+            progress.percent()
+        ));
+    }
+
+    fn repo_started(&self, repo: &str, progress: Progress) {
+        self.events
+            .borrow_mut()
+            .push(format!("start {} {}%", repo, progress.percent()));
+    }
+
+    fn repo_finished(&self, repo: &str, result: &BuildResult, _: Duration, progress: Progress) {
+        self.events.borrow_mut().push(format!(
+            "finish {} {} {}%",
+            repo,
+            result.is_success(),
+            progress.percent()
+        ));
+    }
+
+    fn repo_skipped(&self, repo: &str, progress: Progress) {
+        self.events
+            .borrow_mut()
+            .push(format!("skip {} {}%", repo, progress.percent()));
+    }
+
+    fn run_finished(&self, report: &BuildReport) {
+        self.events
+            .borrow_mut()
+            .push(format!("done {}", report.durations.len()));
+    }
+}
+
+/// Test the progress reporter sees every repo and the report keeps durations
+#[test]
+fn test_progress_reporter() {
+    let (temp_dir, manifest) = setup();
+    let recorder = ProgressRecorder::default();
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .progress(&recorder)
+        .run()
+        .unwrap();
+
+    assert_eq!(
+        recorder.events.into_inner(),
+        vec![
+            "plan 3 0%",
+            "start bllvm-consensus 0%",
+            "finish bllvm-consensus true 20%",
+            "start bllvm-sdk 20%",
+            "finish bllvm-sdk true 40%",
+            "start bllvm-protocol 40%",
+            "finish bllvm-protocol false 60%",
+            "skip governance-app 80%",
+            "skip bllvm-node 100%",
+            "done 3",
+        ]
+    );
+    let durations: Vec<&str> = report.durations.keys().map(String::as_str).collect();
+    assert_eq!(
+        durations,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-sdk"]
+    );
+    assert!(report.to_string().contains("Durations: bllvm-consensus "));
+
+    let json: serde_json::Value = serde_json::from_str(&report.durations_json().unwrap()).unwrap();
+    assert!(json["bllvm-sdk"].as_f64().unwrap() >= 0.0);
+    assert_eq!(json.as_object().unwrap().len(), 3);
+}
+
+/// Test the async executor reports like run_parallel and streams its progress
+#[cfg(feature = "async-executor")]
+#[tokio::test]
+async fn test_run_parallel_async() {
+    use bllvm::async_executor::BuildEvent;
+    use tokio_stream::StreamExt;
+
+    let (temp_dir, manifest) = setup();
+    let executor = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .keep_going(true);
+    let (run, events) = executor.run_parallel_events(4);
+    let (report, events) = tokio::join!(run, events.collect::<Vec<BuildEvent>>());
+    let report = report.unwrap();
+    assert_eq!(
+        report.succeeded,
+        vec!["bllvm-consensus", "bllvm-sdk", "governance-app"]
+    );
+    assert_eq!(report.failed[0].repo, "bllvm-protocol");
+    assert_eq!(report.skipped, vec!["bllvm-node"]);
+    assert!(temp_dir.path().join("governance-app/built").exists());
+
+    let started = |repo: &str| {
+        events
+            .iter()
+            .position(|event| {
+                event
+                    == &BuildEvent::Started {
+                        repo: repo.to_string(),
+                    }
+            })
+            .unwrap()
+    };
+    let finished = events
+        .iter()
+        .position(|event| {
+            event
+                == &BuildEvent::Finished {
+                    repo: "bllvm-sdk".to_string(),
+                }
+        })
+        .unwrap();
+    assert!(finished > started("bllvm-sdk"));
+    assert!(started("governance-app") > finished);
+    assert!(events.iter().any(|event| matches!(
+        event,
+        BuildEvent::Failed { repo, .. } if repo == "bllvm-protocol"
+    )));
+    assert!(events.contains(&BuildEvent::Skipped {
+        repo: "bllvm-node".to_string(),
+        up_to_date: false,
+    }));
+    assert_eq!(events.len(), 9);
+
+    // Without keep-going, nothing new starts after the failure
+    let report = BuildExecutor::new(&manifest)
+        .root(temp_dir.path())
+        .run_parallel_async(1)
+        .await
+        .unwrap();
+    assert_eq!(report.failed[0].repo, "bllvm-protocol");
+    assert_eq!(report.skipped, vec!["governance-app", "bllvm-node"]);
+
+    assert!(BuildExecutor::new(&manifest)
+        .run_parallel_async(0)
+        .await
+        .is_err());
+}
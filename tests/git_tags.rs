@@ -0,0 +1,196 @@
+//! Tests for verifying tags against git remotes
+#![cfg(feature = "git")]
+
+use bllvm::git::{RemoteMap, RetryPolicy};
+use bllvm::tagger::{repo_paths, GitCli, MockGit, ReleaseTagger, TagOptions};
+use bllvm::versions::VersionsManifest;
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=bllvm",
+            "-c",
+            "user.email=bllvm@example.com",
+        ])
+        .args(args)
+        .output()
+        .expect("git should run");
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Test tag lookup against local repositories used as remotes
+#[test]
+fn test_verify_tags() {
+    let temp_dir = TempDir::new().unwrap();
+    for repo in ["bllvm-consensus", "bllvm-protocol"] {
+        let dir = temp_dir.path().join(repo);
+        std::fs::create_dir(&dir).unwrap();
+        git(&dir, &["init", "-q"]);
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+    }
+    let consensus = temp_dir.path().join("bllvm-consensus");
+    git(&consensus, &["tag", "-a", "v0.1.0", "-m", "release"]);
+    let head = git(&consensus, &["rev-parse", "HEAD"]);
+
+    let manifest = VersionsManifest::from_str(&format!(
+        r#"
+[workspace]
+git_host = "{}"
+
+[versions]
+bllvm-consensus = {{ version = "0.1.0", git_tag = "v0.1.0" }}
+bllvm-protocol = {{ version = "0.1.0", git_tag = "v0.1.0" }}
+"#,
+        temp_dir.path().display()
+    ))
+    .unwrap();
+
+    let remotes = RemoteMap::from_manifest(&manifest).unwrap();
+    let checks = manifest
+        .verify_tags(&remotes, RetryPolicy::none())
+        .expect("Should check all tags")
+        .checks;
+    assert_eq!(checks.len(), 2);
+    assert_eq!(checks[0].repo, "bllvm-consensus");
+    assert_eq!(checks[0].commit.as_deref(), Some(head.as_str()));
+    assert!(checks[1].is_missing());
+
+    // A remote that does not exist is an error, not a missing tag
+    let mut remotes = remotes;
+    remotes.insert(
+        "bllvm-protocol",
+        temp_dir.path().join("bllvm-nope").display().to_string(),
+    );
+    let err = manifest
+        .verify_tags(&remotes, RetryPolicy::none())
+        .unwrap_err();
+    assert!(err.to_string().contains("bllvm-nope"));
+}
+
+const RELEASE: &str = r#"
+[versions]
+bllvm-protocol = { version = "0.2.0", git_tag = "v0.2.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+secp256k1 = { version = "0.28.0", git_tag = "0.28.0", external = true }
+"#;
+
+/// Test the release tagger checks every repo first, then tags in order
+#[test]
+fn test_release_tagger() {
+    let manifest = VersionsManifest::from_str(RELEASE).unwrap();
+    let paths = repo_paths(&manifest, Path::new("/src"));
+    let fake = || {
+        MockGit::new()
+            .with_repo("/src/bllvm-consensus", "c0ffee")
+            .with_repo("/src/bllvm-protocol", "beef")
+    };
+    let options = TagOptions {
+        push: Some("origin".to_string()),
+        ..TagOptions::default()
+    };
+
+    // A dry run changes nothing and prints the commands
+    let git = fake();
+    let dry_run = TagOptions {
+        dry_run: true,
+        ..options.clone()
+    };
+    let planned = ReleaseTagger::new(&git)
+        .tag_all(&manifest, &paths, &dry_run)
+        .unwrap();
+    assert_eq!(
+        planned[0].commands,
+        vec![
+            "git -C /src/bllvm-consensus tag -a v0.1.0 -m 'Release bllvm-consensus 0.1.0'",
+            "git -C /src/bllvm-consensus push origin refs/tags/v0.1.0",
+        ]
+    );
+    assert_eq!(planned[1].repo, "bllvm-protocol");
+    assert_eq!(git.tag_message("/src/bllvm-consensus", "v0.1.0"), None);
+    assert!(git.pushed().is_empty());
+
+    // A rerun after the first repo was tagged only tags the rest
+    let git = fake().with_tag("/src/bllvm-consensus", "v0.1.0", "c0ffee");
+    let tagged = ReleaseTagger::new(&git)
+        .tag_all(&manifest, &paths, &options)
+        .unwrap();
+    let existed: Vec<(&str, bool)> = tagged
+        .iter()
+        .map(|t| (t.repo.as_str(), t.existed))
+        .collect();
+    assert_eq!(
+        existed,
+        vec![("bllvm-consensus", true), ("bllvm-protocol", false)]
+    );
+    assert_eq!(
+        git.tag_message("/src/bllvm-protocol", "v0.2.0").unwrap(),
+        "Release bllvm-protocol 0.2.0"
+    );
+    let pushed: Vec<String> = git.pushed().into_iter().map(|(_, _, tag)| tag).collect();
+    assert_eq!(pushed, vec!["v0.1.0", "v0.2.0"]);
+
+    // A tag on another commit or uncommitted changes stop everything
+    let git = fake()
+        .with_tag("/src/bllvm-protocol", "v0.2.0", "dead")
+        .with_changes("/src/bllvm-protocol");
+    let err = ReleaseTagger::new(&git)
+        .tag_all(&manifest, &paths, &options)
+        .unwrap_err();
+    assert!(err.to_string().contains("has uncommitted changes"));
+    assert_eq!(git.tag_message("/src/bllvm-consensus", "v0.1.0"), None);
+
+    let git = fake().with_tag("/src/bllvm-protocol", "v0.2.0", "dead");
+    let err = ReleaseTagger::new(&git)
+        .tag_all(&manifest, &paths, &options)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Tag 'v0.2.0' of 'bllvm-protocol' already exists at dead, but HEAD is beef"
+    );
+    assert!(git.pushed().is_empty());
+}
+
+/// Test the git CLI implementation creates annotated tags and reruns cleanly
+#[test]
+fn test_release_tagger_git() {
+    let temp_dir = TempDir::new().unwrap();
+    for repo in ["bllvm-consensus", "bllvm-protocol"] {
+        let dir = temp_dir.path().join(repo);
+        std::fs::create_dir(&dir).unwrap();
+        git(&dir, &["init", "-q"]);
+        git(&dir, &["config", "user.name", "bllvm"]);
+        git(&dir, &["config", "user.email", "bllvm@example.com"]);
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+    }
+    let manifest = VersionsManifest::from_str(RELEASE).unwrap();
+    let paths = repo_paths(&manifest, temp_dir.path());
+    let tagger = ReleaseTagger::new(GitCli);
+
+    let tagged = tagger
+        .tag_all(&manifest, &paths, &TagOptions::default())
+        .unwrap();
+    assert!(tagged.iter().all(|t| !t.existed));
+    let protocol = temp_dir.path().join("bllvm-protocol");
+    assert_eq!(git(&protocol, &["cat-file", "-t", "v0.2.0"]), "tag");
+    assert_eq!(
+        git(&protocol, &["rev-parse", "v0.2.0^{commit}"]),
+        git(&protocol, &["rev-parse", "HEAD"])
+    );
+
+    let tagged = tagger
+        .tag_all(&manifest, &paths, &TagOptions::default())
+        .unwrap();
+    assert!(tagged.iter().all(|t| t.existed && t.commands.is_empty()));
+
+    std::fs::write(protocol.join("dirty"), "").unwrap();
+    assert!(tagger
+        .tag_all(&manifest, &paths, &TagOptions::default())
+        .is_err());
+}
@@ -0,0 +1,82 @@
+//! Tests for the manifest linter
+
+use bllvm::lint::{LintConfig, Severity};
+use bllvm::versions::VersionsManifest;
+
+const MANIFEST: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.2.0-rc.1", git_tag = "0.2.0-rc.1", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+old-tool = { version = "0.1.0", git_tag = "v0.1.0" }
+
+[lint]
+allow = ["deep-chain"]
+release = true
+"#;
+
+/// Test each rule fires and the [lint] table and overrides set its severity
+#[test]
+fn test_lint_rules() {
+    let manifest = VersionsManifest::from_str(MANIFEST).unwrap();
+    let config = manifest.lint.clone().unwrap();
+    assert!(config.release);
+
+    let findings = manifest.lint(&config).unwrap();
+    let found: Vec<(&str, &str, Severity)> = findings
+        .iter()
+        .map(|f| (f.repo.as_str(), f.rule, f.severity))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            ("bllvm-node", "prerelease-version", Severity::Deny),
+            ("bllvm-node", "tag-convention", Severity::Warn),
+            ("bllvm-node", "unsorted-requires", Severity::Warn),
+            ("old-tool", "isolated-repo", Severity::Warn),
+        ]
+    );
+    assert_eq!(
+        findings[1].to_string(),
+        "warning[tag-convention] bllvm-node: git_tag '0.2.0-rc.1' should be 'v0.2.0-rc.1'"
+    );
+
+    // Overrides replace the table's setting; deep-chain applies once enabled
+    let mut config = config;
+    config.set("deep-chain", Some(Severity::Deny));
+    config.set("tag-convention", None);
+    config.max_depth = Some(1);
+    let findings = manifest.lint(&config).unwrap();
+    let rules: Vec<&str> = findings.iter().map(|f| f.rule).collect();
+    assert_eq!(
+        rules,
+        vec![
+            "deep-chain",
+            "prerelease-version",
+            "unsorted-requires",
+            "isolated-repo"
+        ]
+    );
+    assert_eq!(
+        findings[0].message,
+        "dependency chain of 2 exceeds max_depth 1"
+    );
+
+    // Pre-releases are fine outside a release manifest
+    let findings = manifest.lint(&LintConfig::default()).unwrap();
+    assert!(findings.iter().all(|f| f.rule != "prerelease-version"));
+
+    let config = LintConfig {
+        deny: vec!["no-such-rule".to_string()],
+        ..LintConfig::default()
+    };
+    let err = manifest.lint(&config).unwrap_err();
+    assert!(matches!(&err, bllvm::Error::UnknownLintRule { rule, .. } if rule == "no-such-rule"));
+    assert!(err.to_string().contains("Unknown lint rule 'no-such-rule'"));
+
+    // The table is written back
+    let written = manifest.to_toml_string().unwrap();
+    let mut normalized = manifest.clone();
+    normalized.sort_requires();
+    assert_eq!(VersionsManifest::from_str(&written).unwrap(), normalized);
+}
@@ -0,0 +1,198 @@
+//! Tests for bllvm.lock / versions.lock generation and verification
+
+mod common;
+
+use bllvm::lockfile::{Lockfile, MockTagResolver};
+use bllvm::versions::{ValidationError, VersionsManifest};
+use common::load;
+use std::fs;
+use tempfile::TempDir;
+
+const MANIFEST: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "abc123" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+"#;
+
+/// Test writing and reading back a lockfile
+#[test]
+fn test_lockfile_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let manifest = load(MANIFEST);
+    let lock_path = temp_dir.path().join("bllvm.lock");
+
+    manifest
+        .write_lockfile(&lock_path)
+        .expect("Should write lockfile");
+    let lockfile = Lockfile::from_file(&lock_path).expect("Should read lockfile");
+
+    let names: Vec<&str> = lockfile.locked.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    assert_eq!(lockfile.locked[0].git_commit.as_deref(), Some("abc123"));
+    lockfile
+        .verify_against(&manifest)
+        .expect("Unchanged manifest should verify");
+
+    // Re-writing an unchanged manifest must be byte-identical
+    let first = fs::read(&lock_path).unwrap();
+    manifest.write_lockfile(&lock_path).unwrap();
+    assert_eq!(first, fs::read(&lock_path).unwrap());
+}
+
+/// Test that a version bump without updating the lock is detected
+#[test]
+fn test_lockfile_detects_drift() {
+    let manifest = load(MANIFEST);
+    let lockfile = Lockfile::from_manifest(&manifest).unwrap();
+
+    let bumped = load(&MANIFEST.replace(
+        r#"bllvm-node = { version = "0.1.0", git_tag = "v0.1.0""#,
+        r#"bllvm-node = { version = "0.1.1", git_tag = "v0.1.1""#,
+    ));
+
+    let err = lockfile
+        .verify_against(&bumped)
+        .expect_err("Bumped manifest should not verify")
+        .to_string();
+    assert!(err.contains("bllvm-node"));
+    assert!(!err.contains("bllvm-protocol"));
+}
+
+/// Test locking tags to commits and detecting a moved tag
+#[test]
+fn test_lock_with_resolver() {
+    let temp_dir = TempDir::new().unwrap();
+    let manifest = load(MANIFEST);
+    let resolver = MockTagResolver::new()
+        .with_tag("bllvm-consensus", "v0.1.0", "aaa")
+        .with_tag("bllvm-protocol", "v0.1.0", "bbb")
+        .with_tag("bllvm-node", "v0.1.0", "ccc");
+
+    let lockfile = manifest.lock(&resolver).expect("Should lock");
+    let commits: Vec<&str> = lockfile
+        .locked
+        .iter()
+        .map(|r| r.git_commit.as_deref().unwrap())
+        .collect();
+    assert_eq!(commits, vec!["aaa", "bbb", "ccc"]);
+
+    let lock_path = Lockfile::path_for(temp_dir.path().join("versions.toml"));
+    assert_eq!(lock_path, temp_dir.path().join("versions.lock"));
+    lockfile.to_file(&lock_path).unwrap();
+    let lockfile = Lockfile::from_file(&lock_path).unwrap();
+    lockfile
+        .verify(&manifest, &resolver)
+        .expect("Unmoved tags should verify");
+
+    // Force-pushed tag
+    let moved = resolver.with_tag("bllvm-protocol", "v0.1.0", "ddd");
+    let err = lockfile
+        .verify(&manifest, &moved)
+        .expect_err("Moved tag should not verify");
+    assert!(matches!(&err, bllvm::Error::LockfileOutOfDate { drift } if drift.len() == 1));
+    let err = err.to_string();
+    assert!(err.contains("'bllvm-protocol' tag 'v0.1.0' moved: locked bbb, now ddd"));
+    assert!(!err.contains("bllvm-node"));
+
+    // Unresolvable tags are an error
+    assert!(matches!(
+        manifest.lock(MockTagResolver::new()),
+        Err(bllvm::Error::TagResolution { repo, .. }) if repo == "bllvm-consensus"
+    ));
+}
+
+/// Test that a lockfile from `lock` verifies against a manifest pinning no commits
+#[test]
+fn test_lock_then_verify_against() {
+    let unpinned = MANIFEST.replace(r#", git_commit = "abc123""#, "");
+    let manifest = load(&unpinned);
+    let resolver = MockTagResolver::new()
+        .with_tag("bllvm-consensus", "v0.1.0", "aaa")
+        .with_tag("bllvm-protocol", "v0.1.0", "bbb")
+        .with_tag("bllvm-node", "v0.1.0", "ccc");
+
+    let lockfile = manifest.lock(&resolver).expect("Should lock");
+    lockfile
+        .verify_against(&manifest)
+        .expect("Locked commits should not count as drift");
+
+    // A commit the manifest does pin is still compared
+    let pinned = load(MANIFEST);
+    let err = lockfile
+        .verify_against(&pinned)
+        .expect_err("Pinned commit differs from the locked one")
+        .to_string();
+    assert!(err.contains("'bllvm-consensus' changed"), "{}", err);
+    assert!(!err.contains("bllvm-protocol"), "{}", err);
+}
+
+/// Test that entries locked without a commit only report version or tag drift
+#[test]
+fn test_verify_without_locked_commit() {
+    let manifest = load(MANIFEST);
+    let lockfile = Lockfile::from_manifest(&manifest).unwrap();
+    assert_eq!(lockfile.locked[1].git_commit, None);
+
+    // Only bllvm-consensus has a locked commit, so only it is resolved
+    let resolver = MockTagResolver::new().with_tag("bllvm-consensus", "v0.1.0", "abc123");
+    lockfile
+        .verify(&manifest, &resolver)
+        .expect("Entries without a locked commit should not be resolved");
+
+    let bumped = load(&MANIFEST.replace(
+        r#"bllvm-node = { version = "0.1.0", git_tag = "v0.1.0""#,
+        r#"bllvm-node = { version = "0.1.1", git_tag = "v0.1.1""#,
+    ));
+    let err = lockfile
+        .verify(&bumped, &resolver)
+        .expect_err("Bumped manifest should not verify")
+        .to_string();
+    assert!(
+        err.contains("'bllvm-node' changed: locked 0.1.0 (v0.1.0), manifest has 0.1.1 (v0.1.1)")
+    );
+    assert!(!err.contains("moved"));
+    assert!(!err.contains("bllvm-protocol"));
+}
+
+/// Test filling in and checking pinned commits
+#[test]
+fn test_resolve_and_validate_commits() {
+    let mut manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "abc123" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    let resolver = MockTagResolver::new()
+        .with_tag("bllvm-consensus", "v0.1.0", "abc123")
+        .with_tag("bllvm-protocol", "v0.1.0", "def456");
+
+    assert_eq!(
+        manifest.resolve_commits(&resolver).unwrap(),
+        vec!["bllvm-protocol"]
+    );
+    assert_eq!(
+        manifest.versions["bllvm-protocol"].git_commit.as_deref(),
+        Some("def456")
+    );
+    assert!(manifest.validate_commits(&resolver).unwrap().is_valid());
+
+    // The tag was moved after the commit was pinned
+    let moved = resolver.with_tag("bllvm-consensus", "v0.1.0", "fff999");
+    let result = manifest.validate_commits(&moved).unwrap();
+    assert_eq!(
+        result.errors(),
+        &[ValidationError::CommitMismatch {
+            repo: "bllvm-consensus".to_string(),
+            git_tag: "v0.1.0".to_string(),
+            expected: "abc123".to_string(),
+            found: "fff999".to_string(),
+        }]
+    );
+}
@@ -0,0 +1,57 @@
+//! Tests for comparing manifests
+
+use bllvm::versions::VersionsManifest;
+
+const OLD: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
+legacy-tool = { version = "0.0.9", git_tag = "v0.0.9" }
+"#;
+
+const NEW: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus>=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0", "bllvm-protocol=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#;
+
+/// Test added, removed and modified repos
+#[test]
+fn test_manifest_diff() {
+    let old = VersionsManifest::from_str(OLD).unwrap();
+    let new = VersionsManifest::from_str(NEW).unwrap();
+    let diff = old.diff(&new);
+
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.added[0].name, "bllvm-sdk");
+    assert_eq!(diff.removed[0].name, "legacy-tool");
+
+    // bllvm-node only reordered its requires, so it is unchanged
+    let modified: Vec<&str> = diff.modified.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(modified, vec!["bllvm-consensus", "bllvm-protocol"]);
+
+    // Same version but different requires is still a modification
+    let protocol = &diff.modified[1];
+    assert!(protocol.version.is_none());
+    assert_eq!(protocol.requires_added, vec!["bllvm-consensus>=0.1.0"]);
+    assert_eq!(protocol.requires_removed, vec!["bllvm-consensus=0.1.0"]);
+
+    assert_eq!(
+        diff.to_string(),
+        "Added:\n\
+         - bllvm-sdk 0.1.0\n\
+         Removed:\n\
+         - legacy-tool 0.0.9\n\
+         Modified:\n\
+         - bllvm-consensus: version 0.1.0 -> 0.2.0, git_tag v0.1.0 -> v0.2.0\n\
+         - bllvm-protocol: requires +bllvm-consensus>=0.1.0 -bllvm-consensus=0.1.0\n"
+    );
+
+    let json = serde_json::to_value(&diff).unwrap();
+    assert_eq!(json["modified"][0]["version"]["new"], "0.2.0");
+
+    assert!(new.diff(&new).is_empty());
+}
@@ -0,0 +1,141 @@
+//! Tests for resolving manifests split across several files
+
+use bllvm::manifest_set::ManifestSet;
+use bllvm::versions::VersionsManifest;
+use std::fs;
+use tempfile::TempDir;
+
+const CORE: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#;
+
+const APPS: &str = r#"
+[versions]
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+"#;
+
+/// Test that requires resolve across files as if they were one
+#[test]
+fn test_manifest_set_resolves_across_files() {
+    let temp_dir = TempDir::new().unwrap();
+    let core_path = temp_dir.path().join("core.toml");
+    let apps_path = temp_dir.path().join("apps.toml");
+    fs::write(&core_path, CORE).unwrap();
+    fs::write(&apps_path, APPS).unwrap();
+
+    // The app manifest alone references undefined repos
+    assert!(VersionsManifest::from_file(&apps_path)
+        .unwrap()
+        .build_order()
+        .is_err());
+
+    let set = ManifestSet::from_files(&[&core_path, &apps_path]).unwrap();
+    assert_eq!(
+        set.source_of("bllvm-node"),
+        Some(apps_path.display().to_string().as_str())
+    );
+
+    let merged = set.merged().unwrap();
+    let single =
+        VersionsManifest::from_str(&format!("{}{}", CORE, APPS.replace("[versions]", ""))).unwrap();
+    assert_eq!(merged, single);
+    assert_eq!(
+        merged.build_stages().unwrap(),
+        single.build_stages().unwrap()
+    );
+
+    let merged = VersionsManifest::from_str(CORE)
+        .unwrap()
+        .merge(VersionsManifest::from_str(APPS).unwrap())
+        .unwrap();
+    assert_eq!(merged.build_order().unwrap(), single.build_order().unwrap());
+}
+
+/// Test that conflicting definitions name both files
+#[test]
+fn test_manifest_set_conflict() {
+    let temp_dir = TempDir::new().unwrap();
+    let core_path = temp_dir.path().join("core.toml");
+    let apps_path = temp_dir.path().join("apps.toml");
+    fs::write(&core_path, CORE).unwrap();
+    fs::write(
+        &apps_path,
+        format!(
+            "{}{}",
+            APPS, r#"bllvm-sdk = { version = "0.2.0", git_tag = "v0.2.0" }"#
+        ),
+    )
+    .unwrap();
+
+    let err = ManifestSet::from_files(&[&core_path, &apps_path])
+        .unwrap()
+        .merged()
+        .expect_err("Conflicting versions should fail");
+    assert!(matches!(
+        &err,
+        bllvm::Error::ManifestConflict { item, .. } if item == "Repository 'bllvm-sdk'"
+    ));
+    let err = err.to_string();
+    assert!(err.contains("'bllvm-sdk'"));
+    assert!(err.contains("core.toml (version 0.1.0)"));
+    assert!(err.contains("apps.toml (version 0.2.0)"));
+
+    // Identical definitions are fine
+    let merged = VersionsManifest::from_str(CORE)
+        .unwrap()
+        .merge(VersionsManifest::from_str(CORE).unwrap())
+        .unwrap();
+    assert_eq!(merged.versions.len(), 3);
+}
+
+/// Test that from_file merges included manifests, resolving paths against the including file
+#[test]
+fn test_include() {
+    let temp_dir = TempDir::new().unwrap();
+    let root_path = temp_dir.path().join("versions.toml");
+    fs::create_dir_all(temp_dir.path().join("teams")).unwrap();
+    fs::write(
+        &root_path,
+        "include = [\"teams/apps.toml\"]\n\n[versions]\nbllvm-sdk = { version = \"0.1.0\", git_tag = \"v0.1.0\" }\n",
+    )
+    .unwrap();
+    fs::write(
+        temp_dir.path().join("teams/apps.toml"),
+        format!("include = [\"core.toml\"]\n{}", APPS),
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join("teams/core.toml"), CORE).unwrap();
+
+    let manifest = VersionsManifest::from_file(&root_path).unwrap();
+    assert!(manifest.include.is_empty());
+    let single =
+        VersionsManifest::from_str(&format!("{}{}", CORE, APPS.replace("[versions]", ""))).unwrap();
+    assert_eq!(manifest, single);
+
+    // A conflicting definition names the files on both sides
+    fs::write(
+        &root_path,
+        "include = [\"teams/apps.toml\"]\n\n[versions]\nbllvm-sdk = { version = \"0.2.0\", git_tag = \"v0.2.0\" }\n",
+    )
+    .unwrap();
+    let err = VersionsManifest::from_file(&root_path)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains(&root_path.display().to_string()), "{}", err);
+    assert!(err.contains("apps.toml (version 0.1.0)"), "{}", err);
+
+    // Including a file that is already being loaded is a cycle
+    fs::write(
+        temp_dir.path().join("teams/core.toml"),
+        format!("include = [\"../versions.toml\"]\n{}", CORE),
+    )
+    .unwrap();
+    let err = VersionsManifest::from_file(&root_path)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("include cycle"), "{}", err);
+}
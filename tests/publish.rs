@@ -0,0 +1,64 @@
+//! Tests for release ordering
+
+use bllvm::publish::MockRegistry;
+use bllvm::versions::VersionsManifest;
+
+const MANIFEST: &str = r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.2.0", git_tag = "v0.2.0", requires = ["bllvm-consensus=0.2.0"] }
+bllvm-node = { version = "0.2.0", git_tag = "v0.2.0", requires = ["bllvm-protocol=0.2.0"], optional_requires = ["bllvm-sdk=0.1.0"] }
+"#;
+
+/// Test publish order and skipping versions the registry already has
+#[test]
+fn test_publish_order() {
+    let manifest = VersionsManifest::from_str(MANIFEST).unwrap();
+    assert_eq!(
+        manifest.publish_order().unwrap(),
+        vec![
+            "bllvm-consensus",
+            "bllvm-sdk",
+            "bllvm-protocol",
+            "bllvm-node"
+        ]
+    );
+
+    let registry = MockRegistry::new()
+        .with_published("bllvm-sdk", "0.1.0")
+        .with_published("bllvm-consensus", "0.1.0");
+    let order = manifest.publish_order_skipping(&registry).unwrap();
+    assert_eq!(
+        order.pending,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    assert_eq!(order.published, vec!["bllvm-sdk"]);
+}
+
+/// Test that a stale optional pin blocks publishing but not building
+#[test]
+fn test_publish_order_stale_pin() {
+    let manifest = VersionsManifest::from_str(&MANIFEST.replace(
+        r#"optional_requires = ["bllvm-sdk=0.1.0"]"#,
+        r#"optional_requires = ["bllvm-sdk=0.0.9"]"#,
+    ))
+    .unwrap();
+    assert!(manifest.build_order().is_ok());
+    let err = manifest.publish_order().unwrap_err();
+    assert!(matches!(
+        &err,
+        bllvm::Error::VersionMismatch { repo, dependency, .. }
+            if repo == "bllvm-node" && dependency == "bllvm-sdk"
+    ));
+
+    let yanked = VersionsManifest::from_str(&MANIFEST.replace(
+        r#"bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }"#,
+        r#"bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0", yanked = true }"#,
+    ))
+    .unwrap();
+    assert!(matches!(
+        yanked.publish_order(),
+        Err(bllvm::Error::YankedDependency { .. })
+    ));
+}
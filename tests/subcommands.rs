@@ -206,3 +206,491 @@ fn test_start_subcommand() {
     // Should try to start node (will fail, but parsing should work)
     let _ = cmd.assert();
 }
+
+/// Test graph subcommand renders versions.toml as DOT
+#[test]
+fn test_graph_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("graph")
+        .arg(&versions_path)
+        .arg("--format")
+        .arg("dot");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("digraph"))
+        .stdout(predicate::str::contains(
+            r#""bllvm-protocol" -> "bllvm-consensus";"#,
+        ));
+}
+
+/// Test plan subcommand prints the build plan as JSON
+#[test]
+fn test_plan_subcommand_json() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("plan").arg(&versions_path).arg("--json");
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(r#""stages""#))
+        .stdout(predicate::str::contains(r#""stage": 1"#));
+}
+
+/// Test plan --strict fails on requirements that drifted from the declared version
+#[test]
+fn test_plan_subcommand_strict() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus>=0.1"], optional_requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("plan").arg(&versions_path);
+    cmd.assert().success();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("plan").arg(&versions_path).arg("--strict");
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "'bllvm-protocol' requires 'bllvm-consensus=0.1.0' but 'bllvm-consensus' is declared at 0.2.0",
+        ));
+
+    // The other manifest commands honour it too, wherever it is given
+    for args in [
+        vec!["order"],
+        vec!["graph"],
+        vec!["matrix"],
+        vec!["lint"],
+        vec!["--strict", "stats"],
+    ] {
+        let mut cmd = Command::cargo_bin("bllvm").unwrap();
+        cmd.args(&args).arg(&versions_path);
+        if !args.contains(&"--strict") {
+            cmd.arg("--strict");
+        }
+        cmd.assert().failure().stderr(predicate::str::contains(
+            "do not match the declared version",
+        ));
+    }
+}
+
+/// Test lint subcommand fails only on deny-level findings
+#[test]
+fn test_lint_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("lint").arg(&versions_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "warning[tag-convention] bllvm-consensus: git_tag '0.1.0' should be 'v0.1.0'",
+    ));
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("lint")
+        .arg(&versions_path)
+        .args(["--deny", "tag-convention"]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("error[tag-convention]"))
+        .stderr(predicate::str::contains("1 lint error(s)"));
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("lint")
+        .arg(&versions_path)
+        .args(["--allow", "tag-convention"]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No lint findings"));
+}
+
+/// Test diff subcommand between two manifests
+#[test]
+fn test_diff_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let old_path = temp_dir.path().join("old.toml");
+    let new_path = temp_dir.path().join("new.toml");
+    std::fs::write(
+        &old_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    std::fs::write(
+        &new_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.2.0"] }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("diff").arg(&old_path).arg(&new_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("- bllvm-protocol 0.1.0"))
+        .stdout(predicate::str::contains(
+            "- bllvm-consensus: version 0.1.0 -> 0.2.0",
+        ));
+}
+
+/// Test verify-tags subcommand reports missing tags and honours --offline
+#[cfg(feature = "git")]
+#[test]
+fn test_verify_tags_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let remote = temp_dir.path().join("untagged-remote");
+    std::process::Command::new("git")
+        .arg("init")
+        .arg("-q")
+        .arg(&remote)
+        .status()
+        .unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        format!(
+            r#"
+[versions]
+bllvm-consensus = {{ version = "0.1.0", git_tag = "v0.1.0", git_url = "{}" }}
+"#,
+            remote.display()
+        ),
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("verify-tags").arg(&versions_path);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains("bllvm-consensus v0.1.0: missing"))
+        .stderr(predicate::str::contains(
+            "Missing git tags for bllvm-consensus",
+        ));
+
+    // A remote that does not exist is an error, not a missing tag
+    std::fs::remove_dir_all(&remote).unwrap();
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("verify-tags").arg(&versions_path);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("git ls-remote"));
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("verify-tags").arg(&versions_path).arg("--offline");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("--offline"));
+}
+
+/// Test checkouts subcommand lists undeclared and missing checkouts
+#[cfg(feature = "git")]
+#[test]
+fn test_checkouts_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    for checkout in ["bllvm-consensus", "bllvm-sdk"] {
+        std::fs::create_dir_all(temp_dir.path().join(checkout).join(".git")).unwrap();
+    }
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("checkouts")
+        .arg(temp_dir.path())
+        .arg("--manifest")
+        .arg(&versions_path);
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Not in versions.toml:\n  bllvm-sdk\nNot checked out:\n  bllvm-node\n",
+    ));
+}
+
+/// Test plan subcommand reports cycles as structured JSON
+#[test]
+fn test_plan_subcommand_cycle_json() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("plan").arg(&versions_path).arg("--json");
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(r#""kind": "circular_dependency""#))
+        .stdout(predicate::str::contains(r#""cycle""#));
+}
+
+/// Test order subcommand output modes and exit code on cycles
+#[test]
+fn test_order_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+governance-app = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-sdk=0.1.0"] }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("order").arg(&versions_path);
+    cmd.assert()
+        .success()
+        .stdout("bllvm-consensus\nbllvm-sdk\nbllvm-protocol\ngovernance-app\n");
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("order").arg(&versions_path).arg("--stages");
+    cmd.assert().success().stdout(predicate::str::contains(
+        "Stage 1: bllvm-protocol, governance-app",
+    ));
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("order")
+        .arg(&versions_path)
+        .args(["--target", "bllvm-protocol", "--json"]);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let order: Vec<String> = serde_json::from_slice(&output).unwrap();
+    assert_eq!(order, vec!["bllvm-consensus", "bllvm-protocol"]);
+
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+A = { version = "0.1.0", git_tag = "v0.1.0", requires = ["B=0.1.0"] }
+B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("order").arg(&versions_path);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "Circular dependency detected: A -> B -> A",
+    ));
+}
+
+/// Test stats subcommand with build durations
+#[test]
+fn test_stats_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    let durations_path = temp_dir.path().join("durations.json");
+    std::fs::write(
+        &durations_path,
+        r#"{ "bllvm-consensus": 12.5, "bllvm-protocol": 3 }"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("stats")
+        .arg(&versions_path)
+        .arg("--durations")
+        .arg(&durations_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Longest chain (2): bllvm-consensus -> bllvm-protocol",
+        ))
+        .stdout(predicate::str::contains(
+            "Minimum wall-clock time: 15.5s (serial: 15.5s)",
+        ));
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("stats").arg(&versions_path).arg("--json");
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let stats: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    assert_eq!(stats["repos"]["bllvm-consensus"]["fan_in"], 1);
+}
+
+/// Test init subcommand writes a manifest and refuses to overwrite it
+#[test]
+fn test_init_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let crate_dir = temp_dir.path().join("bllvm-consensus");
+    std::fs::create_dir(&crate_dir).unwrap();
+    std::fs::write(
+        crate_dir.join("Cargo.toml"),
+        "[package]\nname = \"bllvm-consensus\"\nversion = \"0.1.0\"\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("init").arg(temp_dir.path());
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Wrote 1 repositories"));
+    let written = std::fs::read_to_string(temp_dir.path().join("versions.toml")).unwrap();
+    assert!(written.contains("git_tag = \"v0.1.0\""), "{}", written);
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("init").arg(temp_dir.path());
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+}
+
+/// Test build subcommand dry run prints the commands in build order
+#[test]
+fn test_build_subcommand_dry_run() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    std::fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], build = "make", path = "repos/protocol" }
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", build = "cargo build --release" }
+"#,
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("build").arg(&versions_path).arg("--dry-run");
+    cmd.assert().success().stdout(
+        predicate::str::is_match(
+            r"(?s)bllvm-consensus && cargo build --release\).*repos/protocol && make\)",
+        )
+        .unwrap(),
+    );
+}
+
+/// Test bump --dry-run prints the impact and fails when pins break
+#[test]
+fn test_bump_dry_run_subcommand() {
+    let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/versions.toml");
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.args(["bump", "--dry-run", "bllvm-consensus", "0.2.0", manifest]);
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::contains(
+            "bllvm-protocol requires 'bllvm-consensus=0.1.0' (exact pin)",
+        ))
+        .stderr(predicate::str::contains("breaks 2 requirement(s)"));
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.args(["bump", "--dry-run", "governance-app", "0.2.0", manifest]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("Breaks nothing"))
+        .stdout(predicate::str::contains("Rebuild: governance-app"));
+}
+
+/// Test why explains the order and lists every path
+#[test]
+fn test_why_subcommand() {
+    let manifest = concat!(env!("CARGO_MANIFEST_DIR"), "/versions.toml");
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.args(["why", "bllvm-consensus", "bllvm", manifest]);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "bllvm-consensus < bllvm because bllvm requires bllvm-node which requires bllvm-consensus",
+        ))
+        .stdout(predicate::str::contains(
+            "  bllvm -> bllvm-node -> bllvm-protocol -> bllvm-consensus",
+        ));
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.args(["why", "bllvm-consensus", "bllvm-sdkk", manifest]);
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("did you mean 'bllvm-sdk'?"));
+}
+
+/// Test clean-state removes the build state and a leftover lock
+#[test]
+fn test_clean_state_subcommand() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    let state_path = temp_dir.path().join(".bllvm-state.toml");
+    let lock_path = temp_dir.path().join(".bllvm-state.toml.lock");
+    std::fs::write(&state_path, "[built]\n").unwrap();
+    std::fs::write(&lock_path, "1\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("clean-state").arg(&versions_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(".bllvm-state.toml.lock"));
+    assert!(!state_path.exists());
+    assert!(!lock_path.exists());
+
+    let mut cmd = Command::cargo_bin("bllvm").unwrap();
+    cmd.arg("clean-state").arg(&versions_path);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("No build state to remove"));
+}
@@ -1,7 +1,12 @@
 //! Tests for versions.toml parsing and validation
 
-use bllvm::versions::{ValidationResult, VersionsManifest};
+use bllvm::builder::VersionsManifestBuilder;
+use bllvm::executor::BuildExecutor;
+use bllvm::replace::Source;
+use bllvm::source::{JsonSource, ManifestSource};
+use bllvm::versions::{ValidationError, VersionsManifest, Warning};
 use std::fs;
+use std::path::Path;
 use tempfile::TempDir;
 
 /// Test parsing valid versions.toml
@@ -14,11 +19,7 @@ bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-con
 bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"] }
 "#;
 
-    let temp_dir = TempDir::new().unwrap();
-    let versions_path = temp_dir.path().join("versions.toml");
-    fs::write(&versions_path, content).unwrap();
-
-    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse valid TOML");
+    let manifest: VersionsManifest = content.parse().expect("Should parse valid TOML");
     assert_eq!(manifest.versions.len(), 3);
     assert!(manifest.versions.contains_key("bllvm-consensus"));
     assert!(manifest.versions.contains_key("bllvm-protocol"));
@@ -35,11 +36,7 @@ bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-con
 bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }
 "#;
 
-    let temp_dir = TempDir::new().unwrap();
-    let versions_path = temp_dir.path().join("versions.toml");
-    fs::write(&versions_path, content).unwrap();
-
-    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let manifest = VersionsManifest::from_reader(content.as_bytes()).expect("Should parse");
 
     // Verify bllvm-protocol requires bllvm-consensus
     let protocol = manifest.versions.get("bllvm-protocol").unwrap();
@@ -143,10 +140,10 @@ bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-con
         !validation.is_valid(),
         "Missing dependencies should fail validation"
     );
-    assert!(validation
-        .errors()
-        .iter()
-        .any(|e| e.contains("bllvm-consensus")));
+    assert!(validation.errors().iter().any(|e| matches!(
+        e,
+        ValidationError::UnknownDependency { dependency, .. } if dependency == "bllvm-consensus"
+    )));
 }
 
 /// Test build order calculation
@@ -197,9 +194,1137 @@ B = { version = "0.1.0", git_tag = "v0.1.0", requires = ["A=0.1.0"] }
 
     let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
     let result = manifest.build_order();
-    assert!(result.is_err(), "Should fail with circular dependency");
-    assert!(result
+    assert!(
+        matches!(result, Err(bllvm::Error::CircularDependency { .. })),
+        "Should fail with circular dependency"
+    );
+}
+
+/// Test semver range requirements
+#[test]
+fn test_range_requirements() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.3", git_tag = "v0.1.3" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus>=0.1.0, <0.2.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=^0.1", "bllvm-protocol=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let validation = manifest.validate();
+    assert!(
+        validation.is_valid(),
+        "Satisfied ranges should pass validation: {:?}",
+        validation.errors()
+    );
+}
+
+/// Test that an unsatisfiable requirement names the repo and constraint
+#[test]
+fn test_unsatisfied_requirement() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus>=0.1.0, <0.2.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let validation = manifest.validate();
+    assert!(!validation.is_valid());
+    assert!(validation.errors().iter().any(|e| {
+        let msg = e.to_string();
+        msg.contains("bllvm-protocol") && msg.contains("bllvm-consensus>=0.1.0, <0.2.0")
+    }));
+}
+
+/// Test that validate() reports every problem at once
+#[test]
+fn test_validate_collects_all_errors() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1", git_tag = "" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protcol=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.2.0"] }
+bllvm_node = { version = "0.1.0", git_tag = "v0.1.0" }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let validation = manifest.validate();
+    let errors = validation.errors();
+
+    assert!(errors.contains(&ValidationError::InvalidVersion {
+        repo: "bllvm-consensus".to_string(),
+        version: "0.1".to_string(),
+    }));
+    assert!(errors.contains(&ValidationError::EmptyGitTag {
+        repo: "bllvm-consensus".to_string(),
+    }));
+    let typo = ValidationError::UnknownDependency {
+        repo: "bllvm-protocol".to_string(),
+        dependency: "bllvm-protcol".to_string(),
+        suggestion: Some("bllvm-protocol".to_string()),
+    };
+    assert!(errors.contains(&typo));
+    assert_eq!(
+        typo.to_string(),
+        "Repository 'bllvm-protocol' requires 'bllvm-protcol' which is not defined; \
+         did you mean 'bllvm-protocol'?"
+    );
+    assert!(errors.contains(&ValidationError::VersionMismatch {
+        repo: "bllvm-node".to_string(),
+        requirement: "bllvm-protocol=0.2.0".to_string(),
+        dependency: "bllvm-protocol".to_string(),
+        found: "0.1.0".to_string(),
+    }));
+    assert!(errors.contains(&ValidationError::DuplicateRepo {
+        repo: "bllvm_node".to_string(),
+        other: "bllvm-node".to_string(),
+    }));
+    assert_eq!(errors.len(), 5);
+}
+
+/// Test that a repo requiring itself is reported once, not as a cycle
+#[test]
+fn test_self_requirement() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let validation = manifest.validate();
+    assert_eq!(
+        validation.errors(),
+        &[ValidationError::SelfRequirement {
+            repo: "bllvm-consensus".to_string(),
+        }]
+    );
+}
+
+/// Test that strict loading rejects invalid manifests with every problem listed
+#[test]
+fn test_from_file_strict() {
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+
+    fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    assert!(VersionsManifest::from_file_strict(&versions_path).is_ok());
+
+    fs::write(
+        &versions_path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protcol=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    let err = VersionsManifest::from_file_strict(&versions_path)
+        .expect_err("Invalid manifest should be rejected");
+    assert!(matches!(&err, bllvm::Error::InvalidManifest { errors } if errors.len() == 2));
+    let err = err.to_string();
+    assert!(err.contains("2 problem(s)"));
+    assert!(err.contains("invalid version '0.1'"));
+    assert!(err.contains("requires 'bllvm-protcol' which is not defined"));
+}
+
+/// Test that features requested by dependents are merged with the repo's own
+#[test]
+fn test_features_for() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", features = ["serde"] }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0[bignum]"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", features = ["rpc", "wallet"], requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0[bignum, serde]"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    assert!(manifest.validate().is_valid());
+    assert_eq!(
+        manifest.features_for("bllvm-consensus").unwrap(),
+        vec!["bignum", "serde"]
+    );
+    assert_eq!(
+        manifest.features_for("bllvm-node").unwrap(),
+        vec!["rpc", "wallet"]
+    );
+    assert!(manifest.features_for("bllvm-protocol").unwrap().is_empty());
+    assert!(manifest.features_for("bllvm-unknown").is_err());
+
+    let plan = manifest.build_plan().unwrap();
+    assert_eq!(plan.repos[0].name, "bllvm-consensus");
+    assert_eq!(plan.repos[0].features, vec!["bignum", "serde"]);
+}
+
+/// Test that a written manifest reads back identically
+#[test]
+fn test_manifest_round_trip() {
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::copy(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/versions.toml"),
+        &versions_path,
+    )
+    .unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let output_path = temp_dir.path().join("written.toml");
+    manifest.to_file(&output_path).unwrap();
+
+    let reloaded = VersionsManifest::from_file(&output_path).expect("Should parse written file");
+    let mut normalized = manifest.clone();
+    normalized.sort_requires();
+    assert_eq!(reloaded, normalized);
+
+    // Writing is deterministic
+    assert_eq!(
+        reloaded.to_toml_string().unwrap(),
+        manifest.to_toml_string().unwrap()
+    );
+
+    // Same from a string, with repos written sorted by name
+    let parsed = VersionsManifest::from_str(include_str!("../versions.toml")).unwrap();
+    let written = parsed.to_toml_string().unwrap();
+    let mut normalized = parsed.clone();
+    normalized.sort_requires();
+    assert_eq!(VersionsManifest::from_str(&written).unwrap(), normalized);
+    let repos: Vec<&str> = written
+        .lines()
+        .filter_map(|line| line.strip_prefix("[versions.")?.strip_suffix(']'))
+        .collect();
+    let mut sorted = repos.clone();
+    sorted.sort();
+    assert_eq!(repos.len(), parsed.versions.len());
+    assert_eq!(repos, sorted);
+
+    // requires lists are written sorted, so reading back gives the
+    // normalized manifest
+    let unsorted = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus=0.1.0"], optional_requires = ["bllvm-sdk=0.1.0?", "bllvm-bench=0.1.0?"] }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-bench = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    let written = unsorted.to_toml_string().unwrap();
+    let reparsed = VersionsManifest::from_str(&written).unwrap();
+    let mut normalized = unsorted.clone();
+    normalized.sort_requires();
+    assert_eq!(reparsed, normalized);
+    assert_eq!(
+        reparsed.versions["bllvm-node"].requires[0].spec,
+        "bllvm-consensus=0.1.0"
+    );
+    assert_eq!(reparsed.to_toml_string().unwrap(), written);
+}
+
+/// Test that bumping a version can update exact pins that pointed at it
+#[test]
+fn test_set_version_cascade() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0[bignum]"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0", "bllvm-consensus>=0.1.0"] }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let mut manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let mut uncascaded = manifest.clone();
+
+    let updated = manifest
+        .set_version("bllvm-consensus", "0.2.0", "v0.2.0", true)
+        .unwrap();
+    assert_eq!(updated, vec!["bllvm-protocol"]);
+    assert_eq!(manifest.versions["bllvm-consensus"].version, "0.2.0");
+    assert_eq!(manifest.versions["bllvm-consensus"].git_tag, "v0.2.0");
+    assert_eq!(
+        manifest.versions["bllvm-protocol"].requires,
+        vec!["bllvm-consensus=0.2.0[bignum]"]
+    );
+    // Range requirements are left alone
+    assert_eq!(
+        manifest.versions["bllvm-node"].requires[1],
+        "bllvm-consensus>=0.1.0"
+    );
+    assert!(manifest.validate().is_valid());
+
+    manifest.to_file(&versions_path).unwrap();
+    let mut normalized = manifest.clone();
+    normalized.sort_requires();
+    assert_eq!(
+        VersionsManifest::from_file(&versions_path).unwrap(),
+        normalized
+    );
+
+    assert!(uncascaded
+        .set_version("bllvm-consensus", "0.2.0", "v0.2.0", false)
+        .unwrap()
+        .is_empty());
+    assert!(!uncascaded.validate().is_valid());
+    assert!(uncascaded
+        .set_version("bllvm-consensus", "0.2", "v0.2", false)
+        .is_err());
+}
+
+/// Test clone URLs composed from the workspace host
+#[test]
+fn test_git_url_for() {
+    let content = r#"
+[workspace]
+git_host = "https://github.com/BTCDecoded/"
+default_branch = "main"
+
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0", git_url = "https://example.com/forks/bllvm-sdk.git" }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let manifest = VersionsManifest::from_file(&versions_path).expect("Should parse");
+    let workspace = manifest.workspace.as_ref().expect("Should have workspace");
+    assert_eq!(workspace.default_branch.as_deref(), Some("main"));
+
+    assert_eq!(
+        manifest.git_url_for("bllvm-consensus").unwrap(),
+        "https://github.com/BTCDecoded/bllvm-consensus"
+    );
+    assert_eq!(
+        manifest.git_url_for("bllvm-sdk").unwrap(),
+        "https://example.com/forks/bllvm-sdk.git"
+    );
+    assert!(manifest.git_url_for("bllvm-unknown").is_err());
+
+    // Without a workspace host only explicit URLs work
+    let mut manifest = manifest;
+    manifest.workspace = None;
+    assert!(manifest.git_url_for("bllvm-consensus").is_err());
+    assert!(manifest.git_url_for("bllvm-sdk").is_ok());
+
+    manifest.to_file(&versions_path).unwrap();
+    let mut normalized = manifest.clone();
+    normalized.sort_requires();
+    assert_eq!(
+        VersionsManifest::from_file(&versions_path).unwrap(),
+        normalized
+    );
+}
+
+/// Test that a repo defined twice is reported by name with both versions
+#[test]
+fn test_duplicate_repo_key() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.2.0", git_tag = "v0.2.0" }
+"#;
+
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let err = VersionsManifest::from_file(&versions_path)
+        .expect_err("Duplicate key should fail")
+        .to_string();
+    assert!(err.contains("Duplicate repository 'bllvm-node'"));
+    assert!(err.contains("at line 5"));
+    assert!(err.contains("defined with versions 0.1.0 and 0.2.0"));
+
+    // Table form
+    let err = VersionsManifest::from_str(
+        "[versions.bllvm-node]\nversion = \"0.1.0\"\ngit_tag = \"v0.1.0\"\n\n[versions.bllvm-node]\nversion = \"0.1.1\"\n",
+    )
+    .expect_err("Duplicate table should fail")
+    .to_string();
+    assert!(err.contains("Duplicate repository 'bllvm-node' in versions.toml (<inline>)"));
+    assert!(err.contains("0.1.0 and 0.1.1"));
+}
+
+/// Test that profile overrides are applied before ordering and validation
+#[test]
+fn test_with_profile() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+
+[profiles.staging.versions]
+bllvm-consensus = { version = "0.1.1", git_tag = "v0.1.1" }
+bllvm-protocol = { requires = ["bllvm-consensus=0.1.1"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"], add = true }
+
+[profiles.broken.versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+
+[profiles.typo.versions]
+bllvm-nod = { version = "0.1.0" }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).expect("Should parse");
+    let staging = manifest.with_profile("staging").unwrap();
+    assert_eq!(staging.versions["bllvm-consensus"].version, "0.1.1");
+    assert_eq!(staging.versions["bllvm-protocol"].git_tag, "v0.1.0");
+    assert!(staging.profiles.is_empty());
+    assert!(staging.validate().is_valid());
+    assert_eq!(
+        staging.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+
+    // Constraints are checked against the effective versions
+    let broken = manifest.with_profile("broken").unwrap();
+    assert!(!broken.validate().is_valid());
+
+    let err = manifest.with_profile("typo").unwrap_err().to_string();
+    assert!(err.contains("'bllvm-nod' which is not in [versions]"));
+
+    let err = manifest.with_profile("production").unwrap_err().to_string();
+    assert!(err.contains("available profiles: broken, staging, typo"));
+
+    // Profiles survive a round trip
+    let reloaded = VersionsManifest::from_str(&manifest.to_toml_string().unwrap()).unwrap();
+    assert_eq!(reloaded, manifest);
+}
+
+/// Test that loading and ordering report typed errors
+#[test]
+fn test_error_variants() {
+    let temp_dir = TempDir::new().unwrap();
+    let missing = temp_dir.path().join("missing.toml");
+    assert!(matches!(
+        VersionsManifest::from_file(&missing),
+        Err(bllvm::Error::Io { .. })
+    ));
+
+    assert!(matches!(
+        VersionsManifest::from_str("[versions\n"),
+        Err(bllvm::Error::Parse { file, .. }) if file == "<inline>"
+    ));
+    assert!(matches!(
+        VersionsManifest::from_reader(&b"[versions\n"[..]),
+        Err(bllvm::Error::Parse { file, .. }) if file == "<inline>"
+    ));
+
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        manifest.build_order().unwrap_err(),
+        bllvm::Error::UnknownDependency {
+            repo: "bllvm-protocol".to_string(),
+            missing: "bllvm-consensus".to_string(),
+            suggestion: None,
+        }
+    );
+
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.2.0", git_tag = "v0.2.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        manifest.build_order().unwrap_err(),
+        bllvm::Error::VersionMismatch {
+            repo: "bllvm-protocol".to_string(),
+            required: "bllvm-consensus=0.1.0".to_string(),
+            dependency: "bllvm-consensus".to_string(),
+            found: "0.2.0".to_string(),
+        }
+    );
+
+    // Error lists may be empty
+    assert_eq!(
+        bllvm::Error::BumpRefused { errors: vec![] }.to_string(),
+        "Refusing to bump: versions.toml has 0 validation error(s):\n"
+    );
+    let err = bllvm::Error::BumpInvalid {
+        repo: "bllvm-node".to_string(),
+        version: "0.2.0".to_string(),
+        errors: vec![],
+    };
+    assert!(err.to_string().starts_with("Bumping 'bllvm-node' to 0.2.0"));
+}
+
+/// Test that parse errors point at the offending line, column and repo
+#[test]
+fn test_parse_error_locations() {
+    let location = |content: &str| match VersionsManifest::from_str(content) {
+        Err(bllvm::Error::Parse {
+            line, column, repo, ..
+        }) => (line, column, repo),
+        other => panic!("expected a parse error, got {:?}", other),
+    };
+
+    // Bad TOML syntax
+    assert_eq!(
+        location("[versions]\nbllvm-consensus = { version = \"0.1.0\" git_tag = \"v0.1.0\" }\n"),
+        (Some(2), Some(39), Some("bllvm-consensus".to_string()))
+    );
+
+    // `requires` is not an array
+    let content = r#"[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = "bllvm-consensus=0.1.0" }
+"#;
+    assert_eq!(
+        location(content),
+        (Some(3), Some(70), Some("bllvm-protocol".to_string()))
+    );
+    let err = VersionsManifest::from_str(content).unwrap_err().to_string();
+    assert!(
+        err.starts_with(
+            "Failed to parse versions.toml (<inline>) at line 3, column 70 for repository 'bllvm-protocol': invalid type"
+        ),
+        "{}",
+        err
+    );
+
+    // Missing `git_tag`, in table form
+    let content = r#"[versions.bllvm-node]
+version = "0.1.0"
+git_tag = "v0.1.0"
+
+[versions.bllvm-sdk]
+version = "0.1.0"
+"#;
+    assert_eq!(
+        location(content),
+        (Some(5), Some(1), Some("bllvm-sdk".to_string()))
+    );
+
+    // `requires` entry that is not `name=version`, on a continuation line
+    let content = r#"[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = [
+    "bllvm-consensus=not-a-version",
+] }
+"#;
+    assert_eq!(
+        location(content),
+        (Some(4), Some(6), Some("bllvm-protocol".to_string()))
+    );
+    assert!(VersionsManifest::from_str(content)
         .unwrap_err()
         .to_string()
-        .contains("Circular dependency"));
+        .contains("invalid requirement 'bllvm-consensus=not-a-version'"));
+}
+
+/// Test that `BLLVM_OVERRIDE_<REPO>` overrides win over the file
+#[test]
+fn test_apply_overrides() {
+    let mut manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "abc123" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", git_commit = "def456" }
+bllvm-sdk = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+
+    let sha = "0123456789abcdef0123456789abcdef01234567";
+    let overridden = manifest.apply_overrides([
+        ("BLLVM_OVERRIDE_BLLVM_NODE", "my-branch"),
+        ("BLLVM_OVERRIDE_BLLVM_CONSENSUS", sha),
+        ("BLLVM_OVERRIDE_UNKNOWN_REPO", "main"),
+        ("BLLVM_SDK", "ignored"),
+    ]);
+    assert_eq!(overridden, vec!["bllvm-consensus", "bllvm-node"]);
+
+    let node = &manifest.versions["bllvm-node"];
+    assert_eq!(node.git_tag, "my-branch");
+    assert_eq!(node.git_commit, None);
+    assert!(node.overridden);
+
+    let consensus = &manifest.versions["bllvm-consensus"];
+    assert_eq!(consensus.git_commit.as_deref(), Some(sha));
+
+    let sdk = &manifest.versions["bllvm-sdk"];
+    assert_eq!(sdk.git_tag, "v0.1.0");
+    assert!(!sdk.overridden);
+}
+
+/// Test that every supported schema's fixture still parses and migrates
+#[test]
+fn test_schema_fixtures() {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/schema");
+    for (file, schema) in [("v1-implicit.toml", 1), ("v1.toml", 1)] {
+        let path = format!("{}/{}", fixtures, file);
+        let mut manifest = VersionsManifest::from_file(&path).expect(file);
+        assert_eq!(manifest.schema, schema, "{}", file);
+        assert!(manifest.validate().is_valid(), "{}", file);
+        assert!(manifest.build_order().is_ok(), "{}", file);
+
+        manifest.migrate_to_latest();
+        assert_eq!(manifest.schema, bllvm::versions::LATEST_SCHEMA);
+        let reparsed = VersionsManifest::from_str(&manifest.to_toml_string().unwrap()).unwrap();
+        manifest.sort_requires();
+        assert_eq!(reparsed, manifest, "{}", file);
+    }
+}
+
+/// Test that a schema newer than this bllvm is rejected up front
+#[test]
+fn test_future_schema_rejected() {
+    // Unknown future fields must not mask the schema error
+    let err = VersionsManifest::from_str(
+        r#"
+schema = 2
+
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", requires = { bllvm-sdk = "0.1.0" } }
+"#,
+    )
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        bllvm::Error::UnsupportedSchema {
+            schema: 2,
+            latest: 1,
+            ..
+        }
+    ));
+    assert!(err.to_string().contains("this bllvm is too old"));
+
+    assert!(VersionsManifest::from_str("schema = 0\n[versions]\n").is_err());
+}
+
+/// Test that JSON manifests load into the same representation as TOML
+#[test]
+fn test_json_manifest() {
+    let temp_dir = TempDir::new().unwrap();
+    let json_path = temp_dir.path().join("versions.json");
+    fs::write(
+        &json_path,
+        r#"{
+  "versions": {
+    "bllvm-consensus": { "version": "0.1.0", "git_tag": "v0.1.0" },
+    "bllvm-protocol": { "version": "0.1.0", "git_tag": "v0.1.0", "requires": ["bllvm-consensus=0.1.0"] }
+  }
+}"#,
+    )
+    .unwrap();
+    let toml = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    assert_eq!(VersionsManifest::from_file(&json_path).unwrap(), toml);
+
+    fs::write(&json_path, "{\n  \"versions\": {\n    \"a\": 1\n  }\n}").unwrap();
+    match VersionsManifest::from_file(&json_path).unwrap_err() {
+        bllvm::Error::Parse { file, line, .. } => {
+            assert_eq!(file, json_path.display().to_string());
+            assert_eq!(line, Some(3));
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+
+    let yaml_path = temp_dir.path().join("versions.yaml");
+    fs::write(
+        &yaml_path,
+        "versions:\n  bllvm-consensus:\n    version: 0.1.0\n    git_tag: v0.1.0\n",
+    )
+    .unwrap();
+    let yaml = VersionsManifest::from_file(&yaml_path);
+    if cfg!(feature = "yaml") {
+        assert_eq!(yaml.unwrap().versions["bllvm-consensus"].git_tag, "v0.1.0");
+    } else {
+        assert!(yaml.unwrap_err().to_string().contains("`yaml` feature"));
+    }
+}
+
+/// Test selecting a release channel when loading
+#[test]
+fn test_version_channels() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { stable = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }, nightly = { version = "0.2.0-dev", git_tag = "nightly", requires = ["bllvm-consensus=0.2.0"] } }
+"#;
+    let temp_dir = TempDir::new().unwrap();
+    let versions_path = temp_dir.path().join("versions.toml");
+    fs::write(&versions_path, content).unwrap();
+
+    let stable = VersionsManifest::from_file(&versions_path).unwrap();
+    assert_eq!(stable.versions["bllvm-node"].version, "0.1.0");
+    assert_eq!(stable.channels["bllvm-node"].len(), 2);
+    assert!(stable.validate().is_valid());
+
+    // Requirements are those of the selected channel
+    let nightly = VersionsManifest::from_file_channel(&versions_path, "nightly").unwrap();
+    assert_eq!(nightly.versions["bllvm-node"].git_tag, "nightly");
+    assert_eq!(nightly.versions["bllvm-consensus"].version, "0.1.0");
+    assert!(!nightly.validate().is_valid());
+    assert_eq!(stable.resolve_channel("nightly").unwrap(), nightly);
+
+    match VersionsManifest::from_file_channel(&versions_path, "beta").unwrap_err() {
+        bllvm::Error::Parse { repo, message, .. } => {
+            assert_eq!(repo.as_deref(), Some("bllvm-node"));
+            assert_eq!(message, "no 'beta' channel (channels: nightly, stable)");
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert!(stable.resolve_channel("beta").is_err());
+}
+
+/// Test assembling git tags from tag_prefix
+#[test]
+fn test_tag_prefix() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "0.1.0", tag_prefix = "consensus-v" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#,
+    )
+    .unwrap();
+    assert_eq!(
+        manifest.resolved_tag("bllvm-consensus").unwrap(),
+        "consensus-v0.1.0"
+    );
+    assert_eq!(manifest.resolved_tag("bllvm-protocol").unwrap(), "v0.1.0");
+    assert!(manifest.resolved_tag("bllvm-node").is_err());
+
+    let plan = manifest.build_plan().unwrap();
+    assert_eq!(plan.repos[0].git_tag, "consensus-v0.1.0");
+
+    let written = manifest.to_toml_string().unwrap();
+    assert_eq!(VersionsManifest::from_str(&written).unwrap(), manifest);
+
+    // An override is a complete ref
+    let mut overridden = manifest.clone();
+    overridden.apply_overrides([("BLLVM_OVERRIDE_BLLVM_CONSENSUS", "my-branch")]);
+    assert_eq!(
+        overridden.resolved_tag("bllvm-consensus").unwrap(),
+        "my-branch"
+    );
+}
+
+/// Test that requires arrays mixing strings and target-conditional tables parse and round-trip
+#[test]
+fn test_target_conditional_requires() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-ffi = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0", { dep = "bllvm-ffi=0.1.0", target = "windows" }] }
+"#,
+    )
+    .unwrap();
+    let requires = &manifest.versions["bllvm-node"].requires;
+    assert_eq!(requires[0], "bllvm-consensus=0.1.0");
+    assert_eq!(requires[1].spec, "bllvm-ffi=0.1.0");
+    assert_eq!(requires[1].target.as_deref(), Some("windows"));
+    assert!(requires[1].applies_to("windows"));
+    assert!(!requires[1].applies_to("linux"));
+
+    let written = manifest.to_toml_string().unwrap();
+    assert_eq!(VersionsManifest::from_str(&written).unwrap(), manifest);
+
+    // A conditional dependency must exist whatever the target
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = [{ dep = "bllvm-ffi=0.1.0", target = "windows" }] }
+"#,
+    )
+    .unwrap();
+    assert!(manifest
+        .validate()
+        .errors()
+        .contains(&ValidationError::UnknownDependency {
+            repo: "bllvm-node".to_string(),
+            dependency: "bllvm-ffi".to_string(),
+            suggestion: None,
+        }));
+}
+
+/// Test that bllvm_min_version above this bllvm is rejected before anything else
+#[test]
+fn test_bllvm_min_version() {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/compat");
+    let path = format!("{}/min-version.toml", fixtures);
+    let err = VersionsManifest::from_file(&path).unwrap_err();
+    assert_eq!(
+        err,
+        bllvm::Error::BllvmTooOld {
+            file: path.clone(),
+            required: "99.0".to_string(),
+            current: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    );
+    assert!(err.to_string().contains(&format!(
+        "requires bllvm >= 99.0, you have {}",
+        env!("CARGO_PKG_VERSION")
+    )));
+
+    let manifest =
+        VersionsManifest::from_file(format!("{}/unknown-fields.toml", fixtures)).unwrap();
+    assert_eq!(manifest.bllvm_min_version.as_deref(), Some("0.1"));
+    let written = manifest.to_toml_string().unwrap();
+    assert!(written.starts_with("schema = 1\nbllvm_min_version = \"0.1\"\n"));
+}
+
+/// Test that unknown keys in [versions] entries become warnings instead of being dropped
+#[test]
+fn test_unknown_fields_warn() {
+    let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/compat");
+    let manifest =
+        VersionsManifest::from_file(format!("{}/unknown-fields.toml", fixtures)).unwrap();
+    let warnings: Vec<String> = manifest
+        .warnings()
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            "Repository 'bllvm-consensus' has unknown field 'future_field' (ignored)",
+            "Repository 'bllvm-node' has unknown field 'feature', did you mean 'features'? (ignored)",
+            "Repository 'bllvm-protocol' has unknown field 'require', did you mean 'requires'? (ignored)",
+        ]
+    );
+    assert!(manifest.versions["bllvm-protocol"].requires.is_empty());
+    assert!(matches!(
+        &manifest.parse_warnings[2],
+        Warning::UnknownField { repo, field, suggestion: Some(suggestion) }
+            if repo == "bllvm-protocol" && field == "require" && suggestion == "requires"
+    ));
+
+    // A manifest using only known fields has none
+    let manifest = VersionsManifest::from_str(include_str!("../versions.toml")).unwrap();
+    assert!(manifest.parse_warnings.is_empty());
+}
+
+/// Test that the builder validates and round-trips through JSON and TOML
+#[test]
+fn test_manifest_builder() {
+    let manifest = VersionsManifestBuilder::new()
+        .add_repo("bllvm-consensus", "0.1.0", "v0.1.0")
+        .add_repo("bllvm-protocol", "0.1.0", "v0.1.0")
+        .add_requirement("bllvm-protocol", "bllvm-consensus", "0.1.0")
+        .add_requirement("bllvm-protocol", "bllvm-sdk", ">=0.1.0, <0.2.0?")
+        .build()
+        .unwrap();
+    assert_eq!(
+        manifest.versions["bllvm-protocol"].requires[0].spec,
+        "bllvm-consensus=0.1.0"
+    );
+
+    let json = manifest.to_json_string().unwrap();
+    assert_eq!(JsonSource.parse(&json).unwrap(), manifest);
+    let toml = manifest.to_toml_string().unwrap();
+    assert_eq!(VersionsManifest::from_str(&toml).unwrap(), manifest);
+
+    // Same validation as from_file_strict
+    let err = VersionsManifestBuilder::new()
+        .add_repo("bllvm-protocol", "0.1.0", "v0.1.0")
+        .add_requirement("bllvm-protocol", "bllvm-consensus", "0.1.0")
+        .build()
+        .unwrap_err();
+    assert!(
+        err.to_string().starts_with("Invalid versions.toml"),
+        "{}",
+        err
+    );
+
+    let err = VersionsManifestBuilder::new()
+        .add_repo("bllvm-sdk", "0.1.0", "v0.1.0")
+        .add_repo("bllvm-sdk", "0.2.0", "v0.2.0")
+        .add_requirement("bllvm-node", "bllvm-sdk", "0.1.0")
+        .build()
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("'bllvm-sdk' is added twice"), "{}", err);
+    assert!(err.contains("added to 'bllvm-node'"), "{}", err);
+}
+
+/// Test that a dependency listed twice in one entry is a warning if the
+/// entries agree and an error if they conflict, also after a profile
+#[test]
+fn test_duplicate_requires() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0", "bllvm-consensus=0.1.0"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = [
+    "bllvm-protocol=0.1.0",
+    { dep = "bllvm-consensus=^0.1", target = "linux" },
+    { dep = "bllvm-consensus=0.1.0", target = "macos" },
+] }
+
+[profiles.clash.versions]
+bllvm-node = { requires = ["bllvm-protocol=0.1.0", "bllvm-protocol=^0.1"] }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).unwrap();
+    let result = manifest.validate();
+    assert!(result.is_valid());
+    assert_eq!(
+        manifest.warnings(),
+        vec![Warning::DuplicateRequirement {
+            repo: "bllvm-protocol".to_string(),
+            requirement: "bllvm-consensus=0.1.0".to_string(),
+        }]
+    );
+
+    let clash = manifest.with_profile("clash").unwrap().validate();
+    assert!(!clash.is_valid());
+    assert_eq!(
+        clash.errors(),
+        [ValidationError::ConflictingRequirements {
+            repo: "bllvm-node".to_string(),
+            dependency: "bllvm-protocol".to_string(),
+            first: "bllvm-protocol=0.1.0".to_string(),
+            second: "bllvm-protocol=^0.1".to_string(),
+        }]
+    );
+    assert_eq!(
+        clash.errors()[0].to_string(),
+        "Repository 'bllvm-node' requires 'bllvm-protocol' twice with different constraints: 'bllvm-protocol=0.1.0' and 'bllvm-protocol=^0.1'"
+    );
+}
+
+/// Test that per-repo metadata is kept verbatim and passed to the plan
+#[test]
+fn test_repo_metadata() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], metadata = { team = "core", image = "bllvm/node", ports = [8333, 8332], deploy = { replicas = 3 } } }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).unwrap();
+    assert!(manifest.validate().is_valid());
+    assert!(manifest.parse_warnings.is_empty());
+    let metadata = manifest.metadata_for("bllvm-node").unwrap();
+    assert_eq!(metadata["team"], "core");
+    assert_eq!(metadata["ports"], serde_json::json!([8333, 8332]));
+    assert_eq!(metadata["deploy"]["replicas"], 3);
+    assert!(manifest.metadata_for("bllvm-consensus").unwrap().is_empty());
+    assert!(manifest.metadata_for("bllvm-nod").is_err());
+
+    let toml = manifest.to_toml_string().unwrap();
+    assert_eq!(VersionsManifest::from_str(&toml).unwrap(), manifest);
+    let json = manifest.to_json_string().unwrap();
+    assert_eq!(JsonSource.parse(&json).unwrap(), manifest);
+
+    let plan = serde_json::to_value(manifest.build_plan().unwrap()).unwrap();
+    assert_eq!(plan["repos"][1]["metadata"]["image"], "bllvm/node");
+    assert!(plan["repos"][0].get("metadata").is_none());
+}
+
+/// Test that labels are passed through and can select repos
+#[test]
+fn test_repo_labels() {
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", labels = { team = "consensus", tier = "core" } }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], labels = { tier = "core", anything = "goes" } }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"], labels = { tier = "app" } }
+docs = { version = "0.1.0", git_tag = "v0.1.0" }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).unwrap();
+    assert!(manifest.validate().is_valid());
+    assert!(manifest.parse_warnings.is_empty());
+    let labels = manifest.labels_for("bllvm-consensus").unwrap();
+    assert_eq!(labels["team"], "consensus");
+    assert_eq!(labels["tier"], "core");
+    assert!(manifest.labels_for("docs").unwrap().is_empty());
+    assert!(manifest.labels_for("bllvm-nod").is_err());
+
+    assert_eq!(
+        manifest.filter_by_label("tier", "core"),
+        vec!["bllvm-consensus", "bllvm-protocol"]
+    );
+    assert_eq!(
+        manifest.filter_by_label("anything", "goes"),
+        vec!["bllvm-protocol"]
+    );
+    assert!(manifest.filter_by_label("tier", "edge").is_empty());
+    assert!(manifest.filter_by_label("owner", "core").is_empty());
+
+    let toml = manifest.to_toml_string().unwrap();
+    assert_eq!(VersionsManifest::from_str(&toml).unwrap(), manifest);
+}
+
+/// Test that `[replace]` swaps the source of a repo for a local path
+#[test]
+fn test_replace() {
+    let content = r#"
+[workspace]
+git_host = "https://github.com/BTCDecoded"
+
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", build = "cargo build" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"], build = "cargo build" }
+
+[replace]
+bllvm-consensus = { path = "../bllvm-consensus" }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).unwrap();
+    assert!(manifest.validate().is_valid());
+    assert_eq!(
+        manifest.source_of("bllvm-consensus").unwrap(),
+        Source::Path("../bllvm-consensus".into())
+    );
+    assert_eq!(
+        manifest.source_of("bllvm-node").unwrap(),
+        Source::Git {
+            url: "https://github.com/BTCDecoded/bllvm-node".to_string(),
+            tag: "v0.1.0".to_string(),
+        }
+    );
+    assert!(manifest.source_of("bllvm-nod").is_err());
+
+    // Ordering is unchanged, builds run in the replacement
+    assert_eq!(
+        manifest.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-node"]
+    );
+    let steps = BuildExecutor::new(&manifest)
+        .root("/work/ws")
+        .steps()
+        .unwrap();
+    assert_eq!(steps[0].dir, Path::new("/work/ws/../bllvm-consensus"));
+    assert_eq!(steps[1].dir, Path::new("/work/ws/bllvm-node"));
+
+    let toml = manifest.to_toml_string().unwrap();
+    assert_eq!(VersionsManifest::from_str(&toml).unwrap(), manifest);
+
+    let typo = VersionsManifest::from_str(
+        &content.replace("bllvm-consensus = { path", "bllvm-consensu = { path"),
+    )
+    .unwrap();
+    assert_eq!(
+        typo.validate().errors(),
+        [ValidationError::UnknownReplacement {
+            repo: "bllvm-consensu".to_string(),
+            suggestion: Some("bllvm-consensus".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn test_external_packages() {
+    let content = r#"
+[external]
+secp256k1 = { version = ">=0.28" }
+
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0", requires = ["secp256k1>=0.28.2"] }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0", "secp256k1^0.29"] }
+"#;
+
+    let manifest = VersionsManifest::from_str(content).unwrap();
+    assert!(manifest.validate().is_valid());
+    assert_eq!(
+        manifest.build_order().unwrap(),
+        vec!["bllvm-consensus", "bllvm-node"]
+    );
+
+    assert_eq!(
+        manifest.build_order_for(&["bllvm-consensus"]).unwrap(),
+        vec!["bllvm-consensus"]
+    );
+    assert_eq!(
+        manifest.build_stages_for(&["bllvm-node"]).unwrap(),
+        vec![vec!["bllvm-consensus"], vec!["bllvm-node"]]
+    );
+
+    let plan = manifest.build_plan().unwrap();
+    assert_eq!(plan.repos.len(), 2);
+    assert!(plan.repos[0].dependencies.is_empty());
+    assert_eq!(plan.externals.len(), 1);
+    assert_eq!(plan.externals[0].name, "secp256k1");
+    assert_eq!(plan.externals[0].version, ">=0.28");
+    assert_eq!(
+        plan.externals[0].required_by,
+        vec!["bllvm-consensus", "bllvm-node"]
+    );
+    let json = plan.to_json().unwrap();
+    assert!(json.contains("\"externals\""));
+
+    let toml = manifest.to_toml_string().unwrap();
+    assert_eq!(VersionsManifest::from_str(&toml).unwrap(), manifest);
+
+    // A range the declaration rules out
+    let incompatible =
+        VersionsManifest::from_str(&content.replace("secp256k1^0.29", "secp256k1<0.28")).unwrap();
+    assert_eq!(
+        incompatible.validate().errors(),
+        [ValidationError::VersionMismatch {
+            repo: "bllvm-node".to_string(),
+            requirement: "secp256k1<0.28".to_string(),
+            dependency: "secp256k1".to_string(),
+            found: ">=0.28".to_string(),
+        }]
+    );
+
+    // Undeclared names stay errors
+    let undeclared =
+        VersionsManifest::from_str(&content.replace("secp256k1 = {", "k256 = {")).unwrap();
+    assert!(matches!(
+        undeclared.validate().errors()[0],
+        ValidationError::UnknownDependency { .. }
+    ));
+
+    let invalid = VersionsManifest::from_str(&content.replace("\">=0.28\"", "\"latest\"")).unwrap();
+    assert!(matches!(
+        invalid.validate().errors(),
+        [ValidationError::InvalidExternal { name, .. }] if name == "secp256k1"
+    ));
 }
@@ -0,0 +1,145 @@
+//! Tests for watching a manifest for changes
+#![cfg(feature = "watch")]
+
+use std::fs;
+use std::ops::ControlFlow;
+use std::sync::mpsc;
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Test that every save reports the new order or the error, once per save
+#[test]
+fn test_watch() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("versions.toml");
+    fs::write(
+        &path,
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+
+    let (tx, rx) = mpsc::channel();
+    let watched = path.clone();
+    let handle = std::thread::spawn(move || {
+        let mut calls = 0;
+        bllvm::watch::watch(&watched, |order| {
+            calls += 1;
+            tx.send(order.map_err(|e| e.to_string())).unwrap();
+            if calls == 3 {
+                ControlFlow::Break(())
+            } else {
+                ControlFlow::Continue(())
+            }
+        })
+    });
+    let timeout = Duration::from_secs(10);
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap().unwrap(),
+        ["bllvm-consensus"]
+    );
+
+    // Give the watcher time to start, then save twice in quick succession
+    std::thread::sleep(Duration::from_millis(500));
+    let content = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }
+"#;
+    fs::write(&path, "").unwrap();
+    fs::write(&path, content).unwrap();
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap().unwrap(),
+        ["bllvm-consensus", "bllvm-protocol"]
+    );
+    assert!(rx.recv_timeout(bllvm::watch::DEBOUNCE * 3).is_err());
+
+    fs::write(&path, "[versions\n").unwrap();
+    let err = rx.recv_timeout(timeout).unwrap().unwrap_err();
+    assert!(err.contains("versions.toml"), "{}", err);
+    handle.join().unwrap().unwrap();
+}
+
+/// Test that the watcher validates saves, diffs the orders and can be stopped
+#[test]
+fn test_manifest_watcher() {
+    use bllvm::watch::{ManifestWatcher, OrderDiff, WatchUpdate};
+
+    let order = |repos: &[&str]| -> Vec<String> { repos.iter().map(|r| r.to_string()).collect() };
+    assert_eq!(
+        OrderDiff::between(&order(&["a", "b", "c", "d"]), &order(&["a", "c", "b", "e"])),
+        OrderDiff {
+            added: order(&["e"]),
+            removed: order(&["d"]),
+            moved: order(&["c", "b"]),
+        }
+    );
+    assert!(
+        OrderDiff::between(&order(&["a", "b"]), &order(&["a", "x", "b"]))
+            .moved
+            .is_empty()
+    );
+
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("versions.toml");
+    let base = r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+"#;
+    fs::write(&path, base).unwrap();
+
+    let watcher = ManifestWatcher::new(&path).debounce(Duration::from_millis(100));
+    let stop = watcher.stop_handle();
+    let (tx, rx) = mpsc::channel();
+    let handle = std::thread::spawn(move || {
+        watcher.run(|update| {
+            tx.send(update).unwrap();
+            ControlFlow::Continue(())
+        })
+    });
+    let timeout = Duration::from_secs(10);
+    assert_eq!(
+        rx.recv_timeout(timeout).unwrap(),
+        WatchUpdate::Plan {
+            order: vec!["bllvm-consensus".to_string()],
+            changes: None,
+        }
+    );
+    std::thread::sleep(Duration::from_millis(500));
+
+    // A cycle is reported as invalid
+    fs::write(
+        &path,
+        r#"
+[versions]
+a = { version = "0.1.0", git_tag = "v0.1.0", requires = ["b"] }
+b = { version = "0.1.0", git_tag = "v0.1.0", requires = ["a"] }
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+    let WatchUpdate::Invalid { errors } = rx.recv_timeout(timeout).unwrap() else {
+        panic!("cycle should be invalid");
+    };
+    assert!(errors[0].contains("a -> b -> a"), "{:?}", errors);
+
+    // Compared with the last valid plan, not the invalid save
+    fs::write(
+        &path,
+        format!(
+            "{}{}\n",
+            base, r#"bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-consensus=0.1.0"] }"#
+        ),
+    )
+    .unwrap();
+    let WatchUpdate::Plan { order, changes } = rx.recv_timeout(timeout).unwrap() else {
+        panic!("manifest should be valid again");
+    };
+    assert_eq!(order, ["bllvm-consensus", "bllvm-protocol"]);
+    assert_eq!(changes.unwrap().to_string(), "+ bllvm-protocol\n");
+
+    stop.stop();
+    handle.join().unwrap().unwrap();
+}
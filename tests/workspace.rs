@@ -0,0 +1,120 @@
+//! Tests for cloning a manifest's repos
+#![cfg(feature = "git")]
+
+use bllvm::git::RetryPolicy;
+use bllvm::versions::VersionsManifest;
+use bllvm::workspace::{CloneStatus, Workspace};
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args([
+            "-c",
+            "user.name=bllvm",
+            "-c",
+            "user.email=bllvm@example.com",
+        ])
+        .args(args)
+        .output()
+        .expect("git should run");
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Test cloning at pinned refs, re-running on existing checkouts and failures
+#[tokio::test]
+async fn test_clone_all() {
+    let remotes = TempDir::new().unwrap();
+    for repo in ["bllvm-consensus", "bllvm-protocol"] {
+        let dir = remotes.path().join(repo);
+        std::fs::create_dir(&dir).unwrap();
+        git(&dir, &["init", "-q"]);
+        git(&dir, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+        git(&dir, &["tag", "v0.1.0"]);
+    }
+    let protocol = remotes.path().join("bllvm-protocol");
+    let pinned = git(&protocol, &["rev-parse", "HEAD"]);
+    git(&protocol, &["commit", "-q", "--allow-empty", "-m", "later"]);
+    git(&protocol, &["tag", "-f", "v0.1.0"]);
+
+    let manifest = VersionsManifest::from_str(&format!(
+        r#"
+[workspace]
+git_host = "{}"
+
+[versions]
+bllvm-consensus = {{ version = "0.1.0", git_tag = "v0.1.0", path = "repos/consensus" }}
+bllvm-protocol = {{ version = "0.1.0", git_tag = "v0.1.0", git_commit = "{}", requires = ["bllvm-consensus=0.1.0"] }}
+bllvm-node = {{ version = "0.1.0", git_tag = "v0.1.0", requires = ["bllvm-protocol=0.1.0"] }}
+"#,
+        remotes.path().display(),
+        pinned
+    ))
+    .unwrap();
+
+    let dest = TempDir::new().unwrap();
+    let workspace = Workspace::new(&manifest);
+    let results = workspace
+        .clone_all(dest.path(), 2, RetryPolicy::none())
+        .await
+        .unwrap();
+    let repos: Vec<&str> = results.iter().map(|r| r.repo.as_str()).collect();
+    assert_eq!(
+        repos,
+        vec!["bllvm-consensus", "bllvm-protocol", "bllvm-node"]
+    );
+    assert_eq!(results[0].status, CloneStatus::Cloned);
+    assert_eq!(results[1].status, CloneStatus::Cloned);
+    assert!(!results[2].is_success(), "{}", results[2]);
+    assert_eq!(
+        git(&dest.path().join("bllvm-protocol"), &["rev-parse", "HEAD"]),
+        pinned
+    );
+    // Cloned where `path` says, so it is neither missing nor an orphan
+    assert_eq!(results[0].dir, dest.path().join("repos/consensus"));
+    assert!(dest.path().join("repos/consensus/.git").exists());
+    assert_eq!(workspace.missing(dest.path()), vec!["bllvm-node"]);
+    assert!(workspace.orphans(dest.path()).unwrap().is_empty());
+
+    let results = workspace
+        .clone_all(dest.path(), 1, RetryPolicy::none())
+        .await
+        .unwrap();
+    assert_eq!(results[0].status, CloneStatus::UpToDate);
+    assert_eq!(results[1].status, CloneStatus::UpToDate);
+
+    assert!(workspace
+        .clone_all(dest.path(), 0, RetryPolicy::none())
+        .await
+        .is_err());
+}
+
+/// Test finding checkouts missing from the manifest and the reverse
+#[test]
+fn test_orphans_and_missing() {
+    let manifest = VersionsManifest::from_str(
+        r#"
+[versions]
+bllvm-consensus = { version = "0.1.0", git_tag = "v0.1.0" }
+bllvm-protocol = { version = "0.1.0", git_tag = "v0.1.0", path = "protocol" }
+bllvm-node = { version = "0.1.0", git_tag = "v0.1.0" }
+"#,
+    )
+    .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    for checkout in ["bllvm-consensus", "protocol", "bllvm-sdk"] {
+        std::fs::create_dir_all(dir.path().join(checkout).join(".git")).unwrap();
+    }
+    // Not a git repo, and a repo name without its checkout
+    std::fs::create_dir(dir.path().join("notes")).unwrap();
+    std::fs::create_dir(dir.path().join("bllvm-node")).unwrap();
+
+    let workspace = Workspace::new(&manifest);
+    assert_eq!(workspace.orphans(dir.path()).unwrap(), vec!["bllvm-sdk"]);
+    assert_eq!(workspace.missing(dir.path()), vec!["bllvm-node"]);
+}